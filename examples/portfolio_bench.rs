@@ -0,0 +1,38 @@
+// Times loading and scheduling a large portfolio with load_schedules.
+//
+//     cargo run --release --example portfolio_bench [LOANS]
+//
+// The target is thousands of loans per second.
+
+extern crate amortization;
+extern crate time;
+
+use std::env;
+use std::fs;
+use std::time::Instant;
+
+use amortization::Loan;
+
+fn main() {
+    let count = env::args().nth(1).and_then(|count| count.parse().ok()).unwrap_or(2000);
+    let db = env::temp_dir().join("amortization-portfolio-bench.db");
+    let _ = fs::remove_file(&db);
+    amortization::init_db(&db).unwrap();
+
+    let start = time::strptime("2016-04-01", "%F").unwrap().to_timespec();
+    let loans: Vec<Loan> = (0..count).map(|i| {
+        // a mix of car loans and 15 and 30 year mortgages
+        let (principal, years) = [(25000f64, 5), (180000f64, 15), (300000f64, 30)][i % 3];
+        Loan::new(format!("loan {}", i), principal, years * 12, 3f64 + (i % 40) as f64 / 10f64, start)
+    }).collect();
+    amortization::create_loans(&db, &loans).unwrap();
+
+    let started = Instant::now();
+    let schedules = amortization::load_schedules(&db).unwrap();
+    let elapsed = started.elapsed();
+    let seconds = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+    let rows: usize = schedules.iter().map(|&(_, ref schedule)| schedule.len()).sum();
+    println!("{} loans, {} payments in {:.3}s: {:.0} loans/s", schedules.len(), rows, seconds, schedules.len() as f64 / seconds);
+
+    let _ = fs::remove_file(&db);
+}
@@ -0,0 +1,41 @@
+#ifndef AMORTIZATION_H
+#define AMORTIZATION_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct {
+    int32_t period;
+    double payment;
+    double interest;
+    double principal;
+    double balance;
+} amort_installment;
+
+typedef struct {
+    int32_t periods;
+    double total_interest;
+    double total_paid;
+} amort_payoff;
+
+/* Monthly payment for a fully amortizing loan. apr is in percent. */
+double amort_calc_payment(double principal, int32_t periods, double apr);
+
+/* Fills `out` with up to `len` rows and returns the length of the full
+ * schedule. Pass NULL/0 to query the required size. `out` stays owned by the
+ * caller, who allocates and frees it; the library never keeps or frees it. */
+size_t amort_generate_schedule(double principal, int32_t periods, double apr, double payment,
+                               amort_installment *out, size_t len);
+
+/* Months, total interest and total paid to retire `balance` at `payment`. */
+amort_payoff amort_project_payoff(double balance, int32_t max_periods, double apr, double payment);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,83 @@
+// Annual percentage rate of charge (APRC), as defined by the EU Consumer
+// Credit Directive (2008/48/EC, Annex I). Unlike the US APR, which is the
+// monthly rate times twelve, the APRC is the effective yearly rate that makes
+// the present value of everything the lender pays out equal to the present
+// value of everything the borrower pays back, fees included.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CashFlow {
+    // time since the first drawdown, in years
+    pub years: f64,
+    pub amount: f64,
+}
+
+fn present_value(flows: &[CashFlow], rate: f64) -> f64 {
+    flows.iter().map(|flow| flow.amount / (1.0 + rate).powf(flow.years)).sum()
+}
+
+// Solves the APRC equation, returning the rate in percent.
+pub fn aprc(drawdowns: &[CashFlow], repayments: &[CashFlow]) -> f64 {
+    // the repayments' present value falls as the rate rises, so bisect
+    // between 0% and 1000% until it matches the drawdowns
+    let mut low = 0f64;
+    let mut high = 10f64;
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if present_value(repayments, mid) > present_value(drawdowns, mid) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0 * 100.0
+}
+
+// APRC for a loan paying out `credit` up front, less `upfront_fees` paid by
+// the borrower at signing, and repaid by `payments` (one per month, starting
+// a month after the drawdown) plus `monthly_fee` on each of them.
+pub fn loan_aprc<I: IntoIterator<Item=f64>>(credit: f64, upfront_fees: f64, payments: I, monthly_fee: f64) -> f64 {
+    let drawdowns = [CashFlow{ years: 0f64, amount: credit - upfront_fees }];
+    let repayments: Vec<CashFlow> = payments.into_iter().enumerate().map(|(i, payment)| {
+        CashFlow{
+            years: (i + 1) as f64 / 12.0,
+            amount: payment + monthly_fee,
+        }
+    }).collect();
+    aprc(&drawdowns, &repayments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flow(years: f64, amount: f64) -> CashFlow {
+        CashFlow{ years: years, amount: amount }
+    }
+
+    // The worked examples in Annex II of Directive 87/102/EEC, which the
+    // Consumer Credit Directive's formula carries over.
+    #[test]
+    fn directive_examples() {
+        // 1000 lent, repaid with 1200 after a year and a half
+        assert_eq!(format!("{:.2}", aprc(&[flow(0f64, 1000.0)], &[flow(1.5, 1200.0)])), "12.92");
+        // the same, with 50 kept back for administration
+        assert_eq!(format!("{:.2}", aprc(&[flow(0f64, 950.0)], &[flow(1.5, 1200.0)])), "16.85");
+        // 1000 lent, repaid with 600 after one year and 600 after two
+        assert_eq!(format!("{:.2}", aprc(&[flow(0f64, 1000.0)], &[flow(1.0, 600.0), flow(2.0, 600.0)])), "13.07");
+    }
+
+    // Without fees, the APRC of a loan is its APR compounded monthly.
+    #[test]
+    fn loan_without_fees() {
+        let payment = 1000.0 * 0.01 / (1.0 - 1.01f64.powi(-12));
+        assert_eq!(format!("{:.2}", loan_aprc(1000.0, 0f64, vec![payment; 12], 0f64)), "12.68");
+    }
+
+    #[test]
+    fn fees_raise_the_rate() {
+        let payment = 1000.0 * 0.01 / (1.0 - 1.01f64.powi(-12));
+        let plain = loan_aprc(1000.0, 0f64, vec![payment; 12], 0f64);
+        assert!(loan_aprc(1000.0, 20.0, vec![payment; 12], 0f64) > plain);
+        assert!(loan_aprc(1000.0, 0f64, vec![payment; 12], 2.0) > plain);
+    }
+}
@@ -0,0 +1,792 @@
+// Pure amortization math. Nothing in here touches the database or the system
+// clock, so it can be compiled for wasm32-unknown-unknown and shared with
+// other frontends.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use interest::{InterestMethod, Standard};
+
+// Converts an APR (in percent) to the monthly rate.
+pub fn monthly_rate(apr: f64) -> f64 {
+    apr / 100.0 / 12.0
+}
+
+// Payment required to pay off `principal` in `periods` months at `apr`.
+pub fn payment(principal: f64, periods: i32, apr: f64) -> f64 {
+    let rate = monthly_rate(apr);
+    if rate == 0f64 {
+        return principal / periods as f64;
+    }
+
+    (rate / (1.0 - ((1.0 + rate).powf(-periods as f64))))*principal
+}
+
+// Most that paying `payment` every month for `periods` months at `apr` pays
+// off; the inverse of payment.
+pub fn principal(payment: f64, periods: i32, apr: f64) -> f64 {
+    let rate = monthly_rate(apr);
+    if rate == 0f64 {
+        return payment * periods as f64;
+    }
+
+    payment * (1.0 - (1.0 + rate).powf(-periods as f64)) / rate
+}
+
+// When interest is charged: at the end of each month on the balance owed
+// during it (arrears, the usual), or at the start on the balance that will be
+// owed once the payment is made (advance, e.g. discount loans).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    Arrears,
+    Advance,
+}
+
+impl Timing {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Timing::Arrears => "arrears",
+            Timing::Advance => "advance",
+        }
+    }
+}
+
+impl FromStr for Timing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Timing, String> {
+        match s {
+            "arrears" => Ok(Timing::Arrears),
+            "advance" => Ok(Timing::Advance),
+            _ => Err(format!("Unknown interest timing: {}", s)),
+        }
+    }
+}
+
+// Like payment, with interest charged in advance. Each payment covers the
+// coming month's interest on what's left after it, so the balance after
+// payment k is (balance - payment) / (1 - rate).
+pub fn payment_in_advance(principal: f64, periods: i32, apr: f64) -> f64 {
+    let rate = monthly_rate(apr);
+    if rate == 0f64 {
+        return principal / periods as f64;
+    }
+
+    let growth = 1.0 / (1.0 - rate);
+    principal * growth.powi(periods - 1) * (growth - 1.0) / (growth.powi(periods) - 1.0)
+}
+
+// Payment under the given interest timing.
+pub fn payment_timed(principal: f64, periods: i32, apr: f64, timing: Timing) -> f64 {
+    match timing {
+        Timing::Arrears => payment(principal, periods, apr),
+        Timing::Advance => payment_in_advance(principal, periods, apr),
+    }
+}
+
+// Interest charged in advance when `paid` goes towards `balance`: the coming
+// month's interest on the balance left after the payment.
+pub fn interest_in_advance(balance: f64, paid: f64, apr: f64) -> f64 {
+    let rate = monthly_rate(apr);
+    if paid >= balance {
+        return 0f64;
+    }
+    rate * (balance - paid) / (1.0 - rate)
+}
+
+// How the regular payment is made up: the same total every month with
+// interest falling and principal rising (annuity, the usual), or the same
+// principal every month plus that month's interest, so payments fall over
+// time (linear, common for business and European loans). A bullet loan
+// pays only interest until the whole principal falls due at maturity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Repayment {
+    Annuity,
+    Linear,
+    Bullet,
+}
+
+impl Repayment {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Repayment::Annuity => "annuity",
+            Repayment::Linear => "linear",
+            Repayment::Bullet => "bullet",
+        }
+    }
+}
+
+impl FromStr for Repayment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Repayment, String> {
+        match s {
+            "annuity" => Ok(Repayment::Annuity),
+            "linear" => Ok(Repayment::Linear),
+            "bullet" => Ok(Repayment::Bullet),
+            _ => Err(format!("Unknown repayment style: {}", s)),
+        }
+    }
+}
+
+// How often payments are made. Everything else here works in months; a
+// schedule at another frequency is a monthly one with the APR scaled so each
+// period's rate comes out right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frequency {
+    Annually,
+    Quarterly,
+    Monthly,
+    Semimonthly,
+    Biweekly,
+    Weekly,
+}
+
+impl Frequency {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Frequency::Annually => "annually",
+            Frequency::Quarterly => "quarterly",
+            Frequency::Monthly => "monthly",
+            Frequency::Semimonthly => "semimonthly",
+            Frequency::Biweekly => "biweekly",
+            Frequency::Weekly => "weekly",
+        }
+    }
+
+    pub fn per_year(&self) -> i32 {
+        match *self {
+            Frequency::Annually => 1,
+            Frequency::Quarterly => 4,
+            Frequency::Monthly => 12,
+            Frequency::Semimonthly => 24,
+            Frequency::Biweekly => 26,
+            Frequency::Weekly => 52,
+        }
+    }
+
+    // Fully amortizing schedule paying off `principal` over `years`.
+    pub fn schedule(&self, principal: f64, years: i32, apr: f64) -> Schedule {
+        let periods = years * self.per_year();
+        let apr = apr * 12.0 / self.per_year() as f64;
+        Schedule::new(principal, periods, apr, payment(principal, periods, apr))
+    }
+}
+
+// True biweekly payments: half the monthly `payment` every two weeks, with
+// interest charged on each two-week period. The 26 half payments a year add
+// up to a thirteenth monthly payment, all of it principal. Runs until the
+// balance is paid off, or for up to a century if the payment can't manage it.
+pub fn biweekly(balance: f64, apr: f64, payment: f64) -> Schedule {
+    let per_year = Frequency::Biweekly.per_year();
+    Schedule::new(balance, 100 * per_year, apr * 12.0 / per_year as f64, payment / 2.0)
+}
+
+impl FromStr for Frequency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Frequency, String> {
+        match s {
+            "annually" => Ok(Frequency::Annually),
+            "quarterly" => Ok(Frequency::Quarterly),
+            "monthly" => Ok(Frequency::Monthly),
+            "semimonthly" => Ok(Frequency::Semimonthly),
+            "biweekly" => Ok(Frequency::Biweekly),
+            "weekly" => Ok(Frequency::Weekly),
+            _ => Err(format!("Unknown payment frequency: {}", s)),
+        }
+    }
+}
+
+// First payment of a linear loan: an equal share of the principal plus the
+// interest due with it.
+pub fn linear_payment(principal: f64, periods: i32, apr: f64, timing: Timing) -> f64 {
+    let share = principal / periods as f64;
+    match timing {
+        Timing::Arrears => share + interest(principal, apr),
+        Timing::Advance => share + interest(principal - share, apr),
+    }
+}
+
+// Finds the APR at which paying `payment` every month for `periods` months
+// pays off `principal` exactly.
+pub fn solve_apr(principal: f64, periods: i32, payment: f64) -> f64 {
+    if payment * periods as f64 <= principal {
+        return 0f64;
+    }
+
+    // payment() increases with the rate, so bisect between 0% and 1000%
+    let mut low = 0f64;
+    let mut high = 1000f64;
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if self::payment(principal, periods, mid) > payment {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+// Rate charged in `period`: `promo_apr` during a promotion, then `apr` or
+// whichever of `resets`, (first period, apr) in order, last took effect.
+pub fn apr_at(apr: f64, promo_apr: f64, promo_periods: i32, resets: &[(i32, f64)], period: i32) -> f64 {
+    if period <= promo_periods {
+        return promo_apr;
+    }
+    resets.iter()
+        .rev()
+        .find(|&&(first, _)| period >= first)
+        .map_or(apr, |&(_, apr)| apr)
+}
+
+// How a lender rounds an amount, to the given number of decimal places.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rounding {
+    Exact,
+    HalfUp(i32),
+    Truncate(i32),
+    Up(i32),
+}
+
+impl Rounding {
+    pub fn apply(&self, amount: f64) -> f64 {
+        // nudge by a tiny amount so values like 1.005 that can't be
+        // represented exactly still round the way they look
+        const EPSILON: f64 = 1e-9;
+        match *self {
+            Rounding::Exact => amount,
+            Rounding::HalfUp(decimals) => {
+                let factor = 10f64.powi(decimals);
+                (amount * factor + EPSILON).round() / factor
+            },
+            Rounding::Truncate(decimals) => {
+                let factor = 10f64.powi(decimals);
+                (amount * factor + EPSILON).trunc() / factor
+            },
+            Rounding::Up(decimals) => {
+                let factor = 10f64.powi(decimals);
+                (amount * factor - EPSILON).ceil() / factor
+            },
+        }
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rounding::Exact => write!(f, "exact"),
+            Rounding::HalfUp(decimals) => write!(f, "half-up:{}", decimals),
+            Rounding::Truncate(decimals) => write!(f, "truncate:{}", decimals),
+            Rounding::Up(decimals) => write!(f, "up:{}", decimals),
+        }
+    }
+}
+
+impl Rounding {
+    // Parses "exact", or a mode ("half-up", "truncate", "up") with an
+    // optional ":DECIMALS" suffix, defaulting to `decimals` (e.g. the
+    // currency's minor unit).
+    pub fn parse(s: &str, decimals: i32) -> Result<Rounding, String> {
+        let mut parts = s.splitn(2, ':');
+        let mode = parts.next().unwrap();
+        let decimals = match parts.next() {
+            Some(decimals) => match decimals.parse() {
+                Ok(decimals) => decimals,
+                Err(_) => return Err(format!("Invalid decimal places: {}", decimals)),
+            },
+            None => decimals,
+        };
+
+        match mode {
+            "exact" => Ok(Rounding::Exact),
+            "half-up" => Ok(Rounding::HalfUp(decimals)),
+            "truncate" => Ok(Rounding::Truncate(decimals)),
+            "up" => Ok(Rounding::Up(decimals)),
+            _ => Err(format!("Unknown rounding: {}", s)),
+        }
+    }
+}
+
+// Same as Rounding::parse, defaulting to cents.
+impl FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rounding, String> {
+        Rounding::parse(s, 2)
+    }
+}
+
+// Interest accrued on `balance` over a single month.
+pub fn interest(balance: f64, apr: f64) -> f64 {
+    balance * monthly_rate(apr)
+}
+
+// Interest accrued on `balance` over `days` days, using a 365-day year.
+pub fn daily_interest(balance: f64, apr: f64, days: i64) -> f64 {
+    balance * apr / 100.0 / 365.0 * days as f64
+}
+
+// Converts an amount paid `period` months from now into today's dollars,
+// given yearly `inflation` in percent.
+pub fn deflate(amount: f64, inflation: f64, period: i32) -> f64 {
+    amount / (1.0 + inflation / 100.0).powf(period as f64 / 12.0)
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Installment {
+    pub period: i32,
+    pub payment: f64,
+    pub interest: f64,
+    pub principal: f64,
+    pub balance: f64,
+}
+
+// Iterates over the remaining payments of a loan, stopping once the balance
+// reaches zero or the term runs out.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    balance: f64,
+    apr: f64,
+    payment: f64,
+    period: i32,
+    periods: i32,
+    interest_only: i32,
+    // payments not yet due, e.g. in school and the grace period after
+    deferment: i32,
+    subsidized: bool,
+    promo_apr: f64,
+    promo_periods: i32,
+    defer_interest: bool,
+    deferred_interest: f64,
+    interest_rounding: Rounding,
+    payment_rounding: Rounding,
+    timing: Timing,
+    repayment: Repayment,
+    // extra principal paid every month
+    monthly_extra: f64,
+    // (first period, amount) of extra principal paid once a year
+    annual_extras: Vec<(i32, f64)>,
+    lump_sums: Vec<(i32, f64)>,
+    // (first period, apr) of each rate reset of an adjustable-rate loan
+    rate_resets: Vec<(i32, f64)>,
+    // change in apr, in percentage points, after shock_periods payments
+    rate_shock: f64,
+    shock_periods: i32,
+    interest_method: Arc<dyn InterestMethod>,
+    // days the first period's interest covers, if not a regular month
+    first_period_days: i64,
+}
+
+impl Schedule {
+    pub fn new(balance: f64, periods: i32, apr: f64, payment: f64) -> Schedule {
+        Schedule{
+            balance: balance,
+            apr: apr,
+            payment: payment,
+            period: 0,
+            periods: periods,
+            interest_only: 0,
+            deferment: 0,
+            subsidized: false,
+            promo_apr: 0f64,
+            promo_periods: 0,
+            defer_interest: false,
+            deferred_interest: 0f64,
+            interest_rounding: Rounding::Exact,
+            payment_rounding: Rounding::Exact,
+            timing: Timing::Arrears,
+            repayment: Repayment::Annuity,
+            monthly_extra: 0f64,
+            annual_extras: Vec::new(),
+            lump_sums: Vec::new(),
+            rate_resets: Vec::new(),
+            rate_shock: 0f64,
+            shock_periods: 0,
+            interest_method: Arc::new(Standard),
+            first_period_days: 0,
+        }
+    }
+
+    // Only interest is due for the first `periods` payments (e.g. a HELOC's
+    // draw period). Afterwards the payment is recalculated to pay off the
+    // balance over the remaining term.
+    pub fn interest_only(mut self, periods: i32) -> Schedule {
+        self.interest_only = periods;
+        self
+    }
+
+    // Nothing is due for the first `periods` payments, as with student loans
+    // in school and the grace period after. Interest on subsidized loans is
+    // paid by someone else in the meantime; otherwise it's added to the
+    // balance when repayment starts. The payment is then recalculated to pay
+    // off the balance over the remaining term.
+    pub fn deferment(mut self, periods: i32, subsidized: bool) -> Schedule {
+        self.deferment = periods;
+        self.subsidized = subsidized;
+        self
+    }
+
+    // Charges `apr` instead of the regular rate for the first `periods`
+    // payments, re-amortizing at the regular rate afterwards. With
+    // `defer_interest`, interest at the regular rate is tracked during the
+    // promotion and charged all at once if the balance isn't paid off by the
+    // end of it.
+    pub fn promo(mut self, apr: f64, periods: i32, defer_interest: bool) -> Schedule {
+        self.promo_apr = apr;
+        self.promo_periods = periods;
+        self.defer_interest = defer_interest;
+        self
+    }
+
+    // Like payoff, but with every payment converted to today's dollars.
+    pub fn real_payoff(self, inflation: f64) -> Payoff {
+        let mut payoff = Payoff{
+            periods: 0,
+            total_interest: 0f64,
+            total_paid: 0f64,
+        };
+        for inst in self {
+            payoff.periods = inst.period;
+            payoff.total_interest += deflate(inst.interest, inflation, inst.period);
+            payoff.total_paid += deflate(inst.payment, inflation, inst.period);
+        }
+        payoff
+    }
+
+    pub fn balance(&self) -> f64 {
+        self.balance
+    }
+
+    pub fn payment(&self) -> f64 {
+        self.payment
+    }
+
+    fn apr_at(&self, period: i32) -> f64 {
+        let apr = apr_at(self.apr, self.promo_apr, self.promo_periods, &self.rate_resets, period);
+        if self.rate_shock != 0f64 && period > self.shock_periods {
+            apr + self.rate_shock
+        } else {
+            apr
+        }
+    }
+
+    // Rounds each month's interest, and any payment the schedule recalculates,
+    // the way the lender does.
+    pub fn rounding(mut self, interest: Rounding, payment: Rounding) -> Schedule {
+        self.interest_rounding = interest;
+        self.payment_rounding = payment;
+        self
+    }
+
+    // Charges interest in advance or in arrears. `payment` should be
+    // calculated the same way.
+    pub fn timing(mut self, timing: Timing) -> Schedule {
+        self.timing = timing;
+        self
+    }
+
+    // Pays principal as an annuity, in equal (linear) amounts or all at
+    // maturity (bullet). Only annuities use `payment`; the others work out
+    // each from the balance.
+    pub fn repayment(mut self, repayment: Repayment) -> Schedule {
+        self.repayment = repayment;
+        self
+    }
+
+    // Works out each month's interest the lender's way, e.g. on a 360-day
+    // year. The payment isn't recalculated, so a method charging more than a
+    // twelfth of the APR a month leaves a little owing at the end.
+    pub fn interest_method(mut self, method: Arc<dyn InterestMethod>) -> Schedule {
+        self.interest_method = method;
+        self
+    }
+
+    // Charges `days` days of interest in the first period instead of a
+    // month's, as when the first payment is due 45 or 60 days after closing.
+    // The first payment grows by the extra interest (or shrinks, for a short
+    // first period), leaving the rest of the schedule as it was.
+    pub fn first_period_days(mut self, days: i64) -> Schedule {
+        self.first_period_days = days;
+        self
+    }
+
+    // Interest on `balance` for the current period.
+    fn period_interest(&self, balance: f64, apr: f64) -> f64 {
+        if self.period == 1 && self.first_period_days > 0 {
+            self.interest_method.daily(balance, apr, self.first_period_days)
+        } else {
+            self.interest_method.monthly(balance, apr)
+        }
+    }
+
+    // Pays `amount` of extra principal along with every payment.
+    pub fn extra(mut self, amount: f64) -> Schedule {
+        self.monthly_extra = amount;
+        self
+    }
+
+    // Moves the rate by `delta` percentage points after `periods` payments,
+    // re-amortizing the balance at the new rate like a variable-rate loan.
+    pub fn rate_shock(mut self, periods: i32, delta: f64) -> Schedule {
+        self.shock_periods = periods;
+        self.rate_shock = delta;
+        self
+    }
+
+    // Charges `apr` from period `first` on, re-amortizing the balance at the
+    // new rate, like an adjustable-rate loan resetting. Resets must be added
+    // in order.
+    pub fn rate_reset(mut self, first: i32, apr: f64) -> Schedule {
+        self.rate_resets.push((first, apr));
+        self
+    }
+
+    // Pays `amount` of extra principal in period `first` and every twelve
+    // periods after (e.g. a yearly tax refund). May be called more than once.
+    pub fn annual_extra(mut self, first: i32, amount: f64) -> Schedule {
+        self.annual_extras.push((first, amount));
+        self
+    }
+
+    // Pays `amount` of extra principal once, in `period`. May be called more
+    // than once.
+    pub fn lump_sum(mut self, period: i32, amount: f64) -> Schedule {
+        self.lump_sums.push((period, amount));
+        self
+    }
+
+    fn extra_at(&self, period: i32) -> f64 {
+        let annual: f64 = self.annual_extras.iter()
+            .filter(|&&(first, _)| period >= first && (period - first) % 12 == 0)
+            .map(|&(_, amount)| amount)
+            .sum();
+        annual + self.lump_sums.iter().filter(|&&(at, _)| at == period).map(|&(_, amount)| amount).sum::<f64>()
+    }
+
+    // Runs the schedule to the end, totalling what was paid.
+    pub fn payoff(self) -> Payoff {
+        let mut payoff = Payoff{
+            periods: 0,
+            total_interest: 0f64,
+            total_paid: 0f64,
+        };
+        for inst in self {
+            payoff.periods = inst.period;
+            payoff.total_interest += inst.interest;
+            payoff.total_paid += inst.payment;
+        }
+        payoff
+    }
+}
+
+impl Iterator for Schedule {
+    type Item = Installment;
+
+    fn next(&mut self) -> Option<Installment> {
+        if self.period >= self.periods || self.balance <= 0f64 {
+            return None;
+        }
+
+        self.period += 1;
+        let apr = self.apr_at(self.period);
+        if self.period <= self.deferment {
+            // simple interest on the balance, capitalized in the last
+            // period before repayment
+            let accrued = if self.subsidized { 0f64 } else { self.interest_rounding.apply(self.interest_method.monthly(self.balance, apr)) };
+            self.deferred_interest += accrued;
+            let mut principal = 0f64;
+            if self.period == self.deferment {
+                principal -= self.deferred_interest;
+                self.balance += self.deferred_interest;
+                self.deferred_interest = 0f64;
+            }
+            return Some(Installment{
+                period: self.period,
+                payment: 0f64,
+                interest: accrued,
+                principal: principal,
+                balance: self.balance,
+            });
+        }
+        let mut charged = self.interest_rounding.apply(self.period_interest(self.balance, apr));
+        if self.period <= self.promo_periods && self.defer_interest {
+            self.deferred_interest += self.interest_rounding.apply(self.interest_method.monthly(self.balance, self.apr));
+        }
+
+        let promo_ended = self.promo_periods > 0 && self.period == self.promo_periods + 1;
+        let capitalized = if promo_ended { self.deferred_interest } else { 0f64 };
+        charged += capitalized;
+
+        let mut extra = self.monthly_extra + self.extra_at(self.period);
+        let bullet = self.repayment == Repayment::Bullet && self.period < self.periods;
+        if self.period <= self.interest_only || bullet {
+            if extra > self.balance {
+                extra = self.balance;
+            }
+            self.balance -= extra;
+            return Some(Installment{
+                period: self.period,
+                payment: charged + extra,
+                interest: charged,
+                principal: extra,
+                balance: self.balance,
+            });
+        }
+        if self.repayment != Repayment::Annuity {
+            // an equal share of what's left each period, plus the interest;
+            // for a bullet loan this is the final period, so all of it
+            let mut principal = self.payment_rounding.apply(self.balance / (self.periods - self.period + 1) as f64) + extra;
+            if principal > self.balance {
+                principal = self.balance;
+            }
+            if self.timing == Timing::Advance {
+                charged = self.interest_rounding.apply(self.interest_method.monthly(self.balance - principal, apr)) + capitalized;
+            }
+            self.balance -= principal;
+            return Some(Installment{
+                period: self.period,
+                payment: charged + principal,
+                interest: charged,
+                principal: principal,
+                balance: self.balance,
+            });
+        }
+
+        let shocked = self.rate_shock != 0f64 && self.period == self.shock_periods + 1;
+        let reset = self.rate_resets.iter().any(|&(first, _)| first == self.period);
+        let repaying = (self.interest_only > 0 && self.period == self.interest_only + 1) || (self.deferment > 0 && self.period == self.deferment + 1);
+        if promo_ended || shocked || reset || repaying {
+            self.payment = self.payment_rounding.apply(payment_timed(self.balance + capitalized, self.periods - self.period + 1, apr, self.timing));
+        }
+        if self.timing == Timing::Advance {
+            // this payment's interest is on what's left after it
+            let interest = self.interest_rounding.apply(interest_in_advance(self.balance + capitalized, self.payment + extra, apr));
+            charged = interest + capitalized;
+        }
+
+        // the first payment also covers any interest beyond a month's
+        let odd = if self.period == 1 && self.first_period_days > 0 && self.timing == Timing::Arrears {
+            charged - capitalized - self.interest_rounding.apply(self.interest_method.monthly(self.balance, apr))
+        } else {
+            0f64
+        };
+        let mut principal = self.payment + odd - charged + extra;
+        if principal > self.balance {
+            principal = self.balance;
+        }
+        self.balance -= principal;
+
+        Some(Installment{
+            period: self.period,
+            payment: charged + principal,
+            interest: charged,
+            principal: principal,
+            balance: self.balance,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Payoff {
+    pub periods: i32,
+    pub total_interest: f64,
+    pub total_paid: f64,
+}
+
+// Projects how long it takes to pay off `balance` making `payment` every
+// month, giving up after `max_periods`.
+pub fn project_payoff(balance: f64, max_periods: i32, apr: f64, payment: f64) -> Payoff {
+    Schedule::new(balance, max_periods, apr, payment).payoff()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cents(amount: f64) -> String {
+        format!("{:.2}", amount)
+    }
+
+    #[test]
+    fn annuity_payment() {
+        // the textbook 30-year mortgage: $200,000 at 6%
+        assert_eq!(cents(payment(200000.0, 360, 6.0)), "1199.10");
+        assert_eq!(cents(payment(1200.0, 12, 0f64)), "100.00");
+        assert_eq!(cents(principal(payment(200000.0, 360, 6.0), 360, 6.0)), "200000.00");
+        assert_eq!(format!("{:.3}", solve_apr(200000.0, 360, 1199.1010503055138)), "6.000");
+    }
+
+    #[test]
+    fn annuity_schedule() {
+        let schedule: Vec<Installment> = Schedule::new(200000.0, 360, 6.0, payment(200000.0, 360, 6.0)).collect();
+        assert_eq!(schedule.len(), 360);
+        assert_eq!(cents(schedule[0].interest), "1000.00");
+        assert_eq!(cents(schedule[0].principal), "199.10");
+        assert_eq!(cents(schedule[359].balance), "0.00");
+        assert_eq!(cents(schedule.iter().map(|inst| inst.interest).sum()), "231676.38");
+    }
+
+    // Interest charged in advance at 1% a month is interest in arrears at
+    // 1% / (1 - 1%), so the payment is the annuity at that rate.
+    #[test]
+    fn advance_schedule() {
+        let payment = payment_in_advance(1000.0, 12, 12.0);
+        assert_eq!(cents(payment), "88.02");
+        let schedule: Vec<Installment> = Schedule::new(1000.0, 12, 12.0, payment).timing(Timing::Advance).collect();
+        assert_eq!(schedule.len(), 12);
+        // the first payment's interest is on the 911.98 left after it
+        assert_eq!(cents(schedule[0].interest), "9.21");
+        assert_eq!(cents(schedule[11].balance), "0.00");
+        assert_eq!(cents(schedule.iter().map(|inst| inst.interest).sum()), "56.20");
+    }
+
+    // 1200 at 12% over a year: 100 of principal a month, plus interest on
+    // 1200, 1100, ... 100.
+    #[test]
+    fn linear_schedule() {
+        assert_eq!(cents(linear_payment(1200.0, 12, 12.0, Timing::Arrears)), "112.00");
+        let schedule: Vec<Installment> = Schedule::new(1200.0, 12, 12.0, 0f64).repayment(Repayment::Linear).collect();
+        assert_eq!(schedule.len(), 12);
+        for (i, inst) in schedule.iter().enumerate() {
+            assert_eq!(cents(inst.principal), "100.00");
+            assert_eq!(cents(inst.payment), cents(112.0 - i as f64));
+        }
+        assert_eq!(cents(schedule.iter().map(|inst| inst.interest).sum()), "78.00");
+        assert_eq!(cents(schedule[11].balance), "0.00");
+    }
+
+    #[test]
+    fn rate_resets() {
+        let resets = [(13, 5.0), (25, 7.0)];
+        assert_eq!(apr_at(4.0, 0f64, 6, &resets, 6), 0f64);
+        assert_eq!(apr_at(4.0, 0f64, 6, &resets, 7), 4.0);
+        assert_eq!(apr_at(4.0, 0f64, 6, &resets, 13), 5.0);
+        assert_eq!(apr_at(4.0, 0f64, 6, &resets, 24), 5.0);
+        assert_eq!(apr_at(4.0, 0f64, 6, &resets, 300), 7.0);
+
+        // the payment is worked out again at each new rate
+        let schedule: Vec<Installment> = Schedule::new(1200.0, 24, 6.0, payment(1200.0, 24, 6.0)).rate_reset(13, 12.0).collect();
+        assert_eq!(cents(schedule[12].interest), cents(schedule[11].balance * 0.01));
+        assert_eq!(cents(schedule[12].payment), cents(payment(schedule[11].balance, 12, 12.0)));
+        assert_eq!(cents(schedule[23].balance), "0.00");
+    }
+
+    // Interest only until the whole principal falls due with the last
+    // payment.
+    #[test]
+    fn bullet_schedule() {
+        let schedule: Vec<Installment> = Schedule::new(10000.0, 12, 6.0, 0f64).repayment(Repayment::Bullet).collect();
+        assert_eq!(schedule.len(), 12);
+        for inst in schedule[..11].iter() {
+            assert_eq!(cents(inst.payment), "50.00");
+            assert_eq!(cents(inst.balance), "10000.00");
+        }
+        assert_eq!(cents(schedule[11].payment), "10050.00");
+        assert_eq!(cents(schedule[11].balance), "0.00");
+    }
+}
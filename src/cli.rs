@@ -7,185 +7,3248 @@ extern crate time;
 
 extern crate amortization;
 
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
 use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::Arc;
 
 use clap::{Arg, App, SubCommand, ArgMatches};
-use rusqlite::Connection;
 use time::Timespec;
 
-use amortization::Loan;
+use amortization::{aprc, calc, consolidate, export, idr, schema, selftest, server, statement, AddOn, Anchor, Asset, Borrower, ClosingSummary, DeferredPromo, Draw, ExtraSavings, Fee, Lender, Loan, LoanGroup, LoanStatus, LtvPoint, MilestoneEvent, PayoffQuote, RateIndex, Transaction, TransactionKind,
+                    Valuation};
+use amortization::consolidate::Consolidation;
+use amortization::currency::{self, ExchangeRate, Rates};
+use amortization::escrow::{Disbursement, Escrow};
+use amortization::group::Allocation;
+use amortization::i18n::{self, tr, trf};
+use amortization::idr::IdrPlan;
+use amortization::import::{self, PaymentKind, PaymentRow};
+use amortization::interest;
+use amortization::overlay::Overlay;
+use amortization::portfolio::{self, Position};
+use amortization::rebalance::{self, Debt, Plan, Strategy};
+use amortization::render::{self, Cell, Renderer, Table};
+use amortization::reverse::ReverseMortgage;
+use amortization::scenario::{Refinance, Scenario};
+use amortization::trace;
+use amortization::validate;
 
 struct Amortizer {
     verbosity: u64,
+    // yearly inflation used to report amounts in today's dollars
+    inflation: Option<f64>,
+    // color-code loan statuses with ANSI escapes
+    color: bool,
+    // only show loans this member of the household owns
+    user: Option<String>,
+    // writes out tables in the --output format
+    renderer: Arc<dyn Renderer>,
+    // explain loans and payments in plain words, in whole dollars
+    simple: bool,
+    // where schedules listed with -vv start
+    anchor: Anchor,
 }
 
 impl Amortizer {
-    fn print_loan(&self, loan: Loan) {
-        println!("{}: Balance = ${:.2}, APR = {:.2}%", loan.name, loan.balance, loan.apr);
+    fn render(&self, table: &Table) {
+        let stdout = io::stdout();
+        if let Err(err) = self.renderer.render(&mut stdout.lock(), table) {
+            error!("{}", trf("Error writing output: {}", &[&err]));
+            std::process::exit(1);
+        }
+    }
+
+    // Whether output is for reading rather than for other tools, so notes
+    // can go around the tables.
+    fn plain(&self) -> bool {
+        self.renderer.name() == "text"
+    }
+
+    fn paint_status(&self, status: LoanStatus) -> String {
+        if !self.color {
+            return status.label();
+        }
+        let code = match status {
+            LoanStatus::Current => "32",
+            LoanStatus::Grace => "33",
+            LoanStatus::Late => "31",
+            LoanStatus::Delinquent => "1;31",
+            LoanStatus::PaidOff => "34",
+        };
+        format!("\x1b[{}m{}\x1b[0m", code, status.label())
+    }
+
+    fn print_loan(&self, db: &Path, loan: Loan) {
+        // other tools get the loan as list has it, or its schedule with -vv
+        if !self.plain() {
+            if self.verbosity > 1 {
+                self.print_schedule(&loan);
+            } else {
+                let mut table = Table::new(&schema::LIST.columns());
+                table.push(self.list_row(&loan));
+                self.render(&table);
+            }
+            return;
+        }
+        if self.simple {
+            self.explain_loan(&loan);
+            return;
+        }
+        let balance = if loan.lent { tr("Owed to you") } else { tr("Balance") };
+        println!("{}", trf("{}: {} = ${}, APR = {}% [{}]", &[&loan.name, &balance, &loan.money(loan.balance), &format!("{:.2}", loan.apr),
+                                                             &self.paint_status(loan.status)]));
         debug!("Loan details: {:?}", loan);
+        if loan.defer_interest && loan.balance > 0f64 {
+            let promos = amortization::deferred_promos(db, amortization::today()).unwrap_or_else(|err| {
+                error!("{}", trf("Error calculating deferred interest: {}", &[&err]));
+                std::process::exit(1);
+            });
+            for promo in promos.iter().filter(|promo| promo.loan == loan.name) {
+                if promo.due_soon(amortization::today()) || self.verbosity > 0 {
+                    self.print_promo(promo);
+                }
+            }
+        }
+
+        let fees = if self.verbosity > 0 {
+            amortization::load_fees(db, &loan.name).unwrap_or_else(|err| {
+                error!("{}", trf("Error loading fees: {}", &[&err]));
+                std::process::exit(1);
+            })
+        } else {
+            Vec::new()
+        };
+        if !fees.is_empty() {
+            let total: f64 = fees.iter().map(|fee| fee.amount).sum();
+            let borrowed = loan.principal - total;
+            println!("{}", trf("Amount borrowed: {}, amount financed: {}", &[&format!("{:.2}", borrowed), &format!("{:.2}", loan.principal)]));
+            for fee in fees.iter() {
+                println!("  {}: {:.2}", fee.name, fee.amount);
+            }
+            println!("{}", trf("Effective APR: {}%", &[&format!("{:.3}", calc::solve_apr(borrowed, loan.periods, loan.payment))]));
+        }
+
+        if self.verbosity > 0 {
+            let addons = amortization::load_addons(db, &loan.name).unwrap_or_else(|err| {
+                error!("{}", trf("Error loading add-ons: {}", &[&err]));
+                std::process::exit(1);
+            });
+            for part in loan.attribute(&addons).iter().skip(1) {
+                println!("{}", trf("Add-on {}: {} over {} months, {} of the payment",
+                                   &[&part.name, &format!("{:.2}", part.financed), &part.periods, &format!("{:.2}", part.payment)]));
+            }
+        }
 
-        let monthly_apr = loan.apr / 12f64 / 100f64;
         if self.verbosity > 0 {
-            println!("Monthly payment: {:.2}", loan.payment);
+            if loan.promo_periods > 0 {
+                println!("{}", trf("Promotional APR of {}% for {} months{}", &[&format!("{:.2}", loan.promo_apr), &loan.promo_periods,
+                                                                               &(if loan.defer_interest { tr(" (deferred interest)") } else { String::new() })]));
+            }
+            if let Some(ref index) = loan.rate_index {
+                self.print_resets(&loan, index);
+            }
+            if let Some(point) = self.require_ltv(db, &loan.name).last() {
+                println!("{}", trf("Collateral: {}, LTV = {}%, Equity = {}", &[&format!("{:.2}", point.value), &format!("{:.1}", point.ltv()),
+                                                                               &format!("{:.2}", point.equity())]));
+            }
+            if loan.currency != "USD" {
+                println!("{}", trf("Currency: {}", &[&loan.currency]));
+            }
+            if loan.paid_ahead && loan.balance > 0f64 {
+                println!("{}", trf("Paid ahead: next payment due {}", &[&time::strftime("%F", &time::at_utc(loan.next_due(amortization::today()))).unwrap()]));
+            }
+            if let Some(ref owner) = loan.owner {
+                println!("{}", trf("Owner: {}", &[&owner]));
+            }
+            if let Some(ref lender) = loan.lender {
+                self.print_lender(db, lender);
+            }
+            if let Some(ref group) = loan.loan_group {
+                println!("{}", trf("Part of group: {}", &[&group]));
+            }
+            if let Ok(transactions) = amortization::load_transactions(db, Some(&loan.name), None) {
+                let pending = transactions.iter().filter(|transaction| transaction.provisional).count();
+                if pending > 0 {
+                    println!("{}", trf("Payments awaiting review: {} (see pending)", &[&pending]));
+                }
+            }
+            if loan.interest_timing != calc::Timing::Arrears {
+                println!("{}", trf("Interest charged in {}", &[&loan.interest_timing.as_str()]));
+            }
+            if loan.first_payment_days > 0 {
+                println!("{}", trf("Closed {}, {} days before the first payment",
+                                   &[&time::strftime("%F", &time::at_utc(loan.closing_date())).unwrap(), &loan.first_payment_days]));
+            }
+            if loan.interest_method != "standard" {
+                println!("{}", trf("Interest method: {}", &[&loan.interest_method]));
+            }
+            match loan.repayment {
+                calc::Repayment::Linear => println!("{}", tr("Fixed principal (linear) repayment")),
+                calc::Repayment::Bullet => println!("{}", trf("Interest only, principal due {}",
+                                                              &[&time::strftime("%F", &time::at_utc(loan.maturity())).unwrap()])),
+                calc::Repayment::Annuity => {},
+            }
+            if loan.interest_rounding != calc::Rounding::Exact || loan.payment_rounding != calc::Rounding::Exact {
+                println!("{}", trf("Rounding: interest {}, payment {}", &[&loan.interest_rounding, &loan.payment_rounding]));
+            }
+            if loan.unpaid_interest > 0f64 {
+                println!("{}", trf("Deferred interest due at payoff: {}", &[&format!("{:.2}", loan.unpaid_interest)]));
+            }
+            match amortization::payoff_quote(db, &loan.name, amortization::today()) {
+                Ok(quote) => println!("{}", trf("Accrued interest: {}", &[&loan.money(quote.accrued_interest)])),
+                Err(err) => error!("{}", trf("Error calculating accrued interest: {}", &[&err])),
+            };
+            if loan.draw_periods > 0 {
+                println!("{}", trf("Interest-only for {} months, then monthly payment: {}", &[&loan.draw_periods, &loan.money(loan.payment)]));
+            } else if loan.deferment_periods > 0 {
+                println!("{}", trf("Deferred for {} months ({}), then monthly payment: {}",
+                                   &[&loan.deferment_periods, &(if loan.subsidized { tr("subsidized") } else { tr("unsubsidized") }), &loan.money(loan.payment)]));
+                if loan.capitalized_interest > 0f64 {
+                    println!("{}", trf("Interest capitalized at repayment: {}", &[&loan.money(loan.capitalized_interest)]));
+                }
+            } else {
+                println!("{}", trf("Monthly payment: {}", &[&loan.money(loan.payment)]));
+            }
+            let penalty_left = loan.penalty_periods_left(amortization::today());
+            if penalty_left > 0 {
+                println!("{}", trf("Prepayment penalty: {}% of extra principal for {} more months",
+                                   &[&format!("{:.2}", loan.prepayment_penalty), &penalty_left]));
+            }
+            if loan.escrow_payment > 0f64 || loan.pmi_payment > 0f64 {
+                println!("{}", trf("Plus escrow: {}, PMI: {}, for a total of {}",
+                                   &[&format!("{:.2}", loan.escrow_payment), &format!("{:.2}", loan.pmi_payment),
+                                     &format!("{:.2}", loan.payment + loan.escrow_payment + loan.pmi_payment)]));
+            }
+
+            let payoff = loan.schedule().payoff();
+            if let Some(inflation) = self.inflation {
+                let real = loan.schedule().real_payoff(inflation);
+                println!("{}", trf("Total interest: {} ({} in today's dollars at {}% inflation)",
+                                   &[&loan.money(payoff.total_interest), &loan.money(real.total_interest), &format!("{:.2}", inflation)]));
+            } else {
+                println!("{}", trf("Total interest: {}", &[&loan.money(payoff.total_interest)]));
+            }
         } else {
             return;
         }
 
-        let mut date = time::at(loan.start_time);
-        date.tm_mday = 1;
-        let mut balance = loan.balance;
-        for i in 1..loan.periods+1 {
-            let interest = balance * monthly_apr;
-            let mut principal = loan.payment - interest;
-            if principal > balance {
-                principal = balance;
+        if self.verbosity > 1 {
+            self.print_schedule(&loan);
+        }
+        if let Some(inst) = loan.schedule().find(|inst| inst.balance <= 0f64) {
+            println!("{}", trf("Congrats, you'll pay off your loan {} months early!", &[&(loan.periods - inst.period)]));
+        }
+    }
+
+    // The loan's remaining payments, from where --anchor says to start.
+    fn print_schedule(&self, loan: &Loan) {
+        let mut columns = vec!["period", "date", "payment", "principal", "interest", "balance"];
+        if self.inflation.is_some() {
+            columns.push("in today's dollars");
+        }
+        let mut table = Table::new(&columns);
+        let mut notes = Vec::new();
+        for (i, (due, inst)) in loan.schedule_from(self.anchor, amortization::today()).enumerate() {
+            if inst.period == loan.draw_periods + 1 && loan.draw_periods > 0 {
+                notes.push(trf("The draw period ends after payment {}; the payment is then {}", &[&(inst.period - 1), &loan.money(inst.payment)]));
+            }
+            if inst.period == loan.promo_periods + 1 && loan.promo_periods > 0 {
+                notes.push(trf("The promotional rate ends after payment {}; the payment is then {}", &[&(inst.period - 1), &loan.money(inst.payment)]));
+            }
+            let mut row = vec![Cell::Number(inst.period.to_string()), Cell::Text(time::strftime("%F", &time::at_utc(due)).unwrap()),
+                               Cell::Number(loan.money(inst.payment)), Cell::Number(loan.money(inst.principal)), Cell::Number(loan.money(inst.interest)),
+                               Cell::Number(loan.money(inst.balance))];
+            if let Some(inflation) = self.inflation {
+                row.push(Cell::Number(loan.money(calc::deflate(inst.payment, inflation, i as i32 + 1))));
+            }
+            table.push(row);
+        }
+        self.render(&table);
+        if self.plain() {
+            for note in notes {
+                println!("{}", note);
+            }
+        }
+    }
+
+    fn query_loan(&self, db: &Path, name: String) -> Option<Loan> {
+        match amortization::refresh_statuses(db).and_then(|_| amortization::load_loan(db, &name)) {
+            Ok(loan) => Some(loan),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(err) => {
+                error!("{}", trf("Error with statement: {}", &[&err]));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Like query_loan, but exits if the loan doesn't exist.
+    fn require_loan(&self, db: &Path, name: &str) -> Loan {
+        match self.query_loan(db, name.to_string()) {
+            Some(loan) => loan,
+            None => {
+                println!("{}", trf("Could not find loan with the name: {}", &[&name]));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // `start` is the month the fiscal year starts in; years are labeled
+    // FY<year> unless it's January.
+    fn print_income(&self, income: &[(i32, String, f64)], paid: bool, start: i32) {
+        if income.is_empty() {
+            println!("{}", if paid { tr("No interest paid on loans you borrowed") } else { tr("No interest received on loans you lent out") });
+            return;
+        }
+        let label = |year: i32| if start > 1 { format!("FY{}", year) } else { year.to_string() };
+        let mut table = Table::new(&["year", "loan", "interest"]);
+        for &(year, ref name, interest) in income {
+            table.push(vec![Cell::Text(label(year)), Cell::Text(name.clone()), Cell::Number(format!("{:.2}", interest))]);
+        }
+        self.render(&table);
+        if !self.plain() {
+            return;
+        }
+        let mut years: Vec<i32> = income.iter().map(|&(year, _, _)| year).collect();
+        years.dedup();
+        for year in years {
+            let total: f64 = income.iter().filter(|&&(y, _, _)| y == year).map(|&(_, _, interest)| interest).sum();
+            println!("{}", trf("{}: {} in total", &[&label(year), &format!("{:.2}", total)]));
+        }
+    }
+
+    fn print_promo(&self, promo: &DeferredPromo) {
+        let warning = if promo.due_soon(amortization::today()) { tr("Warning: ") } else { String::new() };
+        println!("{}", trf("{}{} in deferred interest will be charged unless the balance of {} is paid off by {}",
+                           &[&warning, &format!("{:.2}", promo.interest), &format!("{:.2}", promo.balance),
+                             &time::strftime("%F", &time::at_utc(promo.deadline)).unwrap()]));
+    }
+
+    // The loan's contract schedule projected both ways: subsidized, with no
+    // interest during deferment, and unsubsidized, with it capitalized when
+    // repayment starts.
+    fn print_deferment(&self, loan: &Loan) {
+        let start = time::strftime("%F", &time::at_utc(loan.due_date(loan.deferment_periods + 1))).unwrap();
+        if self.plain() {
+            println!("{}", trf("{}: {} deferred for {} months, repayment starts {}", &[&loan.name, &loan.money(loan.principal), &loan.deferment_periods, &start]));
+        }
+        let mut table = Table::new(&["plan", "capitalized", "payment", "interest", "total paid"]);
+        let mut costs = Vec::new();
+        for &subsidized in [true, false].iter() {
+            let schedule: Vec<calc::Installment> = loan.contract_schedule().deferment(loan.deferment_periods, subsidized).collect();
+            let capitalized: f64 = schedule.iter().take(loan.deferment_periods as usize).map(|inst| inst.interest).sum();
+            let payment = schedule.get(loan.deferment_periods as usize).map_or(0f64, |inst| inst.payment);
+            let interest: f64 = schedule.iter().map(|inst| inst.interest).sum();
+            table.push(vec![Cell::Text(if subsidized { "subsidized" } else { "unsubsidized" }.to_string()), Cell::Number(loan.money(capitalized)),
+                            Cell::Number(loan.money(payment)), Cell::Number(loan.money(interest)),
+                            Cell::Number(loan.money(schedule.iter().map(|inst| inst.payment).sum()))]);
+            costs.push(interest);
+        }
+        self.render(&table);
+        if self.plain() {
+            println!("{}", trf("Unsubsidized costs {} more", &[&loan.money(costs[1] - costs[0])]));
+        }
+    }
+
+    fn print_idr(&self, loan: Loan, plan: IdrPlan) {
+        let projection = idr::project(loan.balance, loan.apr, &plan);
+        let mut table = Table::new(&["year", "payment", "paid", "interest", "balance"]);
+        for year in projection.years.iter() {
+            table.push(vec![Cell::Number(year.year.to_string()), Cell::Number(format!("{:.2}", year.payment)), Cell::Number(format!("{:.2}", year.paid)),
+                            Cell::Number(format!("{:.2}", year.interest)), Cell::Number(format!("{:.2}", year.balance))]);
+        }
+        self.render(&table);
+        if !self.plain() {
+            return;
+        }
+
+        let standard = loan.schedule().payoff();
+        println!("{}", trf("IDR: ${} paid over {} years, ${} forgiven", &[&format!("{:.2}", projection.total_paid), &projection.years.len(),
+                                                                          &format!("{:.2}", projection.forgiven)]));
+        println!("{}", trf("Standard: ${} paid over {} months (${} interest)", &[&format!("{:.2}", standard.total_paid), &standard.periods,
+                                                                                 &format!("{:.2}", standard.total_interest)]));
+    }
+
+    fn print_reverse(&self, loan: Loan, mortgage: ReverseMortgage, years: i32) {
+        if self.plain() {
+            println!("{}", trf("{}: Balance = ${}, APR = {}%, Draw = ${}/mo", &[&loan.name, &format!("{:.2}", loan.balance), &format!("{:.2}", loan.apr),
+                                                                                &format!("{:.2}", mortgage.monthly_draw)]));
+        }
+
+        let mut table = Table::new(&["year", "balance", "home value", "equity"]);
+        let mut underwater = None;
+        for month in mortgage.projection(years * 12) {
+            if month.period % 12 == 0 {
+                table.push(vec![Cell::Number((month.period / 12).to_string()), Cell::Number(format!("{:.2}", month.balance)),
+                                Cell::Number(format!("{:.2}", month.home_value)), Cell::Number(format!("{:.2}", month.equity()))]);
+            }
+            if underwater.is_none() && month.equity() < 0f64 {
+                underwater = Some(month.period);
             }
-            balance -= principal;
+        }
+        self.render(&table);
+        if let (true, Some(period)) = (self.plain(), underwater) {
+            println!("{}", trf("Balance exceeds home value after {} months", &[&period]));
+        }
+    }
+
+    // Largest loan whose payment, on top of the payments on loans already in
+    // the database, keeps debt payments within `dti` percent of income.
+    fn print_affordability(&self, db: &Path, reporting: &str, income: f64, dti: f64, apr: f64, term: i32, housing: f64, down: Option<f64>) {
+        let rates = self.require_rates(db);
+        let obligations = self.require_loans(db).iter()
+            .filter(|loan| !loan.lent && loan.balance > 0f64)
+            .fold(0f64, |sum, loan| sum + self.convert(&rates, loan.payment + loan.escrow_payment + loan.pmi_payment, &loan.currency, reporting));
+        let monthly = income / 12.0;
+        let limit = monthly * dti / 100.0;
+        println!("{}", trf("Income: {}/mo, debt payments up to {}/mo at {}% DTI",
+                           &[&format!("{:.2}", monthly), &format!("{:.2}", limit), &format!("{:.0}", dti)]));
+        println!("{}", trf("Existing obligations: {}/mo ({}% DTI)", &[&format!("{:.2}", obligations), &format!("{:.1}", obligations / monthly * 100.0)]));
+        if housing > 0f64 {
+            println!("{}", trf("Taxes and insurance: {}/mo", &[&format!("{:.2}", housing)]));
+        }
+        let available = limit - obligations - housing;
+        if available <= 0f64 {
+            println!("{}", tr("No room for another payment: existing obligations already use up the DTI limit"));
+            return;
+        }
+
+        let principal = calc::principal(available, term * 12, apr);
+        println!("{}", trf("Largest loan: {} {} over {} years at {}%", &[&format!("{:.2}", principal), &reporting, &term, &format!("{:.2}", apr)]));
+        if housing > 0f64 {
+            println!("{}", trf("Payment: {}/mo ({} with taxes and insurance)", &[&format!("{:.2}", available), &format!("{:.2}", available + housing)]));
+        } else {
+            println!("{}", trf("Payment: {}/mo", &[&format!("{:.2}", available)]));
+        }
+        if let Some(down) = down {
+            let price = principal + down;
+            println!("{}", trf("With {} down: price up to {} ({}% down)", &[&format!("{:.2}", down), &format!("{:.2}", price),
+                                                                            &format!("{:.1}", down / price * 100.0)]));
+        }
+    }
 
+    // Monthly payment on `principal` at each rate (rows) and term in years
+    // (columns).
+    fn print_rate_sheet(&self, principal: f64, aprs: &[f64], terms: &[i32]) {
+        let columns: Vec<String> = Some("apr".to_string()).into_iter().chain(terms.iter().map(|term| format!("{} yr", term))).collect();
+        let mut table = Table::new(&columns.iter().map(|column| column.as_str()).collect::<Vec<_>>());
+        for &apr in aprs {
+            let mut row = vec![Cell::Number(format!("{:.3}", apr))];
+            row.extend(terms.iter().map(|&term| Cell::Number(format!("{:.2}", calc::payment(principal, term * 12, apr)))));
+            table.push(row);
+        }
+        self.render(&table);
+    }
+
+    fn print_escrow(&self, loan: &Loan, escrow: &Escrow) {
+        let first = time::at_utc(loan.next_due(amortization::today()));
+        let analysis = escrow.analysis(first.tm_mon + 1);
+        if self.plain() {
+            println!("{}", trf("{}: Escrow balance = ${}, Deposit = ${}/mo, Disbursements = ${}/yr",
+                               &[&loan.name, &format!("{:.2}", escrow.balance), &format!("{:.2}", escrow.monthly_deposit), &format!("{:.2}", analysis.annual)]));
+        }
+
+        let mut table = Table::new(&["month", "deposit", "paid out", "balance"]);
+        let mut date = first;
+        date.tm_mday = 1;
+        for month in analysis.months.iter() {
+            table.push(vec![Cell::Text(time::strftime("%Y-%m", &date).unwrap()), Cell::Number(format!("{:.2}", month.deposit)),
+                            Cell::Number(format!("{:.2}", month.paid)), Cell::Number(format!("{:.2}", month.balance))]);
             date.tm_mon += 1;
             if date.tm_mon == 12 {
-                date.tm_mon -= 12;
+                date.tm_mon = 0;
                 date.tm_year += 1;
             }
+        }
+        self.render(&table);
+        if !self.plain() {
+            return;
+        }
+        if let Some(month) = analysis.overdrawn() {
+            println!("{}", trf("Overdrawn by {} in month {} at the current deposit", &[&format!("{:.2}", -month.balance), &month.period]));
+        }
 
-            if self.verbosity > 1 {
-                println!("{}: Interest = {:.2}, Principal = {:.2}, Balance: {:.2}", time::strftime("%F", &date).unwrap(), interest, principal, balance);
+        println!("{}", trf("Required cushion: {}, projected low point: {}", &[&format!("{:.2}", analysis.cushion), &format!("{:.2}", analysis.low_point)]));
+        if analysis.shortage > 0f64 {
+            println!("{}", trf("Shortage: {}, spread over the next 12 payments", &[&format!("{:.2}", analysis.shortage)]));
+        } else if analysis.surplus > 0f64 {
+            println!("{}", trf("Surplus: {}", &[&format!("{:.2}", analysis.surplus)]));
+        }
+        println!("{}", trf("New monthly escrow deposit: {} ({})", &[&format!("{:.2}", analysis.new_deposit),
+                                                                    &format!("{:+.2}", analysis.new_deposit - escrow.monthly_deposit)]));
+    }
+
+    // Where to put `budget` extra each month under each strategy, and how
+    // each plays out against regular payments alone. With prepayment
+    // penalties, the net strategy weighs each penalty against the interest
+    // paying ahead saves.
+    fn print_rebalance(&self, db: &Path, budget: f64) {
+        let today = amortization::today();
+        let debts: Vec<Debt> = self.require_loans(db).into_iter()
+            .filter(|loan| !loan.lent && loan.balance > 0f64)
+            .map(|loan| Debt{ penalty: loan.prepayment_penalty, penalty_months: loan.penalty_periods_left(today),
+                              name: loan.name, balance: loan.balance, apr: loan.apr, payment: loan.payment })
+            .collect();
+        let penalties = debts.iter().any(|debt| debt.penalty_months > 0);
+        let first = next_due_month();
+        let base = rebalance::plan(&debts, budget, None);
+        println!("{}", trf("Regular payments only: debt free {}, ${} in interest",
+                           &[&payoff_month(first, base.months), &format!("{:.2}", base.total_interest)]));
+
+        let strategies: &[Strategy] = if penalties { &[Strategy::Avalanche, Strategy::Snowball, Strategy::Net] } else { &[Strategy::Avalanche, Strategy::Snowball] };
+        let plans: Vec<Plan> = strategies.iter().map(|&strategy| rebalance::plan(&debts, budget, Some(strategy))).collect();
+        for plan in plans.iter() {
+            println!("");
+            println!("{} (${:.2}/mo extra):", plan.strategy.unwrap().as_str(), budget);
+            for &(ref name, extra) in plan.allocation.iter() {
+                println!("  {}", trf("Pay {} extra on {} this month", &[&format!("{:.2}", extra), &name]));
             }
-            if balance <= 0f64 {
-                println!("Congrats, you'll pay off your loan {} months early!", loan.periods - i);
-                break;
+            if plan.allocation.is_empty() {
+                println!("  {}", tr("Hold the extra this month: prepayment penalties outweigh the interest it would save"));
+            }
+            println!("  {}", trf("Debt free {}, ${} in interest (saves ${})", &[&payoff_month(first, plan.months), &format!("{:.2}", plan.total_interest),
+                                                                                &format!("{:.2}", base.total_cost() - plan.total_cost())]));
+            if plan.total_penalties > 0f64 {
+                println!("  {}", trf("${} in prepayment penalties", &[&format!("{:.2}", plan.total_penalties)]));
+            }
+            println!("  {}", trf("{} of {} loans paid off within a year", &[&plan.closed_by(12), &debts.len()]));
+            if self.verbosity > 0 {
+                for &(ref name, month) in plan.payoffs.iter() {
+                    println!("  {}", trf("{} paid off {}", &[&name, &payoff_month(first, month)]));
+                }
+            }
+        }
+
+        if let (Some(avalanche), Some(snowball)) = (plans.get(0), plans.get(1)) {
+            println!("");
+            let saved = snowball.total_cost() - avalanche.total_cost();
+            let cost = if penalties { tr("interest and penalties") } else { tr("interest") };
+            if saved < 0f64 {
+                // a penalty on the highest rate loan can make up the difference
+                println!("{}", trf("Snowball saves ${} more in {}", &[&format!("{:.2}", -saved), &cost]));
+            } else if snowball.closed_by(12) > avalanche.closed_by(12) {
+                println!("{}", trf("Avalanche saves ${} more in {}; snowball closes {} more loans in the first year",
+                                   &[&format!("{:.2}", saved), &cost, &(snowball.closed_by(12) - avalanche.closed_by(12))]));
+            } else {
+                println!("{}", trf("Avalanche saves ${} more in {} and closes loans as quickly", &[&format!("{:.2}", saved), &cost]));
+            }
+        }
+        if let (Some(avalanche), Some(net)) = (plans.get(0), plans.get(2)) {
+            let saved = avalanche.total_cost() - net.total_cost();
+            if saved >= 0.005 {
+                let first = net.allocation.first().map_or(tr("none of them"), |&(ref name, _)| name.clone());
+                println!("{}", trf("Counting prepayment penalties, net saves ${} more than avalanche: put extra on {} first",
+                                   &[&format!("{:.2}", saved), &first]));
+            } else {
+                println!("{}", tr("Prepayment penalties don't change the avalanche order"));
             }
         }
     }
 
-    fn query_loan(&self, db: &Path, name: String) -> Option<Loan> {
-        let conn = Connection::open(db).unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, payment, balance, periods, apr, start_time, time_created FROM loans WHERE name = $0").unwrap();
-
-        let loan_iter = match stmt.query_map(&[&name], |row| {
-            Loan {
-                id: row.get(0),
-                name: row.get(1),
-                payment: row.get(2),
-                balance: row.get(3),
-                periods: row.get(4),
-                apr: row.get(5),
-                start_time: row.get(6),
-                time_created: row.get(7),
-            }
-        }) {
-            Ok(iter) => iter,
+    fn print_consolidation(&self, loans: &[Loan], consolidation: &Consolidation) {
+        let mut table = Table::new(&["loan", "balance", "apr", "payment"]);
+        for loan in loans {
+            table.push(vec![Cell::Text(loan.name.clone()), Cell::Number(format!("{:.2}", loan.balance)), Cell::Number(format!("{:.2}", loan.apr)),
+                            Cell::Number(format!("{:.2}", loan.payment))]);
+        }
+        self.render(&table);
+        if !self.plain() {
+            return;
+        }
+        println!("{}", trf("Current: ${}/mo, ${} in remaining interest", &[&format!("{:.2}", consolidation.old_payment),
+                                                                           &format!("{:.2}", consolidation.old_interest)]));
+        println!("{}", trf("Consolidated: ${} at ${}/mo, ${} in interest", &[&format!("{:.2}", consolidation.balance),
+                                                                             &format!("{:.2}", consolidation.new_payment),
+                                                                             &format!("{:.2}", consolidation.new_interest)]));
+        println!("{}", trf("Change: {}/mo, {} in interest", &[&format!("{:+.2}", consolidation.payment_change()),
+                                                              &format!("{:+.2}", consolidation.interest_change())]));
+    }
+
+    // Compares the loan as scheduled against the same loan under `scenario`.
+    fn print_scenario(&self, loan: &Loan, scenario: &Scenario) {
+        let first = next_due_month();
+        let base = loan.schedule().payoff();
+        let simulated = scenario.apply(loan.schedule(), first.tm_mon + 1).payoff();
+        println!("{}", trf("{}: Balance = ${}, APR = {}%", &[&loan.name, &format!("{:.2}", loan.balance), &format!("{:.2}", loan.apr)]));
+        println!("{}", trf("As scheduled: paid off {}, ${} in interest", &[&payoff_month(first, base.periods), &format!("{:.2}", base.total_interest)]));
+        println!("{}", trf("With changes: paid off {}, ${} in interest", &[&payoff_month(first, simulated.periods), &format!("{:.2}", simulated.total_interest)]));
+        println!("{}", trf("Change: {} months, {} in interest", &[&format!("{:+}", simulated.periods - base.periods),
+                                                                   &format!("{:+.2}", simulated.total_interest - base.total_interest)]));
+    }
+
+    // What putting the scenario off costs, for each delay up to `months`.
+    fn print_cost_of_waiting(&self, loan: &Loan, scenario: &Scenario, months: i32) {
+        let first = next_due_month();
+        let now = scenario.delayed_payoff(loan.schedule(), first.tm_mon + 1, 0);
+        let mut table = Table::new(&["delay", "paid off", "interest", "cost"]);
+        for delay in 0..months + 1 {
+            let payoff = scenario.delayed_payoff(loan.schedule(), first.tm_mon + 1, delay);
+            table.push(vec![Cell::Number(delay.to_string()), Cell::Text(payoff_month(first, payoff.periods)),
+                            Cell::Number(format!("{:.2}", payoff.total_interest)), Cell::Number(format!("{:.2}", payoff.total_interest - now.total_interest))]);
+        }
+        self.render(&table);
+        if self.plain() && months > 0 {
+            let waited = scenario.delayed_payoff(loan.schedule(), first.tm_mon + 1, months);
+            let cost = waited.total_interest - now.total_interest;
+            println!("{}", trf("Waiting {} months costs {} in interest, about {} a month", &[&months, &loan.money(cost), &loan.money(cost / months as f64)]));
+        }
+    }
+
+    // The regular monthly payments against true biweekly payments and
+    // against a twelfth of a payment extra each month, from the current
+    // balance.
+    fn print_strategies(&self, loan: &Loan) {
+        let first = next_due_month();
+        let monthly = loan.schedule().payoff();
+        let extra = loan.schedule().extra(loan.payment / 12.0).payoff();
+        let biweekly = calc::biweekly(loan.balance, loan.apr, loan.payment).payoff();
+        // the last biweekly payment, counting two weeks at a time from the
+        // first of next month
+        let mut last = first;
+        last.tm_mday += (biweekly.periods - 1) * 14;
+        let last = time::at_utc(last.to_timespec());
+
+        let mut table = Table::new(&["strategy", "payment", "paid off", "interest", "saved"]);
+        for &(strategy, payment, ref paid_off, payoff) in [
+            ("monthly", loan.payment, payoff_month(first, monthly.periods), monthly),
+            ("biweekly", loan.payment / 2.0, format!("{} {}", i18n::month_name(last.tm_mon + 1), last.tm_year + 1900), biweekly),
+            ("monthly + 1/12", loan.payment + loan.payment / 12.0, payoff_month(first, extra.periods), extra),
+        ].iter() {
+            table.push(vec![Cell::Text(strategy.to_string()), Cell::Number(loan.money(payment)), Cell::Text(paid_off.clone()),
+                            Cell::Number(loan.money(payoff.total_interest)), Cell::Number(loan.money(monthly.total_interest - payoff.total_interest))]);
+        }
+        self.render(&table);
+    }
+
+    // Each canonical loan and whether this build reproduces it, with the rows
+    // that don't match. Returns whether they all did.
+    fn print_selftest(&self, outcomes: &[selftest::Outcome]) -> bool {
+        let mut table = Table::new(&["case", "rows", "payments", "interest", "result"]);
+        for outcome in outcomes {
+            table.push(vec![Cell::Text(outcome.name.to_string()), Cell::Number(format!("{}/{}", outcome.rows - outcome.mismatches.len(), outcome.rows)),
+                            Cell::Number(outcome.periods.to_string()), Cell::Number(format!("{:.2}", outcome.total_interest)),
+                            Cell::Text(if outcome.passed() { "ok" } else { "MISMATCH" }.to_string())]);
+        }
+        self.render(&table);
+
+        let failed: Vec<&selftest::Outcome> = outcomes.iter().filter(|outcome| !outcome.passed()).collect();
+        if !self.plain() {
+            return failed.is_empty();
+        }
+        for outcome in failed.iter() {
+            println!("{}:", outcome.name);
+            if outcome.periods != outcome.expected_periods {
+                println!("  {}", trf("expected {} payments, got {}", &[&outcome.expected_periods, &outcome.periods]));
+            }
+            if (outcome.total_interest - outcome.expected_interest).abs() >= 0.01 {
+                println!("  {}", trf("expected {} in interest, got {}", &[&format!("{:.2}", outcome.expected_interest),
+                                                                          &format!("{:.2}", outcome.total_interest)]));
+            }
+            for delta in outcome.mismatches.iter() {
+                let expected = delta.expected.unwrap();
+                match delta.computed {
+                    Some(computed) => println!("  {}", trf("payment {}: expected {} = {} interest + {} principal leaving {}, got {} = {} + {} leaving {}",
+                                                           &[&delta.period, &format!("{:.2}", expected.payment), &format!("{:.2}", expected.interest),
+                                                             &format!("{:.2}", expected.principal), &format!("{:.2}", expected.balance),
+                                                             &format!("{:.2}", computed.payment), &format!("{:.2}", computed.interest),
+                                                             &format!("{:.2}", computed.principal), &format!("{:.2}", computed.balance)])),
+                    None => println!("  {}", trf("payment {}: missing", &[&delta.period])),
+                }
+            }
+        }
+        if failed.is_empty() {
+            println!("{}", trf("All {} loans match; this build computes schedules correctly.", &[&outcomes.len()]));
+        } else {
+            println!("{}", trf("{} of {} loans don't match. Don't trust this build with your numbers.", &[&failed.len(), &outcomes.len()]));
+        }
+        failed.is_empty()
+    }
+
+    // The base loan and each add-on with its part of the payment and of the
+    // interest over the life of the loan.
+    fn print_addons(&self, loan: &Loan, addons: &[AddOn]) {
+        let parts = loan.attribute(addons);
+        let payment: f64 = parts.iter().map(|part| part.payment).sum();
+        let interest: f64 = parts.iter().map(|part| part.interest).sum();
+        let share = |amount: f64, total: f64| if total > 0f64 { format!("{:.1}%", amount / total * 100.0) } else { "-".to_string() };
+
+        let mut table = Table::new(&["part", "financed", "months", "payment", "interest", "of payment", "of interest"]);
+        for part in parts.iter() {
+            table.push(vec![Cell::Text(part.name.clone()), Cell::Number(loan.money(part.financed)), Cell::Number(part.periods.to_string()),
+                            Cell::Number(loan.money(part.payment)), Cell::Number(loan.money(part.interest)),
+                            Cell::Number(share(part.payment, payment)), Cell::Number(share(part.interest, interest))]);
+        }
+        self.render(&table);
+        if self.plain() && addons.iter().any(|addon| addon.periods < loan.periods) {
+            println!("{}", tr("Once an add-on is paid for, its part of the payment goes to the base loan."));
+        }
+    }
+
+    // One row per scenario, each measured against its loan as scheduled.
+    fn print_scenarios(&self, db: &Path, scenarios: &[Scenario]) {
+        let first = next_due_month();
+        let mut table = Table::new(&["scenario", "loan", "paid off", "interest", "months", "change in interest"]);
+        for scenario in scenarios {
+            let loan = self.require_loan(db, &scenario.loan);
+            let base = loan.schedule().payoff();
+            let simulated = scenario.apply(loan.schedule(), first.tm_mon + 1).payoff();
+            table.push(vec![Cell::Text(scenario.name.clone()), Cell::Text(scenario.loan.clone()), Cell::Text(payoff_month(first, simulated.periods)),
+                            Cell::Number(format!("{:.2}", simulated.total_interest)), Cell::Number(format!("{:+}", simulated.periods - base.periods)),
+                            Cell::Number(format!("{:+.2}", simulated.total_interest - base.total_interest))]);
+        }
+        self.render(&table);
+    }
+
+    fn print_aprc(&self, db: &Path, loan: Loan, upfront_fees: f64, monthly_fee: f64) {
+        let financed: f64 = match amortization::load_fees(db, &loan.name) {
+            Ok(fees) => fees.iter().map(|fee| fee.amount).sum(),
             Err(err) => {
-                error!("Error with statement: {}", err);
+                error!("{}", trf("Error loading fees: {}", &[&err]));
                 std::process::exit(1);
             }
         };
 
-        for res in loan_iter {
-            return Some(res.unwrap());
+        let credit = loan.principal - financed;
+        let payments = loan.contract_schedule().map(|inst| inst.payment);
+        println!("{}", trf("{}: Credit = ${}, APR = {}%", &[&loan.name, &format!("{:.2}", credit), &format!("{:.2}", loan.apr)]));
+        println!("{}", trf("APRC: {}%", &[&format!("{:.1}", aprc::loan_aprc(credit, upfront_fees, payments, monthly_fee))]));
+    }
+
+    fn print_payoff(&self, quote: PayoffQuote) {
+        let mut table = Table::new(&["date", "principal", "accrued interest", "deferred interest", "total"]);
+        table.push(vec![Cell::Text(time::strftime("%F", &time::at_utc(quote.date)).unwrap()), Cell::Number(format!("{:.2}", quote.balance)),
+                        Cell::Number(format!("{:.2}", quote.accrued_interest)), Cell::Number(format!("{:.2}", quote.deferred_interest)),
+                        Cell::Number(format!("{:.2}", quote.total()))]);
+        self.render(&table);
+    }
+
+    // A group's sub-loans rolled up into one account.
+    fn print_group(&self, db: &Path, group: &LoanGroup) {
+        let loans = amortization::load_group_loans(db, &group.name).unwrap_or_else(|err| {
+            error!("{}", trf("Error with statement: {}", &[&err]));
+            std::process::exit(1);
+        });
+        let today = amortization::today();
+        let mut due = 0f64;
+        let mut table = Table::new(&["loan", "balance", "apr", "due", "status"]);
+        for loan in loans.iter().filter(|loan| loan.balance > 0f64) {
+            table.push(vec![Cell::Text(loan.name.clone()), Cell::Number(loan.money(loan.balance)), Cell::Number(format!("{:.2}", loan.apr)),
+                            Cell::Number(loan.money(loan.amount_due(loan.next_due(today)))), Cell::Text(self.paint_status(loan.status))]);
+            due += loan.amount_due(loan.next_due(today));
+        }
+        self.render(&table);
+        if !self.plain() {
+            return;
+        }
+        let balance: f64 = loans.iter().map(|loan| loan.balance).sum();
+        let weighted = if balance > 0f64 { loans.iter().map(|loan| loan.balance * loan.apr).sum::<f64>() / balance } else { 0f64 };
+        let allocation = match group.allocation {
+            Allocation::Standard => tr("to each in proportion to its payment"),
+            Allocation::Proportional => tr("to each in proportion to what's left on it"),
+            Allocation::HighestRate => tr("to the highest rate first"),
+            Allocation::LowestBalance => tr("to the smallest balance first"),
+        };
+        println!("{}", trf("{}: Balance = {}, APR = {}%, Payment = {}, extra goes {}",
+                           &[&group.name, &format!("{:.2}", balance), &format!("{:.2}", weighted), &format!("{:.2}", due), &allocation]));
+    }
+
+    // The sub-loans' remaining schedules added together.
+    fn print_group_schedule(&self, db: &Path, group: &LoanGroup) {
+        let loans = amortization::load_group_loans(db, &group.name).unwrap_or_else(|err| {
+            error!("{}", trf("Error with statement: {}", &[&err]));
+            std::process::exit(1);
+        });
+        let positions: Vec<Position> = loans.iter().filter(|loan| loan.balance > 0f64).map(|loan| Position{
+            balance: loan.balance,
+            apr: loan.apr,
+            payment: loan.payment,
+            principal: loan.principal,
+            rate: 1f64,
+            schedule: loan.schedule(),
+        }).collect();
+        let first = next_due_month();
+        let mut table = Table::new(&["month", "payment", "principal", "interest", "balance"]);
+        for point in portfolio::payoff_curve(&positions) {
+            table.push(vec![Cell::Text(payoff_month(first, point.period)), Cell::Number(format!("{:.2}", point.payment)),
+                            Cell::Number(format!("{:.2}", point.payment - point.interest)), Cell::Number(format!("{:.2}", point.interest)),
+                            Cell::Number(format!("{:.2}", point.balance))]);
         }
-        return None
+        self.render(&table);
     }
 
-    fn print_loans(&self, db: &Path) {
-        let conn = Connection::open(db).unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, payment, balance, periods, apr, start_time, time_created FROM loans").unwrap();
-
-        let loan_iter = match stmt.query_map(&[], |row| {
-            Loan {
-                id: row.get(0),
-                name: row.get(1),
-                payment: row.get(2),
-                balance: row.get(3),
-                periods: row.get(4),
-                apr: row.get(5),
-                start_time: row.get(6),
-                time_created: row.get(7),
-            }
-        }) {
-            Ok(iter) => iter,
+    fn print_group_payments(&self, db: &Path, group: &LoanGroup) {
+        let group_payments = amortization::load_group_payments(db, &group.name).unwrap_or_else(|err| {
+            error!("{}", trf("Error with statement: {}", &[&err]));
+            std::process::exit(1);
+        });
+        let mut table = Table::new(&["date", "paid", "allocation", "loan", "principal", "interest", "returned"]);
+        for group_payment in group_payments.iter() {
+            for payment in group_payment.payments.iter() {
+                table.push(vec![Cell::Text(time::strftime("%F", &time::at_utc(group_payment.date)).unwrap()), Cell::Number(format!("{:.2}", group_payment.amount)),
+                                Cell::Text(group_payment.allocation.as_str().to_string()), Cell::Text(payment.name.clone()),
+                                Cell::Number(format!("{:.2}", payment.principal)), Cell::Number(format!("{:.2}", payment.interest)),
+                                Cell::Text(if payment.reversed { "yes" } else { "" }.to_string())]);
+            }
+        }
+        self.render(&table);
+    }
+
+    fn print_lender(&self, db: &Path, name: &str) {
+        let lender = match amortization::load_lender(db, name) {
+            Ok(lender) => lender,
+            // linked by name before its details were added
+            Err(rusqlite::Error::QueryReturnedNoRows) => Lender{ name: name.to_string(), account_number: None, url: None, phone: None },
             Err(err) => {
-                error!("Error with statement: {}", err);
+                error!("{}", trf("Error with statement: {}", &[&err]));
                 std::process::exit(1);
             }
         };
 
-        for res in loan_iter {
-            let loan = res.unwrap();
-            self.print_loan(loan);
+        println!("{}", trf("Lender: {}", &[&lender.name]));
+        if let Some(account_number) = lender.account_number {
+            println!("  {}", trf("Account: {}", &[&account_number]));
+        }
+        if let Some(url) = lender.url {
+            println!("  {}", trf("Website: {}", &[&url]));
+        }
+        if let Some(phone) = lender.phone {
+            println!("  {}", trf("Phone: {}", &[&phone]));
         }
     }
-}
 
-fn create_loan_from_args(matches: &ArgMatches) -> Loan {
-    let name = matches.value_of("name").unwrap();
-    let balance: f64 = matches.value_of("balance").unwrap().parse().unwrap();
-    let apr: f64 = matches.value_of("apr").unwrap().parse().unwrap();
-    let term: i32 = matches.value_of("term").unwrap().parse().unwrap();
+    fn print_borrowers(&self, db: &Path, loan: &Loan) {
+        let borrowers = match amortization::load_borrowers(db, &loan.name) {
+            Ok(borrowers) => borrowers,
+            Err(err) => {
+                error!("{}", trf("Error with statement: {}", &[&err]));
+                std::process::exit(1);
+            }
+        };
+
+        let mut table = Table::new(&["borrower", "share", "balance"]);
+        for borrower in borrowers {
+            table.push(vec![Cell::Text(borrower.name), Cell::Number(format!("{:.2}", borrower.share)),
+                            Cell::Number(format!("{:.2}", loan.balance * borrower.share / 100.0))]);
+        }
+        self.render(&table);
+    }
 
-    let start_time: Timespec = if matches.is_present("start") {
-        match time::strptime(matches.value_of("start").unwrap(), "%F") {
-            Ok(t) => t.to_timespec(),
+    fn print_user_totals(&self, db: &Path) {
+        let totals = match amortization::user_totals(db) {
+            Ok(totals) => totals,
             Err(err) => {
-                error!("Error parsing time: {}", err);
+                error!("{}", trf("Error with statement: {}", &[&err]));
                 std::process::exit(1);
-            },
+            }
+        };
+
+        let mut table = Table::new(&["user", "loans", "balance", "paid", "interest"]);
+        for total in totals.into_iter().filter(|total| self.user.as_ref().map_or(true, |user| *user == total.name)) {
+            table.push(vec![Cell::Text(total.name), Cell::Number(total.loans.to_string()), Cell::Number(format!("{:.2}", total.balance)),
+                            Cell::Number(format!("{:.2}", total.paid)), Cell::Number(format!("{:.2}", total.interest))]);
         }
-    } else {
-        time::get_time()
-    };
+        self.render(&table);
+    }
 
-    Loan::new(name.to_string(), balance, term * 12, apr, start_time)
-}
+    fn print_borrower_totals(&self, db: &Path) {
+        let totals = match amortization::borrower_totals(db) {
+            Ok(totals) => totals,
+            Err(err) => {
+                error!("{}", trf("Error with statement: {}", &[&err]));
+                std::process::exit(1);
+            }
+        };
 
-fn create_transaction_from_args(matches: &ArgMatches) -> (String, f64, bool, Timespec){
-    let name = matches.value_of("name").unwrap();
-    let amount: f64 = matches.value_of("amount").unwrap().parse().unwrap();
-    let extra = matches.is_present("extra");
+        let mut table = Table::new(&["borrower", "balance", "paid", "interest"]);
+        for total in totals {
+            table.push(vec![Cell::Text(total.name), Cell::Number(format!("{:.2}", total.balance)), Cell::Number(format!("{:.2}", total.paid)),
+                            Cell::Number(format!("{:.2}", total.interest))]);
+        }
+        self.render(&table);
+    }
+
+    fn print_progress(&self, loan: &Loan, overlay: &Overlay) {
+        let mut first = time::at_utc(loan.start_time);
+        first.tm_mday = 1;
+        if self.plain() {
+            println!("{}", trf("{}: Balance = ${}, {} of {} payments due so far", &[&loan.name, &format!("{:.2}", loan.balance), &overlay.elapsed, &loan.periods]));
+            println!("{}", trf("As contracted: paid off {}", &[&payoff_month(first, overlay.expected_periods)]));
+            println!("{}", trf("As projected: paid off {}", &[&payoff_month(first, overlay.projected_periods)]));
+
+            let months = overlay.months_ahead();
+            let dollars = overlay.dollars_ahead();
+            if months >= 0 {
+                println!("{}", trf("Payoff: {} months early", &[&months]));
+            } else {
+                println!("{}", trf("Payoff: {} months late", &[&-months]));
+            }
+            if dollars >= 0f64 {
+                println!("{}", trf("Balance: ${} ahead of schedule", &[&format!("{:.2}", dollars)]));
+            } else {
+                println!("{}", trf("Balance: ${} behind schedule", &[&format!("{:.2}", -dollars)]));
+            }
+        }
+
+        // other tools always get the months; people ask for them with -v
+        if self.verbosity > 0 || !self.plain() {
+            let mut table = Table::new(&["month", "expected", "balance", "kind", "difference"]);
+            for row in overlay.rows.iter() {
+                let fmt = |balance: Option<f64>| balance.map(|balance| format!("{:.2}", balance)).unwrap_or_else(|| "-".to_string());
+                table.push(vec![Cell::Text(payoff_month(first, row.period)), Cell::Number(fmt(row.expected)), Cell::Number(fmt(row.projected)),
+                                Cell::Text(if row.period <= overlay.elapsed { "actual" } else { "projected" }.to_string()),
+                                Cell::Number(format!("{:+.2}", row.difference()))]);
+            }
+            self.render(&table);
+        }
+    }
 
-    let date: Timespec = if matches.is_present("date") {
-        match time::strptime(matches.value_of("date").unwrap(), "%F") {
-            Ok(t) => t.to_timespec(),
+    // Records each row as a regular or extra payment, as import::Classifier
+    // decides, skipping regular payments that fall short of the amount due.
+    // Pending payments are held for review instead of applied.
+    fn import_payments(&self, db: &Path, name: &str, rows: &[PaymentRow], pending: bool, force: bool) {
+        let loan = self.require_loan(db, name);
+        let mut classifier = match amortization::load_transactions(db, Some(name), None) {
+            Ok(transactions) => import::Classifier::for_loan(&loan, &transactions),
             Err(err) => {
-                error!("Error parsing time: {}", err);
+                error!("{}", trf("Error with statement: {}", &[&err]));
                 std::process::exit(1);
-            },
+            }
+        };
+        let (mut regular, mut extra, mut skipped) = (0, 0, 0);
+        for row in rows {
+            let loan = self.require_loan(db, name);
+            let due = loan.total_due(row.date);
+            let kind = classifier.classify(row, loan.period_at(row.date), due);
+            if kind == PaymentKind::Regular && row.amount < due {
+                println!("{}", trf("Skipping {} on {}: less than the {} due", &[&format!("{:.2}", row.amount),
+                                                                                &time::strftime("%F", &time::at_utc(row.date)).unwrap(), &format!("{:.2}", due)]));
+                skipped += 1;
+                continue;
+            }
+            let duplicate = if force { None } else { self.duplicate(db, name, row.amount, row.date) };
+            if let Some(payment) = duplicate {
+                println!("{}", trf("Skipping {} on {}: already recorded as #{} (use --force to record it anyway)",
+                                   &[&format!("{:.2}", row.amount), &time::strftime("%F", &time::at_utc(row.date)).unwrap(), &payment.id]));
+                skipped += 1;
+                continue;
+            }
+            let commit = if pending { amortization::commit_pending_transaction } else { amortization::commit_transaction };
+            match commit(db, name.to_string(), row.amount, kind == PaymentKind::Extra, row.date, row.method.clone()) {
+                Err(ref err) if amortization::refusal(err).is_some() => {
+                    println!("{}", trf("Skipping {} on {}: {}", &[&format!("{:.2}", row.amount), &time::strftime("%F", &time::at_utc(row.date)).unwrap(), &err]));
+                    skipped += 1;
+                    continue;
+                },
+                Err(err) => {
+                    println!("{}", trf("Error saving to database: {}", &[&err]));
+                    std::process::exit(1);
+                },
+                Ok(_) => (),
+            }
+            if kind == PaymentKind::Regular { regular += 1 } else { extra += 1 }
         }
-    } else {
-        time::get_time()
-    };
+        println!("{}", trf("Imported {} regular and {} extra payments{}, skipped {}",
+                           &[&regular, &extra, &(if pending { tr(" for review") } else { String::new() }), &skipped]));
+    }
 
-    (name.to_string(), amount, extra, date)
-}
+    // A payment that recording `amount` on `date` would likely duplicate.
+    fn duplicate(&self, db: &Path, name: &str, amount: f64, date: Timespec) -> Option<Transaction> {
+        amortization::find_duplicate(db, name, amount, date).unwrap_or_else(|err| {
+            error!("{}", trf("Error with statement: {}", &[&err]));
+            std::process::exit(1);
+        })
+    }
 
-fn main() {
-    env_logger::init().unwrap();
+    // Records the payments scheduled up to today as pending, for a loan
+    // started in the past.
+    fn backfill(&self, db: &Path, name: &str) {
+        let payments = amortization::backfill_payments(db, name, amortization::today()).unwrap_or_else(|err| {
+            println!("{}", trf("Error saving to database: {}", &[&err]));
+            std::process::exit(1);
+        });
+        if payments.is_empty() {
+            println!("{}", tr("No scheduled payments to fill in"));
+            return;
+        }
+        self.print_pending(db, name);
+    }
 
-    let matches = App::new("Amortization Calculator")
-                          .version("0.1.0")
-                          .author("T. Jameson Little <t.jameson.little@gmail.com>")
-                          .about("Calculates an amortization table")
-                          .arg(Arg::with_name("DB")
-                               .help("Database to use")
-                               .index(1))
-                          .arg(Arg::with_name("loan")
-                               .help("Loan to query")
-                               .index(2))
-                          .arg(Arg::with_name("v")
+    // A loan's payments awaiting review, and what the balance comes to once
+    // they're confirmed.
+    fn print_pending(&self, db: &Path, name: &str) {
+        let loan = self.require_loan(db, name);
+        let pending: Vec<Transaction> = match amortization::load_transactions(db, Some(name), None) {
+            Ok(transactions) => transactions.into_iter().filter(|transaction| transaction.provisional).collect(),
+            Err(err) => {
+                error!("{}", trf("Error with statement: {}", &[&err]));
+                std::process::exit(1);
+            }
+        };
+        if pending.is_empty() && self.plain() {
+            println!("{}", trf("No payments on {} awaiting review", &[&loan.name]));
+            return;
+        }
+        let mut table = Table::new(&["id", "date", "kind", "paid", "principal", "interest"]);
+        for payment in pending.iter() {
+            table.push(vec![Cell::Number(payment.id.to_string()), Cell::Text(time::strftime("%F", &time::at_utc(payment.date)).unwrap()),
+                            Cell::Text(payment.kind.as_str().to_string()), Cell::Number(loan.money(payment.principal + payment.interest)),
+                            Cell::Number(loan.money(payment.principal)), Cell::Number(loan.money(payment.interest))]);
+        }
+        self.render(&table);
+        if !self.plain() {
+            return;
+        }
+        let principal = pending.iter().fold(0f64, |sum, payment| sum + payment.principal);
+        println!("{}", trf("{} payments awaiting review. Balance = {}, about {} once they're confirmed",
+                           &[&pending.len(), &loan.money(loan.balance), &loan.money(loan.balance - principal)]));
+        println!("{}", tr("Confirm them with pending --confirm, or reject them with pending --reject"));
+    }
+
+    // What's owed, where the next payment goes and when it'll be done, for
+    // --simple.
+    fn explain_loan(&self, loan: &Loan) {
+        if loan.balance <= 0f64 {
+            println!("{}", trf("{} is paid off. Nothing more is owed!", &[&loan.name]));
+            return;
+        }
+        let payoff = loan.schedule().payoff();
+        let next = loan.schedule().next();
+        if loan.lent {
+            println!("{}", trf("{}: you're owed {} and are paid {} a month.", &[&loan.name, &whole(loan.balance), &whole(loan.payment)]));
+            if let Some(next) = next {
+                println!("{}", trf("Of the next payment, {} is interest, what they pay you for lending the money, and {} pays back what they borrowed.",
+                                   &[&whole(next.interest), &whole(next.payment.round() - next.interest.round())]));
+            }
+            println!("{}", trf("They'll have paid it all back after {} more payments ({}), with {} in interest.",
+                               &[&payoff.periods, &payoff_month(next_due_month(), payoff.periods), &whole(payoff.total_interest)]));
+        } else {
+            println!("{}", trf("{}: you owe {} and pay {} a month.", &[&loan.name, &whole(loan.balance), &whole(loan.payment)]));
+            if let Some(next) = next {
+                println!("{}", trf("Of the next payment, {} goes to interest, what the lender charges for lending you the money, and {} reduces what you owe.",
+                                   &[&whole(next.interest), &whole(next.payment.round() - next.interest.round())]));
+            }
+            println!("{}", trf("Keep it up and you'll owe nothing after {} more payments ({}), having paid {} in interest.",
+                               &[&payoff.periods, &payoff_month(next_due_month(), payoff.periods), &whole(payoff.total_interest)]));
+        }
+    }
+
+    // A sentence on what happened to the money, for --simple.
+    fn explain_transaction(&self, transaction: &Transaction) {
+        let date = time::strftime("%F", &time::at_utc(transaction.date)).unwrap();
+        let amount = whole(transaction.principal);
+        match transaction.kind {
+            TransactionKind::Payment | TransactionKind::Extra => {
+                // rounded so the parts add up to what was paid
+                let paid = (transaction.principal + transaction.interest + transaction.escrow + transaction.pmi).round();
+                let (interest, escrow) = (transaction.interest.round(), (transaction.escrow + transaction.pmi).round());
+                let principal = whole(paid - interest - escrow);
+                print!("{}", trf("On {} you paid {} on {}: ", &[&date, &whole(paid), &transaction.name]));
+                if interest > 0f64 {
+                    print!("{}", trf("{} went to interest and {} reduced what you owe", &[&whole(interest), &principal]));
+                } else {
+                    print!("{}", trf("all {} reduced what you owe", &[&principal]));
+                }
+                if escrow > 0f64 {
+                    print!("{}", trf(", and {} went to taxes and insurance", &[&whole(escrow)]));
+                }
+                println!(".");
+            },
+            TransactionKind::Fee => println!("{}", trf("On {} you were charged a {} fee on {}, added to what you owe.", &[&date, &amount, &transaction.name])),
+            TransactionKind::Draw => println!("{}", trf("On {} you borrowed {} more on {}.", &[&date, &amount, &transaction.name])),
+            TransactionKind::Reversal => println!("{}", trf("On {} a payment on {} bounced, so {} was added back to what you owe.",
+                                                            &[&date, &transaction.name, &amount])),
+            TransactionKind::Capitalization => println!("{}", trf("On {} {} of unpaid interest on {} was added to what you owe.",
+                                                                  &[&date, &amount, &transaction.name])),
+            TransactionKind::Adjustment if transaction.principal > 0f64 => println!("{}", trf("On {} what you owe on {} was corrected down by {}.",
+                                                                                              &[&date, &transaction.name, &amount])),
+            TransactionKind::Adjustment => println!("{}", trf("On {} what you owe on {} was corrected up by {}.", &[&date, &transaction.name, &amount])),
+            TransactionKind::Payoff => println!("{}", trf("On {} the {} left on {} was paid off by a new loan.", &[&date, &amount, &transaction.name])),
+        }
+    }
+
+    fn print_history(&self, db: &Path, name: Option<&str>, method: Option<&str>) {
+        let transactions = match amortization::load_transactions(db, name, method) {
+            Ok(transactions) => transactions,
+            Err(err) => {
+                error!("{}", trf("Error with statement: {}", &[&err]));
+                std::process::exit(1);
+            }
+        };
+        let owned: Option<Vec<String>> = self.user.as_ref().map(|_| self.require_loans(db).into_iter().map(|loan| loan.name).collect());
+        let transactions = transactions.into_iter().filter(|transaction| owned.as_ref().map_or(true, |loans| loans.contains(&transaction.name)));
+
+        if self.simple {
+            for transaction in transactions.filter(|transaction| !transaction.reversed && !transaction.provisional) {
+                self.explain_transaction(&transaction);
+            }
+            return;
+        }
+        let mut table = Table::new(&schema::HISTORY.columns());
+        for transaction in transactions {
+            let state = if transaction.reversed { "reversed" } else if transaction.provisional { "pending" } else { "" };
+            table.push(vec![Cell::Number(transaction.id.to_string()), Cell::Text(time::strftime("%F", &time::at_utc(transaction.date)).unwrap()),
+                            Cell::Text(transaction.name), Cell::Text(transaction.kind.as_str().to_string()),
+                            Cell::Number(format!("{:.2}", transaction.principal)), Cell::Number(format!("{:.2}", transaction.interest)),
+                            Cell::Number(format!("{:.2}", transaction.escrow)), Cell::Number(format!("{:.2}", transaction.pmi)),
+                            Cell::Text(transaction.payment_method.unwrap_or_default()), Cell::Text(state.to_string())]);
+        }
+        self.render(&table);
+    }
+
+    // Savings from extra payments on the loans shown, most first.
+    fn require_savings(&self, db: &Path) -> Vec<ExtraSavings> {
+        let shown: Vec<String> = self.require_loans(db).into_iter().map(|loan| loan.name).collect();
+        match amortization::extra_savings(db) {
+            Ok(savings) => savings.into_iter().filter(|saving| shown.contains(&saving.loan)).collect(),
+            Err(err) => {
+                error!("{}", trf("Error calculating savings: {}", &[&err]));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Loans ranked by the interest their extra payments have saved.
+    fn print_savings(&self, db: &Path) {
+        let savings = self.require_savings(db);
+        if savings.is_empty() && self.plain() {
+            println!("{}", tr("No extra payments yet. See what they would save with simulate --extra."));
+            return;
+        }
+        let mut table = Table::new(&["rank", "loan", "extra paid", "interest saved", "months saved"]);
+        for (i, saving) in savings.iter().enumerate() {
+            table.push(vec![Cell::Number((i + 1).to_string()), Cell::Text(saving.loan.clone()), Cell::Number(format!("{:.2}", saving.extra)),
+                            Cell::Number(format!("{:.2}", saving.interest_saved)), Cell::Number(saving.months_saved.to_string())]);
+        }
+        self.render(&table);
+        if self.plain() && savings.iter().all(|saving| saving.currency == savings[0].currency) {
+            println!("{}", trf("Extra payments of {} {} have saved {} in interest so far.",
+                               &[&format!("{:.2}", savings.iter().map(|saving| saving.extra).sum::<f64>()), &savings[0].currency,
+                                 &format!("{:.2}", savings.iter().map(|saving| saving.interest_saved).sum::<f64>())]));
+        }
+    }
+
+    // The closing summary for `name`, or exits if it isn't paid off.
+    fn require_closing(&self, db: &Path, loan: &Loan) -> ClosingSummary {
+        match amortization::closing_summary(db, &loan.name) {
+            Ok(Some(summary)) => summary,
+            Ok(None) => {
+                println!("{}", trf("{} isn't paid off yet; {} remains.", &[&loan.name, &loan.money(loan.balance)]));
+                std::process::exit(1);
+            },
+            Err(err) => {
+                error!("{}", trf("Error loading closing summary: {}", &[&err]));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // `name`'s closing summary, as a certificate or a one-row table, or
+    // written to `file` as a certificate.
+    fn print_closing(&self, db: &Path, name: &str, file: Option<&str>) {
+        let loan = self.require_loan(db, name);
+        let summary = self.require_closing(db, &loan);
+        if let Some(path) = file {
+            if let Err(err) = File::create(path).and_then(|f| export::closing_text(&mut BufWriter::new(f), &loan, &summary)) {
+                error!("{}", trf("Error writing {}: {}", &[&path, &err]));
+                std::process::exit(1);
+            }
+            println!("{}", trf("Closing summary for {} written to {}", &[&loan.name, &path]));
+            return;
+        }
+        if self.plain() {
+            export::closing_text(&mut io::stdout(), &loan, &summary).unwrap();
+            return;
+        }
+        let mut table = Table::new(&["loan", "paid off", "scheduled", "payments", "total paid", "total interest", "months saved"]);
+        table.push(vec![Cell::Text(summary.loan.clone()), Cell::Text(time::strftime("%F", &time::at_utc(summary.paid_off)).unwrap()),
+                        Cell::Text(time::strftime("%F", &time::at_utc(summary.scheduled)).unwrap()), Cell::Number(summary.payments.to_string()),
+                        Cell::Number(loan.money(summary.total_paid)), Cell::Number(loan.money(summary.total_interest)),
+                        Cell::Number(summary.months_saved().to_string())]);
+        self.render(&table);
+    }
+
+    // Whether the loan is shown under --user.
+    fn owned(&self, loan: &Loan) -> bool {
+        self.user.is_none() || loan.owner == self.user
+    }
+
+    // Loads every loan, with statuses brought up to date, or only the user's
+    // with --user.
+    fn require_loans(&self, db: &Path) -> Vec<Loan> {
+        match amortization::refresh_statuses(db).and_then(|_| amortization::load_loans(db)) {
+            Ok(loans) => loans.into_iter().filter(|loan| self.owned(loan)).collect(),
+            Err(err) => {
+                error!("{}", trf("Error with statement: {}", &[&err]));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    fn require_rates(&self, db: &Path) -> Rates {
+        match amortization::load_rates(db) {
+            Ok(rates) => rates,
+            Err(err) => {
+                error!("{}", trf("Error loading exchange rates: {}", &[&err]));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Converts `amount` to the reporting currency, exiting if there's no
+    // rate for it.
+    fn convert(&self, rates: &Rates, amount: f64, from: &str, to: &str) -> f64 {
+        match rates.convert(amount, from, to) {
+            Some(amount) => amount,
+            None => {
+                println!("{}", trf("No exchange rate between {} and {}. Add one with the rate command.", &[&from, &to]));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Records the milestones reached since they were last checked, running
+    // `notify` through the shell for each with AMORT_LOAN, AMORT_MILESTONE and
+    // AMORT_MESSAGE set, e.g. to pass them on to notify-send.
+    fn check_milestones(&self, db: &Path, notify: Option<&str>) -> Vec<MilestoneEvent> {
+        let events = amortization::check_milestones(db, amortization::today()).unwrap_or_else(|err| {
+            error!("{}", trf("Error checking milestones: {}", &[&err]));
+            std::process::exit(1);
+        });
+        let owned: Option<Vec<String>> = self.user.as_ref().map(|_| self.require_loans(db).into_iter().map(|loan| loan.name).collect());
+        let events: Vec<MilestoneEvent> = events.into_iter().filter(|event| owned.as_ref().map_or(true, |loans| loans.contains(&event.loan))).collect();
+        if let Some(command) = notify {
+            for event in events.iter() {
+                let status = Command::new("sh").arg("-c").arg(command)
+                    .env("AMORT_LOAN", &event.loan)
+                    .env("AMORT_MILESTONE", event.milestone.as_str())
+                    .env("AMORT_MESSAGE", event.milestone.describe(&event.loan))
+                    .status();
+                match status {
+                    Ok(status) if status.success() => {},
+                    Ok(status) => warn!("{}", trf("Notification command failed: {}", &[&status])),
+                    Err(err) => warn!("{}", trf("Couldn't run notification command: {}", &[&err])),
+                }
+            }
+        }
+        events
+    }
+
+    // Every milestone recorded for `name`, or all loans, after checking for
+    // new ones.
+    fn print_milestones(&self, db: &Path, name: Option<&str>, notify: Option<&str>) {
+        self.check_milestones(db, notify);
+        let events = amortization::load_milestones(db, name).unwrap_or_else(|err| {
+            error!("{}", trf("Error loading milestones: {}", &[&err]));
+            std::process::exit(1);
+        });
+        let owned: Option<Vec<String>> = self.user.as_ref().map(|_| self.require_loans(db).into_iter().map(|loan| loan.name).collect());
+        let mut table = Table::new(&["date", "loan", "milestone"]);
+        for event in events.into_iter().filter(|event| owned.as_ref().map_or(true, |loans| loans.contains(&event.loan))) {
+            table.push(vec![Cell::Text(time::strftime("%F", &time::at_utc(event.date)).unwrap()), Cell::Text(event.loan),
+                            Cell::Text(event.milestone.as_str().to_string())]);
+        }
+        self.render(&table);
+    }
+
+    fn print_status(&self, db: &Path, reporting: &str) {
+        let plain = self.plain();
+        for event in self.check_milestones(db, None) {
+            if plain {
+                println!("{}", trf("Milestone: {}", &[&event.milestone.describe(&event.loan)]));
+            }
+        }
+        let rates = self.require_rates(db);
+        let loans = self.require_loans(db);
+        // balances in other currencies get a column converted to the
+        // reporting one
+        let mut columns = vec!["loan", "balance", "currency"];
+        let converted = loans.iter().any(|loan| loan.currency != reporting);
+        if converted {
+            columns.push(reporting);
+        }
+        columns.extend_from_slice(&["payment", "lent", "lender", "collateral", "ltv", "status"]);
+        let mut table = Table::new(&columns);
+        let mut notes = Vec::new();
+        let mut positions = Vec::new();
+        let mut receivable = 0f64;
+        let mut equity = 0f64;
+        for loan in loans {
+            let rate = self.convert(&rates, 1f64, &loan.currency, reporting);
+            let mut row = vec![Cell::Text(loan.name.clone()), Cell::Number(loan.money(loan.balance)), Cell::Text(loan.currency.clone())];
+            if converted {
+                row.push(Cell::Number(format!("{:.2}", loan.balance * rate)));
+            }
+            row.push(Cell::Number(loan.money(loan.payment)));
+            row.push(Cell::Text(if loan.lent { "yes" } else { "" }.to_string()));
+            row.push(Cell::Text(loan.lender.clone().unwrap_or_default()));
+            // money lent out isn't debt
+            if loan.lent {
+                row.extend(vec![Cell::Text(String::new()), Cell::Text(String::new()), Cell::Text(self.paint_status(loan.status))]);
+                table.push(row);
+                receivable += loan.balance * rate;
+                continue;
+            }
+            if loan.repayment == calc::Repayment::Bullet && loan.balance > 0f64 {
+                let warning = if loan.maturity_due_soon(amortization::today()) { tr("Warning: ") } else { String::new() };
+                notes.push(trf("{}{}: principal of {} due at maturity on {}",
+                               &[&warning, &loan.name, &loan.money(loan.balance), &time::strftime("%F", &time::at_utc(loan.maturity())).unwrap()]));
+            }
+            match self.require_ltv(db, &loan.name).last() {
+                Some(point) => {
+                    row.push(Cell::Number(format!("{:.2}", point.value)));
+                    row.push(Cell::Number(format!("{:.1}", point.ltv())));
+                    equity += point.equity() * rate;
+                },
+                None => row.extend(vec![Cell::Text(String::new()), Cell::Text(String::new())]),
+            }
+            row.push(Cell::Text(self.paint_status(loan.status)));
+            table.push(row);
+            positions.push(Position{
+                balance: loan.balance,
+                apr: loan.apr,
+                payment: loan.payment,
+                principal: loan.principal,
+                rate: rate,
+                schedule: loan.schedule(),
+            });
+        }
+
+        let balance: f64 = positions.iter().map(|pos| pos.balance * pos.rate).sum();
+        let curve = portfolio::payoff_curve(&positions);
+        let first = next_due_month();
+        let assets = self.total_assets(db, &rates, reporting, amortization::today());
+        let savings = self.require_savings(db);
+        let saved: f64 = savings.iter().map(|saving| self.convert(&rates, saving.interest_saved, &saving.currency, reporting)).sum();
+        if !plain {
+            // adding zero turns the -0 an empty sum comes to into 0
+            let amount = |value: f64| Cell::Number(format!("{:.2}", value + 0f64));
+            let mut table = Table::new(&schema::SUMMARY.columns());
+            table.push(vec![Cell::Text(reporting.to_string()), amount(balance), amount(portfolio::monthly_obligation(&positions)),
+                            amount(portfolio::weighted_apr(&positions)), amount(receivable), amount(equity), amount(assets),
+                            Cell::Text(if curve.is_empty() { String::new() } else { payoff_month(first, curve.len() as i32) }), amount(saved)]);
+            self.render(&table);
+            return;
+        }
+        self.render(&table);
+        for note in notes {
+            println!("{}", note);
+        }
+        println!("{}", trf("Total balance: {} {}", &[&format!("{:.2}", balance), &reporting]));
+        println!("{}", trf("Total monthly payment: {} {}", &[&format!("{:.2}", portfolio::monthly_obligation(&positions)), &reporting]));
+        println!("{}", trf("Weighted average APR: {}%", &[&format!("{:.2}", portfolio::weighted_apr(&positions))]));
+        if receivable > 0f64 {
+            println!("{}", trf("Total owed to you: {} {}", &[&format!("{:.2}", receivable), &reporting]));
+        }
+        if equity != 0f64 {
+            println!("{}", trf("Total equity: {} {}", &[&format!("{:.2}", equity), &reporting]));
+        }
+        if assets != 0f64 {
+            println!("{}", trf("Total assets: {} {}", &[&format!("{:.2}", assets), &reporting]));
+            println!("{}", trf("Net position: {} {}", &[&format!("{:.2}", assets + receivable - balance), &reporting]));
+        }
+        if !curve.is_empty() {
+            println!("{}", trf("Debt free: {}", &[&payoff_month(first, curve.len() as i32)]));
+        }
+        if let Some(best) = savings.first() {
+            println!("{}", trf("Saved by extra payments so far: {} {} in interest, most on {} ({} months sooner)",
+                               &[&format!("{:.2}", saved), &reporting, &best.loan, &best.months_saved]));
+        }
+
+        if self.verbosity > 0 {
+            let mut table = Table::new(&["month", "balance", "of financed", "monthly interest"]);
+            for point in curve.iter().filter(|point| point.period % 12 == 0 || point.period == curve.len() as i32) {
+                table.push(vec![Cell::Text(payoff_month(first, point.period)), Cell::Number(format!("{:.2}", point.balance)),
+                                Cell::Number(format!("{:.1}%", point.debt_ratio * 100.0)), Cell::Number(format!("{:.2}", point.interest))]);
+            }
+            self.render(&table);
+            if assets != 0f64 {
+                self.print_net_position(db, &rates, reporting);
+            }
+        }
+    }
+
+    // Assets as of `date`, in the reporting currency.
+    fn total_assets(&self, db: &Path, rates: &Rates, reporting: &str, date: Timespec) -> f64 {
+        let assets = amortization::load_assets(db, date).unwrap_or_else(|err| {
+            error!("{}", trf("Error loading assets: {}", &[&err]));
+            std::process::exit(1);
+        });
+        assets.iter().map(|asset| self.convert(rates, asset.value, &asset.currency, reporting)).sum()
+    }
+
+    // Assets less debts at the start of each of the last twelve months,
+    // converted at today's exchange rates.
+    fn print_net_position(&self, db: &Path, rates: &Rates, reporting: &str) {
+        let mut date = time::at_utc(amortization::today());
+        date.tm_mday = 1;
+        date.tm_hour = 0;
+        date.tm_min = 0;
+        date.tm_sec = 0;
+        date.tm_nsec = 0;
+        date.tm_year -= 1;
+        let mut table = Table::new(&["month", "net position", "currency"]);
+        for _ in 0..12 {
+            date.tm_mon += 1;
+            if date.tm_mon == 12 {
+                date.tm_mon = 0;
+                date.tm_year += 1;
+            }
+            let at = date.to_timespec();
+            let balances = amortization::balances_at(db, at).unwrap_or_else(|err| {
+                error!("{}", trf("Error calculating balances: {}", &[&err]));
+                std::process::exit(1);
+            });
+            let mut net = self.total_assets(db, rates, reporting, at);
+            for &(ref loan, balance) in balances.iter() {
+                let balance = self.convert(rates, balance, &loan.currency, reporting);
+                net += if loan.lent { balance } else { -balance };
+            }
+            table.push(vec![Cell::Text(time::strftime("%Y-%m", &date).unwrap()), Cell::Number(format!("{:.2}", net)), Cell::Text(reporting.to_string())]);
+        }
+        self.render(&table);
+    }
+
+    fn print_assets(&self, db: &Path) {
+        let assets = amortization::load_assets(db, amortization::today()).unwrap_or_else(|err| {
+            error!("{}", trf("Error loading assets: {}", &[&err]));
+            std::process::exit(1);
+        });
+        let mut table = Table::new(&["asset", "value", "currency", "date"]);
+        for asset in assets.iter() {
+            table.push(vec![Cell::Text(asset.name.clone()), Cell::Number(format!("{:.2}", asset.value)), Cell::Text(asset.currency.clone()),
+                            Cell::Text(time::strftime("%F", &time::at_utc(asset.date)).unwrap())]);
+        }
+        self.render(&table);
+    }
+
+    // Total payments due across all loans, except those lent out, for each of
+    // the next `months`.
+    fn print_cashflow(&self, db: &Path, reporting: &str, months: usize) {
+        let rates = self.require_rates(db);
+        let mut totals = vec![0f64; months];
+        for loan in self.require_loans(db).into_iter().filter(|loan| !loan.lent) {
+            for (i, inst) in loan.schedule().take(months).enumerate() {
+                totals[i] += self.convert(&rates, inst.payment, &loan.currency, reporting);
+            }
+        }
+
+        let mut table = Table::new(&["month", "payments", "currency"]);
+        let mut date = time::at_utc(amortization::today());
+        date.tm_mday = 1;
+        for total in totals {
+            date.tm_mon += 1;
+            if date.tm_mon == 12 {
+                date.tm_mon -= 12;
+                date.tm_year += 1;
+            }
+            table.push(vec![Cell::Text(time::strftime("%Y-%m", &date).unwrap()), Cell::Number(format!("{:.2}", total)), Cell::Text(reporting.to_string())]);
+        }
+        self.render(&table);
+    }
+
+    fn print_resets(&self, loan: &Loan, index: &str) {
+        println!("{}", trf("Adjustable: {} + {}%, resets after {} months, then every {} months",
+                           &[&index, &format!("{:.2}", loan.margin), &loan.fixed_periods, &loan.reset_periods]));
+        let today = amortization::today();
+        match loan.rate_resets.iter().find(|&&(period, _)| loan.due_date(period) >= today) {
+            Some(&(period, apr)) => {
+                println!("{}", trf("Next reset: {} to {}% (projected)", &[&time::strftime("%F", &time::at_utc(loan.due_date(period))).unwrap(),
+                                                                          &format!("{:.3}", apr)]));
+            },
+            None if loan.rate_resets.len() < loan.reset_periods().len() => println!("{}", trf("No {} values stored to project resets from", &[&index])),
+            None => {},
+        }
+    }
+
+    fn require_ltv(&self, db: &Path, name: &str) -> Vec<LtvPoint> {
+        amortization::ltv_history(db, name).unwrap_or_else(|err| {
+            error!("{}", trf("Error loading valuations: {}", &[&err]));
+            std::process::exit(1);
+        })
+    }
+
+    // Loan-to-value at each valuation, and when PMI can come off based on
+    // the original value.
+    fn print_collateral(&self, db: &Path, loan: &Loan) {
+        let points = self.require_ltv(db, &loan.name);
+        let (first, current) = match (points.first(), points.last()) {
+            (Some(first), Some(current)) => (first, current),
+            _ => {
+                println!("{}", trf("{}: no collateral valuations recorded", &[&loan.name]));
+                return;
+            },
+        };
+        let mut table = Table::new(&["date", "value", "balance", "ltv", "equity"]);
+        for point in points.iter() {
+            table.push(vec![Cell::Text(time::strftime("%F", &time::at_utc(point.date)).unwrap()), Cell::Number(format!("{:.2}", point.value)),
+                            Cell::Number(format!("{:.2}", point.balance)), Cell::Number(format!("{:.1}", point.ltv())),
+                            Cell::Number(format!("{:.2}", point.equity()))]);
+        }
+        self.render(&table);
+        if !self.plain() {
+            return;
+        }
+
+        if current.ltv() <= amortization::PMI_REQUEST_LTV {
+            println!("{}", trf("At or below {}% LTV: PMI can be cancelled on request (the lender may require an appraisal)",
+                               &[&format!("{:.0}", amortization::PMI_REQUEST_LTV)]));
+        }
+        for &(ltv, on_request) in [(amortization::PMI_REQUEST_LTV, true), (amortization::PMI_TERMINATION_LTV, false)].iter() {
+            if let Some(date) = loan.scheduled_ltv(first.value, ltv) {
+                let args: [&dyn fmt::Display; 2] = [&time::strftime("%F", &time::at_utc(date)).unwrap(), &format!("{:.0}", ltv)];
+                if on_request {
+                    println!("{}", trf("PMI can be cancelled on request from {} ({}% of the original value)", &args));
+                } else {
+                    println!("{}", trf("PMI ends automatically from {} ({}% of the original value)", &args));
+                }
+            }
+        }
+    }
+
+    // Draws taken on a construction loan so far, and what it'll cost once
+    // it converts.
+    fn print_draws(&self, db: &Path, loan: &Loan) {
+        let draws = amortization::load_draws(db, &loan.name).unwrap_or_else(|err| {
+            error!("{}", trf("Error loading draws: {}", &[&err]));
+            std::process::exit(1);
+        });
+        let mut table = Table::new(&["date", "drawn"]);
+        for draw in draws.iter() {
+            table.push(vec![Cell::Text(time::strftime("%F", &time::at_utc(draw.date)).unwrap()), Cell::Number(loan.money(draw.amount))]);
+        }
+        self.render(&table);
+        if !self.plain() {
+            return;
+        }
+        println!("{}", trf("Balance: {} {}", &[&loan.money(loan.balance), &loan.currency]));
+        if loan.draw_periods > 0 && loan.draw_periods < loan.periods {
+            println!("{}", trf("Interest-only through {}, then monthly payment: {}",
+                               &[&time::strftime("%F", &time::at_utc(loan.due_date(loan.draw_periods))).unwrap(), &loan.money(loan.payment)]));
+        }
+    }
+
+    // Compares the loan's schedule with the lender's, row by row, then which
+    // rounding settings come closest to it.
+    fn print_validation(&self, loan: &Loan, expected: &[calc::Installment]) {
+        let deltas = validate::compare(expected, loan.contract_schedule());
+        let fmt = |delta: Option<f64>| Cell::Number(delta.map_or("missing".to_string(), |delta| format!("{:+.2}", delta)));
+        let mut table = Table::new(&["period", "payment", "interest", "principal", "balance"]);
+        for delta in deltas.iter().filter(|delta| !delta.matches() || self.verbosity > 0) {
+            table.push(vec![Cell::Number(delta.period.to_string()), fmt(delta.payment()), fmt(delta.interest()), fmt(delta.principal()), fmt(delta.balance())]);
+        }
+        self.render(&table);
+        if !self.plain() {
+            return;
+        }
+        let matched = deltas.iter().filter(|delta| delta.matches()).count();
+        println!("{}", trf("{}: {} of {} rows match (rounding: interest {}, payment {})",
+                           &[&loan.name, &matched, &deltas.len(), &loan.interest_rounding, &loan.payment_rounding]));
+        if matched == deltas.len() {
+            return;
+        }
+
+        let fits = validate::fits(expected, |interest, payment| {
+            let mut loan = loan.clone();
+            loan.set_rounding(interest, payment);
+            loan.contract_schedule()
+        });
+        println!("{}", tr("Closest rounding settings:"));
+        let mut table = Table::new(&["interest rounding", "payment rounding", "rows matched", "off by at most"]);
+        for fit in fits.iter().take(3) {
+            table.push(vec![Cell::Text(fit.interest.to_string()), Cell::Text(fit.payment.to_string()), Cell::Number(format!("{}/{}", fit.matched, fit.rows)),
+                            Cell::Number(format!("{:.2}", fit.max_delta))]);
+        }
+        self.render(&table);
+    }
+
+    // Each index's latest value, or every value with `all`.
+    fn print_indexes(&self, indexes: &[RateIndex], all: bool) {
+        let mut table = Table::new(&["index", "value", "date"]);
+        for index in indexes.iter() {
+            let values = if all { &index.values[..] } else { &index.values[index.values.len().saturating_sub(1)..] };
+            for &(date, value) in values {
+                table.push(vec![Cell::Text(index.name.clone()), Cell::Number(format!("{:.3}", value)), Cell::Text(time::strftime("%F", &time::at_utc(date)).unwrap())]);
+            }
+        }
+        self.render(&table);
+    }
+
+    // The loan as a row of schema::LIST.
+    fn list_row(&self, loan: &Loan) -> Vec<Cell> {
+        vec![Cell::Text(loan.name.clone()), Cell::Number(loan.money(loan.balance)), Cell::Number(format!("{:.3}", loan.apr)),
+             Cell::Number(loan.money(loan.payment)), Cell::Text(loan.currency.clone()), Cell::Text(loan.status.as_str().to_string()),
+             Cell::Text(time::strftime("%F", &time::at_utc(loan.maturity())).unwrap()), Cell::Text(loan.lender.clone().unwrap_or_default()),
+             Cell::Text(loan.lent.to_string())]
+    }
+
+    fn print_loans(&self, db: &Path) {
+        if !self.plain() {
+            let mut table = Table::new(&schema::LIST.columns());
+            for loan in self.require_loans(db) {
+                table.push(self.list_row(&loan));
+            }
+            self.render(&table);
+            return;
+        }
+        for loan in self.require_loans(db) {
+            self.print_loan(db, loan);
+        }
+    }
+}
+
+// Parses an optional argument, exiting with an error if it's malformed.
+fn parse_value<T: FromStr>(matches: &ArgMatches, name: &str) -> Option<T> {
+    matches.value_of(name).map(|val| match val.parse() {
+        Ok(val) => val,
+        Err(_) => {
+            error!("{}", trf("Invalid value for {}: {}", &[&name, &val]));
+            std::process::exit(1);
+        },
+    })
+}
+
+// Parses an optional YYYY-MM-DD argument, exiting if it's malformed.
+fn parse_date(matches: &ArgMatches, name: &str) -> Option<Timespec> {
+    matches.value_of(name).map(|val| match time::strptime(val, "%F") {
+        Ok(t) => t.to_timespec(),
+        Err(err) => {
+            error!("{}", trf("Error parsing time: {}", &[&err]));
+            std::process::exit(1);
+        },
+    })
+}
+
+// Parses repeated NAME=AMOUNT fee arguments.
+fn fees_from_args(matches: &ArgMatches) -> Vec<Fee> {
+    let values = match matches.values_of("fee") {
+        Some(values) => values,
+        None => return Vec::new(),
+    };
+
+    values.map(|val| {
+        let mut parts = val.splitn(2, '=');
+        let name = parts.next().unwrap();
+        match parts.next().map(|amount| amount.parse()) {
+            Some(Ok(amount)) => Fee{ name: name.to_string(), amount: amount },
+            _ => {
+                error!("{}", trf("Fees must look like NAME=AMOUNT, got: {}", &[&val]));
+                std::process::exit(1);
+            },
+        }
+    }).collect()
+}
+
+// Parses repeated NAME=COST[:MONTHS] add-on arguments. Add-ons without a term
+// are paid for over `periods` months.
+fn addons_from_args(matches: &ArgMatches, periods: i32) -> Vec<AddOn> {
+    let values = match matches.values_of("addon") {
+        Some(values) => values,
+        None => return Vec::new(),
+    };
+
+    values.map(|val| {
+        let mut parts = val.splitn(2, '=');
+        let name = parts.next().unwrap();
+        let mut terms = parts.next().unwrap_or("").splitn(2, ':');
+        let cost = terms.next().unwrap().parse::<f64>();
+        let months = terms.next().map_or(Ok(periods), |months| months.parse::<i32>());
+        match (cost, months) {
+            (Ok(cost), Ok(months)) if !name.is_empty() && cost > 0f64 && months > 0 && months <= periods => AddOn{
+                name: name.to_string(),
+                cost: cost,
+                periods: months,
+            },
+            _ => {
+                error!("{}", trf("Add-ons must look like NAME=COST or NAME=COST:MONTHS, with no more than {} months, got: {}", &[&periods, &val]));
+                std::process::exit(1);
+            },
+        }
+    }).collect()
+}
+
+// Parses NAME=PERCENT ownership arguments, which must add up to 100%.
+fn borrowers_from_args(matches: &ArgMatches) -> Vec<Borrower> {
+    let values = match matches.values_of("borrower") {
+        Some(values) => values,
+        None => return Vec::new(),
+    };
+
+    let borrowers: Vec<Borrower> = values.map(|val| {
+        let mut parts = val.splitn(2, '=');
+        let name = parts.next().unwrap();
+        match parts.next().map(|share| share.parse()) {
+            Some(Ok(share)) => Borrower{ name: name.to_string(), share: share },
+            _ => {
+                error!("{}", trf("Borrowers must look like NAME=PERCENT, got: {}", &[&val]));
+                std::process::exit(1);
+            },
+        }
+    }).collect();
+
+    let total: f64 = borrowers.iter().map(|borrower| borrower.share).sum();
+    if (total - 100.0).abs() > 0.001 {
+        error!("{}", trf("Borrower shares must add up to 100%, got {}%", &[&total]));
+        std::process::exit(1);
+    }
+    borrowers
+}
+
+fn create_loan_from_args(matches: &ArgMatches, fees: &[Fee], addons: &[AddOn]) -> Loan {
+    let name = matches.value_of("name").unwrap();
+    let fee_total: f64 = fees.iter().map(|fee| fee.amount).sum::<f64>() + addons.iter().map(|addon| addon.cost).sum::<f64>();
+    let balance: f64 = matches.value_of("balance").unwrap().parse::<f64>().unwrap() + fee_total;
+    let apr: f64 = matches.value_of("apr").unwrap().parse().unwrap();
+    let term: i32 = matches.value_of("term").unwrap().parse().unwrap();
+
+    let first_payment_days = parse_value::<i32>(matches, "first-payment-days");
+    if first_payment_days.map_or(false, |days| days <= 0) {
+        error!("{}", tr("Invalid value for first-payment-days: must be a positive number of days"));
+        std::process::exit(1);
+    }
+    // closing today, the first payment a month or the given number of days later
+    let start_time: Timespec = parse_date(matches, "start").unwrap_or_else(|| match first_payment_days {
+        Some(days) => amortization::today() + time::Duration::days(days as i64),
+        None => amortization::today(),
+    });
+
+    let mut loan = Loan::new(name.to_string(), balance, term * 12, apr, start_time);
+    loan.first_payment_days = first_payment_days.unwrap_or(0);
+    if let Some(draw) = parse_value::<i32>(matches, "draw") {
+        loan.set_draw_periods(draw);
+    }
+    if let Some(deferment) = parse_value::<i32>(matches, "deferment") {
+        loan.set_deferment(deferment, matches.is_present("subsidized"));
+    }
+    loan.currency = matches.value_of("currency").unwrap().to_uppercase();
+    loan.lender = matches.value_of("lender").map(|lender| lender.to_string());
+    loan.owner = matches.value_of("owner").map(|owner| owner.to_string());
+    loan.paid_ahead = matches.is_present("paid-ahead");
+    loan.lent = matches.is_present("lent");
+    if let Some(policy) = parse_value(matches, "skip-policy") {
+        loan.skip_policy = policy;
+    }
+    if let Some(months) = parse_value::<i32>(matches, "promo-months") {
+        let promo_apr = parse_value(matches, "promo-apr").unwrap_or(0f64);
+        loan.set_promo(promo_apr, months, matches.is_present("deferred-interest"));
+    }
+    if let Some(timing) = parse_value(matches, "interest-timing") {
+        loan.set_timing(timing);
+    }
+    if let Some(repayment) = parse_value(matches, "repayment") {
+        loan.set_repayment(repayment);
+    }
+    if let Some(method) = matches.value_of("interest-method") {
+        if interest::lookup(method).is_none() {
+            error!("{}", trf("Unknown interest method: {} (try {})", &[&method, &interest::names().join(", ")]));
+            std::process::exit(1);
+        }
+        loan.set_interest_method(method);
+    }
+    loan.minor_units = parse_value(matches, "minor-units").unwrap_or_else(|| currency::minor_units(&loan.currency));
+    if matches.is_present("interest-rounding") || matches.is_present("payment-rounding") {
+        // rounding defaults to the currency's minor unit
+        let rounding = |name: &str| matches.value_of(name).map_or(Ok(calc::Rounding::Exact), |val| calc::Rounding::parse(val, loan.minor_units))
+            .unwrap_or_else(|err| {
+                error!("{}", trf("Invalid value for {}: {}", &[&name, &err]));
+                std::process::exit(1);
+            });
+        let (interest, payment) = (rounding("interest-rounding"), rounding("payment-rounding"));
+        loan.set_rounding(interest, payment);
+    }
+    loan.escrow_payment = parse_value(matches, "escrow").unwrap_or(0f64);
+    loan.pmi_payment = parse_value(matches, "pmi").unwrap_or(0f64);
+    if let Some(index) = matches.value_of("index") {
+        let fixed = parse_value(matches, "fixed-months").unwrap_or(0);
+        let reset = parse_value(matches, "reset-months").unwrap_or(12);
+        loan.set_index(index.to_string(), parse_value(matches, "margin").unwrap(), fixed, reset);
+    }
+    if let Some(penalty) = parse_value(matches, "prepayment-penalty") {
+        loan.set_prepayment_penalty(penalty, parse_value(matches, "penalty-months").unwrap_or(0));
+    }
+    loan
+}
+
+// Creates the loans defined in `path`, a TOML or CSV file, in one database
+// transaction once every entry checks out.
+fn create_loans_from_file(db: &Path, path: &str) {
+    let mut contents = String::new();
+    if let Err(err) = File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+        error!("{}", trf("Error reading {}: {}", &[&path, &err]));
+        std::process::exit(1);
+    }
+    let rows = if path.to_lowercase().ends_with(".toml") {
+        import::parse_loans_toml(&contents)
+    } else {
+        import::parse_loans_csv(&contents)
+    }.unwrap_or_else(|err| {
+        error!("{}", trf("Error parsing {}: {}", &[&path, &err]));
+        std::process::exit(1);
+    });
+    if rows.is_empty() {
+        error!("{}", trf("No loans defined in {}", &[&path]));
+        std::process::exit(1);
+    }
+
+    let existing: Vec<String> = match amortization::load_loans(db) {
+        Ok(loans) => loans.into_iter().map(|loan| loan.name).collect(),
+        Err(err) => {
+            error!("{}", trf("Error with statement: {}", &[&err]));
+            std::process::exit(1);
+        }
+    };
+    for (i, row) in rows.iter().enumerate() {
+        if existing.contains(&row.name) || rows[..i].iter().any(|other| other.name == row.name) {
+            error!("{}", trf("A loan named {} already exists; nothing was created", &[&row.name]));
+            std::process::exit(1);
+        }
+    }
+
+    let loans: Vec<Loan> = rows.into_iter().map(|row| {
+        let mut loan = Loan::new(row.name, row.balance, row.term * 12, row.apr, row.start.unwrap_or_else(amortization::today));
+        loan.currency = row.currency.unwrap_or_else(|| "USD".to_string());
+        loan.minor_units = currency::minor_units(&loan.currency);
+        loan.lender = row.lender;
+        loan.owner = row.owner;
+        loan.lent = row.lent;
+        loan
+    }).collect();
+    if let Err(err) = amortization::create_loans(db, &loans) {
+        error!("{}", trf("Error adding loans, none were created: {}", &[&err]));
+        std::process::exit(1);
+    }
+    println!("{}", trf("Created {} loans: {}", &[&loans.len(), &(loans.iter().map(|loan| &loan.name[..]).collect::<Vec<_>>().join(", "))]));
+}
+
+fn create_transaction_from_args(matches: &ArgMatches) -> (String, f64, bool, Timespec, Option<String>){
+    let name = matches.value_of("name").unwrap();
+    let amount: f64 = matches.value_of("amount").unwrap().parse().unwrap();
+    let extra = matches.is_present("extra");
+
+    let date: Timespec = parse_date(matches, "date").unwrap_or_else(amortization::today);
+    let method = matches.value_of("method").map(|method| method.to_string());
+
+    (name.to_string(), amount, extra, date, method)
+}
+
+// Reads exchange rates from --file, or a single FROM TO RATE on the command line.
+fn rates_from_args(matches: &ArgMatches) -> Vec<ExchangeRate> {
+    if let Some(path) = matches.value_of("file") {
+        let mut contents = String::new();
+        if let Err(err) = File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+            error!("{}", trf("Error reading {}: {}", &[&path, &err]));
+            std::process::exit(1);
+        }
+        return match currency::parse_rates(&contents) {
+            Ok(rates) => rates,
+            Err(err) => {
+                error!("{}", trf("Error parsing {}: {}", &[&path, &err]));
+                std::process::exit(1);
+            }
+        };
+    }
+
+    vec![ExchangeRate{
+        from: matches.value_of("from").unwrap().to_uppercase(),
+        to: matches.value_of("to").unwrap().to_uppercase(),
+        rate: parse_value(matches, "rate").unwrap(),
+    }]
+}
+
+// An amount rounded to whole dollars, for --simple.
+fn whole(amount: f64) -> String {
+    format!("${:.0}", amount.abs())
+}
+
+// First of next month, when a schedule's first payment is due.
+fn next_due_month() -> time::Tm {
+    let mut date = time::at_utc(amortization::today());
+    date.tm_mday = 1;
+    date.tm_mon += 1;
+    if date.tm_mon == 12 {
+        date.tm_mon -= 12;
+        date.tm_year += 1;
+    }
+    date
+}
+
+// Month of the last of `periods` payments starting at `first`.
+fn payoff_month(first: time::Tm, periods: i32) -> String {
+    let mut date = first;
+    date.tm_mon += periods - 1;
+    date.tm_year += date.tm_mon / 12;
+    date.tm_mon %= 12;
+    format!("{} {}", i18n::month_name(date.tm_mon + 1), date.tm_year + 1900)
+}
+
+// Options shared by simulate and scenario save.
+fn scenario_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("extra")
+            .long("extra")
+            .takes_value(true)
+            .help("extra principal paid every month"),
+        Arg::with_name("annual")
+            .long("annual")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("lump sum paid every year, as AMOUNT@MONTH, e.g. 3000@4 for a tax refund each April (may be repeated)"),
+        Arg::with_name("refi-apr")
+            .long("refi-apr")
+            .takes_value(true)
+            .requires("refi-term")
+            .help("refinance the balance at this apr"),
+        Arg::with_name("refi-term")
+            .long("refi-term")
+            .takes_value(true)
+            .requires("refi-apr")
+            .help("term of the refinanced loan, in years"),
+        Arg::with_name("rate-shock")
+            .long("rate-shock")
+            .takes_value(true)
+            .help("change in apr, in percentage points (e.g. 2 or -0.5)"),
+        Arg::with_name("shock-after")
+            .long("shock-after")
+            .takes_value(true)
+            .requires("rate-shock")
+            .help("payments before the rate changes (defaults to 0)"),
+    ]
+}
+
+fn scenario_from_args(matches: &ArgMatches, name: String, loan: String) -> Scenario {
+    let lump_sums = match matches.values_of("annual") {
+        Some(values) => values.map(|val| match val.parse() {
+            Ok(lump) => lump,
+            Err(err) => {
+                error!("{}", err);
+                std::process::exit(1);
+            },
+        }).collect(),
+        None => Vec::new(),
+    };
+    let refinance = parse_value::<f64>(matches, "refi-apr").map(|apr| {
+        Refinance{
+            apr: apr,
+            periods: parse_value::<i32>(matches, "refi-term").unwrap() * 12,
+        }
+    });
+
+    Scenario{
+        name: name,
+        loan: loan,
+        monthly_extra: parse_value(matches, "extra").unwrap_or(0f64),
+        lump_sums: lump_sums,
+        refinance: refinance,
+        rate_shock: parse_value(matches, "rate-shock").unwrap_or(0f64),
+        shock_periods: parse_value(matches, "shock-after").unwrap_or(0),
+    }
+}
+
+fn idr_plan_from_args(matches: &ArgMatches) -> IdrPlan {
+    IdrPlan{
+        income: parse_value(matches, "income").unwrap(),
+        income_growth: parse_value(matches, "growth").unwrap(),
+        poverty_line: parse_value(matches, "poverty").unwrap(),
+        poverty_multiple: parse_value::<f64>(matches, "protected").unwrap() / 100.0,
+        income_percent: parse_value(matches, "percent").unwrap(),
+        forgiveness_years: parse_value(matches, "years").unwrap(),
+    }
+}
+
+fn reverse_mortgage_from_args(loan: &Loan, matches: &ArgMatches) -> ReverseMortgage {
+    ReverseMortgage{
+        balance: loan.balance,
+        apr: loan.apr,
+        monthly_draw: parse_value(matches, "draw").unwrap(),
+        home_value: parse_value(matches, "home").unwrap(),
+        appreciation: parse_value(matches, "appreciation").unwrap(),
+    }
+}
+
+// The rates from --apr-from to --apr-to, --step apart.
+fn aprs_from_args(matches: &ArgMatches) -> Vec<f64> {
+    let from: f64 = parse_value(matches, "apr-from").unwrap();
+    let to: f64 = parse_value(matches, "apr-to").unwrap();
+    let step: f64 = parse_value(matches, "step").unwrap();
+    if step <= 0f64 || to < from {
+        error!("{}", tr("Rates must run from --apr-from up to --apr-to in positive steps"));
+        std::process::exit(1);
+    }
+    // counted rather than accumulated, so the last rate isn't lost to rounding
+    let count = ((to - from) / step + 1e-9).floor() as i32;
+    (0..count + 1).map(|i| from + step * i as f64).collect()
+}
+
+// Parses an escrow bill's yearly amount and the months it's paid in.
+fn disbursement_from_args(matches: &ArgMatches, name: &str, months: &str) -> Option<Disbursement> {
+    parse_value(matches, name).map(|amount| Disbursement{
+        name: name.to_string(),
+        amount: amount,
+        months: matches.values_of(months).unwrap().map(|month| match month.parse() {
+            Ok(month) if month >= 1 && month <= 12 => month,
+            _ => {
+                error!("{}", trf("Invalid value for {}: {}", &[&months, &month]));
+                std::process::exit(1);
+            },
+        }).collect(),
+    })
+}
+
+fn escrow_from_args(matches: &ArgMatches) -> Escrow {
+    let disbursements = disbursement_from_args(matches, "tax", "tax-months").into_iter()
+        .chain(disbursement_from_args(matches, "insurance", "insurance-months"))
+        .collect();
+    Escrow{
+        balance: parse_value(matches, "balance").unwrap(),
+        monthly_deposit: parse_value(matches, "deposit").unwrap(),
+        disbursements: disbursements,
+        cushion_months: parse_value(matches, "cushion").unwrap(),
+    }
+}
+
+// --trace-file is global, so it may have been given to any subcommand.
+fn trace_file<'a>(matches: &'a ArgMatches) -> Option<&'a str> {
+    matches.value_of("trace-file").or_else(|| matches.subcommand().1.and_then(trace_file))
+}
+
+// So is --user.
+fn user<'a>(matches: &'a ArgMatches) -> Option<&'a str> {
+    matches.value_of("user").or_else(|| matches.subcommand().1.and_then(user))
+}
+
+fn schema_requested(matches: &ArgMatches) -> bool {
+    matches.is_present("schema") || matches.subcommand().1.map_or(false, schema_requested)
+}
+
+fn output_name<'a>(matches: &'a ArgMatches) -> Option<&'a str> {
+    matches.value_of("output").or_else(|| matches.subcommand().1.and_then(output_name))
+}
+
+// The renderer --output names, text by default.
+fn output(matches: &ArgMatches) -> Arc<dyn Renderer> {
+    let name = output_name(matches).unwrap_or("text");
+    render::lookup(name).unwrap_or_else(|| {
+        error!("{}", trf("Unknown output format: {} (try {})", &[&name, &render::names().join(", ")]));
+        std::process::exit(1);
+    })
+}
+
+fn main() {
+    env_logger::init().unwrap();
+    i18n::init();
+
+    let matches = App::new("Amortization Calculator")
+                          .version("0.1.0")
+                          .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                          .about("Calculates an amortization table")
+                          .arg(Arg::with_name("DB")
+                               .help("Database to use")
+                               .index(1))
+                          .arg(Arg::with_name("loan")
+                               .help("Loan to query")
+                               .index(2))
+                          .arg(Arg::with_name("v")
                                .short("v")
                                .multiple(true)
                                .help("Sets the level of verbosity"))
+                          .arg(Arg::with_name("inflation")
+                               .long("inflation")
+                               .takes_value(true)
+                               .help("Also reports amounts in today's dollars, given yearly inflation in percent"))
+                          .arg(Arg::with_name("anchor")
+                               .long("anchor")
+                               .takes_value(true)
+                               .help("Where schedules listed with -vv start: origination, today (the next payment due, the default) or a payment number"))
+                          .arg(Arg::with_name("simple")
+                               .long("simple")
+                               .help("Explains loans and payments in plain words, rounded to whole dollars"))
+                          .arg(Arg::with_name("no-color")
+                               .long("no-color")
+                               .help("Don't color-code loan statuses (also disabled by setting NO_COLOR, or when output isn't a terminal)"))
+                          .arg(Arg::with_name("trace-file")
+                               .long("trace-file")
+                               .takes_value(true)
+                               .global(true)
+                               .help("Appends a line to FILE for every change made to the database"))
+                          .arg(Arg::with_name("user")
+                               .long("user")
+                               .takes_value(true)
+                               .global(true)
+                               .help("Only shows loans owned by USER, one member of a shared household database"))
+                          .arg(Arg::with_name("output")
+                               .long("output")
+                               .takes_value(true)
+                               .global(true)
+                               .help("Format for tables of results: text, json, csv, markdown or html"))
+                          .arg(Arg::with_name("schema")
+                               .long("schema")
+                               .global(true)
+                               .help("Prints the JSON Schema of what the loan listing, export, history or status writes as JSON, instead of running it"))
                           .subcommand(SubCommand::with_name("init")
                                       .about("Initializes the database")
                                       .version("0.1.0")
                                       .author("T. Jameson Little <t.jameson.little@gmail.com>")
                                       .arg(Arg::with_name("DB")
-                                           .help("Sets the database name")
+                                           .help("Sets the database name")
+                                           .required(true)
+                                           .index(1))
+                                      )
+                          .subcommand(SubCommand::with_name("create")
+                                      .about("Creates a new loan")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required_unless("from-file")
+                                           .index(2))
+                                      .arg(Arg::with_name("from-file")
+                                          .long("from-file")
+                                          .takes_value(true)
+                                          .conflicts_with_all(&["name", "balance", "apr", "term"])
+                                          .help("creates every loan defined in a .toml ([[loan]] tables) or .csv file, all or none of them"))
+                                      .arg(Arg::with_name("balance")
+                                          .short("b")
+                                          .long("balance")
+                                          .takes_value(true)
+                                          .required_unless("from-file")
+                                          .help("balance of the loan"))
+                                      .arg(Arg::with_name("start")
+                                          .long("start")
+                                          .takes_value(true)
+                                          .help("first payment due date"))
+                                      .arg(Arg::with_name("first-payment-days")
+                                          .long("first-payment-days")
+                                          .takes_value(true)
+                                          .help("days from closing to the first payment, e.g. 45 or 60, when it isn't a month; the first payment's interest covers all of them"))
+                                      .arg(Arg::with_name("apr")
+                                          .short("a")
+                                          .long("apr")
+                                          .takes_value(true)
+                                          .required_unless("from-file")
+                                          .help("apr"))
+                                      .arg(Arg::with_name("term")
+                                          .short("t")
+                                          .long("term")
+                                          .takes_value(true)
+                                          .required_unless("from-file")
+                                          .help("apr"))
+                                      .arg(Arg::with_name("draw")
+                                          .long("draw")
+                                          .takes_value(true)
+                                          .help("interest-only draw period in months (HELOC)"))
+                                      .arg(Arg::with_name("deferment")
+                                          .long("deferment")
+                                          .takes_value(true)
+                                          .conflicts_with("draw")
+                                          .help("months with no payments due, e.g. in school and the grace period of a student loan"))
+                                      .arg(Arg::with_name("subsidized")
+                                          .long("subsidized")
+                                          .requires("deferment")
+                                          .help("no interest accrues during deferment (otherwise it's added to the balance when repayment starts)"))
+                                      .arg(Arg::with_name("promo-apr")
+                                          .long("promo-apr")
+                                          .takes_value(true)
+                                          .requires("promo-months")
+                                          .help("promotional apr (defaults to 0)"))
+                                      .arg(Arg::with_name("promo-months")
+                                          .long("promo-months")
+                                          .takes_value(true)
+                                          .help("months the promotional apr lasts"))
+                                      .arg(Arg::with_name("deferred-interest")
+                                          .long("deferred-interest")
+                                          .requires("promo-months")
+                                          .help("charge the waived interest if not paid off before the promotion ends"))
+                                      .arg(Arg::with_name("prepayment-penalty")
+                                          .long("prepayment-penalty")
+                                          .takes_value(true)
+                                          .help("percent of any principal paid ahead of schedule charged as a fee"))
+                                      .arg(Arg::with_name("penalty-months")
+                                          .long("penalty-months")
+                                          .takes_value(true)
+                                          .requires("prepayment-penalty")
+                                          .help("months from the start the prepayment penalty applies for (defaults to the whole term)"))
+                                      .arg(Arg::with_name("skip-policy")
+                                          .long("skip-policy")
+                                          .takes_value(true)
+                                          .possible_values(&["capitalize", "defer"])
+                                          .help("what happens to the interest when a payment is skipped (defaults to capitalize)"))
+                                      .arg(Arg::with_name("fee")
+                                          .long("fee")
+                                          .takes_value(true)
+                                          .multiple(true)
+                                          .number_of_values(1)
+                                          .help("fee rolled into the principal, as NAME=AMOUNT (may be repeated)"))
+                                      .arg(Arg::with_name("addon")
+                                          .long("addon")
+                                          .takes_value(true)
+                                          .multiple(true)
+                                          .number_of_values(1)
+                                          .help("add-on product financed with the loan, as NAME=COST or NAME=COST:MONTHS if paid for over fewer months than the loan (may be repeated)"))
+                                      .arg(Arg::with_name("interest-rounding")
+                                          .long("interest-rounding")
+                                          .takes_value(true)
+                                          .help("how the lender rounds interest: exact, half-up, truncate or up, optionally followed by :DECIMALS (default 2)"))
+                                      .arg(Arg::with_name("payment-rounding")
+                                          .long("payment-rounding")
+                                          .takes_value(true)
+                                          .help("how the lender rounds the payment, e.g. up:0 for the next whole dollar"))
+                                      .arg(Arg::with_name("interest-timing")
+                                          .long("interest-timing")
+                                          .takes_value(true)
+                                          .possible_values(&["arrears", "advance"])
+                                          .help("whether each payment's interest is for the month before it or, as with discount loans, the month after (defaults to arrears)"))
+                                      .arg(Arg::with_name("repayment")
+                                          .long("repayment")
+                                          .takes_value(true)
+                                          .possible_values(&["annuity", "linear", "bullet"])
+                                          .help("equal payments (annuity, the default), equal principal each month with interest on top (linear), or interest only with the principal due at the end (bullet)"))
+                                      .arg(Arg::with_name("interest-method")
+                                          .long("interest-method")
+                                          .takes_value(true)
+                                          .help("day count the lender works out interest with: standard (a twelfth of the APR a month, the default), actual/360 or 30/360"))
+                                      .arg(Arg::with_name("currency")
+                                          .long("currency")
+                                          .takes_value(true)
+                                          .default_value("USD")
+                                          .help("currency the loan is held in"))
+                                      .arg(Arg::with_name("minor-units")
+                                          .long("minor-units")
+                                          .takes_value(true)
+                                          .help("decimal places amounts are kept to (defaults to the currency's, e.g. 2 for USD, 0 for JPY, 3 for BHD)"))
+                                      .arg(Arg::with_name("lender")
+                                          .long("lender")
+                                          .takes_value(true)
+                                          .help("lender servicing the loan (see the lender command)"))
+                                      .arg(Arg::with_name("owner")
+                                          .long("owner")
+                                          .takes_value(true)
+                                          .help("member of the household the loan belongs to (see the user command)"))
+                                      .arg(Arg::with_name("borrower")
+                                          .long("borrower")
+                                          .takes_value(true)
+                                          .multiple(true)
+                                          .number_of_values(1)
+                                          .help("borrower's share of the loan, as NAME=PERCENT (may be repeated, must total 100)"))
+                                      .arg(Arg::with_name("paid-ahead")
+                                          .long("paid-ahead")
+                                          .help("payments beyond the amount due push out the next due date"))
+                                      .arg(Arg::with_name("lent")
+                                          .long("lent")
+                                          .help("money you lent out: the balance is owed to you and the interest is income"))
+                                      .arg(Arg::with_name("backfill")
+                                          .long("backfill")
+                                          .help("fill in the payments scheduled before today as pending payments to review"))
+                                      .arg(Arg::with_name("escrow")
+                                          .long("escrow")
+                                          .takes_value(true)
+                                          .help("escrow (taxes and insurance) collected with each payment"))
+                                      .arg(Arg::with_name("pmi")
+                                          .long("pmi")
+                                          .takes_value(true)
+                                          .help("mortgage insurance collected with each payment"))
+                                      .arg(Arg::with_name("index")
+                                          .long("index")
+                                          .takes_value(true)
+                                          .requires("margin")
+                                          .help("adjustable rate: index the rate resets to (see the index command)"))
+                                      .arg(Arg::with_name("margin")
+                                          .long("margin")
+                                          .takes_value(true)
+                                          .requires("index")
+                                          .help("percentage points added to the index at each reset"))
+                                      .arg(Arg::with_name("fixed-months")
+                                          .long("fixed-months")
+                                          .takes_value(true)
+                                          .requires("index")
+                                          .help("months at the initial apr before the first reset, e.g. 60 for a 5/1 ARM (defaults to 0)"))
+                                      .arg(Arg::with_name("reset-months")
+                                          .long("reset-months")
+                                          .takes_value(true)
+                                          .requires("index")
+                                          .help("months between resets after the first (defaults to 12)"))
+                                      )
+                          .subcommand(SubCommand::with_name("impounds")
+                                      .about("Sets the escrow and PMI collected with each payment, so combined payments are split into their parts")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("escrow")
+                                          .long("escrow")
+                                          .takes_value(true)
+                                          .default_value("0")
+                                          .help("escrow (taxes and insurance) collected with each payment"))
+                                      .arg(Arg::with_name("pmi")
+                                          .long("pmi")
+                                          .takes_value(true)
+                                          .default_value("0")
+                                          .help("mortgage insurance collected with each payment"))
+                                      )
+                          .subcommand(SubCommand::with_name("paid-ahead")
+                                      .about("Sets whether payments beyond the amount due push out a loan's next due date")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("off")
+                                          .long("off")
+                                          .help("only reduce principal, keeping the regular due dates"))
+                                      )
+                          .subcommand(SubCommand::with_name("lender")
+                                      .about("Adds or updates a lender's contact details, optionally linking loans to it")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("lender")
+                                           .help("Name of the lender")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("account")
+                                          .long("account")
+                                          .takes_value(true)
+                                          .help("account number"))
+                                      .arg(Arg::with_name("url")
+                                          .long("url")
+                                          .takes_value(true)
+                                          .help("website for payments and statements"))
+                                      .arg(Arg::with_name("phone")
+                                          .long("phone")
+                                          .takes_value(true)
+                                          .help("servicing phone number"))
+                                      .arg(Arg::with_name("loans")
+                                          .long("loan")
+                                          .takes_value(true)
+                                          .multiple(true)
+                                          .number_of_values(1)
+                                          .help("loan serviced by this lender (may be repeated)"))
+                                      )
+                          .subcommand(SubCommand::with_name("group")
+                                      .about("Creates a group of sub-loans paid as one account (e.g. student loan disbursements), changes its members, pays it or shows its schedule")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("group")
+                                           .help("Name of the group")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("allocation")
+                                          .long("allocation")
+                                          .takes_value(true)
+                                          .possible_values(&["standard", "proportional", "highest-rate", "lowest-balance"])
+                                          .help("where a payment beyond the amount due goes (defaults to standard, in proportion to each sub-loan's payment)"))
+                                      .arg(Arg::with_name("loans")
+                                          .long("loan")
+                                          .takes_value(true)
+                                          .multiple(true)
+                                          .number_of_values(1)
+                                          .help("sub-loan to add to the group (may be repeated)"))
+                                      .arg(Arg::with_name("remove")
+                                          .long("remove")
+                                          .takes_value(true)
+                                          .multiple(true)
+                                          .number_of_values(1)
+                                          .help("sub-loan to take out of the group, leaving it as a loan of its own (may be repeated)"))
+                                      .arg(Arg::with_name("pay")
+                                          .long("pay")
+                                          .takes_value(true)
+                                          .help("pay this amount, split across the sub-loans"))
+                                      .arg(Arg::with_name("history")
+                                          .long("history")
+                                          .help("list payments made on the group and how each was split"))
+                                      .arg(Arg::with_name("schedule")
+                                          .long("schedule")
+                                          .help("show the sub-loans' schedules combined, month by month"))
+                                      .arg(Arg::with_name("date")
+                                          .long("date")
+                                          .short("d")
+                                          .takes_value(true)
+                                          .requires("pay")
+                                          .help("date of payment (if omitted, current date assumed)"))
+                                      .arg(Arg::with_name("method")
+                                          .long("method")
+                                          .short("m")
+                                          .takes_value(true)
+                                          .requires("pay")
+                                          .help("how it was paid, e.g. ACH, autopay or \"check #1234\""))
+                                      )
+                          .subcommand(SubCommand::with_name("merge")
+                                      .about("Brings loans and payments over from another database, e.g. a copy on another computer, keeping the newest of each")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to merge into")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("other")
+                                           .help("Database to merge from")
+                                           .required(true)
+                                           .index(2))
+                                      )
+                          .subcommand(SubCommand::with_name("serve")
+                                      .about("Serves a calculator at /calculate and, with --metrics, balances, interest accrued this month and overdue loans as Prometheus metrics at /metrics")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("listen")
+                                          .long("listen")
+                                          .takes_value(true)
+                                          .default_value("127.0.0.1:9898")
+                                          .help("address to serve the calculator on"))
+                                      .arg(Arg::with_name("metrics")
+                                          .long("metrics")
+                                          .takes_value(true)
+                                          .help("also serve metrics on this loopback address, e.g. 127.0.0.1:9899"))
+                                      .arg(Arg::with_name("rate-limit")
+                                          .long("rate-limit")
+                                          .takes_value(true)
+                                          .default_value("60")
+                                          .help("calculations each client may request per minute"))
+                                      )
+                          .subcommand(SubCommand::with_name("changes")
+                                      .about("Lists the change log merge uses to reconcile copies of a database")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("since")
+                                          .long("since")
+                                          .takes_value(true)
+                                          .help("only changes after this time (UTC), e.g. 2026-10-01 or 2026-10-01 12:00:00"))
+                                      )
+                          .subcommand(SubCommand::with_name("user")
+                                      .about("Adds a member of the household, optionally giving them loans, or lists what each user owns")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of the user")
+                                           .index(2))
+                                      .arg(Arg::with_name("loans")
+                                          .long("loan")
+                                          .takes_value(true)
+                                          .multiple(true)
+                                          .number_of_values(1)
+                                          .requires("name")
+                                          .help("loan the user owns (may be repeated)"))
+                                      )
+                          .subcommand(SubCommand::with_name("borrowers")
+                                      .about("Sets who shares a loan, or lists each borrower's share of all loans")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .index(2))
+                                      .arg(Arg::with_name("borrower")
+                                           .help("Borrower's share, as NAME=PERCENT (must total 100)")
+                                           .multiple(true)
+                                           .requires("name")
+                                           .index(3))
+                                      )
+                          .subcommand(SubCommand::with_name("pay")
+                                      .about("Pay a loan")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("amount")
+                                          .short("a")
+                                          .long("amount")
+                                          .takes_value(true)
+                                          .required(true)
+                                          .help("payment amount"))
+                                      .arg(Arg::with_name("extra")
+                                          .short("e")
+                                          .long("extra")
+                                          .takes_value(false)
+                                          .help("this payment goes 100% to principal"))
+                                      .arg(Arg::with_name("date")
+                                          .long("date")
+                                          .short("d")
+                                          .takes_value(true)
+                                          .help("date of payment (if omitted, current date assumed)"))
+                                      .arg(Arg::with_name("method")
+                                          .long("method")
+                                          .short("m")
+                                          .takes_value(true)
+                                          .help("how it was paid, e.g. ACH, autopay or \"check #1234\""))
+                                      .arg(Arg::with_name("force")
+                                          .long("force")
+                                          .help("record it even if a payment for the same amount was already recorded that day"))
+                                      )
+                          .subcommand(SubCommand::with_name("import")
+                                      .about("Records payments from a CSV file of DATE,AMOUNT[,METHOD[,KIND]] lines, oldest first")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("FILE")
+                                           .help("CSV file of payments")
+                                           .required(true)
+                                           .index(3))
+                                      .arg(Arg::with_name("as")
+                                          .long("as")
+                                          .takes_value(true)
+                                          .possible_values(&["regular", "extra"])
+                                          .help("record every payment without a KIND as this, instead of guessing from the amount and date"))
+                                      .arg(Arg::with_name("pending")
+                                          .long("pending")
+                                          .help("hold the payments for review with the pending command instead of applying them"))
+                                      .arg(Arg::with_name("force")
+                                          .long("force")
+                                          .help("record payments even if one for the same amount was already recorded that day"))
+                                      )
+                          .subcommand(SubCommand::with_name("bounce")
+                                      .about("Reverses a payment that was returned unpaid (NSF), putting its principal back on the balance")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("payment")
+                                           .help("Number of the payment, as shown by history")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("fee")
+                                          .long("fee")
+                                          .takes_value(true)
+                                          .help("returned-payment fee added to the balance"))
+                                      )
+                          .subcommand(SubCommand::with_name("backfill")
+                                      .about("Fills in the payments scheduled before today on a loan started in the past, as pending payments to review")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of the loan")
+                                           .required(true)
+                                           .index(2))
+                                      )
+                          .subcommand(SubCommand::with_name("pending")
+                                      .about("Lists payments awaiting review, or confirms or rejects them; they don't touch the balance until confirmed")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of the loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("confirm")
+                                          .long("confirm")
+                                          .help("apply the payments to the balance"))
+                                      .arg(Arg::with_name("reject")
+                                          .long("reject")
+                                          .conflicts_with("confirm")
+                                          .help("delete the payments"))
+                                      .arg(Arg::with_name("id")
+                                          .long("id")
+                                          .takes_value(true)
+                                          .multiple(true)
+                                          .number_of_values(1)
+                                          .help("only this payment, as numbered in the list (may be repeated; defaults to all)"))
+                                      )
+                          .subcommand(SubCommand::with_name("adjust")
+                                      .about("Corrects a loan's balance, e.g. to match the lender's statement")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of the loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("amount")
+                                          .long("amount")
+                                          .takes_value(true)
+                                          .required(true)
+                                          .allow_hyphen_values(true)
+                                          .help("added to the balance, or taken off if negative"))
+                                      .arg(Arg::with_name("date")
+                                          .short("d")
+                                          .long("date")
+                                          .takes_value(true)
+                                          .help("date of the correction (defaults to today)"))
+                                      )
+                          .subcommand(SubCommand::with_name("history")
+                                      .about("Lists payments, fees and everything else that changed a balance")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required_unless("schema")
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Only show payments on this loan")
+                                           .index(2))
+                                      .arg(Arg::with_name("method")
+                                          .long("method")
+                                          .short("m")
+                                          .takes_value(true)
+                                          .help("only show payments whose method starts with this, e.g. check"))
+                                      )
+                          .subcommand(SubCommand::with_name("export")
+                                      .about("Writes loan schedules to stdout for other tools")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required_unless("schema")
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Only export this loan (if omitted, every loan is exported)")
+                                           .index(2))
+                                      .arg(Arg::with_name("format")
+                                          .long("format")
+                                          .short("f")
+                                          .takes_value(true)
+                                          .possible_values(&["csv", "json"])
+                                          .default_value("csv")
+                                          .help("output format"))
+                                      .arg(Arg::with_name("anchor")
+                                          .long("anchor")
+                                          .takes_value(true)
+                                          .help("where each schedule starts: origination (the default), today (the next payment due, from the current balance) or a payment number"))
+                                      )
+                          .subcommand(SubCommand::with_name("calendar")
+                                      .about("Writes a year's calendar of a loan's due dates, payments and missed payments to stdout")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("year")
+                                          .long("year")
+                                          .takes_value(true)
+                                          .help("year to show (if omitted, the current year)"))
+                                      .arg(Arg::with_name("format")
+                                          .long("format")
+                                          .short("f")
+                                          .takes_value(true)
+                                          .possible_values(&["csv", "html"])
+                                          .default_value("csv")
+                                          .help("output format"))
+                                      )
+                          .subcommand(SubCommand::with_name("accrue")
+                                      .about("Records interest accrued since the last payment in the accruals ledger")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Only accrue interest on this loan")
+                                           .index(2))
+                                      .arg(Arg::with_name("date")
+                                          .long("date")
+                                          .short("d")
+                                          .takes_value(true)
+                                          .help("accrue up to this date (if omitted, current date assumed)"))
+                                      )
+                          .subcommand(SubCommand::with_name("payoff")
+                                      .about("Quotes the amount needed to pay off a loan on a given date")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("date")
+                                          .long("date")
+                                          .short("d")
+                                          .takes_value(true)
+                                          .help("payoff date (if omitted, current date assumed)"))
+                                      )
+                          .subcommand(SubCommand::with_name("income")
+                                      .about("Lists interest received on loans you lent out, per fiscal year, for taxes")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("year")
+                                          .long("year")
+                                          .takes_value(true)
+                                          .help("only this fiscal year, numbered by the year it ends in"))
+                                      .arg(Arg::with_name("paid")
+                                          .long("paid")
+                                          .help("list interest paid on loans you borrowed instead"))
+                                      )
+                          .subcommand(SubCommand::with_name("fiscal-year")
+                                      .about("Shows or sets the month the fiscal year starts in, for yearly totals and statements")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("start")
+                                          .long("start")
+                                          .takes_value(true)
+                                          .help("first month of the fiscal year, 1 through 12 (1 for the calendar year)"))
+                                      )
+                          .subcommand(SubCommand::with_name("statement")
+                                      .about("Prints a statement of a loan's payments over a period or month, e.g. to send the borrower of a loan you lent out")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("from")
+                                          .long("from")
+                                          .takes_value(true)
+                                          .help("first day covered (if omitted, the start of this fiscal year)"))
+                                      .arg(Arg::with_name("to")
+                                          .long("to")
+                                          .takes_value(true)
+                                          .help("day after the last covered (if omitted, tomorrow)"))
+                                      .arg(Arg::with_name("month")
+                                          .long("month")
+                                          .takes_value(true)
+                                          .conflicts_with_all(&["from", "to"])
+                                          .help("monthly statement with opening and closing balances, interest and fees, as YYYY-MM"))
+                                      .arg(Arg::with_name("pdf")
+                                          .long("pdf")
+                                          .takes_value(true)
+                                          .help("write the statement to this PDF file instead of printing it"))
+                                      )
+                          .subcommand(SubCommand::with_name("promos")
+                                      .about("Lists deferred-interest promotions and the interest each would charge if not paid off in time")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      )
+                          .subcommand(SubCommand::with_name("progress")
+                                      .about("Compares a loan's contractual schedule with where it's actually headed")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      )
+                          .subcommand(SubCommand::with_name("skip")
+                                      .about("Skips a payment, pushing the remaining due dates out a month")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("date")
+                                          .long("date")
+                                          .short("d")
+                                          .takes_value(true)
+                                          .help("due date being skipped (if omitted, current date assumed)"))
+                                      )
+                          .subcommand(SubCommand::with_name("consolidate")
+                                      .about("Simulates combining several loans into one")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("loans")
+                                           .help("Names of the loans to consolidate")
+                                           .required(true)
+                                           .multiple(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("apr")
+                                          .short("a")
+                                          .long("apr")
+                                          .takes_value(true)
+                                          .required(true)
+                                          .help("apr of the new loan"))
+                                      .arg(Arg::with_name("term")
+                                          .short("t")
+                                          .long("term")
+                                          .takes_value(true)
+                                          .required(true)
+                                          .help("term of the new loan, in years"))
+                                      .arg(Arg::with_name("execute")
+                                          .long("execute")
+                                          .requires("into")
+                                          .help("pay off the old loans and create the new one"))
+                                      .arg(Arg::with_name("into")
+                                          .long("into")
+                                          .takes_value(true)
+                                          .help("name of the new loan"))
+                                      .arg(Arg::with_name("start")
+                                          .long("start")
+                                          .takes_value(true)
+                                          .help("first payment due date of the new loan"))
+                                      )
+                          .subcommand(SubCommand::with_name("rebalance")
+                                      .about("Suggests how to spread an extra monthly budget across loans, by rate (avalanche) or balance (snowball)")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("budget")
+                                          .long("budget")
+                                          .takes_value(true)
+                                          .required(true)
+                                          .help("extra to pay each month on top of the regular payments"))
+                                      )
+                          .subcommand(SubCommand::with_name("simulate")
+                                      .about("Shows how extra payments change a loan's payoff date and interest")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .args(&scenario_args())
+                                      )
+                          .subcommand(SubCommand::with_name("scenario")
+                                      .about("Saves, re-runs and compares named what-if scenarios")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .subcommand(SubCommand::with_name("save")
+                                                  .about("Saves a scenario, replacing any with the same name")
+                                                  .arg(Arg::with_name("DB")
+                                                       .help("Database to use")
+                                                       .required(true)
+                                                       .index(1))
+                                                  .arg(Arg::with_name("scenario")
+                                                       .help("Name of the scenario")
+                                                       .required(true)
+                                                       .index(2))
+                                                  .arg(Arg::with_name("name")
+                                                       .help("Name of loan")
+                                                       .required(true)
+                                                       .index(3))
+                                                  .args(&scenario_args())
+                                                  )
+                                      .subcommand(SubCommand::with_name("run")
+                                                  .about("Runs a saved scenario against its loan")
+                                                  .arg(Arg::with_name("DB")
+                                                       .help("Database to use")
+                                                       .required(true)
+                                                       .index(1))
+                                                  .arg(Arg::with_name("scenario")
+                                                       .help("Name of the scenario")
+                                                       .required(true)
+                                                       .index(2))
+                                                  )
+                                      .subcommand(SubCommand::with_name("compare")
+                                                  .about("Compares saved scenarios side by side")
+                                                  .arg(Arg::with_name("DB")
+                                                       .help("Database to use")
+                                                       .required(true)
+                                                       .index(1))
+                                                  .arg(Arg::with_name("scenarios")
+                                                       .help("Scenarios to compare (defaults to all of them)")
+                                                       .multiple(true)
+                                                       .index(2))
+                                                  )
+                                      )
+                          .subcommand(SubCommand::with_name("addons")
+                                      .about("Shows how much of a loan's payment and interest goes to each financed add-on (insurance, warranties...) and how much to the loan itself")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("addon")
+                                          .long("add")
+                                          .takes_value(true)
+                                          .multiple(true)
+                                          .number_of_values(1)
+                                          .help("records an add-on already included in the amount financed, as NAME=COST or NAME=COST:MONTHS (may be repeated)"))
+                                      )
+                          .subcommand(SubCommand::with_name("strategies")
+                                      .about("Compares paying monthly, true biweekly (half the payment every two weeks) and a twelfth extra each month")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      )
+                          .subcommand(SubCommand::with_name("wait")
+                                      .about("Shows what putting off extra payments or a refinance costs in interest, for each month of delay")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("months")
+                                          .long("months")
+                                          .takes_value(true)
+                                          .default_value("12")
+                                          .help("longest delay to show, in months"))
+                                      .arg(Arg::with_name("scenario")
+                                          .long("scenario")
+                                          .takes_value(true)
+                                          .help("saved scenario to put off, instead of the changes given"))
+                                      .args(&scenario_args())
+                                      )
+                          .subcommand(SubCommand::with_name("afford")
+                                      .about("Finds the largest loan you can take on, given your income, the loans you already have and a debt-to-income limit")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("income")
+                                          .long("income")
+                                          .takes_value(true)
+                                          .required(true)
+                                          .help("gross annual income"))
+                                      .arg(Arg::with_name("dti")
+                                          .long("dti")
+                                          .takes_value(true)
+                                          .default_value("36")
+                                          .help("most of monthly income that may go to debt payments, in percent"))
+                                      .arg(Arg::with_name("apr")
+                                          .long("apr")
+                                          .takes_value(true)
+                                          .required(true)
+                                          .help("rate of the new loan, in percent"))
+                                      .arg(Arg::with_name("term")
+                                          .long("term")
+                                          .takes_value(true)
+                                          .required(true)
+                                          .help("term of the new loan, in years"))
+                                      .arg(Arg::with_name("housing")
+                                          .long("housing")
+                                          .takes_value(true)
+                                          .default_value("0")
+                                          .help("monthly property taxes, insurance and HOA dues that come with it"))
+                                      .arg(Arg::with_name("down")
+                                          .long("down")
+                                          .takes_value(true)
+                                          .help("down payment, to show the price it affords"))
+                                      .arg(Arg::with_name("currency")
+                                          .long("currency")
+                                          .takes_value(true)
+                                          .default_value("USD")
+                                          .help("currency of the income and the new loan"))
+                                      )
+                          .subcommand(SubCommand::with_name("selftest")
+                                      .about("Checks this build against a set of loans with known schedules, before trusting it with money")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      )
+                          .subcommand(SubCommand::with_name("rate-sheet")
+                                      .about("Prints the monthly payment on an amount across a range of rates and terms, for shopping for a loan")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("principal")
+                                          .long("principal")
+                                          .takes_value(true)
+                                          .required(true)
+                                          .help("amount to borrow"))
+                                      .arg(Arg::with_name("apr-from")
+                                          .long("apr-from")
+                                          .takes_value(true)
+                                          .required(true)
+                                          .help("lowest rate, in percent"))
+                                      .arg(Arg::with_name("apr-to")
+                                          .long("apr-to")
+                                          .takes_value(true)
+                                          .required(true)
+                                          .help("highest rate, in percent"))
+                                      .arg(Arg::with_name("step")
+                                          .long("step")
+                                          .takes_value(true)
+                                          .default_value("0.25")
+                                          .help("difference between rates, in percentage points"))
+                                      .arg(Arg::with_name("terms")
+                                          .long("terms")
+                                          .takes_value(true)
+                                          .use_delimiter(true)
+                                          .default_value("10,15,20,30")
+                                          .help("terms in years, e.g. 15,30"))
+                                      )
+                          .subcommand(SubCommand::with_name("aprc")
+                                      .about("Calculates the EU annual percentage rate of charge, including fees")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("upfront")
+                                          .long("upfront")
+                                          .takes_value(true)
+                                          .default_value("0")
+                                          .help("mandatory fees paid at signing (financed fees are included automatically)"))
+                                      .arg(Arg::with_name("monthly-fee")
+                                          .long("monthly-fee")
+                                          .takes_value(true)
+                                          .default_value("0")
+                                          .help("mandatory fee charged with every payment"))
+                                      )
+                          .subcommand(SubCommand::with_name("rate")
+                                      .about("Records exchange rates used to convert between currencies")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("from")
+                                           .help("Currency being converted, e.g. EUR")
+                                           .required_unless("file")
+                                           .index(2))
+                                      .arg(Arg::with_name("to")
+                                           .help("Currency it converts to, e.g. USD")
+                                           .required_unless("file")
+                                           .index(3))
+                                      .arg(Arg::with_name("rate")
+                                           .help("Units of TO per unit of FROM")
+                                           .required_unless("file")
+                                           .index(4))
+                                      .arg(Arg::with_name("file")
+                                          .long("file")
+                                          .short("f")
+                                          .takes_value(true)
+                                          .conflicts_with("from")
+                                          .help("read rates from a file with one FROM TO RATE per line"))
+                                      .arg(Arg::with_name("date")
+                                          .long("date")
+                                          .short("d")
+                                          .takes_value(true)
+                                          .help("date the rates apply from (if omitted, current date assumed)"))
+                                      )
+                          .subcommand(SubCommand::with_name("collateral")
+                                      .about("Records what secures a loan, or shows its loan-to-value over time and when PMI can come off")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("value")
+                                          .long("value")
+                                          .takes_value(true)
+                                          .help("record a valuation (appraisal, estimate) of the collateral"))
+                                      .arg(Arg::with_name("date")
+                                          .long("date")
+                                          .short("d")
+                                          .takes_value(true)
+                                          .requires("value")
+                                          .help("date of the valuation (if omitted, current date assumed)"))
+                                      )
+                          .subcommand(SubCommand::with_name("construction")
+                                      .about("Records draws on a construction loan, or converts it to an amortizing loan once the work is done")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan (created with --draw for the construction period)")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("draw")
+                                          .long("draw")
+                                          .takes_value(true)
+                                          .conflicts_with("complete")
+                                          .help("amount disbursed, added to the balance"))
+                                      .arg(Arg::with_name("complete")
+                                          .long("complete")
+                                          .help("end the construction phase, amortizing what was drawn over the rest of the term"))
+                                      .arg(Arg::with_name("date")
+                                          .long("date")
+                                          .short("d")
+                                          .takes_value(true)
+                                          .help("date of the draw or completion (if omitted, current date assumed)"))
+                                      )
+                          .subcommand(SubCommand::with_name("validate")
+                                      .about("Compares a loan's schedule with one from the lender, showing per-row differences and which rounding settings reproduce it")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
                                            .required(true)
                                            .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("FILE")
+                                           .help("CSV schedule with a header naming payment, interest, principal and balance columns")
+                                           .required(true)
+                                           .index(3))
                                       )
-                          .subcommand(SubCommand::with_name("create")
-                                      .about("Creates a new loan")
+                          .subcommand(SubCommand::with_name("index")
+                                      .about("Imports or shows interest rate index values (SOFR, prime...) that adjustable-rate loans reset against")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of the index (if omitted, lists every index)")
+                                           .index(2))
+                                      .arg(Arg::with_name("file")
+                                          .long("file")
+                                          .short("f")
+                                          .takes_value(true)
+                                          .requires("name")
+                                          .help("import values from a CSV file of DATE,RATE lines; future dates project later resets"))
+                                      )
+                          .subcommand(SubCommand::with_name("asset")
+                                      .about("Records the value of something you own (savings, a house...) for the status net position, or lists them")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of the asset (if omitted, lists every asset)")
+                                           .requires("value")
+                                           .index(2))
+                                      .arg(Arg::with_name("value")
+                                           .help("What it's worth")
+                                           .index(3))
+                                      .arg(Arg::with_name("date")
+                                          .long("date")
+                                          .short("d")
+                                          .takes_value(true)
+                                          .help("date of the value (if omitted, current date assumed)"))
+                                      .arg(Arg::with_name("currency")
+                                          .long("currency")
+                                          .takes_value(true)
+                                          .default_value("USD")
+                                          .help("currency the asset is held in"))
+                                      )
+                          .subcommand(SubCommand::with_name("status")
+                                      .about("Summarizes all loans, and your net position, in a single reporting currency")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required_unless("schema")
+                                           .index(1))
+                                      .arg(Arg::with_name("currency")
+                                          .long("currency")
+                                          .takes_value(true)
+                                          .default_value("USD")
+                                          .help("reporting currency"))
+                                      )
+                          .subcommand(SubCommand::with_name("closing")
+                                      .about("Shows a paid-off loan's closing summary: what it cost and how it went against the schedule")
                                       .version("0.1.0")
                                       .author("T. Jameson Little <t.jameson.little@gmail.com>")
                                       .arg(Arg::with_name("DB")
@@ -196,31 +3259,114 @@ fn main() {
                                            .help("Name of loan")
                                            .required(true)
                                            .index(2))
-                                      .arg(Arg::with_name("balance")
-                                          .short("b")
-                                          .long("balance")
+                                      .arg(Arg::with_name("file")
+                                          .long("file")
+                                          .short("f")
                                           .takes_value(true)
-                                          .required(true)
-                                          .help("balance of the loan"))
-                                      .arg(Arg::with_name("start")
-                                          .long("start")
+                                          .help("write the summary to a file instead"))
+                                      )
+                          .subcommand(SubCommand::with_name("savings")
+                                      .about("Ranks loans by how much interest and time the extra payments made on them have saved")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      )
+                          .subcommand(SubCommand::with_name("milestones")
+                                      .about("Lists the milestones loans have reached: half paid, down to the PMI threshold, in the final year, paid off")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Only this loan's milestones")
+                                           .index(2))
+                                      .arg(Arg::with_name("notify")
+                                          .long("notify")
                                           .takes_value(true)
-                                          .help("first payment due date"))
-                                      .arg(Arg::with_name("apr")
-                                          .short("a")
-                                          .long("apr")
+                                          .help("shell command run for each milestone reached since the last check, with AMORT_LOAN, AMORT_MILESTONE and AMORT_MESSAGE set"))
+                                      )
+                          .subcommand(SubCommand::with_name("cashflow")
+                                      .about("Lists the total due across all loans for each upcoming month")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("currency")
+                                          .long("currency")
+                                          .takes_value(true)
+                                          .default_value("USD")
+                                          .help("reporting currency"))
+                                      .arg(Arg::with_name("months")
+                                          .long("months")
+                                          .takes_value(true)
+                                          .default_value("12")
+                                          .help("months to project"))
+                                      )
+                          .subcommand(SubCommand::with_name("deferment")
+                                      .about("Projects a student loan as subsidized and as unsubsidized through its deferment")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan (created with --deferment)")
+                                           .required(true)
+                                           .index(2))
+                                      )
+                          .subcommand(SubCommand::with_name("idr")
+                                      .about("Projects a loan under an income-driven repayment plan")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("income")
+                                          .long("income")
                                           .takes_value(true)
                                           .required(true)
-                                          .help("apr"))
-                                      .arg(Arg::with_name("term")
-                                          .short("t")
-                                          .long("term")
+                                          .help("annual income"))
+                                      .arg(Arg::with_name("growth")
+                                          .long("growth")
+                                          .takes_value(true)
+                                          .default_value("0")
+                                          .help("yearly income growth, in percent"))
+                                      .arg(Arg::with_name("poverty")
+                                          .long("poverty")
                                           .takes_value(true)
                                           .required(true)
-                                          .help("apr"))
+                                          .help("annual poverty guideline for your household size"))
+                                      .arg(Arg::with_name("protected")
+                                          .long("protected")
+                                          .takes_value(true)
+                                          .default_value("150")
+                                          .help("percent of the poverty guideline excluded from discretionary income"))
+                                      .arg(Arg::with_name("percent")
+                                          .long("percent")
+                                          .takes_value(true)
+                                          .default_value("10")
+                                          .help("percent of discretionary income paid"))
+                                      .arg(Arg::with_name("years")
+                                          .long("years")
+                                          .takes_value(true)
+                                          .default_value("20")
+                                          .help("years until the remaining balance is forgiven"))
                                       )
-                          .subcommand(SubCommand::with_name("pay")
-                                      .about("Pay a loan")
+                          .subcommand(SubCommand::with_name("escrow")
+                                      .about("Projects a mortgage's escrow account over the next year, like an annual escrow review")
                                       .version("0.1.0")
                                       .author("T. Jameson Little <t.jameson.little@gmail.com>")
                                       .arg(Arg::with_name("DB")
@@ -231,56 +3377,1098 @@ fn main() {
                                            .help("Name of loan")
                                            .required(true)
                                            .index(2))
-                                      .arg(Arg::with_name("amount")
-                                          .short("a")
-                                          .long("amount")
+                                      .arg(Arg::with_name("balance")
+                                          .long("balance")
+                                          .takes_value(true)
+                                          .default_value("0")
+                                          .help("current escrow balance"))
+                                      .arg(Arg::with_name("deposit")
+                                          .long("deposit")
+                                          .takes_value(true)
+                                          .default_value("0")
+                                          .help("escrow portion of the current monthly payment"))
+                                      .arg(Arg::with_name("tax")
+                                          .long("tax")
+                                          .takes_value(true)
+                                          .help("yearly property taxes"))
+                                      .arg(Arg::with_name("tax-months")
+                                          .long("tax-months")
+                                          .takes_value(true)
+                                          .use_delimiter(true)
+                                          .default_value("12")
+                                          .help("months (1-12) taxes are paid in, split evenly, e.g. 4,10"))
+                                      .arg(Arg::with_name("insurance")
+                                          .long("insurance")
+                                          .takes_value(true)
+                                          .help("yearly homeowner's insurance premium"))
+                                      .arg(Arg::with_name("insurance-months")
+                                          .long("insurance-months")
+                                          .takes_value(true)
+                                          .use_delimiter(true)
+                                          .default_value("1")
+                                          .help("months (1-12) the premium is paid in"))
+                                      .arg(Arg::with_name("cushion")
+                                          .long("cushion")
+                                          .takes_value(true)
+                                          .default_value("2")
+                                          .help("months of disbursements the lender keeps in reserve"))
+                                      )
+                          .subcommand(SubCommand::with_name("reverse")
+                                      .about("Projects a reverse mortgage balance against the home's value")
+                                      .version("0.1.0")
+                                      .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                                      .arg(Arg::with_name("DB")
+                                           .help("Database to use")
+                                           .required(true)
+                                           .index(1))
+                                      .arg(Arg::with_name("name")
+                                           .help("Name of loan")
+                                           .required(true)
+                                           .index(2))
+                                      .arg(Arg::with_name("home")
+                                          .long("home")
                                           .takes_value(true)
                                           .required(true)
-                                          .help("payment amount"))
-                                      .arg(Arg::with_name("extra")
-                                          .short("e")
-                                          .long("extra")
-                                          .takes_value(false)
-                                          .help("this payment goes 100% to principal"))
-                                      .arg(Arg::with_name("date")
-                                          .long("date")
-                                          .short("d")
+                                          .help("current home value"))
+                                      .arg(Arg::with_name("appreciation")
+                                          .long("appreciation")
                                           .takes_value(true)
-                                          .help("date of payment (if omitted, current date assumed)"))
+                                          .default_value("3")
+                                          .help("yearly home appreciation, in percent"))
+                                      .arg(Arg::with_name("draw")
+                                          .long("draw")
+                                          .takes_value(true)
+                                          .default_value("0")
+                                          .help("amount drawn each month"))
+                                      .arg(Arg::with_name("years")
+                                          .long("years")
+                                          .takes_value(true)
+                                          .default_value("30")
+                                          .help("years to project"))
                                       )
                           .get_matches();
 
-    let app = Amortizer{
-        verbosity: matches.occurrences_of("v"),
-    };
+    if let Some(path) = trace_file(&matches) {
+        if let Err(err) = trace::trace_to(Path::new(path)) {
+            println!("{}", trf("Couldn't open trace file {}: {}", &[&path, &err]));
+            std::process::exit(1);
+        }
+    }
+
+    let app = Amortizer{
+        verbosity: matches.occurrences_of("v"),
+        inflation: parse_value(&matches, "inflation"),
+        // escapes would end up in files and pipes; NO_COLOR counts when set
+        // to anything but an empty string
+        color: !matches.is_present("no-color") && std::env::var_os("NO_COLOR").map_or(true, |value| value.is_empty()) && io::stdout().is_terminal(),
+        user: user(&matches).map(|user| user.to_string()),
+        renderer: output(&matches),
+        simple: matches.is_present("simple"),
+        anchor: parse_value(&matches, "anchor").unwrap_or(Anchor::Today),
+    };
+
+    if schema_requested(&matches) {
+        let name = match matches.subcommand_name() {
+            None => "list",
+            Some("export") => "schedule",
+            Some("history") => "history",
+            Some("status") => "summary",
+            Some(command) => {
+                error!("{}", trf("{} has no JSON schema; try the loan listing, export, history or status", &[&command]));
+                std::process::exit(1);
+            },
+        };
+        print!("{}", schema::lookup(name).unwrap().json());
+        return;
+    }
+
+    if let Some(ref user) = app.user {
+        if let Some(db) = matches.subcommand().1.and_then(|matches| matches.value_of("DB")).or_else(|| matches.value_of("DB")) {
+            match amortization::load_users(Path::new(db)) {
+                Ok(ref users) if !users.contains(user) => {
+                    error!("{}", trf("Unknown user: {}. Add them with the user command.", &[&user]));
+                    std::process::exit(1);
+                },
+                _ => {},
+            }
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("init") {
+        let db = matches.value_of("DB").unwrap();
+        if let Err(err) = amortization::init_db(Path::new(db)) {
+            error!("{}", trf("Error creating database: {}", &[&err]));
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("create") {
+        let db = matches.value_of("DB").unwrap();
+        if let Some(path) = matches.value_of("from-file") {
+            create_loans_from_file(Path::new(db), path);
+            return;
+        }
+        let fees = fees_from_args(matches);
+        let addons = addons_from_args(matches, matches.value_of("term").unwrap().parse::<i32>().unwrap_or(0) * 12);
+        let borrowers = borrowers_from_args(matches);
+        let loan = create_loan_from_args(matches, &fees, &addons);
+        let name = loan.name.clone();
+        if let Err(err) = amortization::create_loan(Path::new(db), loan) {
+            error!("{}", trf("Error adding loan {}: {}", &[&name, &err]));
+            std::process::exit(1);
+        }
+        if !fees.is_empty() {
+            if let Err(err) = amortization::add_fees(Path::new(db), &name, &fees) {
+                println!("{}", trf("Error saving to database: {}", &[&err]));
+            }
+        }
+        if !addons.is_empty() {
+            if let Err(err) = amortization::add_addons(Path::new(db), &name, &addons) {
+                println!("{}", trf("Error saving to database: {}", &[&err]));
+            }
+        }
+        if !borrowers.is_empty() {
+            if let Err(err) = amortization::set_borrowers(Path::new(db), &name, &borrowers) {
+                println!("{}", trf("Error saving to database: {}", &[&err]));
+            }
+        }
+        if matches.is_present("backfill") {
+            app.backfill(Path::new(db), &name);
+        } else {
+            let loan = app.require_loan(Path::new(db), &name);
+            let today = amortization::today();
+            let due = (1..loan.periods + 1).take_while(|&period| loan.due_date(period) <= today).count();
+            if due > 0 {
+                println!("{}", trf("{} payments were due before today. Run backfill to fill them in for review.", &[&due]));
+            }
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("backfill") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        app.backfill(db, &loan.name);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("pending") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        let ids: Vec<i32> = matches.values_of("id").map_or(Vec::new(), |ids| ids.map(|id| id.parse().unwrap_or_else(|_| {
+            error!("{}", trf("Payment numbers must be whole numbers, got: {}", &[&id]));
+            std::process::exit(1);
+        })).collect());
+        if matches.is_present("confirm") {
+            match amortization::confirm_pending(db, &loan.name, &ids) {
+                Ok(confirmed) => {
+                    let mut table = Table::new(&["id", "date", "principal", "interest"]);
+                    for payment in confirmed.iter() {
+                        table.push(vec![Cell::Number(payment.id.to_string()), Cell::Text(time::strftime("%F", &time::at_utc(payment.date)).unwrap()),
+                                        Cell::Number(loan.money(payment.principal)), Cell::Number(loan.money(payment.interest))]);
+                    }
+                    app.render(&table);
+                    if app.plain() {
+                        println!("{}", trf("Confirmed {} payments on {}", &[&confirmed.len(), &loan.name]));
+                    }
+                },
+                Err(err) => println!("{}", trf("Error saving to database: {}", &[&err])),
+            };
+        } else if matches.is_present("reject") {
+            match amortization::reject_pending(db, &loan.name, &ids) {
+                Ok(rejected) => println!("{}", trf("Rejected {} payments on {}", &[&rejected, &loan.name])),
+                Err(err) => println!("{}", trf("Error saving to database: {}", &[&err])),
+            };
+        } else {
+            app.print_pending(db, &loan.name);
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("lender") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let name = matches.value_of("lender").unwrap();
+        // keep existing details that weren't given again
+        let mut lender = match amortization::load_lender(db, name) {
+            Ok(lender) => lender,
+            Err(_) => Lender{ name: name.to_string(), account_number: None, url: None, phone: None },
+        };
+        if let Some(account) = matches.value_of("account") {
+            lender.account_number = Some(account.to_string());
+        }
+        if let Some(url) = matches.value_of("url") {
+            lender.url = Some(url.to_string());
+        }
+        if let Some(phone) = matches.value_of("phone") {
+            lender.phone = Some(phone.to_string());
+        }
+        if let Err(err) = amortization::save_lender(db, &lender) {
+            println!("{}", trf("Error saving to database: {}", &[&err]));
+            std::process::exit(1);
+        }
+        if let Some(loans) = matches.values_of("loans") {
+            for loan in loans {
+                let loan = app.require_loan(db, loan);
+                if let Err(err) = amortization::set_lender(db, &loan.name, name) {
+                    println!("{}", trf("Error saving to database: {}", &[&err]));
+                }
+            }
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("group") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let name = matches.value_of("group").unwrap();
+        let mut group = match amortization::load_group(db, name) {
+            Ok(group) => group,
+            Err(_) => LoanGroup{ name: name.to_string(), allocation: Allocation::Standard },
+        };
+        if let Some(allocation) = parse_value(matches, "allocation") {
+            group.allocation = allocation;
+        }
+        if let Err(err) = amortization::save_group(db, &group) {
+            println!("{}", trf("Error saving to database: {}", &[&err]));
+            std::process::exit(1);
+        }
+        if let Some(loans) = matches.values_of("loans") {
+            for loan in loans {
+                let loan = app.require_loan(db, loan);
+                if let Err(err) = amortization::set_group(db, &loan.name, Some(name)) {
+                    println!("{}", trf("Error saving to database: {}", &[&err]));
+                }
+            }
+        }
+        if let Some(loans) = matches.values_of("remove") {
+            for loan in loans {
+                let loan = app.require_loan(db, loan);
+                if loan.loan_group.as_ref().map_or(true, |group| group != name) {
+                    error!("{}", trf("{} isn't in group {}", &[&loan.name, &name]));
+                    std::process::exit(1);
+                }
+                if let Err(err) = amortization::set_group(db, &loan.name, None) {
+                    println!("{}", trf("Error saving to database: {}", &[&err]));
+                }
+            }
+        }
+        if let Some(amount) = parse_value(matches, "pay") {
+            let date = parse_date(matches, "date").unwrap_or_else(amortization::today);
+            let loans = amortization::load_group_loans(db, name).unwrap_or_else(|err| {
+                error!("{}", trf("Error with statement: {}", &[&err]));
+                std::process::exit(1);
+            });
+            let due: f64 = loans.iter().filter(|loan| loan.balance > 0f64).map(|loan| loan.amount_due(date)).sum();
+            if amount < due {
+                println!("{}", trf("Amount paid is insufficient payment. Expected {}, got {}", &[&format!("{:.2}", due), &amount]));
+                std::process::exit(1);
+            }
+            let method = matches.value_of("method").map(|method| method.to_string());
+            if let Err(err) = amortization::pay_group(db, name, amount, date, method) {
+                println!("{}", trf("Error saving to database: {}", &[&err]));
+            }
+        }
+        if matches.is_present("history") {
+            app.print_group_payments(db, &group);
+        }
+        if matches.is_present("schedule") {
+            app.print_group_schedule(db, &group);
+        }
+        app.print_group(db, &group);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("impounds") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        if let Err(err) = amortization::set_impounds(db, &loan.name, parse_value(matches, "escrow").unwrap(), parse_value(matches, "pmi").unwrap()) {
+            println!("{}", trf("Error saving to database: {}", &[&err]));
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("paid-ahead") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        if let Err(err) = amortization::set_paid_ahead(db, &loan.name, !matches.is_present("off")) {
+            println!("{}", trf("Error saving to database: {}", &[&err]));
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("merge") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let other = Path::new(matches.value_of("other").unwrap());
+        if !other.exists() {
+            error!("{}", trf("No such database: {}", &[&other.display()]));
+            std::process::exit(1);
+        }
+        if db.canonicalize().ok() == other.canonicalize().ok() {
+            error!("{}", tr("Can't merge a database into itself"));
+            std::process::exit(1);
+        }
+        match amortization::merge_db(db, other) {
+            Ok(summary) => println!("{}", trf("Loans: {} added, {} updated, {} deleted. Transactions: {} added, {} updated, {} deleted.",
+                                              &[&summary.loans_added, &summary.loans_updated, &summary.loans_deleted, &summary.transactions_added,
+                                                &summary.transactions_updated, &summary.transactions_deleted])),
+            Err(err) => {
+                println!("{}", trf("Error saving to database: {}", &[&err]));
+                std::process::exit(1);
+            },
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("serve") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let addr = matches.value_of("listen").unwrap();
+        let metrics = matches.value_of("metrics");
+        println!("{}", trf("Serving a calculator on http://{}/calculate", &[&addr]));
+        if let Some(metrics) = metrics {
+            println!("{}", trf("Serving metrics on http://{}/metrics", &[&metrics]));
+        }
+        if let Err(err) = server::serve(db, addr, metrics, parse_value(matches, "rate-limit").unwrap()) {
+            error!("{}", trf("Error serving: {}", &[&err]));
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    if let Some(matches) = matches.subcommand_matches("init") {
-        let db = matches.value_of("DB").unwrap();
-        amortization::init_db(Path::new(db));
+    if let Some(matches) = matches.subcommand_matches("changes") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        match amortization::load_changes(db, matches.value_of("since")) {
+            Ok(changes) => {
+                let mut table = Table::new(&["time", "op", "table", "row", "uid"]);
+                for change in changes {
+                    table.push(vec![Cell::Text(change.time), Cell::Text(change.op), Cell::Text(change.table), Cell::Text(change.row), Cell::Text(change.uid)]);
+                }
+                app.render(&table);
+            },
+            Err(err) => println!("{}", trf("Error with statement: {}", &[&err])),
+        };
         return;
     }
 
-    if let Some(matches) = matches.subcommand_matches("create") {
-        let db = matches.value_of("DB").unwrap();
-        let loan = create_loan_from_args(matches);
-        amortization::create_loan(Path::new(db), loan);
+    if let Some(matches) = matches.subcommand_matches("user") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        match matches.value_of("name") {
+            Some(name) => {
+                if let Err(err) = amortization::add_user(db, name) {
+                    println!("{}", trf("Error saving to database: {}", &[&err]));
+                    std::process::exit(1);
+                }
+                if let Some(loans) = matches.values_of("loans") {
+                    for loan in loans {
+                        let loan = app.require_loan(db, loan);
+                        if let Err(err) = amortization::set_owner(db, &loan.name, Some(name)) {
+                            println!("{}", trf("Error saving to database: {}", &[&err]));
+                        }
+                    }
+                }
+            },
+            None => app.print_user_totals(db),
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("borrowers") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        match matches.value_of("name") {
+            Some(name) if matches.is_present("borrower") => {
+                let name = app.require_loan(db, name).name;
+                if let Err(err) = amortization::set_borrowers(db, &name, &borrowers_from_args(matches)) {
+                    println!("{}", trf("Error saving to database: {}", &[&err]));
+                }
+            },
+            Some(name) => app.print_borrowers(db, &app.require_loan(db, name)),
+            None => app.print_borrower_totals(db),
+        };
         return;
     }
 
     if let Some(matches) = matches.subcommand_matches("pay") {
         let db = matches.value_of("DB").unwrap();
-        let (name, amount, extra, date) = create_transaction_from_args(matches);
-        match amortization::commit_transaction(Path::new(db), name, amount, extra, date) {
+        let (name, amount, extra, date, method) = create_transaction_from_args(matches);
+        if !matches.is_present("force") {
+            if let Some(payment) = app.duplicate(Path::new(db), &name, amount, date) {
+                println!("{}", trf("A payment of {} on {} was already recorded that day (#{}). Use --force to record another.",
+                                   &[&format!("{:.2}", amount), &name, &payment.id]));
+                std::process::exit(1);
+            }
+        }
+        match amortization::commit_transaction(Path::new(db), name, amount, extra, date, method) {
+            Err(ref err) if amortization::refusal(err).is_some() => {
+                println!("{}", err);
+                std::process::exit(1);
+            },
+            Err(err) => {
+                println!("{}", trf("Error saving to database: {}", &[&err]));
+            },
+            Ok(ref transaction) => {
+                if app.simple {
+                    app.explain_transaction(transaction);
+                }
+                if let Ok(Some(summary)) = amortization::closing_summary(Path::new(db), &transaction.name) {
+                    let loan = app.require_loan(Path::new(db), &transaction.name);
+                    println!();
+                    export::closing_text(&mut io::stdout(), &loan, &summary).unwrap();
+                }
+            },
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("import") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        let path = matches.value_of("FILE").unwrap();
+        let mut contents = String::new();
+        let rows = File::open(path).and_then(|mut f| f.read_to_string(&mut contents)).map_err(|err| err.to_string())
+            .and_then(|_| import::parse_payments(&contents));
+        let mut rows = match rows {
+            Ok(rows) => rows,
+            Err(err) => {
+                error!("{}", trf("Error reading {}: {}", &[&path, &err]));
+                std::process::exit(1);
+            }
+        };
+        rows.sort_by_key(|row| row.date);
+        if let Some(kind) = parse_value(matches, "as") {
+            for row in rows.iter_mut().filter(|row| row.kind.is_none()) {
+                row.kind = Some(kind);
+            }
+        }
+        app.import_payments(db, &loan.name, &rows, matches.is_present("pending"), matches.is_present("force"));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("bounce") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let id: i32 = parse_value(matches, "payment").unwrap();
+        let fee = parse_value(matches, "fee").unwrap_or(0f64);
+        match amortization::bounce_payment(db, id, fee) {
+            Ok(transaction) => {
+                println!("{}", trf("Payment #{} on {} reversed. ${} was put back on the balance.",
+                                   &[&transaction.id, &transaction.name, &format!("{:.2}", transaction.principal + fee)]));
+            },
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                println!("{}", trf("No payment #{} that hasn't already been reversed", &[&id]));
+                std::process::exit(1);
+            },
+            Err(err) => println!("{}", trf("Error saving to database: {}", &[&err])),
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("adjust") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        let amount: f64 = parse_value(matches, "amount").unwrap();
+        let date = parse_date(matches, "date").unwrap_or_else(amortization::today);
+        match amortization::adjust_balance(db, &loan.name, amount, date) {
+            Ok(adjustment) => println!("{}", trf("Adjustment #{} recorded. The balance is now {}.", &[&adjustment.id, &loan.money(loan.balance + amount)])),
+            Err(err) => println!("{}", trf("Error saving to database: {}", &[&err])),
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("history") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        app.print_history(db, matches.value_of("name"), matches.value_of("method"));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("export") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let anchor = parse_value(matches, "anchor").unwrap_or(Anchor::Origination);
+        let loans = match matches.value_of("name") {
+            Some(name) => vec![app.require_loan(db, name)],
+            None => amortization::load_loans(db).unwrap_or_else(|err| {
+                error!("{}", trf("Error loading loans: {}", &[&err]));
+                std::process::exit(1);
+            }).into_iter().filter(|loan| app.owned(loan)).collect(),
+        };
+        let stdout = io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+        let res = match matches.value_of("format") {
+            Some("json") => export::portfolio_json(&mut out, &loans, anchor, amortization::today()),
+            _ => export::portfolio_csv(&mut out, &loans, anchor, amortization::today()),
+        };
+        if let Err(err) = res.and_then(|_| out.flush()) {
+            error!("{}", trf("Error writing export: {}", &[&err]));
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("calendar") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        let payments = amortization::load_transactions(db, Some(&loan.name), None).unwrap_or_else(|err| {
+            error!("{}", trf("Error loading payments: {}", &[&err]));
+            std::process::exit(1);
+        });
+        let year = parse_value(matches, "year").unwrap_or_else(|| time::at_utc(amortization::today()).tm_year + 1900);
+        let days = export::calendar(&loan, &payments, year, amortization::today());
+        let stdout = io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+        let res = match matches.value_of("format") {
+            Some("html") => export::calendar_html(&mut out, &loan, &days),
+            _ => export::calendar_csv(&mut out, &loan, &days).map(|_| ()),
+        };
+        if let Err(err) = res.and_then(|_| out.flush()) {
+            error!("{}", trf("Error writing calendar: {}", &[&err]));
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("accrue") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let date = parse_date(matches, "date").unwrap_or_else(amortization::today);
+        match amortization::accrue_interest(db, matches.value_of("name"), date) {
+            Ok(accrued) => {
+                let mut table = Table::new(&["loan", "accrued"]);
+                for (name, interest) in accrued {
+                    table.push(vec![Cell::Text(name), Cell::Number(format!("{:.2}", interest))]);
+                }
+                app.render(&table);
+            },
+            Err(err) => println!("{}", trf("Error saving to database: {}", &[&err])),
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("payoff") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let name = app.require_loan(db, matches.value_of("name").unwrap()).name;
+        let date = parse_date(matches, "date").unwrap_or_else(amortization::today);
+        match amortization::payoff_quote(db, &name, date) {
+            Ok(quote) => app.print_payoff(quote),
+            Err(err) => println!("{}", trf("Error with statement: {}", &[&err])),
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("income") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let paid = matches.is_present("paid");
+        let start = amortization::fiscal_year_start(db).unwrap_or_else(|err| {
+            error!("{}", trf("Error loading fiscal year: {}", &[&err]));
+            std::process::exit(1);
+        });
+        let user = app.user.as_ref().map(|user| &user[..]);
+        let income = if paid {
+            amortization::interest_paid(db, parse_value(matches, "year"), user)
+        } else {
+            amortization::interest_income(db, parse_value(matches, "year"), user)
+        };
+        match income {
+            Ok(income) => app.print_income(&income, paid, start),
+            Err(err) => println!("{}", trf("Error with statement: {}", &[&err])),
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("fiscal-year") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        if let Some(start) = parse_value::<i32>(matches, "start") {
+            if start < 1 || start > 12 {
+                error!("{}", trf("Invalid value for start: {}", &[&start]));
+                std::process::exit(1);
+            }
+            if let Err(err) = amortization::set_fiscal_year_start(db, start) {
+                println!("{}", trf("Error saving to database: {}", &[&err]));
+                std::process::exit(1);
+            }
+        }
+        match amortization::fiscal_year_start(db) {
+            Ok(start) => {
+                let year = amortization::fiscal_year(amortization::today(), start);
+                println!("{}", trf("Fiscal year starts in {}; FY{} began {}", &[&i18n::month_name(start), &year,
+                                                                                &time::strftime("%F", &time::at_utc(amortization::fiscal_year_begins(year, start))).unwrap()]));
+            },
+            Err(err) => println!("{}", trf("Error loading fiscal year: {}", &[&err])),
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("statement") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        let lender = loan.lender.as_ref().and_then(|lender| amortization::load_lender(db, lender).ok());
+        let borrowers = amortization::load_borrowers(db, &loan.name).unwrap_or_else(|err| {
+            error!("{}", trf("Error loading borrowers: {}", &[&err]));
+            std::process::exit(1);
+        });
+
+        let lines = if let Some(month) = matches.value_of("month") {
+            let month = match time::strptime(&format!("{}-01", month), "%F") {
+                Ok(month) => month,
+                Err(_) => {
+                    error!("{}", trf("Invalid value for month: {}", &[&month]));
+                    std::process::exit(1);
+                },
+            };
+            match amortization::monthly_statement(db, &loan.name, month.tm_year + 1900, month.tm_mon + 1) {
+                Ok(monthly) => statement::monthly(&monthly, lender.as_ref(), &borrowers),
+                Err(err) => {
+                    error!("{}", trf("Error with statement: {}", &[&err]));
+                    std::process::exit(1);
+                },
+            }
+        } else {
+            let from = parse_date(matches, "from").unwrap_or_else(|| {
+                let start = amortization::fiscal_year_start(db).unwrap_or(1);
+                amortization::fiscal_year_begins(amortization::fiscal_year(amortization::today(), start), start)
+            });
+            let to = parse_date(matches, "to").unwrap_or_else(|| amortization::today() + time::Duration::days(1));
+            match amortization::load_transactions(db, Some(&loan.name), None) {
+                Ok(payments) => statement::statement(&loan, lender.as_ref(), &borrowers, &payments, from, to),
+                Err(err) => {
+                    error!("{}", trf("Error with statement: {}", &[&err]));
+                    std::process::exit(1);
+                },
+            }
+        };
+
+        let res = match matches.value_of("pdf") {
+            Some(path) => File::create(path).and_then(|mut f| statement::write_pdf(&mut f, &lines)),
+            None => {
+                let stdout = io::stdout();
+                let mut out = stdout.lock();
+                statement::write_text(&mut out, &lines)
+            },
+        };
+        if let Err(err) = res {
+            error!("{}", trf("Error writing statement: {}", &[&err]));
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("promos") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        match amortization::refresh_statuses(db).and_then(|_| amortization::deferred_promos(db, amortization::today())) {
+            Ok(promos) => {
+                if promos.is_empty() && app.plain() {
+                    println!("{}", tr("No deferred-interest promotions running"));
+                    return;
+                }
+                let today = amortization::today();
+                let mut table = Table::new(&["loan", "deferred interest", "balance", "deadline", "due soon"]);
+                for promo in promos.iter() {
+                    table.push(vec![Cell::Text(promo.loan.clone()), Cell::Number(format!("{:.2}", promo.interest)), Cell::Number(format!("{:.2}", promo.balance)),
+                                    Cell::Text(time::strftime("%F", &time::at_utc(promo.deadline)).unwrap()),
+                                    Cell::Text(if promo.due_soon(today) { "yes" } else { "" }.to_string())]);
+                }
+                app.render(&table);
+                if app.plain() {
+                    for promo in promos.iter().filter(|promo| promo.due_soon(today)) {
+                        print!("{}: ", promo.loan);
+                        app.print_promo(promo);
+                    }
+                }
+            },
+            Err(err) => println!("{}", trf("Error with statement: {}", &[&err])),
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("progress") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        match amortization::loan_overlay(db, &loan.name, amortization::today()) {
+            Ok(overlay) => app.print_progress(&loan, &overlay),
+            Err(err) => println!("{}", trf("Error with statement: {}", &[&err])),
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("skip") {
+        let db = matches.value_of("DB").unwrap();
+        let name = matches.value_of("name").unwrap().to_string();
+        let date = parse_date(matches, "date").unwrap_or_else(amortization::today);
+        match amortization::skip_payment(Path::new(db), name, date) {
+            Err(ref err) if amortization::refusal(err).is_some() => {
+                println!("{}", err);
+                std::process::exit(1);
+            },
             Err(err) => {
-                println!("Error saving to database: {}", err);
+                println!("{}", trf("Error saving to database: {}", &[&err]));
             },
             _ => (),
         };
         return;
     }
 
+    if let Some(matches) = matches.subcommand_matches("consolidate") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let names: Vec<String> = matches.values_of("loans").unwrap().map(|name| name.to_string()).collect();
+        let loans: Vec<Loan> = names.iter().map(|name| app.require_loan(db, name)).collect();
+        let apr: f64 = parse_value(matches, "apr").unwrap();
+        let term: i32 = parse_value(matches, "term").unwrap();
+
+        let consolidation = consolidate::simulate(loans.iter().map(|loan| loan.schedule()), apr, term * 12);
+        app.print_consolidation(&loans, &consolidation);
+
+        if matches.is_present("execute") {
+            let start_time = parse_date(matches, "start").unwrap_or_else(amortization::today);
+            let loan = Loan::new(matches.value_of("into").unwrap().to_string(), consolidation.balance, term * 12, apr, start_time);
+            match amortization::consolidate_loans(db, &names, loan) {
+                Ok(_) => println!("{}", trf("Consolidated {} loans", &[&names.len()])),
+                Err(err) => println!("{}", trf("Error saving to database: {}", &[&err])),
+            };
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("simulate") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        let scenario = scenario_from_args(matches, String::new(), loan.name.clone());
+        app.print_scenario(&loan, &scenario);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("addons") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        let mut addons = amortization::load_addons(db, &loan.name).unwrap_or_else(|err| {
+            error!("{}", trf("Error loading add-ons: {}", &[&err]));
+            std::process::exit(1);
+        });
+        let added = addons_from_args(matches, loan.periods);
+        if !added.is_empty() {
+            let total: f64 = addons.iter().chain(added.iter()).map(|addon| addon.cost).sum();
+            if total > loan.principal {
+                error!("{}", trf("Add-ons come to {}, more than the {} financed", &[&format!("{:.2}", total), &format!("{:.2}", loan.principal)]));
+                std::process::exit(1);
+            }
+            if let Err(err) = amortization::add_addons(db, &loan.name, &added) {
+                error!("{}", trf("Error saving to database: {}", &[&err]));
+                std::process::exit(1);
+            }
+            addons.extend(added);
+        }
+        app.print_addons(&loan, &addons);
+        return;
+    }
+
+    if matches.subcommand_matches("selftest").is_some() {
+        if !app.print_selftest(&selftest::run()) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("strategies") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        app.print_strategies(&app.require_loan(db, matches.value_of("name").unwrap()));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("wait") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        let scenario = match matches.value_of("scenario") {
+            Some(name) => match amortization::load_scenario(db, name) {
+                Ok(ref scenario) if scenario.loan != loan.name => {
+                    error!("{}", trf("Scenario {} is for {}, not {}", &[&name, &scenario.loan, &loan.name]));
+                    std::process::exit(1);
+                },
+                Ok(scenario) => scenario,
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    println!("{}", trf("Could not find scenario with the name: {}", &[&name]));
+                    std::process::exit(1);
+                },
+                Err(err) => {
+                    error!("{}", trf("Error with statement: {}", &[&err]));
+                    std::process::exit(1);
+                },
+            },
+            None => scenario_from_args(matches, String::new(), loan.name.clone()),
+        };
+        if scenario.monthly_extra == 0f64 && scenario.lump_sums.is_empty() && scenario.refinance.is_none() && scenario.rate_shock == 0f64 {
+            error!("{}", tr("Nothing to put off: give --extra, --annual, --refi-apr or --scenario"));
+            std::process::exit(1);
+        }
+        let months: i32 = parse_value(matches, "months").unwrap();
+        if months < 0 {
+            error!("{}", trf("Invalid value for months: {}", &[&months]));
+            std::process::exit(1);
+        }
+        app.print_cost_of_waiting(&loan, &scenario, months);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("scenario") {
+        if let Some(matches) = matches.subcommand_matches("save") {
+            let db = Path::new(matches.value_of("DB").unwrap());
+            let loan = app.require_loan(db, matches.value_of("name").unwrap());
+            let scenario = scenario_from_args(matches, matches.value_of("scenario").unwrap().to_string(), loan.name.clone());
+            match amortization::save_scenario(db, &scenario) {
+                Ok(_) => app.print_scenario(&loan, &scenario),
+                Err(err) => println!("{}", trf("Error saving to database: {}", &[&err])),
+            };
+        } else if let Some(matches) = matches.subcommand_matches("run") {
+            let db = Path::new(matches.value_of("DB").unwrap());
+            let name = matches.value_of("scenario").unwrap();
+            match amortization::load_scenario(db, name) {
+                Ok(scenario) => app.print_scenario(&app.require_loan(db, &scenario.loan), &scenario),
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    println!("{}", trf("Could not find scenario with the name: {}", &[&name]));
+                    std::process::exit(1);
+                },
+                Err(err) => println!("{}", trf("Error with statement: {}", &[&err])),
+            };
+        } else if let Some(matches) = matches.subcommand_matches("compare") {
+            let db = Path::new(matches.value_of("DB").unwrap());
+            let mut scenarios = match amortization::load_scenarios(db) {
+                Ok(scenarios) => scenarios,
+                Err(err) => {
+                    error!("{}", trf("Error with statement: {}", &[&err]));
+                    std::process::exit(1);
+                }
+            };
+            if let Some(names) = matches.values_of("scenarios") {
+                let names: Vec<&str> = names.collect();
+                scenarios.retain(|scenario| names.contains(&scenario.name.as_str()));
+            }
+            app.print_scenarios(db, &scenarios);
+        } else {
+            println!("{}", matches.usage());
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("aprc") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        app.print_aprc(db, loan, parse_value(matches, "upfront").unwrap(), parse_value(matches, "monthly-fee").unwrap());
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("rate") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let rates = rates_from_args(matches);
+        let date = parse_date(matches, "date").unwrap_or_else(amortization::today);
+        match amortization::add_rates(db, &rates, date) {
+            Ok(_) => println!("{}", trf("Recorded {} exchange rates", &[&rates.len()])),
+            Err(err) => println!("{}", trf("Error saving to database: {}", &[&err])),
+        };
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("collateral") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        if let Some(value) = parse_value(matches, "value") {
+            let valuation = Valuation{
+                date: parse_date(matches, "date").unwrap_or_else(amortization::today),
+                value: value,
+            };
+            if let Err(err) = amortization::add_valuation(db, &loan.name, &valuation) {
+                error!("{}", trf("Error saving to database: {}", &[&err]));
+                std::process::exit(1);
+            }
+        }
+        app.print_collateral(db, &loan);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("construction") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let mut loan = app.require_loan(db, matches.value_of("name").unwrap());
+        let date = parse_date(matches, "date").unwrap_or_else(amortization::today);
+        if let Some(amount) = parse_value(matches, "draw") {
+            let draw = Draw{
+                date: date,
+                amount: amount,
+            };
+            if let Err(err) = amortization::record_draw(db, &loan.name, &draw) {
+                error!("{}", trf("Error saving to database: {}", &[&err]));
+                std::process::exit(1);
+            }
+            loan = app.require_loan(db, &loan.name);
+        } else if matches.is_present("complete") {
+            if date >= loan.maturity() {
+                error!("{}", trf("{} matures on {}, before it could convert", &[&loan.name, &time::strftime("%F", &time::at_utc(loan.maturity())).unwrap()]));
+                std::process::exit(1);
+            }
+            loan = amortization::complete_construction(db, &loan.name, date).unwrap_or_else(|err| {
+                error!("{}", trf("Error saving to database: {}", &[&err]));
+                std::process::exit(1);
+            });
+        }
+        app.print_draws(db, &loan);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("validate") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        let path = matches.value_of("FILE").unwrap();
+        let mut contents = String::new();
+        if let Err(err) = File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+            error!("{}", trf("Error reading {}: {}", &[&path, &err]));
+            std::process::exit(1);
+        }
+        match import::parse_schedule(&contents) {
+            Ok(expected) => app.print_validation(&loan, &expected),
+            Err(err) => {
+                error!("{}", trf("Error parsing {}: {}", &[&path, &err]));
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("index") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let name = matches.value_of("name");
+        if let Some(path) = matches.value_of("file") {
+            let mut contents = String::new();
+            if let Err(err) = File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+                error!("{}", trf("Error reading {}: {}", &[&path, &err]));
+                std::process::exit(1);
+            }
+            let values = match import::parse_index_values(&contents) {
+                Ok(values) => values,
+                Err(err) => {
+                    error!("{}", trf("Error parsing {}: {}", &[&path, &err]));
+                    std::process::exit(1);
+                },
+            };
+            match amortization::add_index_values(db, name.unwrap(), &values) {
+                Ok(_) => println!("{}", trf("Recorded {} values of {}", &[&values.len(), &name.unwrap()])),
+                Err(err) => println!("{}", trf("Error saving to database: {}", &[&err])),
+            };
+            return;
+        }
+
+        let indexes = match name {
+            Some(name) => amortization::load_index(db, name).map(|index| vec![index]),
+            None => amortization::load_indexes(db),
+        };
+        match indexes {
+            Ok(indexes) => app.print_indexes(&indexes, name.is_some()),
+            Err(err) => {
+                error!("{}", trf("Error loading indexes: {}", &[&err]));
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("asset") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        if let Some(name) = matches.value_of("name") {
+            let asset = Asset{
+                name: name.to_string(),
+                currency: matches.value_of("currency").unwrap().to_uppercase(),
+                date: parse_date(matches, "date").unwrap_or_else(amortization::today),
+                value: parse_value(matches, "value").unwrap(),
+            };
+            if let Err(err) = amortization::record_asset(db, &asset) {
+                error!("{}", trf("Error saving to database: {}", &[&err]));
+                std::process::exit(1);
+            }
+        }
+        app.print_assets(db);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("status") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        app.print_status(db, &matches.value_of("currency").unwrap().to_uppercase());
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("savings") {
+        app.print_savings(Path::new(matches.value_of("DB").unwrap()));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("closing") {
+        app.print_closing(Path::new(matches.value_of("DB").unwrap()), matches.value_of("name").unwrap(), matches.value_of("file"));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("milestones") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        app.print_milestones(db, matches.value_of("name"), matches.value_of("notify"));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("cashflow") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        app.print_cashflow(db, &matches.value_of("currency").unwrap().to_uppercase(), parse_value(matches, "months").unwrap());
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("deferment") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        if loan.deferment_periods == 0 {
+            error!("{}", trf("{} has no deferment; create it with --deferment", &[&loan.name]));
+            std::process::exit(1);
+        }
+        app.print_deferment(&loan);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("idr") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        app.print_idr(loan, idr_plan_from_args(matches));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("rebalance") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        app.print_rebalance(db, parse_value(matches, "budget").unwrap());
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("afford") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        app.print_affordability(db, &matches.value_of("currency").unwrap().to_uppercase(), parse_value(matches, "income").unwrap(),
+                                parse_value(matches, "dti").unwrap(), parse_value(matches, "apr").unwrap(), parse_value(matches, "term").unwrap(),
+                                parse_value(matches, "housing").unwrap(), parse_value(matches, "down"));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("rate-sheet") {
+        let terms: Vec<i32> = matches.values_of("terms").unwrap().map(|term| match term.parse() {
+            Ok(term) if term > 0 => term,
+            _ => {
+                error!("{}", trf("Invalid value for terms: {}", &[&term]));
+                std::process::exit(1);
+            },
+        }).collect();
+        app.print_rate_sheet(parse_value(matches, "principal").unwrap(), &aprs_from_args(matches), &terms);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("escrow") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        app.print_escrow(&loan, &escrow_from_args(matches));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("reverse") {
+        let db = Path::new(matches.value_of("DB").unwrap());
+        let loan = app.require_loan(db, matches.value_of("name").unwrap());
+        let mortgage = reverse_mortgage_from_args(&loan, matches);
+        app.print_reverse(loan, mortgage, parse_value(matches, "years").unwrap());
+        return;
+    }
+
     if !matches.is_present("DB") {
-        println!("Must provide the database to operate on.");
+        println!("{}", tr("Must provide the database to operate on."));
         std::process::exit(1);
     }
     let db = Path::new(matches.value_of("DB").unwrap());
@@ -288,9 +4476,9 @@ fn main() {
         let name = matches.value_of("name").unwrap();
         let loan = app.query_loan(db, name.to_string());
         if let Some(loan) = loan {
-            app.print_loan(loan);
+            app.print_loan(db, loan);
         } else {
-            println!("Could not find loan with the name: {}", name);
+            println!("{}", trf("Could not find loan with the name: {}", &[&name]));
             std::process::exit(1);
         }
     } else {
@@ -0,0 +1,45 @@
+// Compares a set of loans against rolling them into a single new loan.
+
+use calc;
+use calc::Schedule;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Consolidation {
+    pub balance: f64,
+    pub old_payment: f64,
+    pub old_interest: f64,
+    pub new_payment: f64,
+    pub new_interest: f64,
+}
+
+impl Consolidation {
+    pub fn payment_change(&self) -> f64 {
+        self.new_payment - self.old_payment
+    }
+
+    pub fn interest_change(&self) -> f64 {
+        self.new_interest - self.old_interest
+    }
+}
+
+// `loans` holds the remaining schedule of each loan being consolidated; the
+// new loan pays off their combined balance over `periods` months at `apr`.
+pub fn simulate<I: IntoIterator<Item=Schedule>>(loans: I, apr: f64, periods: i32) -> Consolidation {
+    let mut balance = 0f64;
+    let mut old_payment = 0f64;
+    let mut old_interest = 0f64;
+    for schedule in loans {
+        balance += schedule.balance();
+        old_payment += schedule.payment();
+        old_interest += schedule.payoff().total_interest;
+    }
+
+    let new_payment = calc::payment(balance, periods, apr);
+    Consolidation{
+        balance: balance,
+        old_payment: old_payment,
+        old_interest: old_interest,
+        new_payment: new_payment,
+        new_interest: calc::project_payoff(balance, periods, apr, new_payment).total_interest,
+    }
+}
@@ -0,0 +1,83 @@
+// Exchange rates, for adding up loans held in different currencies.
+
+use std::collections::HashMap;
+
+// One unit of `from` is worth `rate` units of `to`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeRate {
+    pub from: String,
+    pub to: String,
+    pub rate: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Rates {
+    rates: HashMap<(String, String), f64>,
+}
+
+impl Rates {
+    pub fn new() -> Rates {
+        Rates::default()
+    }
+
+    // Adds a rate, replacing any earlier one for the same pair.
+    pub fn insert(&mut self, rate: ExchangeRate) {
+        self.rates.insert((rate.from, rate.to), rate.rate);
+    }
+
+    // Converts `amount` between currencies, using the inverse rate if only
+    // the opposite direction is known. None if there's no rate either way.
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Option<f64> {
+        if from == to {
+            return Some(amount);
+        }
+        if let Some(rate) = self.rates.get(&(from.to_string(), to.to_string())) {
+            return Some(amount * rate);
+        }
+        self.rates.get(&(to.to_string(), from.to_string())).map(|rate| amount / rate)
+    }
+}
+
+// Digits after the decimal point in a currency's minor unit, per ISO 4217:
+// none for yen, three for the Bahraini dinar, and two for most others.
+pub fn minor_units(code: &str) -> i32 {
+    match &code.to_uppercase()[..] {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX" | "UYI" | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        "CLF" | "UYW" => 4,
+        _ => 2,
+    }
+}
+
+// Formats an amount with the given number of decimal places, e.g. from
+// minor_units.
+pub fn format_amount(amount: f64, decimals: i32) -> String {
+    format!("{:.*}", decimals.max(0) as usize, amount)
+}
+
+// Parses a rates file: one `FROM TO RATE` entry per line, separated by
+// spaces or commas. Blank lines and lines starting with # are skipped.
+pub fn parse_rates(contents: &str) -> Result<Vec<ExchangeRate>, String> {
+    let mut rates = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|field| !field.is_empty()).collect();
+        if fields.len() != 3 {
+            return Err(format!("line {}: expected FROM TO RATE, got: {}", i + 1, line));
+        }
+        let rate = match fields[2].parse() {
+            Ok(rate) => rate,
+            Err(_) => return Err(format!("line {}: invalid rate: {}", i + 1, fields[2])),
+        };
+        rates.push(ExchangeRate{
+            from: fields[0].to_uppercase(),
+            to: fields[1].to_uppercase(),
+            rate: rate,
+        });
+    }
+    Ok(rates)
+}
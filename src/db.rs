@@ -0,0 +1,3848 @@
+use std;
+use std::path::Path;
+use std::str::FromStr;
+use std::thread;
+use rusqlite;
+use rusqlite::Connection;
+use time;
+use time::Timespec;
+
+use std::sync::Arc;
+
+use calc;
+use currency;
+use group;
+use i18n::{tr, trf};
+use interest::{self, InterestMethod};
+use overlay::Overlay;
+use scenario::{LumpSum, Refinance, Scenario};
+use trace::traced;
+
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub id: i32,
+    pub name: String,
+    pub principal: f64,
+    pub interest: f64,
+    pub date: Timespec,
+    pub time_created: Timespec,
+    // how it was paid: ACH, check #1234, autopay...
+    pub payment_method: Option<String>,
+    // returned unpaid (NSF): kept for the record, but no longer counts as paid
+    pub reversed: bool,
+    // parts of a combined payment that went to escrow and mortgage insurance
+    // rather than the loan itself
+    pub escrow: f64,
+    pub pmi: f64,
+    pub kind: TransactionKind,
+    // pending review, e.g. filled in from the schedule or imported: kept out
+    // of the balance until confirmed, and its split is only an estimate
+    pub provisional: bool,
+}
+
+impl Transaction {
+    // Something other than a payment that changes the balance by `amount`.
+    fn charge(name: &str, kind: TransactionKind, amount: f64, date: Timespec) -> Transaction {
+        Transaction{
+            id: 0,
+            name: name.to_string(),
+            principal: -amount,
+            interest: 0f64,
+            date: date,
+            time_created: time::get_time(),
+            payment_method: None,
+            reversed: false,
+            escrow: 0f64,
+            pmi: 0f64,
+            kind: kind,
+            provisional: false,
+        }
+    }
+
+    // Whether it's money received that still counts as paid.
+    pub fn paid(&self) -> bool {
+        self.kind.is_payment() && !self.reversed && !self.provisional
+    }
+}
+
+// What a transaction is. Every kind's principal is what it takes off the
+// balance, so fees, draws, capitalized interest and reversed payments are
+// negative, and the balance is always the loan's principal less the sum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransactionKind {
+    Payment,
+    // on top of what was due, all of it principal
+    Extra,
+    // charged after the loan was made, e.g. for a returned payment
+    Fee,
+    // a correction to the balance, either way
+    Adjustment,
+    // money disbursed on a construction loan
+    Draw,
+    // undoes a payment that was returned unpaid
+    Reversal,
+    // interest added to the balance
+    Capitalization,
+    // the whole balance, paid off by a new loan replacing it
+    Payoff,
+}
+
+impl TransactionKind {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            TransactionKind::Payment => "payment",
+            TransactionKind::Extra => "extra",
+            TransactionKind::Fee => "fee",
+            TransactionKind::Adjustment => "adjustment",
+            TransactionKind::Draw => "draw",
+            TransactionKind::Reversal => "reversal",
+            TransactionKind::Capitalization => "capitalization",
+            TransactionKind::Payoff => "payoff",
+        }
+    }
+
+    pub fn is_payment(&self) -> bool {
+        *self == TransactionKind::Payment || *self == TransactionKind::Extra || *self == TransactionKind::Payoff
+    }
+}
+
+impl FromStr for TransactionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TransactionKind, String> {
+        match s {
+            "payment" => Ok(TransactionKind::Payment),
+            "extra" => Ok(TransactionKind::Extra),
+            "fee" => Ok(TransactionKind::Fee),
+            "adjustment" => Ok(TransactionKind::Adjustment),
+            "draw" => Ok(TransactionKind::Draw),
+            "reversal" => Ok(TransactionKind::Reversal),
+            "capitalization" => Ok(TransactionKind::Capitalization),
+            "payoff" => Ok(TransactionKind::Payoff),
+            _ => Err(format!("Unknown transaction kind: {}", s)),
+        }
+    }
+}
+
+// Transactions that are payments and haven't been returned, whether or not
+// they're still pending review.
+macro_rules! recorded {
+    () => ("kind IN ('payment', 'extra', 'payoff') AND NOT reversed")
+}
+const RECORDED: &str = recorded!();
+
+// Transactions that are confirmed payments received and not returned.
+const PAID: &str = concat!(recorded!(), " AND NOT provisional");
+
+// What happens to the interest for a skipped payment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkipPolicy {
+    // added to the balance, so it accrues interest itself
+    Capitalize,
+    // set aside and due at payoff
+    Defer,
+}
+
+impl SkipPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            SkipPolicy::Capitalize => "capitalize",
+            SkipPolicy::Defer => "defer",
+        }
+    }
+}
+
+impl FromStr for SkipPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SkipPolicy, String> {
+        match s {
+            "capitalize" => Ok(SkipPolicy::Capitalize),
+            "defer" => Ok(SkipPolicy::Defer),
+            _ => Err(format!("Unknown skip policy: {}", s)),
+        }
+    }
+}
+
+// Where a listed schedule starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    // the first payment, from the full principal as contracted
+    Origination,
+    // the next payment due, from the current balance
+    Today,
+    // the given payment (1-based), as contracted
+    Payment(i32),
+}
+
+impl FromStr for Anchor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Anchor, String> {
+        match s {
+            "origination" => Ok(Anchor::Origination),
+            "today" => Ok(Anchor::Today),
+            _ => match s.parse() {
+                Ok(period) if period >= 1 => Ok(Anchor::Payment(period)),
+                _ => Err(format!("Unknown anchor: {}", s)),
+            },
+        }
+    }
+}
+
+// Where a loan stands, derived from its due dates and the payments made.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoanStatus {
+    Current,
+    // a payment is due but still within the grace period
+    Grace,
+    Late,
+    Delinquent,
+    PaidOff,
+}
+
+// Days after a due date before an unpaid payment is late, and before it's
+// delinquent.
+const GRACE_DAYS: i64 = 15;
+const DELINQUENT_DAYS: i64 = 30;
+
+impl LoanStatus {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            LoanStatus::Current => "current",
+            LoanStatus::Grace => "grace",
+            LoanStatus::Late => "late",
+            LoanStatus::Delinquent => "delinquent",
+            LoanStatus::PaidOff => "paid off",
+        }
+    }
+
+    // The status as shown to people, in their language.
+    pub fn label(&self) -> String {
+        match *self {
+            LoanStatus::Current => tr("current"),
+            LoanStatus::Grace => tr("grace"),
+            LoanStatus::Late => tr("late"),
+            LoanStatus::Delinquent => tr("delinquent"),
+            LoanStatus::PaidOff => tr("paid off"),
+        }
+    }
+
+    // Status of a payment that was due `days` ago and hasn't been made.
+    fn overdue(days: i64) -> LoanStatus {
+        if days <= GRACE_DAYS {
+            LoanStatus::Grace
+        } else if days <= DELINQUENT_DAYS {
+            LoanStatus::Late
+        } else {
+            LoanStatus::Delinquent
+        }
+    }
+}
+
+impl FromStr for LoanStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<LoanStatus, String> {
+        match s {
+            "current" => Ok(LoanStatus::Current),
+            "grace" => Ok(LoanStatus::Grace),
+            "late" => Ok(LoanStatus::Late),
+            "delinquent" => Ok(LoanStatus::Delinquent),
+            "paid off" => Ok(LoanStatus::PaidOff),
+            _ => Err(format!("Unknown loan status: {}", s)),
+        }
+    }
+}
+
+// A fee rolled into the loan's principal (origination, warranty, taxes...).
+#[derive(Debug, Clone)]
+pub struct Fee {
+    pub name: String,
+    pub amount: f64,
+}
+
+// A product financed with the loan (credit insurance, an extended
+// warranty...) and paid for over its own term, which may be shorter than the
+// loan's.
+#[derive(Debug, Clone)]
+pub struct AddOn {
+    pub name: String,
+    pub cost: f64,
+    pub periods: i32,
+}
+
+// The share of a loan's payment and interest that goes to one part of it:
+// the base loan or one of its add-ons.
+#[derive(Debug, Clone)]
+pub struct Attribution {
+    pub name: String,
+    pub financed: f64,
+    pub periods: i32,
+    pub payment: f64,
+    pub interest: f64,
+}
+
+// Someone responsible for part of a loan, e.g. one of two partners.
+#[derive(Debug, Clone)]
+pub struct Borrower {
+    pub name: String,
+    // ownership, in percent
+    pub share: f64,
+}
+
+// One borrower's portion of every loan they share.
+#[derive(Debug, Clone)]
+pub struct BorrowerTotals {
+    pub name: String,
+    pub balance: f64,
+    pub paid: f64,
+    pub interest: f64,
+}
+
+// What merge_db brought over from the other database.
+#[derive(Debug, Clone, Default)]
+pub struct MergeSummary {
+    pub loans_added: usize,
+    pub loans_updated: usize,
+    pub transactions_added: usize,
+    pub transactions_updated: usize,
+    pub loans_deleted: usize,
+    pub transactions_deleted: usize,
+}
+
+// An entry in the change log: a loan or transaction (by its id) inserted,
+// updated or deleted at `time`. Replicas merge each other's logs, so the last
+// change to a record can be decided the same way on both.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub uid: String,
+    // UTC, to the millisecond
+    pub time: String,
+    pub table: String,
+    pub row: String,
+    pub op: String,
+}
+
+// Everything one member of the household owns, across their loans.
+#[derive(Debug, Clone)]
+pub struct UserTotals {
+    pub name: String,
+    pub loans: i32,
+    pub balance: f64,
+    pub paid: f64,
+    pub interest: f64,
+}
+
+// Who services a loan, and how to reach them.
+#[derive(Debug, Clone)]
+pub struct Lender {
+    pub name: String,
+    pub account_number: Option<String>,
+    pub url: Option<String>,
+    pub phone: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Loan {
+    pub id: i32,
+    pub name: String,
+    pub payment: f64,
+    pub balance: f64,
+    pub periods: i32,
+    pub apr: f64,
+    pub start_time: Timespec,
+    pub time_created: Timespec,
+    // interest-only months at the start of the loan (HELOC draw period)
+    pub draw_periods: i32,
+    // promotional rate charged for the first promo_periods months
+    pub promo_apr: f64,
+    pub promo_periods: i32,
+    // charge the interest waived during the promotion if it isn't paid off in time
+    pub defer_interest: bool,
+    pub skip_policy: SkipPolicy,
+    // interest from skipped payments that's due at payoff
+    pub unpaid_interest: f64,
+    pub last_skip: Option<Timespec>,
+    // original amount financed, including any fees
+    pub principal: f64,
+    pub interest_rounding: calc::Rounding,
+    pub payment_rounding: calc::Rounding,
+    // ISO 4217 code, e.g. USD
+    pub currency: String,
+    // decimal places amounts are kept and shown to: 2 for USD, 0 for JPY
+    pub minor_units: i32,
+    // as of the last operation on the loan
+    pub status: LoanStatus,
+    // name of the lender servicing the loan
+    pub lender: Option<String>,
+    // payments beyond the amount due cover the next due dates as well as
+    // paying down principal
+    pub paid_ahead: bool,
+    // due dates covered by the payments made, as of the last operation on
+    // the loan
+    pub paid_through: i32,
+    // money lent out to someone else, so the balance is owed to us and the
+    // interest is income
+    pub lent: bool,
+    // adjustable rate: after fixed_periods payments, the rate resets to the
+    // named index plus margin, and again every reset_periods payments
+    pub rate_index: Option<String>,
+    pub margin: f64,
+    pub fixed_periods: i32,
+    pub reset_periods: i32,
+    // (first period, apr) of each reset, projected from the index's stored
+    // values when the loan is loaded
+    pub rate_resets: Vec<(i32, f64)>,
+    // collected along with each regular payment for escrow (taxes and
+    // insurance) and mortgage insurance
+    pub escrow_payment: f64,
+    pub pmi_payment: f64,
+    // whether each payment's interest is for the month before it or the
+    // month after
+    pub interest_timing: calc::Timing,
+    // equal payments, equal principal with falling payments, or interest
+    // only with the principal due at maturity
+    pub repayment: calc::Repayment,
+    // student loans: payments not due while in school and the grace period,
+    // during which subsidized loans accrue no interest
+    pub deferment_periods: i32,
+    pub subsidized: bool,
+    // interest added to the balance when repayment started
+    pub capitalized_interest: f64,
+    // name of the group this is a sub-loan of, paid along with the others
+    pub loan_group: Option<String>,
+    // percent of any principal paid ahead of schedule charged as a fee, for
+    // the first penalty_periods months, or the whole term if 0
+    pub prepayment_penalty: f64,
+    pub penalty_periods: i32,
+    // member of the household the loan belongs to, for a shared database
+    pub owner: Option<String>,
+    // name of the day count the lender works out interest with; see
+    // interest for the built-in ones
+    pub interest_method: String,
+    // days from closing to the first payment, if not a regular month; the
+    // first payment's interest covers all of them
+    pub first_payment_days: i32,
+}
+
+const LOAN_COLUMNS: &str = "id, name, payment, balance, periods, apr, start_time, time_created, draw_periods, promo_apr, promo_periods, defer_interest, \
+                                    skip_policy, unpaid_interest, last_skip, principal, interest_rounding, payment_rounding, currency, status, lender, \
+                                    paid_ahead, paid_through, lent, rate_index, margin, fixed_periods, reset_periods, escrow_payment, pmi_payment, \
+                                    interest_timing, minor_units, repayment, \
+                                    deferment_periods, subsidized, capitalized_interest, loan_group, prepayment_penalty, penalty_periods, owner, \
+                                    interest_method, first_payment_days";
+
+impl Loan {
+    fn from_row(row: &rusqlite::Row) -> Loan {
+        Loan{
+            id: row.get(0),
+            name: row.get(1),
+            payment: row.get(2),
+            balance: row.get(3),
+            periods: row.get(4),
+            apr: row.get(5),
+            start_time: row.get(6),
+            time_created: row.get(7),
+            draw_periods: row.get(8),
+            promo_apr: row.get(9),
+            promo_periods: row.get(10),
+            defer_interest: row.get(11),
+            skip_policy: row.get::<i32, String>(12).parse().unwrap_or(SkipPolicy::Capitalize),
+            unpaid_interest: row.get(13),
+            last_skip: row.get(14),
+            principal: row.get(15),
+            interest_rounding: row.get::<i32, String>(16).parse().unwrap_or(calc::Rounding::Exact),
+            payment_rounding: row.get::<i32, String>(17).parse().unwrap_or(calc::Rounding::Exact),
+            currency: row.get(18),
+            status: row.get::<i32, String>(19).parse().unwrap_or(LoanStatus::Current),
+            lender: row.get(20),
+            paid_ahead: row.get(21),
+            paid_through: row.get(22),
+            lent: row.get(23),
+            rate_index: row.get(24),
+            margin: row.get(25),
+            fixed_periods: row.get(26),
+            reset_periods: row.get(27),
+            rate_resets: Vec::new(),
+            escrow_payment: row.get(28),
+            pmi_payment: row.get(29),
+            interest_timing: row.get::<i32, String>(30).parse().unwrap_or(calc::Timing::Arrears),
+            minor_units: row.get(31),
+            repayment: row.get::<i32, String>(32).parse().unwrap_or(calc::Repayment::Annuity),
+            deferment_periods: row.get(33),
+            subsidized: row.get(34),
+            capitalized_interest: row.get(35),
+            loan_group: row.get(36),
+            prepayment_penalty: row.get(37),
+            penalty_periods: row.get(38),
+            owner: row.get(39),
+            interest_method: row.get(40),
+            first_payment_days: row.get(41),
+        }
+    }
+
+    fn load_from_db(conn: &Connection, name: &String) -> rusqlite::Result<Loan> {
+        let sql = format!("SELECT {} FROM loans WHERE name = $0", LOAN_COLUMNS);
+        let loan = try!(conn.query_row(&sql, &[name], |row| Loan::from_row(&row)));
+        with_resets(conn, loan)
+    }
+
+    pub fn new(name: String, principal: f64, periods: i32, apr: f64, start_time: Timespec) -> Loan {
+        Loan{
+            id: 0,
+            name: name.clone(),
+            payment: calc::payment(principal, periods, apr),
+            balance: principal,
+            periods: periods,
+            apr: apr,
+            start_time: start_time,
+            time_created: time::get_time(),
+            draw_periods: 0,
+            promo_apr: 0f64,
+            promo_periods: 0,
+            defer_interest: false,
+            skip_policy: SkipPolicy::Capitalize,
+            unpaid_interest: 0f64,
+            last_skip: None,
+            principal: principal,
+            interest_rounding: calc::Rounding::Exact,
+            payment_rounding: calc::Rounding::Exact,
+            currency: "USD".to_string(),
+            minor_units: 2,
+            status: LoanStatus::Current,
+            lender: None,
+            paid_ahead: false,
+            paid_through: 0,
+            lent: false,
+            rate_index: None,
+            margin: 0f64,
+            fixed_periods: 0,
+            reset_periods: 12,
+            rate_resets: Vec::new(),
+            escrow_payment: 0f64,
+            pmi_payment: 0f64,
+            interest_timing: calc::Timing::Arrears,
+            repayment: calc::Repayment::Annuity,
+            deferment_periods: 0,
+            subsidized: false,
+            capitalized_interest: 0f64,
+            loan_group: None,
+            prepayment_penalty: 0f64,
+            penalty_periods: 0,
+            owner: None,
+            interest_method: "standard".to_string(),
+            first_payment_days: 0,
+        }
+    }
+
+    // Makes the first `draw_periods` payments interest-only. The regular
+    // payment then amortizes the balance over the rest of the term.
+    pub fn set_draw_periods(&mut self, draw_periods: i32) {
+        self.draw_periods = draw_periods;
+        self.recalc_payment();
+    }
+
+    // Defers the first `deferment_periods` payments, e.g. while in school
+    // and the grace period. The regular payment then amortizes the balance,
+    // with any interest capitalized, over the rest of the term.
+    pub fn set_deferment(&mut self, deferment_periods: i32, subsidized: bool) {
+        self.deferment_periods = deferment_periods;
+        self.subsidized = subsidized;
+        self.recalc_payment();
+    }
+
+    // Interest to be added to the balance when repayment starts: simple
+    // interest over the deferment, unless the loan is subsidized or it's
+    // already been added.
+    pub fn deferment_interest(&self) -> f64 {
+        if self.subsidized || self.capitalized_interest > 0f64 {
+            return 0f64;
+        }
+        (1..self.deferment_periods + 1).map(|period| self.calc_interest_payment(period)).sum()
+    }
+
+    // Charges `apr` for the first `periods` months. The regular payment is
+    // based on the promotional rate until it expires.
+    pub fn set_promo(&mut self, apr: f64, periods: i32, defer_interest: bool) {
+        self.promo_apr = apr;
+        self.promo_periods = periods;
+        self.defer_interest = defer_interest;
+        self.recalc_payment();
+    }
+
+    // Rounds interest and payments the way the lender does, so schedules
+    // match their statements.
+    pub fn set_rounding(&mut self, interest: calc::Rounding, payment: calc::Rounding) {
+        self.interest_rounding = interest;
+        self.payment_rounding = payment;
+        self.recalc_payment();
+    }
+
+    // Charges interest in advance or in arrears.
+    pub fn set_timing(&mut self, timing: calc::Timing) {
+        self.interest_timing = timing;
+        self.recalc_payment();
+    }
+
+    // Works out interest with the method registered as `name`, recalculating
+    // the payment.
+    pub fn set_interest_method(&mut self, name: &str) {
+        self.interest_method = name.to_string();
+        self.recalc_payment();
+    }
+
+    // Pays principal as an annuity, in equal (linear) amounts or all at
+    // maturity (bullet).
+    pub fn set_repayment(&mut self, repayment: calc::Repayment) {
+        self.repayment = repayment;
+        self.recalc_payment();
+    }
+
+    // Charges `percent` of any principal paid ahead of schedule in the first
+    // `periods` months, or over the whole term if 0.
+    pub fn set_prepayment_penalty(&mut self, percent: f64, periods: i32) {
+        self.prepayment_penalty = percent;
+        self.penalty_periods = periods;
+    }
+
+    // Months left, as of `today`, in which paying ahead costs a penalty.
+    pub fn penalty_periods_left(&self, today: Timespec) -> i32 {
+        if self.prepayment_penalty <= 0f64 {
+            return 0;
+        }
+        let end = if self.penalty_periods > 0 { self.penalty_periods } else { self.periods };
+        std::cmp::max(end - months_between(self.start_time, today), 0)
+    }
+
+    // Regular payment for the first amortizing phase of the loan: after the
+    // draw period, at the promotional rate if there is one. For a linear loan
+    // it's the first, largest, payment; for a bullet loan the interest.
+    fn recalc_payment(&mut self) {
+        let apr = if self.promo_periods > 0 { self.promo_apr } else { self.apr };
+        let periods = self.periods - self.draw_periods - self.deferment_periods;
+        let balance = self.balance + self.deferment_interest();
+        self.payment = self.payment_rounding.apply(match self.repayment {
+            calc::Repayment::Annuity => calc::payment_timed(balance, periods, apr, self.interest_timing),
+            calc::Repayment::Linear => calc::linear_payment(balance, periods, apr, self.interest_timing),
+            calc::Repayment::Bullet => self.interest_method().monthly(balance, apr),
+        });
+    }
+
+    // Makes the loan adjustable: after `fixed_periods` payments the rate
+    // resets to `index` plus `margin`, then again every `reset_periods`.
+    pub fn set_index(&mut self, index: String, margin: f64, fixed_periods: i32, reset_periods: i32) {
+        self.rate_index = Some(index);
+        self.margin = margin;
+        self.fixed_periods = fixed_periods;
+        self.reset_periods = reset_periods;
+    }
+
+    // Rate charged in the given period (1-based) of the loan.
+    pub fn apr_at(&self, period: i32) -> f64 {
+        calc::apr_at(self.apr, self.promo_apr, self.promo_periods, &self.rate_resets, period)
+    }
+
+    // Periods in which an adjustable rate resets.
+    pub fn reset_periods(&self) -> Vec<i32> {
+        if self.rate_index.is_none() {
+            return Vec::new();
+        }
+        let mut periods = Vec::new();
+        let mut period = self.fixed_periods + 1;
+        while period <= self.periods {
+            periods.push(period);
+            if self.reset_periods <= 0 {
+                break;
+            }
+            period += self.reset_periods;
+        }
+        periods
+    }
+
+    // Rate at each reset, as the index stood (or is projected to stand) on
+    // that reset's due date, plus the margin. Resets before the index's first
+    // value are left out.
+    pub fn project_resets(&self, index: &RateIndex) -> Vec<(i32, f64)> {
+        self.reset_periods().into_iter()
+            .filter_map(|period| index.value_at(self.due_date(period)).map(|value| (period, value + self.margin)))
+            .collect()
+    }
+
+    fn with_options(&self, schedule: calc::Schedule) -> calc::Schedule {
+        let schedule = schedule
+            .interest_only(self.draw_periods)
+            .promo(self.promo_apr, self.promo_periods, self.defer_interest)
+            .rounding(self.interest_rounding, self.payment_rounding)
+            .timing(self.interest_timing)
+            .repayment(self.repayment)
+            .interest_method(self.interest_method())
+            .first_period_days(self.first_payment_days as i64);
+        self.rate_resets.iter().fold(schedule, |schedule, &(first, apr)| schedule.rate_reset(first, apr))
+    }
+
+    // Remaining payments, starting from the current balance.
+    pub fn schedule(&self) -> calc::Schedule {
+        // once capitalized, deferment interest is already in the balance
+        self.with_options(calc::Schedule::new(self.balance, self.periods, self.apr, self.payment))
+            .deferment(self.deferment_periods, self.subsidized || self.capitalized_interest > 0f64)
+    }
+
+    // Payments as originally contracted, starting from the full principal.
+    pub fn contract_schedule(&self) -> calc::Schedule {
+        self.with_options(calc::Schedule::new(self.principal, self.periods, self.apr, self.payment))
+            .deferment(self.deferment_periods, self.subsidized)
+    }
+
+    // Payments from `anchor` on, as of `today`, each numbered by its period
+    // of the loan and paired with its due date.
+    pub fn schedule_from<'a>(&'a self, anchor: Anchor, today: Timespec) -> Box<dyn Iterator<Item = (Timespec, calc::Installment)> + 'a> {
+        match anchor {
+            Anchor::Origination => self.schedule_from(Anchor::Payment(1), today),
+            Anchor::Today => {
+                let offset = std::cmp::max(months_between(self.start_time, self.next_due(today)), 0);
+                Box::new(self.schedule().map(move |mut inst| {
+                    inst.period += offset;
+                    (self.due_date(inst.period), inst)
+                }))
+            },
+            Anchor::Payment(first) => Box::new(self.contract_schedule()
+                .skip_while(move |inst| inst.period < first)
+                .map(move |inst| (self.due_date(inst.period), inst))),
+        }
+    }
+
+    // Payment due in the given period (1-based) of the loan.
+    pub fn payment_due(&self, period: i32) -> f64 {
+        let bullet = self.repayment == calc::Repayment::Bullet && period < self.periods;
+        if period <= self.deferment_periods {
+            0f64
+        } else if period <= self.draw_periods || bullet {
+            self.calc_interest_payment(period)
+        } else if self.repayment != calc::Repayment::Annuity {
+            let remaining = std::cmp::max(self.periods - period + 1, 1);
+            let principal = self.payment_rounding.apply(self.balance / remaining as f64).min(self.balance);
+            let interest = match self.interest_timing {
+                calc::Timing::Arrears => self.calc_interest_payment(period),
+                calc::Timing::Advance => self.interest_rounding.apply(self.interest_method().monthly(self.balance - principal, self.apr_at(period))),
+            };
+            principal + interest
+        } else if period == 1 && self.first_payment_days > 0 && self.interest_timing == calc::Timing::Arrears {
+            // plus the interest for any days beyond a month
+            self.payment + self.calc_interest_payment(1) - self.interest_rounding.apply(self.interest_method().monthly(self.balance, self.apr_at(1)))
+        } else if (self.promo_periods > 0 && period > self.promo_periods) || self.rate_resets.iter().any(|&(first, _)| period >= first) {
+            self.payment_rounding.apply(calc::payment_timed(self.balance, self.periods - period + 1, self.apr_at(period), self.interest_timing))
+        } else {
+            self.payment
+        }
+    }
+}
+
+impl Loan {
+    fn calc_interest_payment(&self, period: i32) -> f64 {
+        self.interest_rounding.apply(self.period_interest(period, self.balance))
+    }
+
+    // Interest on `balance` in the given period (1-based): a month's, or for
+    // the first period every day since closing.
+    fn period_interest(&self, period: i32, balance: f64) -> f64 {
+        if period == 1 && self.first_payment_days > 0 {
+            self.interest_method().daily(balance, self.apr_at(period), self.first_payment_days as i64)
+        } else {
+            self.interest_method().monthly(balance, self.apr_at(period))
+        }
+    }
+
+    // Date the loan closed and interest started accruing: a month before the
+    // first payment, unless first_payment_days says otherwise.
+    pub fn closing_date(&self) -> Timespec {
+        if self.first_payment_days > 0 {
+            self.start_time - time::Duration::days(self.first_payment_days as i64)
+        } else {
+            add_months(self.start_time, -1)
+        }
+    }
+
+    // Splits the loan's payment and interest between the base loan and each
+    // of `addons`, the base loan first. Each add-on is paid off over its own
+    // term at the loan's rate and the base loan gets whatever is left, so
+    // while an add-on is being paid for less of the payment goes to the base
+    // loan.
+    pub fn attribute(&self, addons: &[AddOn]) -> Vec<Attribution> {
+        let parts: Vec<Attribution> = addons.iter().map(|addon| {
+            let payment = self.payment_rounding.apply(calc::payment(addon.cost, addon.periods, self.apr));
+            let schedule = calc::Schedule::new(addon.cost, addon.periods, self.apr, payment)
+                .rounding(self.interest_rounding, self.payment_rounding)
+                .interest_method(self.interest_method());
+            Attribution{
+                name: addon.name.clone(),
+                financed: addon.cost,
+                periods: addon.periods,
+                payment: payment,
+                interest: schedule.payoff().total_interest,
+            }
+        }).collect();
+
+        let total = self.contract_schedule().payoff();
+        let mut attributions = vec![Attribution{
+            name: "base loan".to_string(),
+            financed: self.principal - parts.iter().map(|part| part.financed).sum::<f64>(),
+            periods: self.periods,
+            payment: (self.payment - parts.iter().map(|part| part.payment).sum::<f64>()).max(0f64),
+            interest: total.total_interest - parts.iter().map(|part| part.interest).sum::<f64>(),
+        }];
+        attributions.extend(parts);
+        attributions
+    }
+
+    // The method interest is worked out with, or the standard one if it
+    // isn't registered in this build.
+    pub fn interest_method(&self) -> Arc<dyn InterestMethod> {
+        interest::method(&self.interest_method)
+    }
+
+    // Date the payment for the given period (1-based) is due.
+    pub fn due_date(&self, period: i32) -> Timespec {
+        add_months(self.start_time, period - 1)
+    }
+
+    // Period (1-based) of the loan that `date` falls in, counting from the
+    // first due date.
+    pub fn period_at(&self, date: Timespec) -> i32 {
+        months_between(self.start_time, date) + 1
+    }
+
+    // Date the last payment is due, and with it anything left of the
+    // principal.
+    pub fn maturity(&self) -> Timespec {
+        self.due_date(self.periods)
+    }
+
+    // Whether a bullet loan's principal falls due soon enough, as of
+    // `today`, to warn about.
+    pub fn maturity_due_soon(&self, today: Timespec) -> bool {
+        self.repayment == calc::Repayment::Bullet && self.balance > 0f64 && (self.maturity().sec - today.sec) / 86400 <= MATURITY_WARNING_DAYS
+    }
+
+    // First due date on or after `today`, or later if the loan is paid
+    // ahead past it.
+    pub fn next_due(&self, today: Timespec) -> Timespec {
+        let period = std::cmp::max(months_between(self.start_time, today) + 1, 1);
+        let due = self.due_date(period);
+        let due = if due < today {
+            self.due_date(period + 1)
+        } else {
+            due
+        };
+        if self.paid_ahead {
+            std::cmp::max(due, self.due_date(self.paid_through + 1))
+        } else {
+            due
+        }
+    }
+
+    // Payment due for the period `date` falls in. Nothing is due for a period
+    // already covered by paying ahead.
+    pub fn amount_due(&self, date: Timespec) -> f64 {
+        let period = self.period_at(date);
+        if self.paid_ahead && period <= self.paid_through {
+            0f64
+        } else {
+            self.payment_due(period)
+        }
+    }
+
+    // Everything collected with the payment for the period `date` falls in:
+    // principal and interest, plus escrow and PMI.
+    pub fn total_due(&self, date: Timespec) -> f64 {
+        let due = self.amount_due(date);
+        if due > 0f64 {
+            due + self.escrow_payment + self.pmi_payment
+        } else {
+            0f64
+        }
+    }
+
+    // Splits a combined payment (PITI) into (escrow, PMI, principal and
+    // interest). Escrow and PMI are taken out first, up to what the loan
+    // collects for them.
+    pub fn split_combined(&self, amount: f64) -> (f64, f64, f64) {
+        let escrow = amount.min(self.escrow_payment).max(0f64);
+        let pmi = (amount - escrow).min(self.pmi_payment).max(0f64);
+        (escrow, pmi, amount - escrow - pmi)
+    }
+
+    // How many due dates, from the first, `paid` in total covers.
+    pub fn periods_covered(&self, paid: f64) -> i32 {
+        let mut due = 0f64;
+        for period in 1..self.periods+1 {
+            due += self.payment_due(period);
+            if due > paid + 0.005 {
+                return period - 1;
+            }
+        }
+        self.periods
+    }
+
+    // First due date on which the contract schedule brings the balance down
+    // to `ltv` percent of `value`, e.g. when PMI can be dropped.
+    pub fn scheduled_ltv(&self, value: f64, ltv: f64) -> Option<Timespec> {
+        self.contract_schedule()
+            .find(|inst| inst.balance <= value * ltv / 100.0 + 0.005)
+            .map(|inst| self.due_date(inst.period))
+    }
+
+    // An amount to the currency's minor unit, e.g. 1234.50 for USD or 1235 for JPY.
+    pub fn money(&self, amount: f64) -> String {
+        currency::format_amount(amount, self.minor_units)
+    }
+
+    // Splits a payment made on `date` into (interest, principal), with
+    // interest rounded to the currency's minor unit. Extra payments go
+    // entirely towards principal.
+    pub fn split_payment(&self, amount: f64, extra: bool, date: Timespec) -> (f64, f64) {
+        if extra {
+            return (0f64, amount);
+        }
+        let period = self.period_at(date);
+        if period <= self.deferment_periods && self.subsidized {
+            // no interest is owed while it's subsidized
+            return (0f64, amount);
+        }
+        let interest = match self.interest_timing {
+            calc::Timing::Arrears => self.calc_interest_payment(period),
+            calc::Timing::Advance => self.interest_rounding.apply(calc::interest_in_advance(self.balance, amount, self.apr_at(period))),
+        };
+        let interest = calc::Rounding::HalfUp(self.minor_units).apply(interest);
+        (interest, amount - interest)
+    }
+
+    // Status as of `today`, given everything paid on the loan so far. Each
+    // due date is checked in turn until one isn't covered by `paid`.
+    pub fn status_at(&self, paid: f64, today: Timespec) -> LoanStatus {
+        if self.balance <= 0f64 {
+            return LoanStatus::PaidOff;
+        }
+
+        let mut due = 0f64;
+        for period in 1..self.periods+1 {
+            let due_date = add_months(self.start_time, period - 1);
+            if due_date > today {
+                return LoanStatus::Current;
+            }
+            due += self.payment_due(period);
+            if due > paid + 0.005 {
+                return LoanStatus::overdue((today.sec - due_date.sec) / 86400);
+            }
+        }
+
+        // past the last due date with a balance left
+        LoanStatus::overdue((today.sec - add_months(self.start_time, self.periods - 1).sec) / 86400)
+    }
+}
+
+// Everything paid on `name` so far, not counting pending or returned
+// payments.
+fn total_paid(conn: &Connection, name: &str) -> rusqlite::Result<f64> {
+    conn.query_row(&format!("SELECT COALESCE(SUM(principal + interest), 0.0) FROM transactions WHERE name = $0 AND {}", PAID),
+                   &[&name], |row| row.get(0))
+}
+
+// Recomputes and stores a loan's status as of `today`, along with how many
+// due dates its payments cover.
+fn update_status(conn: &Connection, name: &String, today: Timespec) -> rusqlite::Result<LoanStatus> {
+    let loan = try!(Loan::load_from_db(conn, name));
+    let paid = try!(total_paid(conn, name));
+    let paid_through = loan.periods_covered(paid);
+    if paid_through != loan.paid_through {
+        try!(conn.execute("UPDATE loans SET paid_through = $0 WHERE name = $1", &[&paid_through, name]));
+    }
+    let status = loan.status_at(paid, today);
+    if status != loan.status {
+        try!(traced("status", &[("loan", name), ("from", &loan.status.as_str()), ("to", &status.as_str())], || {
+            conn.execute("UPDATE loans SET status = $0 WHERE name = $1", &[&status.as_str(), name]).map(|_| ())
+        }));
+        // a bounced payment or an adjustment can reopen a loan
+        if status == LoanStatus::PaidOff {
+            try!(record_closing(conn, &loan, today));
+        } else if loan.status == LoanStatus::PaidOff {
+            try!(conn.execute("DELETE FROM closings WHERE loan = $0", &[name]));
+        }
+    }
+    Ok(status)
+}
+
+// Schema changes made after the initial release, applied in order.
+// PRAGMA user_version records how many a database has already seen.
+const MIGRATIONS: &[&str] = &[
+    "ALTER TABLE loans ADD COLUMN draw_periods INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE loans ADD COLUMN promo_apr REAL NOT NULL DEFAULT 0.0;
+     ALTER TABLE loans ADD COLUMN promo_periods INTEGER NOT NULL DEFAULT 0;
+     ALTER TABLE loans ADD COLUMN defer_interest INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE loans ADD COLUMN skip_policy TEXT NOT NULL DEFAULT 'capitalize';
+     ALTER TABLE loans ADD COLUMN unpaid_interest REAL NOT NULL DEFAULT 0.0;
+     ALTER TABLE loans ADD COLUMN last_skip TEXT;",
+    "ALTER TABLE loans ADD COLUMN principal REAL NOT NULL DEFAULT 0.0;
+     UPDATE loans SET principal = balance + COALESCE((SELECT SUM(principal) FROM transactions WHERE transactions.name = loans.name), 0.0);
+     CREATE TABLE fees (
+           id              INTEGER PRIMARY KEY,
+           loan            TEXT NOT NULL,
+           name            TEXT NOT NULL,
+           amount          REAL NOT NULL
+     );",
+    "ALTER TABLE loans ADD COLUMN interest_rounding TEXT NOT NULL DEFAULT 'exact';
+     ALTER TABLE loans ADD COLUMN payment_rounding TEXT NOT NULL DEFAULT 'exact';",
+    "ALTER TABLE loans ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD';
+     CREATE TABLE exchange_rates (
+           id              INTEGER PRIMARY KEY,
+           from_currency   TEXT NOT NULL,
+           to_currency     TEXT NOT NULL,
+           rate            REAL NOT NULL,
+           date            TEXT NOT NULL
+     );",
+    "ALTER TABLE transactions ADD COLUMN payment_method TEXT;",
+    "ALTER TABLE loans ADD COLUMN status TEXT NOT NULL DEFAULT 'current';",
+    "CREATE TABLE scenarios (
+           id              INTEGER PRIMARY KEY,
+           name            TEXT NOT NULL UNIQUE,
+           loan            TEXT NOT NULL,
+           monthly_extra   REAL NOT NULL,
+           lump_sums       TEXT NOT NULL,
+           refi_apr        REAL,
+           refi_periods    INTEGER,
+           rate_shock      REAL NOT NULL,
+           shock_periods   INTEGER NOT NULL
+     );",
+    "CREATE TABLE accruals (
+           id              INTEGER PRIMARY KEY,
+           loan            TEXT NOT NULL,
+           date            TEXT NOT NULL,
+           days            INTEGER NOT NULL,
+           balance         REAL NOT NULL,
+           interest        REAL NOT NULL,
+           time_created    TEXT NOT NULL
+     );",
+    "CREATE TABLE borrowers (
+           id              INTEGER PRIMARY KEY,
+           loan            TEXT NOT NULL,
+           name            TEXT NOT NULL,
+           share           REAL NOT NULL
+     );",
+    "ALTER TABLE loans ADD COLUMN lender TEXT;
+     CREATE TABLE lenders (
+           id              INTEGER PRIMARY KEY,
+           name            TEXT NOT NULL UNIQUE,
+           account_number  TEXT,
+           url             TEXT,
+           phone           TEXT
+     );",
+    "CREATE TABLE schedule_cache (
+           loan            TEXT NOT NULL,
+           key             TEXT NOT NULL,
+           rows            TEXT NOT NULL,
+           PRIMARY KEY (loan, key)
+     );",
+    "ALTER TABLE loans ADD COLUMN paid_ahead INTEGER NOT NULL DEFAULT 0;
+     ALTER TABLE loans ADD COLUMN paid_through INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE transactions ADD COLUMN reversed INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE loans ADD COLUMN lent INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE fees ADD COLUMN date TEXT;",
+    "ALTER TABLE loans ADD COLUMN rate_index TEXT;
+     ALTER TABLE loans ADD COLUMN margin REAL NOT NULL DEFAULT 0.0;
+     ALTER TABLE loans ADD COLUMN fixed_periods INTEGER NOT NULL DEFAULT 0;
+     ALTER TABLE loans ADD COLUMN reset_periods INTEGER NOT NULL DEFAULT 12;
+     CREATE TABLE rate_indexes (
+           id              INTEGER PRIMARY KEY,
+           name            TEXT NOT NULL,
+           date            TEXT NOT NULL,
+           value           REAL NOT NULL
+     );",
+    "CREATE TABLE valuations (
+           id              INTEGER PRIMARY KEY,
+           loan            TEXT NOT NULL,
+           date            TEXT NOT NULL,
+           value           REAL NOT NULL
+     );",
+    "CREATE TABLE asset_values (
+           id              INTEGER PRIMARY KEY,
+           name            TEXT NOT NULL,
+           currency        TEXT NOT NULL,
+           date            TEXT NOT NULL,
+           value           REAL NOT NULL
+     );",
+    "ALTER TABLE loans ADD COLUMN escrow_payment REAL NOT NULL DEFAULT 0.0;
+     ALTER TABLE loans ADD COLUMN pmi_payment REAL NOT NULL DEFAULT 0.0;
+     ALTER TABLE transactions ADD COLUMN escrow REAL NOT NULL DEFAULT 0.0;
+     ALTER TABLE transactions ADD COLUMN pmi REAL NOT NULL DEFAULT 0.0;",
+    "ALTER TABLE loans ADD COLUMN interest_timing TEXT NOT NULL DEFAULT 'arrears';",
+    "ALTER TABLE loans ADD COLUMN minor_units INTEGER NOT NULL DEFAULT 2;",
+    "ALTER TABLE loans ADD COLUMN repayment TEXT NOT NULL DEFAULT 'annuity';",
+    "CREATE TABLE draws (
+           id              INTEGER PRIMARY KEY,
+           loan            TEXT NOT NULL,
+           date            TEXT NOT NULL,
+           amount          REAL NOT NULL
+     );",
+    "ALTER TABLE loans ADD COLUMN deferment_periods INTEGER NOT NULL DEFAULT 0;
+     ALTER TABLE loans ADD COLUMN subsidized INTEGER NOT NULL DEFAULT 0;
+     ALTER TABLE loans ADD COLUMN capitalized_interest REAL NOT NULL DEFAULT 0.0;",
+    "ALTER TABLE loans ADD COLUMN loan_group TEXT;
+     CREATE TABLE loan_groups (
+           id              INTEGER PRIMARY KEY,
+           name            TEXT NOT NULL UNIQUE,
+           allocation      TEXT NOT NULL
+     );",
+    "CREATE TABLE group_payments (
+           id              INTEGER PRIMARY KEY,
+           loan_group      TEXT NOT NULL,
+           date            TEXT NOT NULL,
+           amount          REAL NOT NULL,
+           allocation      TEXT NOT NULL,
+           time_created    TEXT NOT NULL
+     );
+     ALTER TABLE transactions ADD COLUMN group_payment INTEGER;",
+    "ALTER TABLE loans ADD COLUMN prepayment_penalty REAL NOT NULL DEFAULT 0.0;
+     ALTER TABLE loans ADD COLUMN penalty_periods INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE transactions ADD COLUMN kind TEXT NOT NULL DEFAULT 'payment';
+     INSERT INTO transactions (name, principal, interest, date, time_created, kind)
+           SELECT name, -principal, -interest, date, time_created, 'reversal' FROM transactions WHERE reversed;
+     INSERT INTO transactions (name, principal, interest, date, time_created, kind)
+           SELECT loan, -amount, 0.0, date, date, 'fee' FROM fees WHERE date IS NOT NULL;
+     INSERT INTO transactions (name, principal, interest, date, time_created, kind)
+           SELECT loan, -amount, 0.0, date, date, 'draw' FROM draws;",
+    "ALTER TABLE transactions ADD COLUMN provisional INTEGER NOT NULL DEFAULT 0;",
+    "CREATE TABLE settings (
+           key             TEXT PRIMARY KEY,
+           value           TEXT NOT NULL
+     );",
+    "CREATE TABLE users (
+           id              INTEGER PRIMARY KEY,
+           name            TEXT NOT NULL UNIQUE
+     );
+     ALTER TABLE loans ADD COLUMN owner TEXT;",
+    "ALTER TABLE loans ADD COLUMN uid TEXT;
+     ALTER TABLE loans ADD COLUMN time_modified TEXT;
+     UPDATE loans SET uid = lower(hex(randomblob(16))), time_modified = time_created;
+     CREATE UNIQUE INDEX loans_uid ON loans (uid);
+     CREATE TRIGGER loans_uid AFTER INSERT ON loans WHEN NEW.uid IS NULL BEGIN
+           UPDATE loans SET uid = lower(hex(randomblob(16))), time_modified = NEW.time_created WHERE id = NEW.id;
+     END;
+     CREATE TRIGGER loans_modified AFTER UPDATE ON loans WHEN NEW.time_modified IS OLD.time_modified BEGIN
+           UPDATE loans SET time_modified = datetime('now') WHERE id = NEW.id;
+     END;
+     ALTER TABLE transactions ADD COLUMN uid TEXT;
+     ALTER TABLE transactions ADD COLUMN time_modified TEXT;
+     UPDATE transactions SET uid = lower(hex(randomblob(16))), time_modified = time_created;
+     CREATE UNIQUE INDEX transactions_uid ON transactions (uid);
+     CREATE TRIGGER transactions_uid AFTER INSERT ON transactions WHEN NEW.uid IS NULL BEGIN
+           UPDATE transactions SET uid = lower(hex(randomblob(16))), time_modified = NEW.time_created WHERE id = NEW.id;
+     END;
+     CREATE TRIGGER transactions_modified AFTER UPDATE ON transactions WHEN NEW.time_modified IS OLD.time_modified BEGIN
+           UPDATE transactions SET time_modified = datetime('now') WHERE id = NEW.id;
+     END;",
+    "CREATE TABLE changes (
+           id              INTEGER PRIMARY KEY,
+           uid             TEXT NOT NULL UNIQUE,
+           time            TEXT NOT NULL,
+           tbl             TEXT NOT NULL,
+           row_uid         TEXT NOT NULL,
+           op              TEXT NOT NULL
+     );
+     CREATE INDEX changes_row ON changes (tbl, row_uid);
+     DROP TRIGGER loans_uid;
+     CREATE TRIGGER loans_insert AFTER INSERT ON loans BEGIN
+           UPDATE loans SET uid = lower(hex(randomblob(16))), time_modified = NEW.time_created WHERE id = NEW.id AND NEW.uid IS NULL;
+           INSERT INTO changes (uid, time, tbl, row_uid, op)
+                 SELECT lower(hex(randomblob(16))), strftime('%Y-%m-%d %H:%M:%f', 'now'), 'loans', uid, 'insert' FROM loans
+                 WHERE id = NEW.id AND NOT EXISTS (SELECT 1 FROM settings WHERE key = 'merging');
+     END;
+     CREATE TRIGGER loans_log_update AFTER UPDATE ON loans WHEN NEW.time_modified IS OLD.time_modified BEGIN
+           INSERT INTO changes (uid, time, tbl, row_uid, op)
+                 SELECT lower(hex(randomblob(16))), strftime('%Y-%m-%d %H:%M:%f', 'now'), 'loans', NEW.uid, 'update'
+                 WHERE NOT EXISTS (SELECT 1 FROM settings WHERE key = 'merging');
+     END;
+     CREATE TRIGGER loans_log_delete AFTER DELETE ON loans BEGIN
+           INSERT INTO changes (uid, time, tbl, row_uid, op)
+                 SELECT lower(hex(randomblob(16))), strftime('%Y-%m-%d %H:%M:%f', 'now'), 'loans', OLD.uid, 'delete'
+                 WHERE NOT EXISTS (SELECT 1 FROM settings WHERE key = 'merging');
+     END;
+     DROP TRIGGER transactions_uid;
+     CREATE TRIGGER transactions_insert AFTER INSERT ON transactions BEGIN
+           UPDATE transactions SET uid = lower(hex(randomblob(16))), time_modified = NEW.time_created WHERE id = NEW.id AND NEW.uid IS NULL;
+           INSERT INTO changes (uid, time, tbl, row_uid, op)
+                 SELECT lower(hex(randomblob(16))), strftime('%Y-%m-%d %H:%M:%f', 'now'), 'transactions', uid, 'insert' FROM transactions
+                 WHERE id = NEW.id AND NOT EXISTS (SELECT 1 FROM settings WHERE key = 'merging');
+     END;
+     CREATE TRIGGER transactions_log_update AFTER UPDATE ON transactions WHEN NEW.time_modified IS OLD.time_modified BEGIN
+           INSERT INTO changes (uid, time, tbl, row_uid, op)
+                 SELECT lower(hex(randomblob(16))), strftime('%Y-%m-%d %H:%M:%f', 'now'), 'transactions', NEW.uid, 'update'
+                 WHERE NOT EXISTS (SELECT 1 FROM settings WHERE key = 'merging');
+     END;
+     CREATE TRIGGER transactions_log_delete AFTER DELETE ON transactions BEGIN
+           INSERT INTO changes (uid, time, tbl, row_uid, op)
+                 SELECT lower(hex(randomblob(16))), strftime('%Y-%m-%d %H:%M:%f', 'now'), 'transactions', OLD.uid, 'delete'
+                 WHERE NOT EXISTS (SELECT 1 FROM settings WHERE key = 'merging');
+     END;",
+    "ALTER TABLE loans ADD COLUMN interest_method TEXT NOT NULL DEFAULT 'standard';",
+    // dates used to be stored as the instant they were entered; anything not
+    // already at midnight was most likely entered on this machine's
+    // calendar day
+    "UPDATE transactions SET date = date(date, 'localtime') || ' 00:00:00' WHERE time(date) <> '00:00:00';
+     UPDATE loans SET start_time = date(start_time, 'localtime') || ' 00:00:00' WHERE time(start_time) <> '00:00:00';",
+    "ALTER TABLE loans ADD COLUMN first_payment_days INTEGER NOT NULL DEFAULT 0;",
+    "CREATE TABLE milestones (
+           id              INTEGER PRIMARY KEY,
+           loan            TEXT NOT NULL,
+           kind            TEXT NOT NULL,
+           date            TEXT NOT NULL,
+           UNIQUE (loan, kind)
+     );",
+    "CREATE TABLE addons (
+           id              INTEGER PRIMARY KEY,
+           loan            TEXT NOT NULL,
+           name            TEXT NOT NULL,
+           cost            REAL NOT NULL,
+           periods         INTEGER NOT NULL
+     );",
+    "CREATE TABLE closings (
+           id              INTEGER PRIMARY KEY,
+           loan            TEXT NOT NULL UNIQUE,
+           paid_off        TEXT NOT NULL,
+           scheduled       TEXT NOT NULL,
+           payments        INTEGER NOT NULL,
+           total_paid      REAL NOT NULL,
+           total_interest  REAL NOT NULL
+     );",
+];
+
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: i32 = try!(conn.query_row("PRAGMA user_version", &[], |row| row.get(0)));
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        try!(traced("migrate", &[("version", &(i + 1))], || {
+            conn.execute_batch(&format!("BEGIN; {} PRAGMA user_version = {}; COMMIT;", sql, i + 1))
+        }));
+    }
+    Ok(())
+}
+
+// Opens a database, bringing its schema up to date.
+fn open(db: &Path) -> rusqlite::Result<Connection> {
+    let conn = try!(Connection::open(db));
+    try!(migrate(&conn));
+    Ok(conn)
+}
+
+// Number of whole months between two dates, e.g. how many payments into a
+// loan a given date falls.
+fn months_between(from: Timespec, to: Timespec) -> i32 {
+    let from = time::at_utc(from);
+    let to = time::at_utc(to);
+    (to.tm_year - from.tm_year) * 12 + (to.tm_mon - from.tm_mon)
+}
+
+// Loan starts, payment dates and the like are calendar days, stored as
+// midnight UTC on the day so they read back as the same day in any timezone.
+// Today is the calendar day in the local timezone (TZ), so a payment entered
+// late at night is dated the day it was made.
+pub fn today() -> Timespec {
+    calendar_day(&time::now())
+}
+
+// `date` with the time of day dropped.
+pub fn date_only(date: Timespec) -> Timespec {
+    calendar_day(&time::at_utc(date))
+}
+
+// Midnight UTC on the calendar day of `tm`.
+fn calendar_day(tm: &time::Tm) -> Timespec {
+    let mut day = time::at_utc(Timespec::new(0, 0));
+    day.tm_year = tm.tm_year;
+    day.tm_mon = tm.tm_mon;
+    day.tm_mday = tm.tm_mday;
+    day.to_timespec()
+}
+
+// A date as YYYY-MM-DD.
+fn day(date: Timespec) -> String {
+    time::strftime("%F", &time::at_utc(date)).unwrap()
+}
+
+// `date` moved by `months`, on the same day of the month or the month's last
+// day if it's shorter, so a loan due on the 31st is due February 28th.
+fn add_months(date: Timespec, months: i32) -> Timespec {
+    let mut tm = time::at_utc(date);
+    let month = tm.tm_mon + months;
+    tm.tm_year += month / 12;
+    tm.tm_mon = month % 12;
+    if tm.tm_mon < 0 {
+        tm.tm_mon += 12;
+        tm.tm_year -= 1;
+    }
+    let day = tm.tm_mday;
+    tm.tm_mday = 1;
+    let mut next = tm;
+    next.tm_mon += 1;
+    let last = time::at_utc(next.to_timespec() - time::Duration::days(1)).tm_mday;
+    tm.tm_mday = std::cmp::min(day, last);
+    tm.to_timespec()
+}
+
+pub fn init_db(path: &Path) -> rusqlite::Result<()> {
+    traced("init", &[("db", &path.display())], || {
+        let conn = try!(Connection::open(path));
+        try!(conn.execute_batch("
+                BEGIN;
+                CREATE TABLE IF NOT EXISTS loans (
+                      id              INTEGER PRIMARY KEY,
+                      name            TEXT NOT NULL,
+                      payment         REAL NOT NULL,
+                      balance         REAL NOT NULL,
+                      periods         INTEGER NOT NULL,
+                      apr             REAL NOT NULL,
+                      start_time      TEXT NOT NULL,
+                      time_created    TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS transactions (
+                      id              INTEGER PRIMARY KEY,
+                      name            TEXT NOT NULL,
+                      principal       REAL NOT NULL,
+                      interest        REAL NOT NULL,
+                      from_account    TEXT,
+                      to_account      TEXT,
+                      date            TEXT NOT NULL,
+                      time_created    TEXT NOT NULL
+                );
+                COMMIT;
+            "));
+        migrate(&conn)
+    })
+}
+
+// Whether `db` has been set up with init_db. Fails if it isn't an SQLite
+// database at all.
+pub fn has_schema(db: &Path) -> rusqlite::Result<bool> {
+    let conn = try!(Connection::open(db));
+    let tables: i64 = try!(conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name IN ('loans', 'transactions')",
+                                          &[], |row| row.get(0)));
+    Ok(tables == 2)
+}
+
+fn insert_loan(conn: &Connection, loan: &Loan) -> rusqlite::Result<()> {
+    try!(conn.execute("INSERT INTO loans (name, payment, balance, periods, apr, start_time, time_created, draw_periods, promo_apr, promo_periods, defer_interest,
+                                       skip_policy, principal, interest_rounding, payment_rounding, currency, lender, paid_ahead,
+                                       lent, rate_index, margin, fixed_periods, reset_periods, escrow_payment, pmi_payment,
+                                       interest_timing, minor_units, repayment, deferment_periods, subsidized, loan_group,
+                                       prepayment_penalty, penalty_periods, owner, interest_method,
+                                       first_payment_days)
+                  VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28,
+                          $29, $30, $31, $32, $33, $34, $35, $36)",
+                 &[&loan.name, &loan.payment, &loan.balance, &loan.periods, &loan.apr, &date_only(loan.start_time), &loan.time_created, &loan.draw_periods,
+                   &loan.promo_apr, &loan.promo_periods, &loan.defer_interest, &loan.skip_policy.as_str(), &loan.principal,
+                   &loan.interest_rounding.to_string(), &loan.payment_rounding.to_string(), &loan.currency, &loan.lender, &loan.paid_ahead,
+                   &loan.lent, &loan.rate_index, &loan.margin, &loan.fixed_periods, &loan.reset_periods, &loan.escrow_payment, &loan.pmi_payment,
+                   &loan.interest_timing.as_str(), &loan.minor_units, &loan.repayment.as_str(),
+                   &loan.deferment_periods, &loan.subsidized, &loan.loan_group, &loan.prepayment_penalty, &loan.penalty_periods, &loan.owner,
+                   &loan.interest_method, &loan.first_payment_days]));
+    if let Some(ref owner) = loan.owner {
+        try!(conn.execute("INSERT OR IGNORE INTO users (name) VALUES ($0)", &[owner]));
+    }
+    Ok(())
+}
+
+const TRANSACTION_COLUMNS: &str = "id, name, principal, interest, date, time_created, payment_method, reversed, escrow, pmi, kind, provisional";
+
+impl Transaction {
+    fn from_row(row: &rusqlite::Row) -> Transaction {
+        Transaction{
+            id: row.get(0),
+            name: row.get(1),
+            principal: row.get(2),
+            interest: row.get(3),
+            date: row.get(4),
+            time_created: row.get(5),
+            payment_method: row.get(6),
+            reversed: row.get(7),
+            escrow: row.get(8),
+            pmi: row.get(9),
+            kind: row.get::<i32, String>(10).parse().unwrap_or(TransactionKind::Payment),
+            provisional: row.get(11),
+        }
+    }
+
+    fn insert(&self, conn: &Connection) -> rusqlite::Result<()> {
+        try!(conn.execute("INSERT INTO transactions (name, principal, interest, date, time_created, payment_method, escrow, pmi, kind, provisional)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+                   &[&self.name, &self.principal, &self.interest, &date_only(self.date), &self.time_created, &self.payment_method, &self.escrow, &self.pmi,
+                     &self.kind.as_str(), &self.provisional]));
+        Ok(())
+    }
+
+    // Inserts the transaction and, unless it's pending, takes its principal
+    // off the loan's balance.
+    fn record(&self, conn: &Connection) -> rusqlite::Result<()> {
+        try!(self.insert(conn));
+        if self.provisional {
+            return Ok(());
+        }
+        try!(conn.execute("UPDATE loans SET balance = balance - $0 WHERE name = $1", &[&self.principal, &self.name]));
+        try!(invalidate_schedules(conn, &self.name));
+        Ok(())
+    }
+}
+
+pub fn create_loan(db: &Path, loan: Loan) -> rusqlite::Result<()> {
+    traced("create_loan", &[("loan", &loan.name), ("principal", &loan.principal), ("apr", &loan.apr), ("periods", &loan.periods)], || {
+        let conn = try!(open(db));
+        try!(insert_loan(&conn, &loan));
+        try!(update_status(&conn, &loan.name, today()));
+        Ok(())
+    })
+}
+
+// Adds many loans in one database transaction, e.g. when importing a
+// portfolio.
+pub fn create_loans(db: &Path, loans: &[Loan]) -> rusqlite::Result<()> {
+    traced("create_loans", &[("loans", &loans.len())], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        for loan in loans {
+            try!(insert_loan(&tx, loan));
+            try!(update_status(&tx, &loan.name, today()));
+        }
+        tx.commit()
+    })
+}
+
+// Records fees that were rolled into a loan's principal.
+pub fn add_fees(db: &Path, loan: &str, fees: &[Fee]) -> rusqlite::Result<()> {
+    let total: f64 = fees.iter().map(|fee| fee.amount).sum();
+    traced("add_fees", &[("loan", &loan), ("fees", &fees.len()), ("total", &total)], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        for fee in fees {
+            try!(tx.execute("INSERT INTO fees (loan, name, amount) VALUES ($1, $2, $3)", &[&loan, &fee.name, &fee.amount]));
+        }
+        tx.commit()
+    })
+}
+
+// Fees rolled into the principal when the loan was made. Fees charged later
+// have a date and are left out.
+pub fn load_fees(db: &Path, loan: &str) -> rusqlite::Result<Vec<Fee>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT name, amount FROM fees WHERE loan = $0 AND date IS NULL ORDER BY id"));
+    let rows = try!(stmt.query_map(&[&loan], |row| {
+        Fee{
+            name: row.get(0),
+            amount: row.get(1),
+        }
+    }));
+
+    let mut fees = Vec::new();
+    for fee in rows {
+        fees.push(try!(fee));
+    }
+    Ok(fees)
+}
+
+// Records add-on products financed as part of a loan's principal.
+pub fn add_addons(db: &Path, loan: &str, addons: &[AddOn]) -> rusqlite::Result<()> {
+    let total: f64 = addons.iter().map(|addon| addon.cost).sum();
+    traced("add_addons", &[("loan", &loan), ("addons", &addons.len()), ("total", &total)], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        for addon in addons {
+            try!(tx.execute("INSERT INTO addons (loan, name, cost, periods) VALUES ($1, $2, $3, $4)",
+                            &[&loan, &addon.name, &addon.cost, &addon.periods]));
+        }
+        tx.commit()
+    })
+}
+
+pub fn load_addons(db: &Path, loan: &str) -> rusqlite::Result<Vec<AddOn>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT name, cost, periods FROM addons WHERE loan = $0 ORDER BY id"));
+    let rows = try!(stmt.query_map(&[&loan], |row| {
+        AddOn{
+            name: row.get(0),
+            cost: row.get(1),
+            periods: row.get(2),
+        }
+    }));
+
+    let mut addons = Vec::new();
+    for addon in rows {
+        addons.push(try!(addon));
+    }
+    Ok(addons)
+}
+
+// Records exchange rates as of `date`.
+pub fn add_rates(db: &Path, rates: &[currency::ExchangeRate], date: Timespec) -> rusqlite::Result<()> {
+    traced("add_rates", &[("rates", &rates.len()), ("date", &day(date))], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        for rate in rates {
+            try!(tx.execute("INSERT INTO exchange_rates (from_currency, to_currency, rate, date) VALUES ($1, $2, $3, $4)",
+                            &[&rate.from, &rate.to, &rate.rate, &date]));
+        }
+        tx.commit()
+    })
+}
+
+// Loads the most recent rate for each currency pair.
+pub fn load_rates(db: &Path) -> rusqlite::Result<currency::Rates> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT from_currency, to_currency, rate FROM exchange_rates ORDER BY date, id"));
+    let rows = try!(stmt.query_map(&[], |row| {
+        currency::ExchangeRate{
+            from: row.get(0),
+            to: row.get(1),
+            rate: row.get(2),
+        }
+    }));
+
+    let mut rates = currency::Rates::new();
+    for rate in rows {
+        rates.insert(try!(rate));
+    }
+    Ok(rates)
+}
+
+// A published interest rate index (SOFR, prime...) that adjustable-rate
+// loans reset against. Values may run into the future, e.g. a forward curve,
+// to project later resets.
+#[derive(Debug, Clone)]
+pub struct RateIndex {
+    pub name: String,
+    // (date, rate in percent), oldest first
+    pub values: Vec<(Timespec, f64)>,
+}
+
+impl RateIndex {
+    // The index as of `date`: its latest value on or before then. Past the
+    // last value, the index is assumed to stay where it was.
+    pub fn value_at(&self, date: Timespec) -> Option<f64> {
+        self.values.iter()
+            .take_while(|&&(from, _)| from <= date)
+            .last()
+            .map(|&(_, value)| value)
+    }
+}
+
+// Records values of a rate index, replacing any already stored for the same
+// dates.
+pub fn add_index_values(db: &Path, name: &str, values: &[(Timespec, f64)]) -> rusqlite::Result<()> {
+    traced("add_index_values", &[("index", &name), ("values", &values.len())], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        for &(date, value) in values {
+            try!(tx.execute("DELETE FROM rate_indexes WHERE name = $0 AND date = $1", &[&name, &date]));
+            try!(tx.execute("INSERT INTO rate_indexes (name, date, value) VALUES ($1, $2, $3)", &[&name, &date, &value]));
+        }
+        tx.commit()
+    })
+}
+
+fn load_index_from_db(conn: &Connection, name: &str) -> rusqlite::Result<RateIndex> {
+    let mut stmt = try!(conn.prepare("SELECT date, value FROM rate_indexes WHERE name = $0 ORDER BY date"));
+    let rows = try!(stmt.query_map(&[&name], |row| (row.get(0), row.get(1))));
+
+    let mut values = Vec::new();
+    for value in rows {
+        values.push(try!(value));
+    }
+    Ok(RateIndex{
+        name: name.to_string(),
+        values: values,
+    })
+}
+
+pub fn load_index(db: &Path, name: &str) -> rusqlite::Result<RateIndex> {
+    let conn = try!(open(db));
+    load_index_from_db(&conn, name)
+}
+
+// Every rate index with stored values, by name.
+pub fn load_indexes(db: &Path) -> rusqlite::Result<Vec<RateIndex>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT DISTINCT name FROM rate_indexes ORDER BY name"));
+    let rows = try!(stmt.query_map(&[], |row| row.get::<i32, String>(0)));
+
+    let mut indexes = Vec::new();
+    for name in rows {
+        indexes.push(try!(load_index_from_db(&conn, &try!(name))));
+    }
+    Ok(indexes)
+}
+
+// Fills in an adjustable-rate loan's resets from its index.
+fn with_resets(conn: &Connection, mut loan: Loan) -> rusqlite::Result<Loan> {
+    if let Some(name) = loan.rate_index.clone() {
+        let index = try!(load_index_from_db(conn, &name));
+        loan.rate_resets = loan.project_resets(&index);
+    }
+    Ok(loan)
+}
+
+// Replaces a loan's borrowers.
+pub fn set_borrowers(db: &Path, loan: &str, borrowers: &[Borrower]) -> rusqlite::Result<()> {
+    traced("set_borrowers", &[("loan", &loan), ("borrowers", &borrowers.len())], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        try!(tx.execute("DELETE FROM borrowers WHERE loan = $0", &[&loan]));
+        for borrower in borrowers {
+            try!(tx.execute("INSERT INTO borrowers (loan, name, share) VALUES ($1, $2, $3)", &[&loan, &borrower.name, &borrower.share]));
+        }
+        tx.commit()
+    })
+}
+
+pub fn load_borrowers(db: &Path, loan: &str) -> rusqlite::Result<Vec<Borrower>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT name, share FROM borrowers WHERE loan = $0 ORDER BY id"));
+    let rows = try!(stmt.query_map(&[&loan], |row| {
+        Borrower{
+            name: row.get(0),
+            share: row.get(1),
+        }
+    }));
+
+    let mut borrowers = Vec::new();
+    for borrower in rows {
+        borrowers.push(try!(borrower));
+    }
+    Ok(borrowers)
+}
+
+// Each borrower's share of the balance, payments and interest across all the
+// loans they're on.
+pub fn borrower_totals(db: &Path) -> rusqlite::Result<Vec<BorrowerTotals>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare(&format!("
+            SELECT b.name,
+                   SUM(l.balance * b.share / 100.0),
+                   SUM(COALESCE(t.paid, 0.0) * b.share / 100.0),
+                   SUM(COALESCE(t.interest, 0.0) * b.share / 100.0)
+            FROM borrowers b
+            JOIN loans l ON l.name = b.loan
+            LEFT JOIN (SELECT name, SUM(principal + interest) AS paid, SUM(interest) AS interest FROM transactions
+                       WHERE {} GROUP BY name) t ON t.name = b.loan
+            GROUP BY b.name
+            ORDER BY b.name", PAID)));
+    let rows = try!(stmt.query_map(&[], |row| {
+        BorrowerTotals{
+            name: row.get(0),
+            balance: row.get(1),
+            paid: row.get(2),
+            interest: row.get(3),
+        }
+    }));
+
+    let mut totals = Vec::new();
+    for total in rows {
+        totals.push(try!(total));
+    }
+    Ok(totals)
+}
+
+pub fn add_user(db: &Path, name: &str) -> rusqlite::Result<()> {
+    traced("add_user", &[("user", &name)], || {
+        let conn = try!(open(db));
+        try!(conn.execute("INSERT OR IGNORE INTO users (name) VALUES ($0)", &[&name]));
+        Ok(())
+    })
+}
+
+pub fn load_users(db: &Path) -> rusqlite::Result<Vec<String>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT name FROM users ORDER BY name"));
+    let rows = try!(stmt.query_map(&[], |row| row.get(0)));
+
+    let mut users = Vec::new();
+    for user in rows {
+        users.push(try!(user));
+    }
+    Ok(users)
+}
+
+// Gives the loan to `owner`, adding them as a user if needed, or to nobody
+// in particular.
+pub fn set_owner(db: &Path, loan: &str, owner: Option<&str>) -> rusqlite::Result<()> {
+    traced("set_owner", &[("loan", &loan), ("owner", &owner.unwrap_or(""))], || {
+        let conn = try!(open(db));
+        if let Some(owner) = owner {
+            try!(conn.execute("INSERT OR IGNORE INTO users (name) VALUES ($0)", &[&owner]));
+        }
+        try!(conn.execute("UPDATE loans SET owner = $0 WHERE name = $1", &[&owner, &loan]));
+        Ok(())
+    })
+}
+
+// Each user's balances, payments and interest paid across the loans they
+// own. Users who own nothing are listed with zeroes.
+pub fn user_totals(db: &Path) -> rusqlite::Result<Vec<UserTotals>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare(&format!("
+            SELECT u.name,
+                   COUNT(l.id),
+                   COALESCE(SUM(l.balance), 0.0),
+                   COALESCE(SUM(t.paid), 0.0),
+                   COALESCE(SUM(t.interest), 0.0)
+            FROM users u
+            LEFT JOIN loans l ON l.owner = u.name
+            LEFT JOIN (SELECT name, SUM(principal + interest) AS paid, SUM(interest) AS interest FROM transactions
+                       WHERE {} GROUP BY name) t ON t.name = l.name
+            GROUP BY u.name
+            ORDER BY u.name", PAID)));
+    let rows = try!(stmt.query_map(&[], |row| {
+        UserTotals{
+            name: row.get(0),
+            loans: row.get(1),
+            balance: row.get(2),
+            paid: row.get(3),
+            interest: row.get(4),
+        }
+    }));
+
+    let mut totals = Vec::new();
+    for total in rows {
+        totals.push(try!(total));
+    }
+    Ok(totals)
+}
+
+// Adds a lender, or updates the details of an existing one.
+pub fn save_lender(db: &Path, lender: &Lender) -> rusqlite::Result<()> {
+    traced("save_lender", &[("lender", &lender.name)], || {
+        let conn = try!(open(db));
+        try!(conn.execute("INSERT OR REPLACE INTO lenders (name, account_number, url, phone) VALUES ($1, $2, $3, $4)",
+                          &[&lender.name, &lender.account_number, &lender.url, &lender.phone]));
+        Ok(())
+    })
+}
+
+pub fn load_lender(db: &Path, name: &str) -> rusqlite::Result<Lender> {
+    let conn = try!(open(db));
+    conn.query_row("SELECT name, account_number, url, phone FROM lenders WHERE name = $0", &[&name], |row| {
+        Lender{
+            name: row.get(0),
+            account_number: row.get(1),
+            url: row.get(2),
+            phone: row.get(3),
+        }
+    })
+}
+
+pub fn set_lender(db: &Path, loan: &str, lender: &str) -> rusqlite::Result<()> {
+    traced("set_lender", &[("loan", &loan), ("lender", &lender)], || {
+        let conn = try!(open(db));
+        try!(conn.execute("UPDATE loans SET lender = $0 WHERE name = $1", &[&lender, &loan]));
+        Ok(())
+    })
+}
+
+// Sub-loans shown and paid as one account.
+#[derive(Debug, Clone)]
+pub struct LoanGroup {
+    pub name: String,
+    // where payments beyond the amount due go
+    pub allocation: group::Allocation,
+}
+
+// Adds a group, or changes how an existing one allocates payments.
+pub fn save_group(db: &Path, group: &LoanGroup) -> rusqlite::Result<()> {
+    traced("save_group", &[("group", &group.name), ("allocation", &group.allocation.as_str())], || {
+        let conn = try!(open(db));
+        try!(conn.execute("INSERT OR REPLACE INTO loan_groups (name, allocation) VALUES ($1, $2)", &[&group.name, &group.allocation.as_str()]));
+        Ok(())
+    })
+}
+
+pub fn load_group(db: &Path, name: &str) -> rusqlite::Result<LoanGroup> {
+    let conn = try!(open(db));
+    conn.query_row("SELECT name, allocation FROM loan_groups WHERE name = $0", &[&name], |row| {
+        LoanGroup{
+            name: row.get(0),
+            allocation: row.get::<i32, String>(1).parse().unwrap_or(group::Allocation::Standard),
+        }
+    })
+}
+
+// Adds `loan` to `group`, or takes it out of its group with None.
+pub fn set_group(db: &Path, loan: &str, group: Option<&str>) -> rusqlite::Result<()> {
+    traced("set_group", &[("loan", &loan), ("group", &group.unwrap_or_default())], || {
+        let conn = try!(open(db));
+        try!(conn.execute("UPDATE loans SET loan_group = $0 WHERE name = $1", &[&group, &loan]));
+        Ok(())
+    })
+}
+
+// The sub-loans in `group`.
+pub fn load_group_loans(db: &Path, group: &str) -> rusqlite::Result<Vec<Loan>> {
+    Ok(try!(load_loans(db)).into_iter().filter(|loan| loan.loan_group.as_ref().map_or(false, |name| name == group)).collect())
+}
+
+// A single payment made on a group, and the payment it became on each
+// sub-loan.
+#[derive(Debug, Clone)]
+pub struct GroupPayment {
+    pub id: i32,
+    pub loan_group: String,
+    pub date: Timespec,
+    pub amount: f64,
+    // the rule it was split by
+    pub allocation: group::Allocation,
+    pub payments: Vec<Transaction>,
+}
+
+// Splits a payment of `amount` on `group` across its sub-loans by the group's
+// allocation rule, recording a payment on each. The split is kept with the
+// group payment.
+pub fn pay_group(db: &Path, group: &str, amount: f64, date: Timespec, payment_method: Option<String>) -> rusqlite::Result<GroupPayment> {
+    let allocation = try!(load_group(db, group)).allocation;
+    let loans: Vec<Loan> = try!(load_group_loans(db, group)).into_iter().filter(|loan| loan.balance > 0f64).collect();
+    let lots: Vec<group::Lot> = loans.iter().map(|loan| group::Lot{
+        name: loan.name.clone(),
+        apr: loan.apr,
+        due: loan.amount_due(date),
+        payoff: loan.balance + loan.split_payment(loan.balance, false, date).0,
+    }).collect();
+
+    // the group payment and its split are recorded together or not at all
+    let (id, recorded) = try!(traced("group_payment", &[("group", &group), ("amount", &amount), ("date", &day(date)), ("allocation", &allocation.as_str())], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        try!(tx.execute("INSERT INTO group_payments (loan_group, date, amount, allocation, time_created) VALUES ($1, $2, $3, $4, $5)",
+                        &[&group, &date, &amount, &allocation.as_str(), &time::get_time()]));
+        let id = tx.last_insert_rowid() as i32;
+        let mut recorded = Vec::new();
+        for ((loan, lot), part) in loans.iter().zip(lots.iter()).zip(group::allocate(&lots, amount, allocation)) {
+            // rounding mustn't take a payment below what's due
+            let part = calc::Rounding::HalfUp(loan.minor_units).apply(part).max(part.min(lot.due));
+            if part > 0f64 {
+                let payment = try!(record_payment(&tx, loan.name.clone(), part, false, date, payment_method.clone(), false));
+                try!(tx.execute("UPDATE transactions SET group_payment = $0 WHERE id = $1", &[&id, &(payment.1).id]));
+                recorded.push(payment);
+            }
+        }
+        try!(tx.commit());
+        Ok((id, recorded))
+    }));
+
+    let mut payments = Vec::new();
+    for (loan, payment, updated) in recorded {
+        report_payment(&loan, &payment, &updated, date);
+        payments.push(payment);
+    }
+    Ok(GroupPayment{
+        id: id,
+        loan_group: group.to_string(),
+        date: date,
+        amount: amount,
+        allocation: allocation,
+        payments: payments,
+    })
+}
+
+// Payments made on `group`, oldest first, each with its split.
+pub fn load_group_payments(db: &Path, group: &str) -> rusqlite::Result<Vec<GroupPayment>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT id, loan_group, date, amount, allocation FROM group_payments WHERE loan_group = $0 ORDER BY date, id"));
+    let rows = try!(stmt.query_map(&[&group], |row| {
+        GroupPayment{
+            id: row.get(0),
+            loan_group: row.get(1),
+            date: row.get(2),
+            amount: row.get(3),
+            allocation: row.get::<i32, String>(4).parse().unwrap_or(group::Allocation::Standard),
+            payments: Vec::new(),
+        }
+    }));
+
+    let mut group_payments = Vec::new();
+    for group_payment in rows {
+        let mut group_payment = try!(group_payment);
+        let mut stmt = try!(conn.prepare(&format!("SELECT {} FROM transactions WHERE group_payment = $0 ORDER BY id", TRANSACTION_COLUMNS)));
+        let payments = try!(stmt.query_map(&[&group_payment.id], Transaction::from_row));
+        for payment in payments {
+            group_payment.payments.push(try!(payment));
+        }
+        group_payments.push(group_payment);
+    }
+    Ok(group_payments)
+}
+
+// Sets what's collected for escrow and mortgage insurance with each regular
+// payment, so combined payments can be split.
+pub fn set_impounds(db: &Path, loan: &str, escrow: f64, pmi: f64) -> rusqlite::Result<()> {
+    traced("impounds", &[("loan", &loan), ("escrow", &escrow), ("pmi", &pmi)], || {
+        let conn = try!(open(db));
+        try!(conn.execute("UPDATE loans SET escrow_payment = $0, pmi_payment = $1 WHERE name = $2", &[&escrow, &pmi, &loan]));
+        Ok(())
+    })
+}
+
+// Turns paid-ahead mode on or off for a loan.
+pub fn set_paid_ahead(db: &Path, loan: &str, paid_ahead: bool) -> rusqlite::Result<()> {
+    traced("paid_ahead", &[("loan", &loan), ("paid_ahead", &paid_ahead)], || {
+        let conn = try!(open(db));
+        try!(conn.execute("UPDATE loans SET paid_ahead = $0 WHERE name = $1", &[&paid_ahead, &loan]));
+        Ok(())
+    })
+}
+
+pub fn load_loan(db: &Path, name: &str) -> rusqlite::Result<Loan> {
+    let conn = try!(open(db));
+    Loan::load_from_db(&conn, &name.to_string())
+}
+
+// Brings every loan's status up to date, since time passing alone can make a
+// loan late. Deferred interest from promotions that ran out is charged first.
+pub fn refresh_statuses(db: &Path) -> rusqlite::Result<()> {
+    try!(charge_deferred_interest(db, today()));
+    try!(capitalize_deferment_interest(db, today()));
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT name FROM loans"));
+    let rows = try!(stmt.query_map(&[], |row| row.get::<i32, String>(0)));
+
+    let today = today();
+    for name in rows {
+        try!(update_status(&conn, &try!(name), today));
+    }
+    Ok(())
+}
+
+// Below this many loans, schedules aren't worth spreading across threads.
+const PARALLEL_SCHEDULES: usize = 64;
+
+// Computes the remaining schedule of each loan, spread across the available
+// cores for large portfolios.
+pub fn schedules(loans: Vec<Loan>) -> Vec<(Loan, Vec<calc::Installment>)> {
+    let compute = |loans: Vec<Loan>| loans.into_iter().map(|loan| {
+        let schedule = loan.schedule().collect();
+        (loan, schedule)
+    }).collect::<Vec<_>>();
+
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    if threads == 1 || loans.len() < PARALLEL_SCHEDULES {
+        return compute(loans);
+    }
+
+    let per_thread = (loans.len() + threads - 1) / threads;
+    let mut loans = loans.into_iter();
+    let workers: Vec<_> = (0..threads).map(|_| {
+        let chunk: Vec<Loan> = loans.by_ref().take(per_thread).collect();
+        thread::spawn(move || compute(chunk))
+    }).collect();
+    workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+}
+
+// Every loan, with its status worked out as of `today` but not saved, for
+// readers such as the metrics that mustn't change the database. Unlike after
+// refresh_statuses, interest from promotions or deferments that have ended
+// isn't on the balances until something else charges it.
+pub fn load_loans_as_of(db: &Path, today: Timespec) -> rusqlite::Result<Vec<Loan>> {
+    let conn = try!(open(db));
+    let mut loans = try!(load_loans(db));
+    for loan in loans.iter_mut() {
+        let paid = try!(total_paid(&conn, &loan.name));
+        loan.status = loan.status_at(paid, today);
+    }
+    Ok(loans)
+}
+
+// Every loan in the database with its remaining schedule, loaded in one
+// query. Much faster than load_loan and schedule for each loan in a
+// portfolio of hundreds.
+pub fn load_schedules(db: &Path) -> rusqlite::Result<Vec<(Loan, Vec<calc::Installment>)>> {
+    load_loans(db).map(schedules)
+}
+
+fn parse_installment(line: &str) -> Option<calc::Installment> {
+    let fields: Vec<&str> = line.split(',').collect();
+    if fields.len() != 5 {
+        return None;
+    }
+    let amount = |i: usize| fields[i].parse::<f64>().ok();
+    match (fields[0].parse().ok(), amount(1), amount(2), amount(3), amount(4)) {
+        (Some(period), Some(payment), Some(interest), Some(principal), Some(balance)) => Some(calc::Installment{
+            period: period,
+            payment: payment,
+            interest: interest,
+            principal: principal,
+            balance: balance,
+        }),
+        _ => None,
+    }
+}
+
+// Schedules are cached under their own parameters, so a loan whose balance,
+// rate or terms have changed since can never be handed a stale one. Rows are
+// stored as "period,payment,interest,principal,balance" lines.
+fn cached(conn: &Connection, name: &str, schedule: calc::Schedule) -> rusqlite::Result<Vec<calc::Installment>> {
+    let key = format!("{:?}", schedule);
+    let rows: Option<String> = match conn.query_row("SELECT rows FROM schedule_cache WHERE loan = $0 AND key = $1", &[&name, &key], |row| row.get(0)) {
+        Ok(rows) => Some(rows),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(err) => return Err(err),
+    };
+    if let Some(rows) = rows {
+        let parsed: Option<Vec<calc::Installment>> = rows.lines().map(parse_installment).collect();
+        // anything unreadable is simply computed again
+        if let Some(installments) = parsed {
+            return Ok(installments);
+        }
+    }
+
+    let installments: Vec<calc::Installment> = schedule.collect();
+    let rows = installments.iter().map(|inst| format!("{},{},{},{},{}", inst.period, inst.payment, inst.interest, inst.principal, inst.balance))
+        .collect::<Vec<_>>().join("\n");
+    try!(conn.execute("INSERT OR REPLACE INTO schedule_cache (loan, key, rows) VALUES ($1, $2, $3)", &[&name, &key, &rows]));
+    Ok(installments)
+}
+
+// Drops the cached schedules of a loan whose balance or payments changed.
+fn invalidate_schedules(conn: &Connection, name: &str) -> rusqlite::Result<()> {
+    try!(conn.execute("DELETE FROM schedule_cache WHERE loan = $0", &[&name]));
+    Ok(())
+}
+
+// Same as loan.schedule(), but saved in the database the first time so
+// refreshing a view doesn't recompute it.
+pub fn cached_schedule(db: &Path, loan: &Loan) -> rusqlite::Result<Vec<calc::Installment>> {
+    let conn = try!(open(db));
+    cached(&conn, &loan.name, loan.schedule())
+}
+
+// Same as loan.contract_schedule(), cached like cached_schedule.
+pub fn cached_contract_schedule(db: &Path, loan: &Loan) -> rusqlite::Result<Vec<calc::Installment>> {
+    let conn = try!(open(db));
+    cached(&conn, &loan.name, loan.contract_schedule())
+}
+
+pub fn load_loans(db: &Path) -> rusqlite::Result<Vec<Loan>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare(&format!("SELECT {} FROM loans", LOAN_COLUMNS)));
+    let rows = try!(stmt.query_map(&[], Loan::from_row));
+
+    let mut loans = Vec::new();
+    for loan in rows {
+        loans.push(try!(with_resets(&conn, try!(loan))));
+    }
+    Ok(loans)
+}
+
+// Month (1 through 12) the fiscal year starts in; January unless set.
+pub fn fiscal_year_start(db: &Path) -> rusqlite::Result<i32> {
+    let conn = try!(open(db));
+    let value: Option<String> = match conn.query_row("SELECT value FROM settings WHERE key = 'fiscal_year_start'", &[], |row| row.get(0)) {
+        Ok(value) => Some(value),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(err) => return Err(err),
+    };
+    Ok(value.and_then(|value| value.parse().ok()).unwrap_or(1))
+}
+
+pub fn set_fiscal_year_start(db: &Path, month: i32) -> rusqlite::Result<()> {
+    traced("set_fiscal_year_start", &[("month", &month)], || {
+        let conn = try!(open(db));
+        try!(conn.execute("INSERT OR REPLACE INTO settings (key, value) VALUES ('fiscal_year_start', $0)", &[&month.to_string()]));
+        Ok(())
+    })
+}
+
+// Fiscal year `date` falls in, for a fiscal year starting in month `start`.
+// A fiscal year is numbered by the calendar year it ends in, so with an
+// October start, October 2026 is in fiscal 2027.
+pub fn fiscal_year(date: Timespec, start: i32) -> i32 {
+    let tm = time::at_utc(date);
+    if start > 1 && tm.tm_mon + 1 >= start { tm.tm_year + 1901 } else { tm.tm_year + 1900 }
+}
+
+// First day of fiscal `year`.
+pub fn fiscal_year_begins(year: i32, start: i32) -> Timespec {
+    let year = if start > 1 { year - 1 } else { year };
+    time::strptime(&format!("{}-{:02}-01", year, start), "%F").unwrap().to_timespec()
+}
+
+// Interest on loans we lent out if `lent`, otherwise on those we borrowed,
+// per fiscal year and loan, oldest year first. Only fiscal `year` and loans
+// `owner` owns, if given.
+fn interest_by_year(db: &Path, lent: bool, year: Option<i32>, owner: Option<&str>) -> rusqlite::Result<Vec<(i32, String, f64)>> {
+    let start = try!(fiscal_year_start(db));
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare(&format!("SELECT CAST(substr(t.date, 1, 4) AS INTEGER) + ($0 > 1 AND CAST(substr(t.date, 6, 2) AS INTEGER) >= $0) AS year,
+                                      t.name, SUM(t.interest) FROM transactions t
+                                      JOIN loans l ON l.name = t.name
+                                      WHERE l.lent = $1 AND {} AND ($2 IS NULL OR l.owner = $2)
+                                      GROUP BY year, t.name HAVING $3 IS NULL OR year = $3 ORDER BY year, t.name", PAID)));
+    let rows = try!(stmt.query_map(&[&start, &lent, &owner, &year], |row| (row.get(0), row.get(1), row.get(2))));
+
+    let mut income = Vec::new();
+    for row in rows {
+        income.push(try!(row));
+    }
+    Ok(income)
+}
+
+// Interest received on loans we lent out, per fiscal year and loan, oldest
+// year first. Only `year` and loans `owner` owns, if given.
+pub fn interest_income(db: &Path, year: Option<i32>, owner: Option<&str>) -> rusqlite::Result<Vec<(i32, String, f64)>> {
+    interest_by_year(db, true, year, owner)
+}
+
+// Interest paid on loans we borrowed, e.g. for a business deduction, laid out
+// like interest_income.
+pub fn interest_paid(db: &Path, year: Option<i32>, owner: Option<&str>) -> rusqlite::Result<Vec<(i32, String, f64)>> {
+    interest_by_year(db, false, year, owner)
+}
+
+// Loads transactions oldest first, optionally only those for one loan or whose
+// payment method starts with `method` (case-insensitive, so "check" finds
+// "Check #1234").
+pub fn load_transactions(db: &Path, loan: Option<&str>, method: Option<&str>) -> rusqlite::Result<Vec<Transaction>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare(&format!("SELECT {} FROM transactions
+                                               WHERE ($0 IS NULL OR name = $0) AND ($1 IS NULL OR payment_method LIKE $1 || '%')
+                                               ORDER BY date, id", TRANSACTION_COLUMNS)));
+    let rows = try!(stmt.query_map(&[&loan, &method], Transaction::from_row));
+
+    let mut transactions = Vec::new();
+    for transaction in rows {
+        transactions.push(try!(transaction));
+    }
+    Ok(transactions)
+}
+
+fn scenario_from_row(row: &rusqlite::Row) -> Scenario {
+    let lump_sums: String = row.get(3);
+    let refi_apr: Option<f64> = row.get(4);
+    let refi_periods: Option<i32> = row.get(5);
+    Scenario{
+        name: row.get(0),
+        loan: row.get(1),
+        monthly_extra: row.get(2),
+        lump_sums: lump_sums.split(',').filter_map(|lump| lump.parse::<LumpSum>().ok()).collect(),
+        refinance: match (refi_apr, refi_periods) {
+            (Some(apr), Some(periods)) => Some(Refinance{ apr: apr, periods: periods }),
+            _ => None,
+        },
+        rate_shock: row.get(6),
+        shock_periods: row.get(7),
+    }
+}
+
+const SCENARIO_COLUMNS: &str = "name, loan, monthly_extra, lump_sums, refi_apr, refi_periods, rate_shock, shock_periods";
+
+// Saves a scenario, replacing any existing one with the same name.
+pub fn save_scenario(db: &Path, scenario: &Scenario) -> rusqlite::Result<()> {
+    let lump_sums: Vec<String> = scenario.lump_sums.iter().map(|lump| lump.to_string()).collect();
+    let refi_apr = scenario.refinance.map(|refi| refi.apr);
+    let refi_periods = scenario.refinance.map(|refi| refi.periods);
+    traced("save_scenario", &[("loan", &scenario.loan), ("scenario", &scenario.name)], || {
+        let conn = try!(open(db));
+        try!(conn.execute(&format!("INSERT OR REPLACE INTO scenarios ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)", SCENARIO_COLUMNS),
+                          &[&scenario.name, &scenario.loan, &scenario.monthly_extra, &lump_sums.join(","), &refi_apr, &refi_periods,
+                            &scenario.rate_shock, &scenario.shock_periods]));
+        Ok(())
+    })
+}
+
+pub fn load_scenario(db: &Path, name: &str) -> rusqlite::Result<Scenario> {
+    let conn = try!(open(db));
+    conn.query_row(&format!("SELECT {} FROM scenarios WHERE name = $0", SCENARIO_COLUMNS), &[&name], |row| scenario_from_row(&row))
+}
+
+pub fn load_scenarios(db: &Path) -> rusqlite::Result<Vec<Scenario>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare(&format!("SELECT {} FROM scenarios ORDER BY name", SCENARIO_COLUMNS)));
+    let rows = try!(stmt.query_map(&[], scenario_from_row));
+
+    let mut scenarios = Vec::new();
+    for scenario in rows {
+        scenarios.push(try!(scenario));
+    }
+    Ok(scenarios)
+}
+
+// What it takes to pay a loan off in full on a given date.
+#[derive(Debug, Clone, Copy)]
+pub struct PayoffQuote {
+    pub date: Timespec,
+    pub balance: f64,
+    // interest accrued since the last payment
+    pub accrued_interest: f64,
+    // interest from skipped payments
+    pub deferred_interest: f64,
+}
+
+impl PayoffQuote {
+    pub fn total(&self) -> f64 {
+        self.balance + self.accrued_interest + self.deferred_interest
+    }
+}
+
+fn last_payment(conn: &Connection, name: &String) -> rusqlite::Result<Option<Timespec>> {
+    conn.query_row(&format!("SELECT MAX(date) FROM transactions WHERE name = $0 AND {}", PAID), &[name], |row| row.get(0))
+}
+
+// Interest accrued on `loan` since the later of its last accrual and its last
+// payment, up to `date`. Interest starts accruing at closing. Returns the start of the span, its length in days and the interest.
+fn pending_accrual(conn: &Connection, loan: &Loan, date: Timespec) -> rusqlite::Result<(Timespec, i64, f64)> {
+    let last_accrual: Option<Timespec> = try!(conn.query_row("SELECT MAX(date) FROM accruals WHERE loan = $0", &[&loan.name], |row| row.get(0)));
+    let mut start = loan.closing_date();
+    for last in [last_accrual, try!(last_payment(conn, &loan.name))].iter() {
+        if let Some(last) = *last {
+            if last > start {
+                start = last;
+            }
+        }
+    }
+
+    let days = (date.sec - start.sec) / 86400;
+    if days <= 0 || loan.balance <= 0f64 {
+        return Ok((start, 0, 0f64));
+    }
+    let period = loan.period_at(date);
+    if period <= loan.deferment_periods && loan.subsidized {
+        return Ok((start, days, 0f64));
+    }
+    let apr = loan.apr_at(period);
+    Ok((start, days, loan.interest_method().daily(loan.balance, apr, days)))
+}
+
+// Interest accrued since the last payment, including what's accrued since the
+// last ledger entry but hasn't been recorded yet.
+fn accrued_interest(conn: &Connection, loan: &Loan, date: Timespec) -> rusqlite::Result<f64> {
+    let since = try!(last_payment(conn, &loan.name));
+    let recorded: f64 = try!(conn.query_row("SELECT COALESCE(SUM(interest), 0.0) FROM accruals WHERE loan = $0 AND date > COALESCE($1, '')",
+                                            &[&loan.name, &since], |row| row.get(0)));
+    let (_, _, pending) = try!(pending_accrual(conn, loan, date));
+    Ok(recorded + pending)
+}
+
+fn record_accrual(conn: &Connection, loan: &Loan, date: Timespec) -> rusqlite::Result<f64> {
+    let (_, days, interest) = try!(pending_accrual(conn, loan, date));
+    if days > 0 {
+        try!(traced("accrue", &[("loan", &loan.name), ("date", &day(date)), ("days", &days), ("interest", &interest)], || {
+            conn.execute("INSERT INTO accruals (loan, date, days, balance, interest, time_created) VALUES ($1, $2, $3, $4, $5, $6)",
+                         &[&loan.name, &date, &days, &loan.balance, &interest, &time::get_time()]).map(|_| ())
+        }));
+    }
+    Ok(interest)
+}
+
+// Records the interest each loan (or just `name`) has accrued up to `date`
+// in the accruals ledger. Returns the loans and amounts recorded.
+pub fn accrue_interest(db: &Path, name: Option<&str>, date: Timespec) -> rusqlite::Result<Vec<(String, f64)>> {
+    let mut conn = try!(open(db));
+    let tx = try!(conn.transaction());
+    let loans = {
+        let mut stmt = try!(tx.prepare(&format!("SELECT {} FROM loans WHERE $0 IS NULL OR name = $0", LOAN_COLUMNS)));
+        let rows = try!(stmt.query_map(&[&name], Loan::from_row));
+        let mut loans = Vec::new();
+        for loan in rows {
+            loans.push(try!(loan));
+        }
+        loans
+    };
+
+    let mut accrued = Vec::new();
+    for loan in loans {
+        let interest = try!(record_accrual(&tx, &loan, date));
+        accrued.push((loan.name, interest));
+    }
+    try!(tx.commit());
+    Ok(accrued)
+}
+
+// Interest each loan has accrued from the first of `today`'s month through
+// `today`: the ledger entries since then plus what's accrued since the last
+// one. Entries reaching back before the month count in proportion.
+pub fn interest_accrued_this_month(db: &Path, today: Timespec) -> rusqlite::Result<Vec<(String, f64)>> {
+    let mut first = time::at_utc(today);
+    first.tm_mday = 1;
+    first.tm_hour = 0;
+    first.tm_min = 0;
+    first.tm_sec = 0;
+    first.tm_nsec = 0;
+    let first = first.to_timespec();
+    // share of `days` ending on `date` that falls within the month
+    let within = |date: Timespec, days: i64, interest: f64| {
+        if days <= 0 { 0f64 } else { interest * std::cmp::min(days, (date.sec - first.sec) / 86400) as f64 / days as f64 }
+    };
+
+    let conn = try!(open(db));
+    let mut accrued = Vec::new();
+    for loan in try!(load_loans(db)) {
+        let mut stmt = try!(conn.prepare("SELECT date, days, interest FROM accruals WHERE loan = $0 AND date > $1"));
+        let rows = try!(stmt.query_map(&[&loan.name, &first], |row| (row.get(0), row.get(1), row.get(2))));
+        let mut interest = 0f64;
+        for row in rows {
+            let (date, days, amount): (Timespec, i64, f64) = try!(row);
+            interest += within(date, days, amount);
+        }
+        let (_, days, pending) = try!(pending_accrual(&conn, &loan, today));
+        interest += within(today, days, pending);
+        accrued.push((loan.name, interest));
+    }
+    Ok(accrued)
+}
+
+pub fn payoff_quote(db: &Path, name: &str, date: Timespec) -> rusqlite::Result<PayoffQuote> {
+    let conn = try!(open(db));
+    let loan = try!(Loan::load_from_db(&conn, &name.to_string()));
+    Ok(PayoffQuote{
+        date: date,
+        balance: loan.balance,
+        accrued_interest: try!(accrued_interest(&conn, &loan, date)),
+        deferred_interest: loan.unpaid_interest,
+    })
+}
+
+// Days before a deferred-interest promotion ends to start warning about it.
+pub const PROMO_WARNING_DAYS: i64 = 60;
+
+// Days before a bullet loan's principal falls due to start warning about it.
+pub const MATURITY_WARNING_DAYS: i64 = 90;
+
+// A "no interest if paid in full" promotion still running on a loan.
+#[derive(Debug, Clone)]
+pub struct DeferredPromo {
+    pub loan: String,
+    // the balance must be paid off by the last promotional due date
+    pub deadline: Timespec,
+    pub balance: f64,
+    // interest at the regular rate so far, charged all at once if the
+    // balance isn't cleared by the deadline
+    pub interest: f64,
+}
+
+impl DeferredPromo {
+    // Whether the deadline is close enough, as of `today`, to warn about.
+    pub fn due_soon(&self, today: Timespec) -> bool {
+        (self.deadline.sec - today.sec) / 86400 <= PROMO_WARNING_DAYS
+    }
+}
+
+// Interest waived so far by a deferred-interest promotion, at the regular
+// rate on the balance owed at each promotional due date up to `date`.
+fn deferred_interest(conn: &Connection, loan: &Loan, date: Timespec) -> rusqlite::Result<f64> {
+    let mut stmt = try!(conn.prepare(&format!("SELECT principal, date FROM transactions WHERE name = $0 AND {}", PAID)));
+    let rows = try!(stmt.query_map(&[&loan.name], |row| (row.get::<i32, f64>(0), row.get::<i32, Timespec>(1))));
+    let mut payments = Vec::new();
+    for payment in rows {
+        payments.push(try!(payment));
+    }
+
+    let mut deferred = 0f64;
+    for period in 1..loan.promo_periods+1 {
+        let due = loan.due_date(period);
+        if due > date {
+            break;
+        }
+        let paid: f64 = payments.iter().filter(|&&(_, paid_on)| paid_on < due).map(|&(principal, _)| principal).sum();
+        deferred += loan.interest_rounding.apply(loan.interest_method().monthly(loan.principal - paid, loan.apr));
+    }
+    Ok(deferred)
+}
+
+fn deferred_promo(conn: &Connection, loan: &Loan, date: Timespec) -> rusqlite::Result<DeferredPromo> {
+    Ok(DeferredPromo{
+        loan: loan.name.clone(),
+        deadline: loan.due_date(loan.promo_periods),
+        balance: loan.balance,
+        interest: try!(deferred_interest(conn, loan, date)),
+    })
+}
+
+// Loans with a deferred-interest promotion that hasn't been paid off or
+// charged yet, as of `date`.
+pub fn deferred_promos(db: &Path, date: Timespec) -> rusqlite::Result<Vec<DeferredPromo>> {
+    let conn = try!(open(db));
+    let mut promos = Vec::new();
+    for loan in try!(load_loans(db)) {
+        if loan.defer_interest && loan.promo_periods > 0 && loan.balance > 0f64 {
+            promos.push(try!(deferred_promo(&conn, &loan, date)));
+        }
+    }
+    Ok(promos)
+}
+
+// Charges the deferred interest on every promotion whose deadline passed
+// before `today` with a balance left, adding it to the balance. Returns the
+// loans and amounts charged.
+pub fn charge_deferred_interest(db: &Path, today: Timespec) -> rusqlite::Result<Vec<(String, f64)>> {
+    let mut charged = Vec::new();
+    for promo in try!(deferred_promos(db, today)) {
+        if promo.deadline >= today {
+            continue;
+        }
+        try!(traced("deferred_interest", &[("loan", &promo.loan), ("deadline", &day(promo.deadline)), ("interest", &promo.interest)], || {
+            let mut conn = try!(open(db));
+            let tx = try!(conn.transaction());
+            try!(tx.execute("UPDATE loans SET defer_interest = 0 WHERE name = $0", &[&promo.loan]));
+            try!(Transaction::charge(&promo.loan, TransactionKind::Capitalization, promo.interest, promo.deadline).record(&tx));
+            try!(update_status(&tx, &promo.loan, today));
+            tx.commit()
+        }));
+        charged.push((promo.loan, promo.interest));
+    }
+    Ok(charged)
+}
+
+// Adds the interest accrued during deferment to the balance of every
+// unsubsidized loan whose repayment has started by `today`. Returns the loans
+// and amounts capitalized.
+pub fn capitalize_deferment_interest(db: &Path, today: Timespec) -> rusqlite::Result<Vec<(String, f64)>> {
+    let mut capitalized = Vec::new();
+    for loan in try!(load_loans(db)) {
+        let interest = loan.deferment_interest();
+        if loan.deferment_periods == 0 || interest <= 0f64 || loan.due_date(loan.deferment_periods) >= today {
+            continue;
+        }
+        try!(traced("capitalize", &[("loan", &loan.name), ("interest", &interest)], || {
+            let mut conn = try!(open(db));
+            let tx = try!(conn.transaction());
+            try!(tx.execute("UPDATE loans SET capitalized_interest = $0 WHERE name = $1", &[&interest, &loan.name]));
+            let date = loan.due_date(loan.deferment_periods + 1);
+            try!(Transaction::charge(&loan.name, TransactionKind::Capitalization, interest, date).record(&tx));
+            try!(update_status(&tx, &loan.name, today));
+            tx.commit()
+        }));
+        capitalized.push((loan.name, interest));
+    }
+    Ok(capitalized)
+}
+
+// The loan's contractual schedule against its actual balances so far and the
+// projection from here.
+pub fn loan_overlay(db: &Path, name: &str, today: Timespec) -> rusqlite::Result<Overlay> {
+    let conn = try!(open(db));
+    let loan = try!(Loan::load_from_db(&conn, &name.to_string()));
+
+    let mut elapsed = 0;
+    while elapsed < loan.periods && add_months(loan.start_time, elapsed) <= today {
+        elapsed += 1;
+    }
+
+    // principal paid in each period, from the payment history
+    let mut paid = vec![0f64; elapsed as usize];
+    {
+        let mut stmt = try!(conn.prepare(&format!("SELECT principal, date FROM transactions WHERE name = $0 AND {}", PAID)));
+        let rows = try!(stmt.query_map(&[&loan.name], |row| (row.get::<i32, f64>(0), row.get::<i32, Timespec>(1))));
+        for row in rows {
+            let (principal, date) = try!(row);
+            // early payments count towards the first period, prepayments
+            // towards the latest one
+            let period = months_between(loan.start_time, date);
+            let i = if period < 0 { 0 } else { period as usize };
+            if let Some(last) = paid.len().checked_sub(1) {
+                paid[if i > last { last } else { i }] += principal;
+            }
+        }
+    }
+
+    let mut balance = loan.principal;
+    let actual: Vec<f64> = paid.iter().map(|principal| {
+        balance -= *principal;
+        balance
+    }).collect();
+    Ok(Overlay::new(loan.contract_schedule(), &actual, loan.schedule()))
+}
+
+// A payment already recorded on `name` for `amount` on the same day as
+// `date`, pending or not, which recording another would likely duplicate.
+pub fn find_duplicate(db: &Path, name: &str, amount: f64, date: Timespec) -> rusqlite::Result<Option<Transaction>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare(&format!("SELECT {} FROM transactions
+                                               WHERE name = $0 AND substr(date, 1, 10) = $1 AND {}
+                                                     AND abs(principal + interest + escrow + pmi - $2) < 0.005
+                                               ORDER BY id", TRANSACTION_COLUMNS, RECORDED)));
+    let mut rows = try!(stmt.query_map(&[&name, &day(date), &amount], Transaction::from_row));
+    match rows.next() {
+        Some(row) => row.map(Some),
+        None => Ok(None),
+    }
+}
+
+// Records a payment on `name`, returning it as saved. A regular payment short
+// of what's due is refused with Refusal::InsufficientPayment.
+pub fn commit_transaction(db: &Path, name: String, amount: f64, extra: bool, date: Timespec, payment_method: Option<String>) -> rusqlite::Result<Transaction> {
+    commit_payment(db, name, amount, extra, date, payment_method, false)
+}
+
+// Records a payment on `name` as pending review, leaving the balance alone
+// until it's confirmed with confirm_pending.
+pub fn commit_pending_transaction(db: &Path, name: String, amount: f64, extra: bool, date: Timespec, payment_method: Option<String>)
+                                  -> rusqlite::Result<Transaction> {
+    commit_payment(db, name, amount, extra, date, payment_method, true)
+}
+
+fn commit_payment(db: &Path, name: String, amount: f64, extra: bool, date: Timespec, payment_method: Option<String>, pending: bool)
+                  -> rusqlite::Result<Transaction> {
+    let (loan_name, method) = (name.clone(), payment_method.clone().unwrap_or_default());
+    traced("payment", &[("loan", &loan_name), ("amount", &amount), ("extra", &extra), ("date", &day(date)), ("method", &method), ("pending", &pending)], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        let (loan, transaction, updated) = try!(record_payment(&tx, name, amount, extra, date, payment_method, pending));
+        try!(tx.commit());
+        report_payment(&loan, &transaction, &updated, date);
+        Ok(transaction)
+    })
+}
+
+// Records a payment on `name` on `conn`, which should be in a transaction.
+// Returns the loan as it was, the payment as saved and the loan after it.
+fn record_payment(conn: &Connection, name: String, amount: f64, extra: bool, date: Timespec, payment_method: Option<String>, pending: bool)
+                  -> rusqlite::Result<(Loan, Transaction, Loan)> {
+    let loan = try!(Loan::load_from_db(conn, &name));
+
+    let mut transaction = {
+        // a regular payment may include escrow and PMI; extra goes
+        // entirely to the loan
+        let (escrow, pmi, paid) = if extra { (0f64, 0f64, amount) } else { loan.split_combined(amount) };
+        if !extra && loan.amount_due(date) > paid {
+            return Err(Refusal::InsufficientPayment{ expected: loan.total_due(date), paid: amount }.into());
+        }
+        let (interest, principal) = loan.split_payment(paid, extra, date);
+
+        Transaction{
+            id: 0,
+            name: name,
+            principal: principal,
+            interest: interest,
+            date: date,
+            time_created: time::get_time(),
+            payment_method: payment_method,
+            reversed: false,
+            escrow: escrow,
+            pmi: pmi,
+            kind: if extra { TransactionKind::Extra } else { TransactionKind::Payment },
+            provisional: pending,
+        }
+    };
+
+    if !pending {
+        try!(record_accrual(conn, &loan, date));
+    }
+    try!(transaction.record(conn));
+    transaction.id = conn.last_insert_rowid() as i32;
+    try!(update_status(conn, &transaction.name, today()));
+    let updated = try!(Loan::load_from_db(conn, &transaction.name));
+    Ok((loan, transaction, updated))
+}
+
+// Tells the user what a payment recorded by record_payment did.
+fn report_payment(loan: &Loan, transaction: &Transaction, updated: &Loan, date: Timespec) {
+    if transaction.provisional {
+        println!("{}", trf("Payment #{} recorded for review: about ${} towards the balance and ${} in interest once confirmed.",
+                           &[&transaction.id, &loan.money(transaction.principal), &loan.money(transaction.interest)]));
+        return;
+    }
+    println!("{}", trf("Payment received. You paid ${} towards the balance, ${} in interest and have ${} remaining on your loan.",
+                       &[&loan.money(transaction.principal), &loan.money(transaction.interest), &loan.money(loan.balance - transaction.principal)]));
+    if transaction.escrow > 0f64 || transaction.pmi > 0f64 {
+        println!("{}", trf("${} went to escrow and ${} to mortgage insurance.", &[&format!("{:.2}", transaction.escrow), &format!("{:.2}", transaction.pmi)]));
+    }
+    if updated.paid_ahead && updated.balance > 0f64 {
+        println!("{}", trf("Next payment due {}.", &[&day(updated.next_due(date))]));
+    }
+}
+
+// Takes a payment back off the books, returning the principal it paid to the
+// loan's balance. Interest accrued up to it stays in the accruals ledger.
+pub fn remove_transaction(db: &Path, transaction: &Transaction) -> rusqlite::Result<()> {
+    traced("remove_payment", &[("loan", &transaction.name), ("payment", &transaction.id), ("principal", &transaction.principal)], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        try!(tx.execute("DELETE FROM transactions WHERE id = $0", &[&transaction.id]));
+        if !transaction.provisional {
+            try!(tx.execute("UPDATE loans SET balance = balance + $0 WHERE name = $1", &[&transaction.principal, &transaction.name]));
+        }
+        try!(invalidate_schedules(&tx, &transaction.name));
+        try!(update_status(&tx, &transaction.name, today()));
+        tx.commit()
+    })
+}
+
+// Puts back a payment taken off by remove_transaction, under its old id.
+pub fn restore_transaction(db: &Path, transaction: &Transaction) -> rusqlite::Result<()> {
+    traced("restore_payment", &[("loan", &transaction.name), ("payment", &transaction.id), ("principal", &transaction.principal)], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        try!(tx.execute("INSERT INTO transactions (id, name, principal, interest, date, time_created, payment_method, reversed, escrow, pmi, kind,
+                                                   provisional)
+                         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+                        &[&transaction.id, &transaction.name, &transaction.principal, &transaction.interest, &transaction.date,
+                          &transaction.time_created, &transaction.payment_method, &transaction.reversed, &transaction.escrow, &transaction.pmi,
+                          &transaction.kind.as_str(), &transaction.provisional]));
+        if !transaction.provisional {
+            try!(tx.execute("UPDATE loans SET balance = balance - $0 WHERE name = $1", &[&transaction.principal, &transaction.name]));
+        }
+        try!(invalidate_schedules(&tx, &transaction.name));
+        try!(update_status(&tx, &transaction.name, today()));
+        tx.commit()
+    })
+}
+
+// Marks payment `id` as returned unpaid (NSF). A reversal puts its principal
+// back on the loan's balance, along with `fee` if the lender charges one for
+// returned payments. The payment stays in the history, flagged as reversed. A
+// payment that doesn't exist or was already reversed gives
+// QueryReturnedNoRows.
+pub fn bounce_payment(db: &Path, id: i32, fee: f64) -> rusqlite::Result<Transaction> {
+    traced("bounce", &[("payment", &id), ("fee", &fee)], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        let mut transaction = try!(tx.query_row(&format!("SELECT {} FROM transactions WHERE id = $0 AND {}", TRANSACTION_COLUMNS, PAID),
+                                                &[&id], |row| Transaction::from_row(&row)));
+        try!(tx.execute("UPDATE transactions SET reversed = 1 WHERE id = $0", &[&id]));
+        let today = today();
+        let mut reversal = Transaction::charge(&transaction.name, TransactionKind::Reversal, transaction.principal, today);
+        reversal.interest = -transaction.interest;
+        try!(reversal.record(&tx));
+        if fee > 0f64 {
+            try!(tx.execute("INSERT INTO fees (loan, name, amount, date) VALUES ($1, 'Returned payment', $2, $3)",
+                            &[&transaction.name, &fee, &today]));
+            try!(Transaction::charge(&transaction.name, TransactionKind::Fee, fee, today).record(&tx));
+        }
+        try!(update_status(&tx, &transaction.name, today));
+        try!(tx.commit());
+        transaction.reversed = true;
+        Ok(transaction)
+    })
+}
+
+// Fills in the payments scheduled on or before `today` for a loan started in
+// the past, each for the amount that was due, as pending payments to review.
+// Due dates already covered by a payment are left alone.
+pub fn backfill_payments(db: &Path, name: &str, today: Timespec) -> rusqlite::Result<Vec<Transaction>> {
+    traced("backfill", &[("loan", &name), ("today", &day(today))], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        // pending payments leave the stored balance alone, so it's run down
+        // here to split each one
+        let mut loan = try!(Loan::load_from_db(&tx, &name.to_string()));
+        let mut payments = Vec::new();
+        for period in 1..loan.periods + 1 {
+            let due = loan.due_date(period);
+            if due > today || loan.balance <= 0f64 {
+                break;
+            }
+            let covered: i64 = try!(tx.query_row(&format!("SELECT COUNT(*) FROM transactions
+                                                           WHERE name = $0 AND date > $1 AND date <= $2 AND {}", RECORDED),
+                                                 &[&loan.name, &add_months(due, -1), &due], |row| row.get(0)));
+            let amount = loan.amount_due(due);
+            if covered > 0 || amount <= 0f64 {
+                continue;
+            }
+
+            let (interest, principal) = loan.split_payment(amount, false, due);
+            // the last payment clears whatever's left
+            let principal = if period == loan.periods { loan.balance } else { principal.min(loan.balance) };
+            let mut payment = Transaction::charge(name, TransactionKind::Payment, -principal, due);
+            payment.interest = interest;
+            payment.escrow = loan.escrow_payment;
+            payment.pmi = loan.pmi_payment;
+            payment.provisional = true;
+            try!(payment.record(&tx));
+            payment.id = tx.last_insert_rowid() as i32;
+            loan.balance -= principal;
+            payments.push(payment);
+        }
+        try!(tx.commit());
+        Ok(payments)
+    })
+}
+
+fn load_pending(conn: &Connection, name: &str, ids: &[i32]) -> rusqlite::Result<Vec<Transaction>> {
+    let mut stmt = try!(conn.prepare(&format!("SELECT {} FROM transactions WHERE name = $0 AND provisional ORDER BY date, id", TRANSACTION_COLUMNS)));
+    let rows = try!(stmt.query_map(&[&name], Transaction::from_row));
+    let mut pending = Vec::new();
+    for row in rows {
+        let transaction = try!(row);
+        if ids.is_empty() || ids.contains(&transaction.id) {
+            pending.push(transaction);
+        }
+    }
+    Ok(pending)
+}
+
+// Applies a loan's pending payments, oldest first: each is split again
+// against the balance as it stands, then taken off it. Only those in `ids`,
+// or all of them if it's empty. Returns them as confirmed.
+pub fn confirm_pending(db: &Path, name: &str, ids: &[i32]) -> rusqlite::Result<Vec<Transaction>> {
+    traced("confirm_pending", &[("loan", &name), ("payments", &ids.len())], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        let mut confirmed = Vec::new();
+        for mut payment in try!(load_pending(&tx, name, ids)) {
+            let loan = try!(Loan::load_from_db(&tx, &name.to_string()));
+            let amount = payment.principal + payment.interest;
+            let (_, principal) = loan.split_payment(amount, payment.kind == TransactionKind::Extra, payment.date);
+            // rounding shouldn't leave a cent behind on the last one
+            let principal = if loan.balance - principal < 0.005 { loan.balance } else { principal };
+            try!(record_accrual(&tx, &loan, payment.date));
+            try!(tx.execute("UPDATE transactions SET principal = $0, interest = $1, provisional = 0 WHERE id = $2",
+                            &[&principal, &(amount - principal), &payment.id]));
+            try!(tx.execute("UPDATE loans SET balance = balance - $0 WHERE name = $1", &[&principal, &name]));
+            payment.principal = principal;
+            payment.interest = amount - principal;
+            payment.provisional = false;
+            confirmed.push(payment);
+        }
+        try!(invalidate_schedules(&tx, name));
+        try!(update_status(&tx, &name.to_string(), today()));
+        try!(tx.commit());
+        Ok(confirmed)
+    })
+}
+
+// Deletes a loan's pending payments without ever applying them. Only those in
+// `ids`, or all of them if it's empty. Returns how many there were.
+pub fn reject_pending(db: &Path, name: &str, ids: &[i32]) -> rusqlite::Result<usize> {
+    traced("reject_pending", &[("loan", &name), ("payments", &ids.len())], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        let pending = try!(load_pending(&tx, name, ids));
+        for payment in pending.iter() {
+            try!(tx.execute("DELETE FROM transactions WHERE id = $0", &[&payment.id]));
+        }
+        try!(tx.commit());
+        Ok(pending.len())
+    })
+}
+
+// Corrects a loan's balance by `amount`, up if positive and down if not,
+// e.g. to match the lender's statement.
+pub fn adjust_balance(db: &Path, name: &str, amount: f64, date: Timespec) -> rusqlite::Result<Transaction> {
+    traced("adjust", &[("loan", &name), ("amount", &amount), ("date", &day(date))], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        let loan = try!(Loan::load_from_db(&tx, &name.to_string()));
+        try!(record_accrual(&tx, &loan, date));
+        let mut adjustment = Transaction::charge(name, TransactionKind::Adjustment, amount, date);
+        try!(adjustment.record(&tx));
+        adjustment.id = tx.last_insert_rowid() as i32;
+        try!(update_status(&tx, &name.to_string(), today()));
+        try!(tx.commit());
+        Ok(adjustment)
+    })
+}
+
+// PMI can be cancelled on request once a mortgage is scheduled to reach 80%
+// of the home's original value, and ends automatically at 78%.
+pub const PMI_REQUEST_LTV: f64 = 80.0;
+pub const PMI_TERMINATION_LTV: f64 = 78.0;
+
+// An appraisal or estimate of what secures a loan (a house, a car...).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Valuation {
+    pub date: Timespec,
+    pub value: f64,
+}
+
+// A loan's balance against its collateral as of a valuation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LtvPoint {
+    pub date: Timespec,
+    pub value: f64,
+    pub balance: f64,
+}
+
+impl LtvPoint {
+    // Loan-to-value, in percent.
+    pub fn ltv(&self) -> f64 {
+        self.balance / self.value * 100.0
+    }
+
+    pub fn equity(&self) -> f64 {
+        self.value - self.balance
+    }
+}
+
+pub fn add_valuation(db: &Path, loan: &str, valuation: &Valuation) -> rusqlite::Result<()> {
+    traced("add_valuation", &[("loan", &loan), ("value", &valuation.value), ("date", &day(valuation.date))], || {
+        let conn = try!(open(db));
+        try!(conn.execute("INSERT INTO valuations (loan, date, value) VALUES ($1, $2, $3)", &[&loan, &valuation.date, &valuation.value]));
+        Ok(())
+    })
+}
+
+// A loan's collateral valuations, oldest first.
+pub fn load_valuations(db: &Path, loan: &str) -> rusqlite::Result<Vec<Valuation>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT date, value FROM valuations WHERE loan = $0 ORDER BY date, id"));
+    let rows = try!(stmt.query_map(&[&loan], |row| {
+        Valuation{
+            date: row.get(0),
+            value: row.get(1),
+        }
+    }));
+
+    let mut valuations = Vec::new();
+    for valuation in rows {
+        valuations.push(try!(valuation));
+    }
+    Ok(valuations)
+}
+
+// Balance of a loan at the start of `date`: today's plus what every
+// transaction since took off it.
+fn balance_at(conn: &Connection, loan: &Loan, date: Timespec) -> rusqlite::Result<f64> {
+    let since: f64 = try!(conn.query_row("SELECT COALESCE(SUM(principal), 0.0) FROM transactions WHERE name = $0 AND date >= $1 AND NOT provisional",
+                                         &[&loan.name, &date], |row| row.get(0)));
+    Ok(loan.balance + since)
+}
+
+// Loan-to-value at each of a loan's valuations, oldest first, ending with
+// today's balance against the latest valuation.
+pub fn ltv_history(db: &Path, name: &str) -> rusqlite::Result<Vec<LtvPoint>> {
+    let valuations = try!(load_valuations(db, name));
+    let conn = try!(open(db));
+    let loan = try!(Loan::load_from_db(&conn, &name.to_string()));
+
+    let mut points = Vec::new();
+    for valuation in valuations.iter() {
+        points.push(LtvPoint{
+            date: valuation.date,
+            value: valuation.value,
+            balance: try!(balance_at(&conn, &loan, valuation.date)),
+        });
+    }
+    if let Some(latest) = valuations.last() {
+        points.push(LtvPoint{
+            date: today(),
+            value: latest.value,
+            balance: loan.balance,
+        });
+    }
+    Ok(points)
+}
+
+// Points in paying off a loan worth pointing out, each recorded once, the
+// first time it's noticed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Milestone {
+    // half the amount financed paid off
+    HalfPaid,
+    // balance down to PMI_REQUEST_LTV of the collateral's first valuation,
+    // so PMI can be cancelled on request
+    PmiThreshold,
+    // twelve or fewer payments left
+    FinalYear,
+    PaidOff,
+}
+
+impl Milestone {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Milestone::HalfPaid => "half paid",
+            Milestone::PmiThreshold => "pmi threshold",
+            Milestone::FinalYear => "final year",
+            Milestone::PaidOff => "paid off",
+        }
+    }
+
+    // A sentence about `loan` reaching the milestone.
+    pub fn describe(&self, loan: &str) -> String {
+        match *self {
+            Milestone::HalfPaid => format!("{} is half paid off", loan),
+            Milestone::PmiThreshold => format!("{} is down to {:.0}% of the original value; PMI can be cancelled on request", loan, PMI_REQUEST_LTV),
+            Milestone::FinalYear => format!("{} is in its final year of payments", loan),
+            Milestone::PaidOff => format!("{} is paid off", loan),
+        }
+    }
+}
+
+impl FromStr for Milestone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Milestone, String> {
+        match s {
+            "half paid" => Ok(Milestone::HalfPaid),
+            "pmi threshold" => Ok(Milestone::PmiThreshold),
+            "final year" => Ok(Milestone::FinalYear),
+            "paid off" => Ok(Milestone::PaidOff),
+            _ => Err(format!("Unknown milestone: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MilestoneEvent {
+    pub loan: String,
+    pub milestone: Milestone,
+    // when it was first noticed
+    pub date: Timespec,
+}
+
+// Milestones `loan` has reached, whether recorded yet or not.
+fn reached_milestones(conn: &Connection, loan: &Loan) -> rusqlite::Result<Vec<Milestone>> {
+    let mut reached = Vec::new();
+    if loan.principal > 0f64 && loan.balance <= loan.principal / 2.0 {
+        reached.push(Milestone::HalfPaid);
+    }
+    if loan.pmi_payment > 0f64 {
+        let original: Option<f64> = match conn.query_row("SELECT value FROM valuations WHERE loan = $0 ORDER BY date, id LIMIT 1", &[&loan.name],
+                                                         |row| row.get(0)) {
+            Ok(value) => Some(value),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(err) => return Err(err),
+        };
+        if original.map_or(false, |value| value > 0f64 && loan.balance / value * 100.0 <= PMI_REQUEST_LTV) {
+            reached.push(Milestone::PmiThreshold);
+        }
+    }
+    if loan.balance > 0f64 && loan.schedule().count() <= 12 {
+        reached.push(Milestone::FinalYear);
+    }
+    if loan.balance <= 0f64 {
+        reached.push(Milestone::PaidOff);
+    }
+    Ok(reached)
+}
+
+// Records the milestones each loan has reached as of `today` that weren't
+// already, returning them.
+pub fn check_milestones(db: &Path, today: Timespec) -> rusqlite::Result<Vec<MilestoneEvent>> {
+    let loans = try!(load_loans(db));
+    traced("check_milestones", &[("date", &day(today))], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        let mut events = Vec::new();
+        for loan in loans.iter() {
+            for milestone in try!(reached_milestones(&tx, &loan)) {
+                if try!(tx.execute("INSERT OR IGNORE INTO milestones (loan, kind, date) VALUES ($0, $1, $2)",
+                                   &[&loan.name, &milestone.as_str(), &today])) > 0 {
+                    events.push(MilestoneEvent{
+                        loan: loan.name.clone(),
+                        milestone: milestone,
+                        date: today,
+                    });
+                }
+            }
+        }
+        try!(tx.commit());
+        Ok(events)
+    })
+}
+
+// Milestones recorded for `name`, or every loan, oldest first.
+pub fn load_milestones(db: &Path, name: Option<&str>) -> rusqlite::Result<Vec<MilestoneEvent>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT loan, kind, date FROM milestones WHERE $0 IS NULL OR loan = $0 ORDER BY date, id"));
+    let rows = try!(stmt.query_map(&[&name], |row| {
+        MilestoneEvent{
+            loan: row.get(0),
+            milestone: row.get::<i32, String>(1).parse().unwrap_or(Milestone::PaidOff),
+            date: row.get(2),
+        }
+    }));
+
+    let mut events = Vec::new();
+    for event in rows {
+        events.push(try!(event));
+    }
+    Ok(events)
+}
+
+// A paid-off loan's closing summary: what it cost in the end, and how that
+// went against the schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosingSummary {
+    pub loan: String,
+    pub paid_off: Timespec,
+    // when the last payment was due as contracted
+    pub scheduled: Timespec,
+    pub payments: i32,
+    pub total_paid: f64,
+    pub total_interest: f64,
+}
+
+impl ClosingSummary {
+    // Months it was paid off ahead of schedule, or behind if negative.
+    pub fn months_saved(&self) -> i32 {
+        months_between(self.paid_off, self.scheduled)
+    }
+}
+
+// Records the closing summary for `loan`, paid off by `today` at the latest,
+// unless there already is one.
+fn record_closing(conn: &Connection, loan: &Loan, today: Timespec) -> rusqlite::Result<()> {
+    let (payments, total_paid, total_interest, last): (i32, f64, f64, Option<Timespec>) =
+        try!(conn.query_row(&format!("SELECT COUNT(*), COALESCE(SUM(principal + interest), 0.0), COALESCE(SUM(interest), 0.0), MAX(date)
+                                      FROM transactions WHERE name = $0 AND {}", PAID),
+                            &[&loan.name], |row| (row.get(0), row.get(1), row.get(2), row.get(3))));
+    try!(conn.execute("INSERT OR IGNORE INTO closings (loan, paid_off, scheduled, payments, total_paid, total_interest) VALUES ($1, $2, $3, $4, $5, $6)",
+                      &[&loan.name, &last.unwrap_or(today), &loan.maturity(), &payments, &total_paid, &total_interest]));
+    Ok(())
+}
+
+// The closing summary for `name`, or None if it isn't paid off. Loans paid
+// off before summaries were kept get theirs now.
+pub fn closing_summary(db: &Path, name: &str) -> rusqlite::Result<Option<ClosingSummary>> {
+    let conn = try!(open(db));
+    let loan = try!(Loan::load_from_db(&conn, &name.to_string()));
+    if loan.balance > 0f64 {
+        return Ok(None);
+    }
+    try!(record_closing(&conn, &loan, today()));
+    conn.query_row("SELECT loan, paid_off, scheduled, payments, total_paid, total_interest FROM closings WHERE loan = $0", &[&name], |row| {
+        Some(ClosingSummary{
+            loan: row.get(0),
+            paid_off: row.get(1),
+            scheduled: row.get(2),
+            payments: row.get(3),
+            total_paid: row.get(4),
+            total_interest: row.get(5),
+        })
+    })
+}
+
+// What the extra payments made on a loan so far have saved, against its
+// schedule as contracted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtraSavings {
+    pub loan: String,
+    pub currency: String,
+    pub extra: f64,
+    pub interest_saved: f64,
+    pub months_saved: i32,
+}
+
+// Savings from extra payments on each loan that's had any, most interest
+// saved first. Each extra payment is applied to the contractual schedule in
+// the period it was made, so missed or late regular payments don't count
+// against it. Money lent out is left out.
+pub fn extra_savings(db: &Path) -> rusqlite::Result<Vec<ExtraSavings>> {
+    let transactions = try!(load_transactions(db, None, None));
+    let mut savings = Vec::new();
+    for loan in try!(load_loans(db)).into_iter().filter(|loan| !loan.lent) {
+        let extras: Vec<(i32, f64)> = transactions.iter()
+            .filter(|transaction| transaction.name == loan.name && transaction.kind == TransactionKind::Extra && !transaction.reversed && !transaction.provisional)
+            .map(|transaction| (std::cmp::max(months_between(loan.start_time, transaction.date) + 1, 1), transaction.principal))
+            .collect();
+        if extras.is_empty() {
+            continue;
+        }
+
+        let contract = loan.contract_schedule().payoff();
+        let actual = extras.iter().fold(loan.contract_schedule(), |schedule, &(period, amount)| schedule.lump_sum(period, amount)).payoff();
+        savings.push(ExtraSavings{
+            loan: loan.name.clone(),
+            currency: loan.currency.clone(),
+            extra: extras.iter().map(|&(_, amount)| amount).sum(),
+            interest_saved: contract.total_interest - actual.total_interest,
+            months_saved: contract.periods - actual.periods,
+        });
+    }
+    savings.sort_by(|a, b| b.interest_saved.partial_cmp(&a.interest_saved).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(savings)
+}
+
+// Money disbursed on a construction loan, which is paid out in tranches as
+// the work progresses rather than all at closing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Draw {
+    pub date: Timespec,
+    pub amount: f64,
+}
+
+// Adds a draw to the loan's balance. Interest up to the draw is accrued on the
+// balance before it, so interest is only ever charged on what's been drawn.
+pub fn record_draw(db: &Path, name: &str, draw: &Draw) -> rusqlite::Result<()> {
+    traced("draw", &[("loan", &name), ("amount", &draw.amount), ("date", &day(draw.date))], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        let mut loan = try!(Loan::load_from_db(&tx, &name.to_string()));
+        try!(record_accrual(&tx, &loan, draw.date));
+        try!(tx.execute("INSERT INTO draws (loan, date, amount) VALUES ($1, $2, $3)", &[&name, &draw.date, &draw.amount]));
+        try!(Transaction::charge(name, TransactionKind::Draw, draw.amount, draw.date).insert(&tx));
+        // the payment after conversion grows with every draw
+        loan.balance += draw.amount;
+        let draw_periods = loan.draw_periods;
+        loan.set_draw_periods(draw_periods);
+        try!(tx.execute("UPDATE loans SET balance = $0, principal = principal + $1, payment = $2 WHERE name = $3",
+                        &[&loan.balance, &draw.amount, &loan.payment, &name]));
+        try!(invalidate_schedules(&tx, name));
+        try!(update_status(&tx, &name.to_string(), today()));
+        tx.commit()
+    })
+}
+
+// A loan's draws, oldest first.
+pub fn load_draws(db: &Path, loan: &str) -> rusqlite::Result<Vec<Draw>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT date, amount FROM draws WHERE loan = $0 ORDER BY date, id"));
+    let rows = try!(stmt.query_map(&[&loan], |row| {
+        Draw{
+            date: row.get(0),
+            amount: row.get(1),
+        }
+    }));
+
+    let mut draws = Vec::new();
+    for draw in rows {
+        draws.push(try!(draw));
+    }
+    Ok(draws)
+}
+
+// Ends the construction phase on `date`: payments due up to then stay
+// interest-only, and the rest amortize what was drawn over the remaining
+// term. Returns the converted loan.
+pub fn complete_construction(db: &Path, name: &str, date: Timespec) -> rusqlite::Result<Loan> {
+    traced("complete_construction", &[("loan", &name), ("date", &day(date))], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        let mut loan = try!(Loan::load_from_db(&tx, &name.to_string()));
+        let mut interest_only = 0;
+        while interest_only < loan.periods - 1 && loan.due_date(interest_only + 1) <= date {
+            interest_only += 1;
+        }
+        loan.repayment = calc::Repayment::Annuity;
+        loan.set_draw_periods(interest_only);
+        try!(tx.execute("UPDATE loans SET draw_periods = $0, payment = $1, repayment = $2 WHERE name = $3",
+                        &[&loan.draw_periods, &loan.payment, &loan.repayment.as_str(), &name]));
+        try!(invalidate_schedules(&tx, name));
+        try!(update_status(&tx, &name.to_string(), today()));
+        try!(tx.commit());
+        Ok(loan)
+    })
+}
+
+// Something owned, weighed against the loans for a net position: a savings
+// account, a house... Each recorded value stands until the next one.
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub name: String,
+    pub currency: String,
+    pub date: Timespec,
+    pub value: f64,
+}
+
+pub fn record_asset(db: &Path, asset: &Asset) -> rusqlite::Result<()> {
+    traced("record_asset", &[("asset", &asset.name), ("value", &asset.value), ("date", &day(asset.date))], || {
+        let conn = try!(open(db));
+        try!(conn.execute("INSERT INTO asset_values (name, currency, date, value) VALUES ($1, $2, $3, $4)",
+                          &[&asset.name, &asset.currency, &asset.date, &asset.value]));
+        Ok(())
+    })
+}
+
+// Every asset as of `date`, at its latest value recorded by then.
+pub fn load_assets(db: &Path, date: Timespec) -> rusqlite::Result<Vec<Asset>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT name, currency, date, value FROM asset_values a
+                                      WHERE id = (SELECT id FROM asset_values b WHERE b.name = a.name AND b.date <= $0 ORDER BY date DESC, id DESC LIMIT 1)
+                                      ORDER BY name"));
+    let rows = try!(stmt.query_map(&[&date], |row| {
+        Asset{
+            name: row.get(0),
+            currency: row.get(1),
+            date: row.get(2),
+            value: row.get(3),
+        }
+    }));
+
+    let mut assets = Vec::new();
+    for asset in rows {
+        assets.push(try!(asset));
+    }
+    Ok(assets)
+}
+
+// Each loan that had started by `date`, with its balance then.
+pub fn balances_at(db: &Path, date: Timespec) -> rusqlite::Result<Vec<(Loan, f64)>> {
+    let loans = try!(load_loans(db));
+    let conn = try!(open(db));
+    let mut balances = Vec::new();
+    for loan in loans.into_iter().filter(|loan| loan.start_time <= date) {
+        let balance = try!(balance_at(&conn, &loan, date));
+        balances.push((loan, balance));
+    }
+    Ok(balances)
+}
+
+// A loan's activity over one calendar month, for a statement.
+#[derive(Debug, Clone)]
+pub struct MonthlyStatement {
+    pub loan: Loan,
+    // first day of the month, and of the next one
+    pub from: Timespec,
+    pub to: Timespec,
+    pub opening_balance: f64,
+    // payments dated in the month, including any since returned unpaid
+    pub payments: Vec<Transaction>,
+    // a month's interest on the opening balance
+    pub interest: f64,
+    // fees charged in the month, e.g. for returned payments
+    pub fees: Vec<(Timespec, Fee)>,
+    // everything else that changed the balance: draws, adjustments, returned
+    // payments and capitalized interest
+    pub adjustments: Vec<Transaction>,
+    pub closing_balance: f64,
+    // next payment due after the month, and how much
+    pub next_due: Timespec,
+    pub amount_due: f64,
+}
+
+// Builds the statement for `month` (1 through 12) of `year`. Balances are
+// worked back from today's using the transactions since.
+pub fn monthly_statement(db: &Path, name: &str, year: i32, month: i32) -> rusqlite::Result<MonthlyStatement> {
+    let conn = try!(open(db));
+    let loan = try!(Loan::load_from_db(&conn, &name.to_string()));
+    let from = time::strptime(&format!("{}-{:02}-01", year, month), "%F").unwrap().to_timespec();
+    let to = add_months(from, 1);
+
+    let mut stmt = try!(conn.prepare(&format!("SELECT {} FROM transactions WHERE name = $0 AND date >= $1 AND NOT provisional ORDER BY date, id",
+                                              TRANSACTION_COLUMNS)));
+    let rows = try!(stmt.query_map(&[&name, &from], Transaction::from_row));
+    let mut since = Vec::new();
+    for row in rows {
+        since.push(try!(row));
+    }
+    let mut stmt = try!(conn.prepare("SELECT date, name, amount FROM fees WHERE loan = $0 AND date >= $1 ORDER BY date, id"));
+    let rows = try!(stmt.query_map(&[&name, &from], |row| (row.get::<i32, Timespec>(0), Fee{ name: row.get(1), amount: row.get(2) })));
+    let mut fees = Vec::new();
+    for row in rows {
+        fees.push(try!(row));
+    }
+
+    // the balance at a date is today's plus what every transaction since
+    // took off it
+    let balance_at = |date: Timespec| {
+        loan.balance + since.iter().filter(|transaction| transaction.date >= date).map(|transaction| transaction.principal).sum::<f64>()
+    };
+    let opening_balance = balance_at(from);
+    let closing_balance = balance_at(to);
+    let period = std::cmp::max(months_between(loan.start_time, from) + 1, 1);
+    let next_due = loan.next_due(to);
+
+    Ok(MonthlyStatement{
+        from: from,
+        to: to,
+        opening_balance: opening_balance,
+        payments: since.iter().filter(|transaction| transaction.date < to && transaction.kind.is_payment()).cloned().collect(),
+        adjustments: since.iter().filter(|transaction| transaction.date < to && !transaction.kind.is_payment() && transaction.kind != TransactionKind::Fee)
+            .cloned().collect(),
+        interest: loan.interest_rounding.apply(loan.period_interest(period, opening_balance)),
+        fees: fees.into_iter().filter(|&(charged, _)| charged < to).collect(),
+        closing_balance: closing_balance,
+        next_due: next_due,
+        amount_due: if closing_balance > 0f64 { loan.amount_due(next_due) } else { 0f64 },
+        loan: loan,
+    })
+}
+
+// Deletes a loan along with its payments and everything else kept about it.
+pub fn delete_loan(db: &Path, name: &str) -> rusqlite::Result<()> {
+    traced("delete_loan", &[("loan", &name)], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        try!(delete_loan_rows(&tx, name));
+        tx.commit()
+    })
+}
+
+fn delete_loan_rows(conn: &Connection, name: &str) -> rusqlite::Result<()> {
+    try!(conn.execute("DELETE FROM transactions WHERE name = $0", &[&name]));
+    for table in ["fees", "accruals", "borrowers", "scenarios", "valuations", "milestones", "addons", "closings"].iter() {
+        try!(conn.execute(&format!("DELETE FROM {} WHERE loan = $0", table), &[&name]));
+    }
+    try!(conn.execute("DELETE FROM loans WHERE name = $0", &[&name]));
+    invalidate_schedules(conn, name)
+}
+
+// A change the loan's terms don't allow, as opposed to a database failure.
+// It comes back wrapped in a rusqlite::Error; get it out with refusal().
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Refusal {
+    // a regular payment of `paid` that doesn't cover the `expected` due
+    InsufficientPayment{ expected: f64, paid: f64 },
+    // a second skip within a year of the last, made on `last`
+    SkipTooSoon{ last: Timespec },
+}
+
+impl std::fmt::Display for Refusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Refusal::InsufficientPayment{ expected, paid } => f.write_str(&trf("Amount paid is insufficient payment. Expected {}, got {}",
+                                                                               &[&format!("{:.2}", expected), &format!("{:.2}", paid)])),
+            Refusal::SkipTooSoon{ last } => f.write_str(&trf("Only one payment may be skipped per year. The last was skipped on {}", &[&day(last)])),
+        }
+    }
+}
+
+impl std::error::Error for Refusal {}
+
+impl From<Refusal> for rusqlite::Error {
+    fn from(refusal: Refusal) -> rusqlite::Error {
+        rusqlite::Error::FromSqlConversionFailure(Box::new(refusal))
+    }
+}
+
+// The refusal `err` carries, if it's one rather than a database failure.
+pub fn refusal(err: &rusqlite::Error) -> Option<&Refusal> {
+    match *err {
+        rusqlite::Error::FromSqlConversionFailure(ref err) => err.downcast_ref(),
+        _ => None,
+    }
+}
+
+// Skips the payment due on `date`, pushing every later due date out a month.
+// The month's interest is capitalized or deferred according to the loan's
+// skip policy. Only one skip is allowed per year; a second is refused with
+// Refusal::SkipTooSoon.
+pub fn skip_payment(db: &Path, name: String, date: Timespec) -> rusqlite::Result<()> {
+    let loan_name = name.clone();
+    traced("skip", &[("loan", &loan_name), ("date", &day(date))], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+        let loan = try!(Loan::load_from_db(&tx, &name));
+
+        if let Some(last) = loan.last_skip {
+            if date < add_months(last, 12) {
+                return Err(Refusal::SkipTooSoon{ last: last }.into());
+            }
+        }
+
+        // the skipped period's interest, with every later payment moving
+        // back a period
+        let interest = loan.calc_interest_payment(loan.period_at(date));
+        let (capitalized, deferred) = match loan.skip_policy {
+            SkipPolicy::Capitalize => (interest, 0f64),
+            SkipPolicy::Defer => (0f64, interest),
+        };
+
+        try!(tx.execute("UPDATE loans SET unpaid_interest = unpaid_interest + $0, start_time = $1, last_skip = $2 WHERE name = $3",
+                        &[&deferred, &loan.due_date(2), &date, &name]));
+        if capitalized > 0f64 {
+            try!(Transaction::charge(&name, TransactionKind::Capitalization, capitalized, date).record(&tx));
+        }
+        try!(invalidate_schedules(&tx, &name));
+        try!(update_status(&tx, &name, today()));
+        try!(tx.commit());
+
+        let interest = format!("{:.2}", interest);
+        if loan.skip_policy == SkipPolicy::Capitalize {
+            println!("{}", trf("Payment skipped. ${} in interest was added to the balance; payments now end a month later.", &[&interest]));
+        } else {
+            println!("{}", trf("Payment skipped. ${} in interest was deferred until payoff; payments now end a month later.", &[&interest]));
+        }
+        Ok(())
+    })
+}
+
+// Pays off each of `names` in full and opens `loan` in their place, all in
+// one database transaction.
+pub fn consolidate_loans(db: &Path, names: &[String], loan: Loan) -> rusqlite::Result<()> {
+    traced("consolidate", &[("loans", &names.join(",")), ("into", &loan.name), ("principal", &loan.principal)], || {
+        let mut conn = try!(open(db));
+        let tx = try!(conn.transaction());
+
+        for name in names {
+            let old = try!(Loan::load_from_db(&tx, name));
+            let payoff = Transaction{
+                id: 0,
+                name: old.name,
+                principal: old.balance,
+                interest: 0f64,
+                date: loan.start_time,
+                time_created: time::get_time(),
+                payment_method: None,
+                reversed: false,
+                escrow: 0f64,
+                pmi: 0f64,
+                // paid, but not a prepayment: extra_savings leaves it out
+                kind: TransactionKind::Payoff,
+                provisional: false,
+            };
+            try!(payoff.record(&tx));
+            try!(update_status(&tx, name, today()));
+        }
+        try!(insert_loan(&tx, &loan));
+        try!(update_status(&tx, &loan.name, today()));
+        tx.commit()
+    })
+}
+
+// Loan columns merge_db copies from a newer version of a loan. The balance,
+// status and due dates paid through follow from the transactions instead.
+const MERGED_LOAN_COLUMNS: &[&str] = &[
+    "payment", "periods", "apr", "start_time", "draw_periods", "promo_apr", "promo_periods", "defer_interest", "skip_policy",
+    "unpaid_interest", "last_skip", "principal", "interest_rounding", "payment_rounding", "currency", "lender", "paid_ahead", "lent",
+    "rate_index", "margin", "fixed_periods", "reset_periods", "escrow_payment", "pmi_payment", "interest_timing", "minor_units",
+    "repayment", "deferment_periods", "subsidized", "capitalized_interest", "loan_group", "prepayment_penalty", "penalty_periods",
+    "owner", "interest_method", "first_payment_days", "time_modified",
+];
+
+const MERGED_TRANSACTION_COLUMNS: &[&str] = &[
+    "principal", "interest", "date", "time_created", "payment_method", "reversed", "escrow", "pmi", "kind", "provisional", "time_modified",
+];
+
+// What a transaction took off its loan's balance.
+fn balance_effect(principal: f64, provisional: bool) -> f64 {
+    if provisional { 0f64 } else { principal }
+}
+
+// Changes logged in `db` after `since`, oldest first.
+pub fn load_changes(db: &Path, since: Option<&str>) -> rusqlite::Result<Vec<LogEntry>> {
+    let conn = try!(open(db));
+    let mut stmt = try!(conn.prepare("SELECT uid, time, tbl, row_uid, op FROM changes WHERE $0 IS NULL OR time > $0 ORDER BY time, uid"));
+    let rows = try!(stmt.query_map(&[&since], |row| {
+        LogEntry{
+            uid: row.get(0),
+            time: row.get(1),
+            table: row.get(2),
+            row: row.get(3),
+            op: row.get(4),
+        }
+    }));
+
+    let mut changes = Vec::new();
+    for change in rows {
+        changes.push(try!(change));
+    }
+    Ok(changes)
+}
+
+// Last change `schema`'s log has for a row, as (time, change id, op).
+fn latest_change(conn: &Connection, schema: &str, table: &str, row: &str) -> rusqlite::Result<Option<(String, String, String)>> {
+    match conn.query_row(&format!("SELECT time, uid, op FROM {}.changes WHERE tbl = $0 AND row_uid = $1 ORDER BY time DESC, uid DESC LIMIT 1", schema),
+                         &[&table, &row], |row| (row.get(0), row.get(1), row.get(2))) {
+        Ok(change) => Ok(Some(change)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+// When a row was last changed, by the log if it has the row and otherwise by
+// the row's own time_modified. Changes at the same time are ordered by id.
+fn version(conn: &Connection, schema: &str, table: &str, row: &str, modified: &str) -> rusqlite::Result<(String, String)> {
+    let change = try!(latest_change(conn, schema, table, row));
+    Ok(change.map_or((modified.to_string(), String::new()), |(time, uid, _)| (time, uid)))
+}
+
+// Brings loans and transactions over from `other`, e.g. a copy kept on
+// another computer. Records are matched by the id each gets when created,
+// falling back to the loan's name, or a transaction's loan, date, amounts and
+// kind, for copies made before ids were added. Where both have a record, the
+// one changed last by the change logs wins, deletions included, and the
+// logs are merged so both sides agree afterwards. Balances move by whatever
+// the merged transactions change.
+pub fn merge_db(db: &Path, other: &Path) -> rusqlite::Result<MergeSummary> {
+    traced("merge", &[("from", &other.display())], || {
+        // bring the other schema up to date, giving its records ids
+        try!(open(other));
+        let mut conn = try!(open(db));
+        try!(conn.execute("ATTACH DATABASE $0 AS other", &[&other.to_string_lossy().into_owned()]));
+        let mut summary = MergeSummary::default();
+        {
+            let tx = try!(conn.transaction());
+            // what's brought over is logged as the other side's changes, not ours
+            try!(tx.execute("INSERT OR REPLACE INTO settings (key, value) VALUES ('merging', '1')", &[]));
+            let mut touched = Vec::new();
+
+            let deleted: Vec<(String, String)> = {
+                let mut stmt = try!(tx.prepare("SELECT DISTINCT tbl, row_uid FROM other.changes WHERE op = 'delete' ORDER BY tbl DESC"));
+                let rows = try!(stmt.query_map(&[], |row| (row.get(0), row.get(1))));
+                let mut deleted = Vec::new();
+                for row in rows {
+                    deleted.push(try!(row));
+                }
+                deleted
+            };
+            for (table, row) in deleted {
+                let theirs = match try!(latest_change(&tx, "other", &table, &row)) {
+                    Some((time, uid, ref op)) if op == "delete" => (time, uid),
+                    _ => continue,
+                };
+                if table == "transactions" {
+                    let local: Option<(String, f64, bool, String)> = match tx.query_row(
+                        "SELECT name, principal, provisional, time_modified FROM transactions WHERE uid = $0", &[&row],
+                        |row| (row.get(0), row.get(1), row.get(2), row.get(3))) {
+                        Ok(local) => Some(local),
+                        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                        Err(err) => return Err(err),
+                    };
+                    if let Some((name, principal, provisional, modified)) = local {
+                        if theirs > try!(version(&tx, "main", &table, &row, &modified)) {
+                            try!(tx.execute("DELETE FROM transactions WHERE uid = $0", &[&row]));
+                            try!(tx.execute("UPDATE loans SET balance = balance + $0 WHERE name = $1", &[&balance_effect(principal, provisional), &name]));
+                            summary.transactions_deleted += 1;
+                            touched.push(name);
+                        }
+                    }
+                } else {
+                    let local: Option<(String, String)> = match tx.query_row("SELECT name, time_modified FROM loans WHERE uid = $0", &[&row],
+                                                                             |row| (row.get(0), row.get(1))) {
+                        Ok(local) => Some(local),
+                        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                        Err(err) => return Err(err),
+                    };
+                    if let Some((name, modified)) = local {
+                        if theirs > try!(version(&tx, "main", &table, &row, &modified)) {
+                            try!(delete_loan_rows(&tx, &name));
+                            summary.loans_deleted += 1;
+                            touched.retain(|touched| *touched != name);
+                        }
+                    }
+                }
+            }
+
+            let loans: Vec<(i32, String, String, String)> = {
+                let mut stmt = try!(tx.prepare("SELECT id, uid, name, time_modified FROM other.loans ORDER BY id"));
+                let rows = try!(stmt.query_map(&[], |row| (row.get(0), row.get(1), row.get(2), row.get(3))));
+                let mut loans = Vec::new();
+                for row in rows {
+                    loans.push(try!(row));
+                }
+                loans
+            };
+            for (other_id, uid, name, modified) in loans {
+                let theirs = try!(version(&tx, "other", "loans", &uid, &modified));
+                let local: Option<(String, String, String)> = match tx.query_row("SELECT name, uid, time_modified FROM loans WHERE uid = $0 OR name = $1
+                                                                                  ORDER BY uid = $0 DESC LIMIT 1", &[&uid, &name],
+                                                                                 |row| (row.get(0), row.get(1), row.get(2))) {
+                    Ok(local) => Some(local),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                    Err(err) => return Err(err),
+                };
+                // a new loan's balance already counts its transactions
+                let added = local.is_none();
+                let name = match local {
+                    None => {
+                        // unless we deleted it more recently
+                        if let Some((time, change, _)) = try!(latest_change(&tx, "main", "loans", &uid)) {
+                            if (time, change) > theirs {
+                                continue;
+                            }
+                        }
+                        let columns = LOAN_COLUMNS.trim_start_matches("id, ");
+                        try!(tx.execute(&format!("INSERT INTO loans ({0}, uid, time_modified) SELECT {0}, uid, time_modified FROM other.loans WHERE id = $0",
+                                                 columns), &[&other_id]));
+                        summary.loans_added += 1;
+                        name
+                    },
+                    Some((local, local_uid, local_modified)) => {
+                        if theirs > try!(version(&tx, "main", "loans", &local_uid, &local_modified)) {
+                            let columns: Vec<String> = MERGED_LOAN_COLUMNS.iter()
+                                .map(|column| format!("{0} = (SELECT {0} FROM other.loans WHERE id = $0)", column))
+                                .collect();
+                            try!(tx.execute(&format!("UPDATE loans SET {} WHERE name = $1", columns.join(", ")), &[&other_id, &local]));
+                            summary.loans_updated += 1;
+                        }
+                        local
+                    },
+                };
+
+                let transactions: Vec<(i32, String, f64, bool, String)> = {
+                    let mut stmt = try!(tx.prepare("SELECT t.id, t.uid, t.principal, t.provisional, t.time_modified FROM other.transactions t
+                                                    JOIN other.loans l ON l.name = t.name WHERE l.id = $0 ORDER BY t.id"));
+                    let rows = try!(stmt.query_map(&[&other_id], |row| (row.get(0), row.get(1), row.get(2), row.get(3), row.get(4))));
+                    let mut transactions = Vec::new();
+                    for row in rows {
+                        transactions.push(try!(row));
+                    }
+                    transactions
+                };
+                let mut change = 0f64;
+                for (id, uid, principal, provisional, modified) in transactions {
+                    let theirs = try!(version(&tx, "other", "transactions", &uid, &modified));
+                    let local: Option<(i32, String, f64, bool, String)> = match tx.query_row(
+                        "SELECT l.id, l.uid, l.principal, l.provisional, l.time_modified FROM transactions l, other.transactions o
+                         WHERE o.id = $0 AND l.name = $1 AND (l.uid = $2 OR (l.date = o.date AND l.principal = o.principal AND l.interest = o.interest
+                                                                             AND l.kind = o.kind AND l.time_created = o.time_created))
+                         ORDER BY l.uid = $2 DESC LIMIT 1", &[&id, &name, &uid], |row| (row.get(0), row.get(1), row.get(2), row.get(3), row.get(4))) {
+                        Ok(local) => Some(local),
+                        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                        Err(err) => return Err(err),
+                    };
+                    let columns = MERGED_TRANSACTION_COLUMNS.join(", ");
+                    match local {
+                        None => {
+                            if let Some((time, change, _)) = try!(latest_change(&tx, "main", "transactions", &uid)) {
+                                if (time, change) > theirs {
+                                    continue;
+                                }
+                            }
+                            try!(tx.execute(&format!("INSERT INTO transactions (name, uid, {0}) SELECT $0, uid, {0} FROM other.transactions WHERE id = $1",
+                                                     columns), &[&name, &id]));
+                            change += balance_effect(principal, provisional);
+                            summary.transactions_added += 1;
+                        },
+                        Some((local_id, local_uid, local_principal, local_provisional, local_modified)) => {
+                            if theirs > try!(version(&tx, "main", "transactions", &local_uid, &local_modified)) {
+                                let columns: Vec<String> = MERGED_TRANSACTION_COLUMNS.iter()
+                                    .map(|column| format!("{0} = (SELECT {0} FROM other.transactions WHERE id = $0)", column))
+                                    .collect();
+                                try!(tx.execute(&format!("UPDATE transactions SET {} WHERE id = $1", columns.join(", ")), &[&id, &local_id]));
+                                change += balance_effect(principal, provisional) - balance_effect(local_principal, local_provisional);
+                                summary.transactions_updated += 1;
+                            }
+                        },
+                    }
+                }
+                if change != 0f64 && !added {
+                    try!(tx.execute("UPDATE loans SET balance = balance - $0 WHERE name = $1", &[&change, &name]));
+                }
+                touched.push(name);
+            }
+
+            touched.sort();
+            touched.dedup();
+            for name in touched.iter() {
+                try!(invalidate_schedules(&tx, name));
+                try!(update_status(&tx, name, today()));
+            }
+            try!(tx.execute("INSERT OR IGNORE INTO users (name) SELECT DISTINCT owner FROM loans WHERE owner IS NOT NULL", &[]));
+            try!(tx.execute("INSERT OR IGNORE INTO changes (uid, time, tbl, row_uid, op) SELECT uid, time, tbl, row_uid, op FROM other.changes", &[]));
+            try!(tx.execute("DELETE FROM settings WHERE key = 'merging'", &[]));
+            try!(tx.commit());
+        }
+        try!(conn.execute("DETACH DATABASE other", &[]));
+        Ok(summary)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+
+    // A fresh database for the named test, in the temp directory.
+    fn test_db(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("amortization-{}-{}.db", name, process::id()));
+        let _ = fs::remove_file(&path);
+        init_db(&path).unwrap();
+        path
+    }
+
+    fn date(s: &str) -> Timespec {
+        time::strptime(s, "%F").unwrap().to_timespec()
+    }
+
+    fn cents(amount: f64) -> String {
+        format!("{:.2}", amount)
+    }
+
+    // Runs `sql` directly, for settings no function changes.
+    fn execute(db: &Path, sql: &str) {
+        open(db).unwrap().execute_batch(sql).unwrap();
+    }
+
+    // 12,000 at 12% over a year from January 2024: 120 of interest a month.
+    fn add_loan(db: &Path, name: &str) -> Loan {
+        create_loan(db, Loan::new(name.to_string(), 12000.0, 12, 12.0, date("2024-01-01"))).unwrap();
+        load_loan(db, name).unwrap()
+    }
+
+    #[test]
+    fn skip_capitalizes_a_period_of_interest() {
+        let db = test_db("skip");
+        add_loan(&db, "car");
+        skip_payment(&db, "car".to_string(), date("2024-03-05")).unwrap();
+
+        let loan = load_loan(&db, "car").unwrap();
+        assert_eq!(loan.start_time, date("2024-02-01"));
+        assert_eq!(loan.last_skip, Some(date("2024-03-05")));
+        assert_eq!(cents(loan.balance), "12120.00");
+        let transactions = load_transactions(&db, Some("car"), None).unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].kind, TransactionKind::Capitalization);
+        fs::remove_file(&db).unwrap();
+    }
+
+    #[test]
+    fn skip_defers_interest_to_payoff() {
+        let db = test_db("skip-defer");
+        add_loan(&db, "car");
+        execute(&db, "UPDATE loans SET skip_policy = 'defer' WHERE name = 'car'");
+        skip_payment(&db, "car".to_string(), date("2024-03-05")).unwrap();
+
+        let loan = load_loan(&db, "car").unwrap();
+        assert_eq!(cents(loan.balance), "12000.00");
+        assert_eq!(cents(loan.unpaid_interest), "120.00");
+        assert!(load_transactions(&db, Some("car"), None).unwrap().is_empty());
+        fs::remove_file(&db).unwrap();
+    }
+
+    #[test]
+    fn consolidating_pays_off_the_old_loans() {
+        let db = test_db("consolidate");
+        add_loan(&db, "card");
+        add_loan(&db, "store");
+        commit_transaction(&db, "card".to_string(), 500.0, true, date("2024-01-15"), None).unwrap();
+        let loan = Loan::new("combined".to_string(), 23500.0, 24, 8.0, date("2024-02-01"));
+        consolidate_loans(&db, &["card".to_string(), "store".to_string()], loan).unwrap();
+
+        for name in ["card", "store"].iter() {
+            let old = load_loan(&db, name).unwrap();
+            assert_eq!(cents(old.balance), "0.00");
+            assert_eq!(old.status, LoanStatus::PaidOff);
+            let payoff = load_transactions(&db, Some(name), None).unwrap().pop().unwrap();
+            assert_eq!(payoff.kind, TransactionKind::Payoff);
+            assert_eq!(payoff.date, date("2024-02-01"));
+        }
+        assert_eq!(cents(load_loan(&db, "combined").unwrap().balance), "23500.00");
+
+        // only the extra payment made on purpose saved anything
+        let savings = extra_savings(&db).unwrap();
+        assert_eq!(savings.len(), 1);
+        assert_eq!(savings[0].loan, "card");
+        assert_eq!(cents(savings[0].extra), "500.00");
+        fs::remove_file(&db).unwrap();
+    }
+
+    #[test]
+    fn bounced_payments_go_back_on_the_balance() {
+        let db = test_db("bounce");
+        add_loan(&db, "car");
+        commit_transaction(&db, "car".to_string(), 1200.0, false, date("2024-02-01"), None).unwrap();
+        assert_eq!(cents(load_loan(&db, "car").unwrap().balance), "10920.00");
+        let id = load_transactions(&db, Some("car"), None).unwrap()[0].id;
+
+        let bounced = bounce_payment(&db, id, 25.0).unwrap();
+        assert!(bounced.reversed);
+        assert_eq!(cents(load_loan(&db, "car").unwrap().balance), "12025.00");
+        let kinds: Vec<_> = load_transactions(&db, Some("car"), None).unwrap().iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, vec![TransactionKind::Payment, TransactionKind::Reversal, TransactionKind::Fee]);
+        let fees: f64 = open(&db).unwrap().query_row("SELECT SUM(amount) FROM fees WHERE loan = 'car' AND date IS NOT NULL",
+                                                     &[], |row| row.get(0)).unwrap();
+        assert_eq!(cents(fees), "25.00");
+
+        // a payment only bounces once
+        match bounce_payment(&db, id, 25.0) {
+            Err(rusqlite::Error::QueryReturnedNoRows) => (),
+            other => panic!("bounced twice: {:?}", other.map(|t| t.id)),
+        }
+        assert_eq!(cents(load_loan(&db, "car").unwrap().balance), "12025.00");
+        fs::remove_file(&db).unwrap();
+    }
+
+    #[test]
+    fn merging_brings_over_what_the_other_copy_added() {
+        let db = test_db("merge");
+        add_loan(&db, "car");
+        let copy = env::temp_dir().join(format!("amortization-merge-copy-{}.db", process::id()));
+        fs::copy(&db, &copy).unwrap();
+        commit_transaction(&copy, "car".to_string(), 1200.0, false, date("2024-02-01"), None).unwrap();
+        add_loan(&db, "boat");
+
+        let summary = merge_db(&db, &copy).unwrap();
+        assert_eq!((summary.loans_added, summary.transactions_added), (0, 1));
+        assert_eq!(cents(load_loan(&db, "car").unwrap().balance), "10920.00");
+
+        let summary = merge_db(&copy, &db).unwrap();
+        assert_eq!((summary.loans_added, summary.transactions_added), (1, 0));
+        assert_eq!(cents(load_loan(&copy, "car").unwrap().balance), "10920.00");
+        assert_eq!(cents(load_loan(&copy, "boat").unwrap().balance), "12000.00");
+
+        // once both agree, merging again changes nothing
+        let summary = merge_db(&db, &copy).unwrap();
+        assert_eq!((summary.loans_added, summary.loans_updated, summary.transactions_added, summary.transactions_updated), (0, 0, 0, 0));
+        assert_eq!(load_transactions(&db, Some("car"), None).unwrap().len(), 1);
+        fs::remove_file(&db).unwrap();
+        fs::remove_file(&copy).unwrap();
+    }
+
+    #[test]
+    fn schedules_are_cached_until_the_loan_changes() {
+        let db = test_db("cache");
+        let loan = add_loan(&db, "car");
+        let schedule: Vec<_> = loan.schedule().collect();
+        assert_eq!(cached_schedule(&db, &loan).unwrap(), schedule);
+
+        // served from the cache from then on
+        execute(&db, "UPDATE schedule_cache SET rows = '1,1,1,1,1'");
+        assert_eq!(cached_schedule(&db, &loan).unwrap().len(), 1);
+        // unless it can't be read
+        execute(&db, "UPDATE schedule_cache SET rows = 'garbage'");
+        assert_eq!(cached_schedule(&db, &loan).unwrap(), schedule);
+
+        commit_transaction(&db, "car".to_string(), 1200.0, false, date("2024-02-01"), None).unwrap();
+        let cached: i64 = open(&db).unwrap().query_row("SELECT COUNT(*) FROM schedule_cache", &[], |row| row.get(0)).unwrap();
+        assert_eq!(cached, 0);
+        let loan = load_loan(&db, "car").unwrap();
+        assert_eq!(cached_schedule(&db, &loan).unwrap(), loan.schedule().collect::<Vec<_>>());
+        fs::remove_file(&db).unwrap();
+    }
+
+    #[test]
+    fn statements_cover_one_month() {
+        let db = test_db("statement");
+        add_loan(&db, "car");
+        commit_transaction(&db, "car".to_string(), 1200.0, false, date("2024-02-01"), None).unwrap();
+        commit_transaction(&db, "car".to_string(), 1200.0, false, date("2024-03-01"), None).unwrap();
+
+        let february = monthly_statement(&db, "car", 2024, 2).unwrap();
+        assert_eq!((february.from, february.to), (date("2024-02-01"), date("2024-03-01")));
+        assert_eq!(cents(february.opening_balance), "12000.00");
+        assert_eq!(cents(february.interest), "120.00");
+        assert_eq!(february.payments.len(), 1);
+        assert_eq!(cents(february.payments[0].principal), "1080.00");
+        assert!(february.adjustments.is_empty() && february.fees.is_empty());
+        assert_eq!(cents(february.closing_balance), "10920.00");
+        assert_eq!(february.next_due, date("2024-03-01"));
+
+        // balances are worked back from today's
+        let march = monthly_statement(&db, "car", 2024, 3).unwrap();
+        assert_eq!(cents(march.opening_balance), "10920.00");
+        assert_eq!(cents(march.interest), "109.20");
+        assert_eq!(cents(march.closing_balance), "9829.20");
+        fs::remove_file(&db).unwrap();
+    }
+
+    #[test]
+    fn imports_are_classified_against_the_ledger() {
+        use import::{Classifier, PaymentKind, PaymentRow};
+
+        let db = test_db("import");
+        add_loan(&db, "car");
+        commit_transaction(&db, "car".to_string(), 1200.0, false, date("2024-02-01"), None).unwrap();
+        commit_transaction(&db, "car".to_string(), 1200.0, false, date("2024-03-01"), None).unwrap();
+        let bounced = load_transactions(&db, Some("car"), None).unwrap()[1].id;
+        bounce_payment(&db, bounced, 0.0).unwrap();
+
+        let loan = load_loan(&db, "car").unwrap();
+        let mut classifier = Classifier::for_loan(&loan, &load_transactions(&db, Some("car"), None).unwrap());
+        let mut classify = |day: &str, amount: f64| {
+            let row = PaymentRow{ date: date(day), amount: amount, method: None, kind: None };
+            classifier.classify(&row, loan.period_at(row.date), 1066.19)
+        };
+        // February is paid already
+        assert_eq!(classify("2024-02-20", 500.0), PaymentKind::Extra);
+        // March's payment bounced, so it's still due
+        assert_eq!(classify("2024-03-10", 1066.19), PaymentKind::Regular);
+        assert_eq!(classify("2024-03-15", 300.0), PaymentKind::Extra);
+        fs::remove_file(&db).unwrap();
+    }
+
+    // A year is a year to the day, not twelve calendar months.
+    #[test]
+    fn one_skip_a_year() {
+        let db = test_db("skip-year");
+        add_loan(&db, "car");
+        skip_payment(&db, "car".to_string(), date("2024-01-31")).unwrap();
+
+        let err = skip_payment(&db, "car".to_string(), date("2025-01-01")).unwrap_err();
+        match refusal(&err) {
+            Some(&Refusal::SkipTooSoon{ last }) => assert_eq!(last, date("2024-01-31")),
+            _ => panic!("expected a refusal, got {}", err),
+        }
+        let loan = load_loan(&db, "car").unwrap();
+        assert_eq!(loan.start_time, date("2024-02-01"));
+
+        skip_payment(&db, "car".to_string(), date("2025-01-31")).unwrap();
+        assert_eq!(load_loan(&db, "car").unwrap().start_time, date("2024-03-01"));
+        fs::remove_file(&db).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+// Escrow accounts, where part of each mortgage payment is set aside for the
+// lender to pay property taxes and insurance. An analysis projects the
+// account over the coming year, the way a servicer's annual escrow review
+// does, and works out what the monthly deposit has to be.
+
+// A bill paid out of escrow once a year, split evenly across `months` (1 to
+// 12), e.g. property taxes due in two installments.
+#[derive(Debug, Clone)]
+pub struct Disbursement {
+    pub name: String,
+    pub amount: f64,
+    pub months: Vec<i32>,
+}
+
+impl Disbursement {
+    fn paid_in(&self, month: i32) -> f64 {
+        if self.months.contains(&month) {
+            self.amount / self.months.len() as f64
+        } else {
+            0f64
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Escrow {
+    pub balance: f64,
+    // set aside with every payment
+    pub monthly_deposit: f64,
+    pub disbursements: Vec<Disbursement>,
+    // months of disbursements the lender keeps as a cushion; two at most
+    // under RESPA
+    pub cushion_months: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EscrowMonth {
+    pub period: i32,
+    // calendar month, 1 to 12
+    pub month: i32,
+    pub deposit: f64,
+    pub paid: f64,
+    pub balance: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct EscrowAnalysis {
+    // the coming year at the current deposit
+    pub months: Vec<EscrowMonth>,
+    pub annual: f64,
+    // lowest balance the account may fall to
+    pub cushion: f64,
+    // lowest balance over the year at the deposit disbursements require
+    pub low_point: f64,
+    // below the cushion at the low point, spread over the next year
+    pub shortage: f64,
+    // above the cushion at the low point, normally refunded
+    pub surplus: f64,
+    pub new_deposit: f64,
+}
+
+impl EscrowAnalysis {
+    // First month the account would be overdrawn at the current deposit.
+    pub fn overdrawn(&self) -> Option<&EscrowMonth> {
+        self.months.iter().find(|month| month.balance < 0f64)
+    }
+}
+
+impl Escrow {
+    pub fn annual(&self) -> f64 {
+        self.disbursements.iter().map(|disbursement| disbursement.amount).sum()
+    }
+
+    // The next twelve months, starting with calendar month `first` (1 to
+    // 12), depositing `deposit` at the start of each.
+    pub fn projection(&self, first: i32, deposit: f64) -> Vec<EscrowMonth> {
+        let mut balance = self.balance;
+        (0..12).map(|i| {
+            let month = (first - 1 + i) % 12 + 1;
+            let paid: f64 = self.disbursements.iter().map(|disbursement| disbursement.paid_in(month)).sum();
+            balance += deposit - paid;
+            EscrowMonth{
+                period: i + 1,
+                month: month,
+                deposit: deposit,
+                paid: paid,
+                balance: balance,
+            }
+        }).collect()
+    }
+
+    // Reviews the account for the year starting with calendar month `first`.
+    pub fn analysis(&self, first: i32) -> EscrowAnalysis {
+        let annual = self.annual();
+        let required = annual / 12.0;
+        let cushion = required * self.cushion_months;
+        let low_point = self.projection(first, required).iter()
+            .map(|month| month.balance)
+            .fold(f64::INFINITY, f64::min);
+
+        let shortage = if low_point < cushion { cushion - low_point } else { 0f64 };
+        let surplus = if low_point > cushion { low_point - cushion } else { 0f64 };
+        EscrowAnalysis{
+            months: self.projection(first, self.monthly_deposit),
+            annual: annual,
+            cushion: cushion,
+            low_point: low_point,
+            shortage: shortage,
+            surplus: surplus,
+            new_deposit: required + shortage / 12.0,
+        }
+    }
+}
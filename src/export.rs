@@ -0,0 +1,259 @@
+// Writes schedules and payment history out as CSV for spreadsheets and other
+// tools, as TSV for pasting straight into one, or as JSON. Rows are written
+// as the schedule is computed, encoded the way render's tables are, so even a
+// whole portfolio's worth never has to fit in memory; wrap files in a
+// BufWriter. A year's due dates and payments
+// can also be written as a calendar, in CSV or as an HTML page, and a paid-off
+// loan's closing summary as plain text.
+
+use std::io::{self, Write};
+
+use time;
+
+use db::{self, Anchor, ClosingSummary, Loan, Transaction};
+use render::{self, Cell};
+
+fn date(ts: time::Timespec) -> String {
+    time::strftime("%F", &time::at_utc(ts)).unwrap()
+}
+
+const SCHEDULE_COLUMNS: [&str; 6] = ["period", "date", "payment", "principal", "interest", "balance"];
+
+// A schedule's rows from `anchor` on, one installment at a time.
+fn installments<'a>(loan: &'a Loan, anchor: Anchor, today: time::Timespec) -> Box<dyn Iterator<Item = [String; 6]> + 'a> {
+    Box::new(loan.schedule_from(anchor, today).map(move |(due, inst)| {
+        [inst.period.to_string(), date(due), loan.money(inst.payment), loan.money(inst.principal),
+         loan.money(inst.interest), loan.money(inst.balance)]
+    }))
+}
+
+fn schedule<W: Write>(out: &mut W, loan: &Loan, sep: char) -> io::Result<usize> {
+    try!(render::csv_row(out, sep, &SCHEDULE_COLUMNS));
+    let mut rows = 0;
+    for fields in installments(loan, Anchor::Origination, db::today()) {
+        try!(render::csv_row(out, sep, &fields));
+        rows += 1;
+    }
+    Ok(rows)
+}
+
+// Writes one JSON object per installment from `anchor` on, with the date as
+// a string and everything else as numbers. The loan's name comes first if
+// `name` is set.
+fn json_rows<W: Write>(out: &mut W, loan: &Loan, anchor: Anchor, today: time::Timespec, name: bool, first: &mut bool) -> io::Result<usize> {
+    let mut rows = 0;
+    let columns: Vec<&str> = Some("loan").into_iter().filter(|_| name).chain(SCHEDULE_COLUMNS.iter().cloned()).collect();
+    for fields in installments(loan, anchor, today) {
+        let mut cells = if name { vec![Cell::Text(loan.name.clone())] } else { Vec::new() };
+        cells.extend(SCHEDULE_COLUMNS.iter().zip(fields.iter()).map(|(column, value)| {
+            if *column == "date" { Cell::Text(value.clone()) } else { Cell::Number(value.clone()) }
+        }));
+        try!(write!(out, "{}\n  {}", if *first { "" } else { "," }, render::json_object(&columns, &cells)));
+        *first = false;
+        rows += 1;
+    }
+    Ok(rows)
+}
+
+fn history<W: Write>(out: &mut W, transactions: &[Transaction], sep: char) -> io::Result<usize> {
+    try!(render::csv_row(out, sep, &["date", "loan", "kind", "principal", "interest", "escrow", "pmi", "payment_method", "reversed"]));
+    for transaction in transactions {
+        try!(render::csv_row(out, sep, &[date(transaction.date), transaction.name.clone(), transaction.kind.as_str().to_string(), format!("{:.2}", transaction.principal),
+                                         format!("{:.2}", transaction.interest), format!("{:.2}", transaction.escrow), format!("{:.2}", transaction.pmi),
+                                         transaction.payment_method.clone().unwrap_or_default(), transaction.reversed.to_string()]));
+    }
+    Ok(transactions.len())
+}
+
+// Writes the loan's full contractual schedule. Returns the number of rows
+// written, not counting the header.
+pub fn schedule_csv<W: Write>(out: &mut W, loan: &Loan) -> io::Result<usize> {
+    schedule(out, loan, ',')
+}
+
+// Same as schedule_csv, tab separated.
+pub fn schedule_tsv<W: Write>(out: &mut W, loan: &Loan) -> io::Result<usize> {
+    schedule(out, loan, '\t')
+}
+
+// Same as schedule_csv, as a JSON array of objects.
+pub fn schedule_json<W: Write>(out: &mut W, loan: &Loan) -> io::Result<usize> {
+    try!(write!(out, "["));
+    let rows = try!(json_rows(out, loan, Anchor::Origination, db::today(), false, &mut true));
+    try!(writeln!(out, "\n]"));
+    Ok(rows)
+}
+
+// Writes the schedules of every loan one after the other, from `anchor` on
+// as of `today`, with the loan's name as the first column. Returns the number
+// of rows written, not counting the header.
+pub fn portfolio_csv<'a, W: Write, I: IntoIterator<Item = &'a Loan>>(out: &mut W, loans: I, anchor: Anchor, today: time::Timespec) -> io::Result<usize> {
+    let columns: Vec<&str> = Some("loan").into_iter().chain(SCHEDULE_COLUMNS.iter().cloned()).collect();
+    try!(render::csv_row(out, ',', &columns));
+    let mut rows = 0;
+    for loan in loans {
+        for fields in installments(loan, anchor, today) {
+            let fields: Vec<&str> = Some(&loan.name[..]).into_iter().chain(fields.iter().map(|field| &field[..])).collect();
+            try!(render::csv_row(out, ',', &fields));
+            rows += 1;
+        }
+    }
+    Ok(rows)
+}
+
+// Same as portfolio_csv, as one JSON array with a "loan" member in each
+// object.
+pub fn portfolio_json<'a, W: Write, I: IntoIterator<Item = &'a Loan>>(out: &mut W, loans: I, anchor: Anchor, today: time::Timespec) -> io::Result<usize> {
+    try!(write!(out, "["));
+    let (mut rows, mut first) = (0, true);
+    for loan in loans {
+        rows += try!(json_rows(out, loan, anchor, today, true, &mut first));
+    }
+    try!(writeln!(out, "\n]"));
+    Ok(rows)
+}
+
+// Writes transactions as loaded by load_transactions. Returns the number of rows
+// written, not counting the header.
+pub fn history_csv<W: Write>(out: &mut W, transactions: &[Transaction]) -> io::Result<usize> {
+    history(out, transactions, ',')
+}
+
+// Same as history_csv, tab separated.
+pub fn history_tsv<W: Write>(out: &mut W, transactions: &[Transaction]) -> io::Result<usize> {
+    history(out, transactions, '\t')
+}
+
+// One day of a loan's payment calendar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalendarDay {
+    pub date: time::Timespec,
+    // a payment falls due
+    pub due: bool,
+    // the due date passed without everything due by then being paid
+    pub missed: bool,
+    // recorded payments, not counting returned ones
+    pub paid: f64,
+}
+
+impl CalendarDay {
+    // What the day is marked as, worst first: missed, paid, due or nothing.
+    pub fn mark(&self) -> &'static str {
+        if self.missed {
+            "missed"
+        } else if self.paid > 0f64 {
+            "paid"
+        } else if self.due {
+            "due"
+        } else {
+            ""
+        }
+    }
+}
+
+const DAY: i64 = 86400;
+
+// Every day of `year` for `loan`, given its payments as loaded by
+// load_transactions. Due dates up to `today` are checked against what had
+// been paid by the end of the day.
+pub fn calendar(loan: &Loan, payments: &[Transaction], year: i32, today: time::Timespec) -> Vec<CalendarDay> {
+    let payments: Vec<&Transaction> = payments.iter().filter(|payment| payment.paid()).collect();
+    let mut dues = Vec::new();
+    let mut owed = 0f64;
+    for period in 1..loan.periods + 1 {
+        let due = loan.due_date(period);
+        owed += loan.payment_due(period);
+        let paid: f64 = payments.iter().filter(|payment| payment.date.sec < due.sec + DAY).map(|payment| payment.principal + payment.interest).sum();
+        dues.push((due.sec / DAY, due <= today && paid + 0.005 < owed));
+    }
+
+    let mut start = time::empty_tm();
+    start.tm_year = year - 1900;
+    start.tm_mday = 1;
+    let mut days = Vec::new();
+    let mut date = start.to_timespec();
+    while time::at_utc(date).tm_year == year - 1900 {
+        let due = dues.iter().find(|&&(day, _)| day == date.sec / DAY);
+        days.push(CalendarDay{
+            date: date,
+            due: due.is_some(),
+            missed: due.map_or(false, |&(_, missed)| missed),
+            paid: payments.iter().filter(|payment| payment.date.sec / DAY == date.sec / DAY).fold(0f64, |paid, payment| paid + payment.principal + payment.interest),
+        });
+        date.sec += DAY;
+    }
+    days
+}
+
+// Writes the calendar one day per row. Returns the number of rows written,
+// not counting the header.
+pub fn calendar_csv<W: Write>(out: &mut W, loan: &Loan, days: &[CalendarDay]) -> io::Result<usize> {
+    try!(render::csv_row(out, ',', &["date", "weekday", "due", "paid", "missed", "mark"]));
+    for day in days {
+        try!(render::csv_row(out, ',', &[date(day.date), time::strftime("%a", &time::at_utc(day.date)).unwrap(), day.due.to_string(), loan.money(day.paid),
+                                         day.missed.to_string(), day.mark().to_string()]));
+    }
+    Ok(days.len())
+}
+
+// Writes the calendar as a standalone HTML page: a month grid for each month,
+// with due dates, payments and missed payments coloured in.
+pub fn calendar_html<W: Write>(out: &mut W, loan: &Loan, days: &[CalendarDay]) -> io::Result<()> {
+    let year = days.first().map_or(0, |day| time::at_utc(day.date).tm_year + 1900);
+    try!(writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{} {}</title>", render::html_escape(&loan.name), year));
+    try!(writeln!(out, "<style>\n\
+                        body {{ font-family: sans-serif; }}\n\
+                        table {{ display: inline-table; margin: 1em; border-collapse: collapse; }}\n\
+                        td {{ width: 2em; height: 2em; text-align: center; }}\n\
+                        .due {{ outline: 2px solid #4a76c9; }}\n\
+                        .paid {{ background: #6cc070; }}\n\
+                        .missed {{ background: #e05c5c; }}\n\
+                        </style>\n</head>\n<body>"));
+    try!(writeln!(out, "<h1>{} {}</h1>", render::html_escape(&loan.name), year));
+    try!(writeln!(out, "<p><span class=\"due\">due</span> <span class=\"paid\">paid</span> <span class=\"missed\">missed</span></p>"));
+    for month in 0..12 {
+        let month_days: Vec<&CalendarDay> = days.iter().filter(|day| time::at_utc(day.date).tm_mon == month).collect();
+        let first = match month_days.first() {
+            Some(day) => time::at_utc(day.date),
+            None => continue,
+        };
+        try!(writeln!(out, "<table>\n<caption>{}</caption>", time::strftime("%B", &first).unwrap()));
+        try!(writeln!(out, "<tr><th>Su</th><th>Mo</th><th>Tu</th><th>We</th><th>Th</th><th>Fr</th><th>Sa</th></tr>"));
+        // blank cells before the first, which falls on tm_wday (0 = Sunday)
+        let mut cells: Vec<String> = (0..first.tm_wday).map(|_| "<td></td>".to_string()).collect();
+        for day in month_days.iter() {
+            let title = if day.paid > 0f64 { format!(" title=\"paid {}\"", loan.money(day.paid)) } else { String::new() };
+            let class = match (day.due, day.mark()) {
+                (_, "") => String::new(),
+                (true, mark) if mark != "due" => format!(" class=\"due {}\"", mark),
+                (_, mark) => format!(" class=\"{}\"", mark),
+            };
+            cells.push(format!("<td{}{}>{}</td>", class, title, time::at_utc(day.date).tm_mday));
+        }
+        for week in cells.chunks(7) {
+            try!(writeln!(out, "<tr>{}</tr>", week.join("")));
+        }
+        try!(writeln!(out, "</table>"));
+    }
+    writeln!(out, "</body>\n</html>")
+}
+
+// Writes `summary` as a short certificate, for printing or filing away.
+pub fn closing_text<W: Write>(out: &mut W, loan: &Loan, summary: &ClosingSummary) -> io::Result<()> {
+    try!(writeln!(out, "Closing summary for {}", loan.name));
+    if let Some(ref lender) = loan.lender {
+        try!(writeln!(out, "Lender: {}", lender));
+    }
+    try!(writeln!(out, "Borrowed: {}", loan.money(loan.principal)));
+    try!(writeln!(out, "Paid off: {}", date(summary.paid_off)));
+    try!(writeln!(out, "Scheduled payoff: {}", date(summary.scheduled)));
+    try!(writeln!(out, "Payments made: {}", summary.payments));
+    try!(writeln!(out, "Total paid: {}", loan.money(summary.total_paid)));
+    try!(writeln!(out, "Total interest: {}", loan.money(summary.total_interest)));
+    let months = summary.months_saved();
+    match months {
+        0 => writeln!(out, "Paid off on schedule."),
+        _ if months > 0 => writeln!(out, "Paid off {} month{} early.", months, if months == 1 { "" } else { "s" }),
+        _ => writeln!(out, "Paid off {} month{} late.", -months, if months == -1 { "" } else { "s" }),
+    }
+}
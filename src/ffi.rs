@@ -0,0 +1,49 @@
+// C bindings for the calculation core, so non-Rust applications can link
+// against the cdylib. See include/amortization.h for the matching header.
+
+use std::slice;
+
+use calc;
+use calc::{Installment, Payoff};
+
+// Monthly payment for a fully amortizing loan.
+#[no_mangle]
+pub extern "C" fn amort_calc_payment(principal: f64, periods: i32, apr: f64) -> f64 {
+    calc::payment(principal, periods, apr)
+}
+
+/// Writes the schedule into `out`, which has room for `len` rows. Returns the
+/// number of rows in the full schedule; if that's larger than `len`, the
+/// output was truncated and the caller should retry with a bigger buffer.
+///
+/// # Safety
+///
+/// `out` must be NULL, in which case `len` is ignored and nothing is written,
+/// or point to `len` writable, properly aligned `Installment`s that nothing
+/// else reads or writes during the call. The buffer belongs to the caller
+/// throughout: this function never keeps the pointer after returning and
+/// nothing in this library frees it, so the caller frees it however it was
+/// allocated.
+#[no_mangle]
+pub unsafe extern "C" fn amort_generate_schedule(principal: f64, periods: i32, apr: f64, payment: f64, out: *mut Installment, len: usize) -> usize {
+    let buf: &mut [Installment] = if out.is_null() {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(out, len)
+    };
+
+    let mut count = 0;
+    for inst in calc::Schedule::new(principal, periods, apr, payment) {
+        if count < buf.len() {
+            buf[count] = inst;
+        }
+        count += 1;
+    }
+    count
+}
+
+// Projects the payoff of `balance` when paying `payment` every month.
+#[no_mangle]
+pub extern "C" fn amort_project_payoff(balance: f64, max_periods: i32, apr: f64, payment: f64) -> Payoff {
+    calc::project_payoff(balance, max_periods, apr, payment)
+}
@@ -0,0 +1,114 @@
+// Loan groups: several sub-loans, e.g. student loan disbursements at
+// different rates, serviced as one account with a single payment. The
+// payment covers what's due on each sub-loan, and anything over that is
+// spread across them by the group's allocation rule.
+
+use std::str::FromStr;
+
+// Where a group payment beyond the amount due goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Allocation {
+    // in proportion to each one's regular payment, as most servicers do
+    // unless told otherwise
+    Standard,
+    // in proportion to what's left on each
+    Proportional,
+    // the highest rate first
+    HighestRate,
+    // the smallest balance first
+    LowestBalance,
+}
+
+impl Allocation {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Allocation::Standard => "standard",
+            Allocation::Proportional => "proportional",
+            Allocation::HighestRate => "highest-rate",
+            Allocation::LowestBalance => "lowest-balance",
+        }
+    }
+}
+
+impl FromStr for Allocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Allocation, String> {
+        match s {
+            "standard" => Ok(Allocation::Standard),
+            "proportional" => Ok(Allocation::Proportional),
+            "highest-rate" => Ok(Allocation::HighestRate),
+            "lowest-balance" => Ok(Allocation::LowestBalance),
+            _ => Err(format!("Unknown allocation: {}", s)),
+        }
+    }
+}
+
+// A sub-loan as of a payment.
+#[derive(Debug, Clone)]
+pub struct Lot {
+    pub name: String,
+    pub apr: f64,
+    // regular payment due
+    pub due: f64,
+    // what it takes to pay it off, interest included
+    pub payoff: f64,
+}
+
+impl Lot {
+    // Most that can go towards it beyond what's due.
+    fn room(&self) -> f64 {
+        (self.payoff - self.due).max(0f64)
+    }
+}
+
+// Spreads `left` over the lots by `weight`, giving none more than its payoff.
+// Whatever a lot can't take goes round again to the rest.
+fn spread<F: Fn(&Lot) -> f64>(lots: &[Lot], parts: &mut [f64], left: f64, weight: F) {
+    let mut left = left;
+    while left > 0.005 {
+        let open: Vec<usize> = (0..lots.len()).filter(|&i| lots[i].payoff - parts[i] > 0.005).collect();
+        let total: f64 = open.iter().map(|&i| weight(&lots[i])).sum();
+        if total <= 0f64 {
+            break;
+        }
+        let share = left;
+        for i in open {
+            let extra = (share * weight(&lots[i]) / total).min(lots[i].payoff - parts[i]);
+            parts[i] += extra;
+            left -= extra;
+        }
+    }
+}
+
+// Splits `amount` across `lots`, in the same order. Each gets what's due
+// first; a payment short of the total due is split in proportion to it
+// instead. No lot gets more than its payoff, so the parts add up to less than
+// `amount` if it would pay off the whole group.
+pub fn allocate(lots: &[Lot], amount: f64, allocation: Allocation) -> Vec<f64> {
+    let due: f64 = lots.iter().map(|lot| lot.due).sum();
+    if amount <= due {
+        return lots.iter().map(|lot| if due > 0f64 { amount * lot.due / due } else { 0f64 }).collect();
+    }
+
+    let mut parts: Vec<f64> = lots.iter().map(|lot| lot.due).collect();
+    let mut left = amount - due;
+    match allocation {
+        Allocation::Standard => spread(lots, &mut parts, left, |lot| lot.due),
+        Allocation::Proportional => spread(lots, &mut parts, left, Lot::room),
+        Allocation::HighestRate | Allocation::LowestBalance => {
+            let mut order: Vec<usize> = (0..lots.len()).collect();
+            if allocation == Allocation::HighestRate {
+                order.sort_by(|&a, &b| lots[b].apr.partial_cmp(&lots[a].apr).unwrap());
+            } else {
+                order.sort_by(|&a, &b| lots[a].payoff.partial_cmp(&lots[b].payoff).unwrap());
+            }
+            for i in order {
+                let extra = left.min(lots[i].room());
+                parts[i] += extra;
+                left -= extra;
+            }
+        },
+    }
+    parts
+}
@@ -1,26 +1,1616 @@
+extern crate cairo;
 extern crate clap;
+extern crate gdk;
+extern crate gdk_sys;
+extern crate gio;
+extern crate glib;
 extern crate gtk;
+extern crate gtk_sys;
+extern crate time;
 
 extern crate amortization;
 
-use std::path::{PathBuf};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::os::raw::c_void;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::rc::Rc;
+use std::time::SystemTime;
 
-use clap::{App};
+use clap::{App, Arg};
+use glib::{IsA, StaticVariantType, ToVariant};
+use glib::translate::{from_glib_full, from_glib_none, ToGlibPtr};
 use gtk::prelude::*;
-use gtk::{Button, FileChooserDialog, FileChooserAction, MenuBar, MenuItem, Window, WindowType};
+use gtk::{AboutDialog, ApplicationWindow, Button, Calendar, CellRendererText, CellRendererToggle, CheckButton, ComboBoxText, Dialog, DrawingArea, Entry, FileChooserDialog, FileChooserAction, Grid, HeaderBar, InfoBar, Label, ListStore,
+          MessageDialog, Notebook, RadioButton, ScrolledWindow, SearchEntry, SpinButton, Stack, Statusbar, TreeModelFilter, TreeStore, TreeView, TreeViewColumn, Window};
+
+use amortization::{ExtraSavings, Fee, Loan, LoanStatus, MilestoneEvent};
+use amortization::calc;
+use amortization::{export, import, trace};
+use amortization::i18n::{format_amount, format_date, tr, trf};
+use amortization::import::PaymentKind;
+
+const RECORD_SCHEDULED: i32 = 1;
+
+// A change made from the GUI that Edit > Undo can take back.
+enum Change {
+    // a new loan and the fees rolled into it
+    AddLoan(Loan, Vec<Fee>),
+    // payments recorded together, e.g. by one import
+    Payments(Vec<amortization::Transaction>),
+}
+
+impl Change {
+    fn revert(&self, db: &Path) -> Result<(), String> {
+        let res = match *self {
+            Change::AddLoan(ref loan, _) => amortization::delete_loan(db, &loan.name),
+            Change::Payments(ref payments) => payments.iter().rev().map(|payment| amortization::remove_transaction(db, payment)).collect(),
+        };
+        res.map_err(|err| err.to_string())
+    }
+
+    fn apply(&self, db: &Path) -> Result<(), String> {
+        let res = match *self {
+            Change::AddLoan(ref loan, ref fees) => amortization::create_loan(db, loan.clone()).and_then(|_| {
+                if fees.is_empty() { Ok(()) } else { amortization::add_fees(db, &loan.name, fees) }
+            }),
+            Change::Payments(ref payments) => payments.iter().map(|payment| amortization::restore_transaction(db, payment)).collect(),
+        };
+        res.map_err(|err| err.to_string())
+    }
+
+    fn describe(&self) -> String {
+        match *self {
+            Change::AddLoan(ref loan, _) => trf("adding {}", &[&loan.name]),
+            Change::Payments(ref payments) if payments.len() == 1 => trf("the payment on {}", &[&payments[0].name]),
+            Change::Payments(ref payments) => trf("{} payments", &[&payments.len()]),
+        }
+    }
+}
+
+// The loans in the open database, shown as a table.
+struct LoanList {
+    window: Window,
+    // shows which database is open
+    header: HeaderBar,
+    // the File > Recent submenu
+    recent: gio::Menu,
+    store: ListStore,
+    view: TreeView,
+    // narrow the list down by name or lender, and by status
+    search: SearchEntry,
+    status_filter: ComboBoxText,
+    // totals across every loan
+    summary: Statusbar,
+    // payments overdue or coming up, and the loan the action button pays
+    reminders: InfoBar,
+    reminder_text: Label,
+    reminder_button: Button,
+    reminder_loan: RefCell<Option<String>>,
+    settings: RefCell<Settings>,
+    // changes made here, most recent last, and the actions that step through them
+    undo: RefCell<Vec<Change>>,
+    redo: RefCell<Vec<Change>>,
+    undo_action: gio::SimpleAction,
+    redo_action: gio::SimpleAction,
+    db: RefCell<Option<PathBuf>>,
+    // when the database was last loaded, to notice outside changes
+    modified: RefCell<Option<SystemTime>>,
+}
+
+impl LoanList {
+    fn new(window: &Window) -> Rc<LoanList> {
+        // the last column holds the lender, only used for searching
+        let store = ListStore::new(&[gtk::Type::String, gtk::Type::String, gtk::Type::String, gtk::Type::String, gtk::Type::String, gtk::Type::String,
+                                     gtk::Type::String]);
+        let search = SearchEntry::new();
+        let status_filter = ComboBoxText::new();
+        status_filter.append(Some(""), &tr("All statuses"));
+        status_filter.append(Some("open"), &tr("Not paid off"));
+        for status in [LoanStatus::Current, LoanStatus::Grace, LoanStatus::Late, LoanStatus::Delinquent, LoanStatus::PaidOff].iter() {
+            status_filter.append(Some(status.as_str()), &status.label());
+        }
+        status_filter.set_active(0);
+
+        let filter = TreeModelFilter::new(&store, None);
+        {
+            let search = search.clone();
+            let status_filter = status_filter.clone();
+            filter.set_visible_func(move |model, iter| {
+                let text = |col| model.get_value(iter, col).get::<String>().unwrap_or_default().to_lowercase();
+                let query = search.get_text().unwrap_or_default().trim().to_lowercase();
+                let status = status_filter.get_active_id().unwrap_or_default();
+                let matches_status = match &status[..] {
+                    "" => true,
+                    "open" => text(4) != LoanStatus::PaidOff.as_str(),
+                    status => text(4) == status,
+                };
+                matches_status && (text(0).contains(&query) || text(6).contains(&query))
+            });
+        }
+        {
+            let filter = filter.clone();
+            search.connect_search_changed(move |_| filter.refilter());
+        }
+        {
+            let filter = filter.clone();
+            status_filter.connect_changed(move |_| filter.refilter());
+        }
+
+        let reminders = InfoBar::new();
+        reminders.set_no_show_all(true);
+        reminders.set_show_close_button(true);
+        let reminder_text = Label::new(None);
+        reminder_text.set_halign(gtk::Align::Start);
+        if let Some(area) = reminders.get_content_area().and_then(|area| area.downcast::<gtk::Container>().ok()) {
+            area.add(&reminder_text);
+        }
+        let reminder_button = reminders.add_button(&tr("Record Scheduled Payment"), RECORD_SCHEDULED).unwrap();
+
+        let view = TreeView::new_with_model(&filter);
+        for (i, title) in ["Name", "Balance", "APR", "Payment", "Status", "Milestone"].iter().enumerate() {
+            let cell = CellRendererText::new();
+            let column = TreeViewColumn::new();
+            column.set_title(&tr(title));
+            column.pack_start(&cell, true);
+            column.add_attribute(&cell, "text", i as i32);
+            view.append_column(&column);
+        }
+
+        let header = HeaderBar::new();
+        header.set_title(Some(&tr("Amortization Calculator")));
+        header.set_show_close_button(true);
+        let recent = gio::Menu::new();
+        fill_recent_menu(&recent);
+        let undo_action = gio::SimpleAction::new("undo", None);
+        undo_action.set_enabled(false);
+        let redo_action = gio::SimpleAction::new("redo", None);
+        redo_action.set_enabled(false);
+
+        Rc::new(LoanList{
+            window: window.clone(),
+            header: header,
+            recent: recent,
+            store: store,
+            view: view,
+            search: search,
+            status_filter: status_filter,
+            summary: Statusbar::new(),
+            reminders: reminders,
+            reminder_text: reminder_text,
+            reminder_button: reminder_button,
+            reminder_loan: RefCell::new(None),
+            settings: RefCell::new(Settings::load()),
+            undo: RefCell::new(Vec::new()),
+            redo: RefCell::new(Vec::new()),
+            undo_action: undo_action,
+            redo_action: redo_action,
+            db: RefCell::new(None),
+            modified: RefCell::new(None),
+        })
+    }
+
+    fn open(&self, db: PathBuf) {
+        if let Err(err) = remember_recent(&db) {
+            show_error(&self.window, &trf("Couldn't save the list of recent databases: {}", &[&err]));
+        }
+        fill_recent_menu(&self.recent);
+        self.header.set_subtitle(db.file_name().and_then(|name| name.to_str()));
+        *self.db.borrow_mut() = Some(db);
+        // changes to the last database can't be undone from this one
+        self.undo.borrow_mut().clear();
+        self.redo.borrow_mut().clear();
+        self.update_history();
+        self.refresh();
+    }
+
+    // Remembers a change so it can be undone. Anything undone before it can
+    // no longer be redone.
+    fn did(&self, change: Change) {
+        self.undo.borrow_mut().push(change);
+        self.redo.borrow_mut().clear();
+        self.update_history();
+    }
+
+    fn update_history(&self) {
+        self.undo_action.set_enabled(!self.undo.borrow().is_empty());
+        self.redo_action.set_enabled(!self.redo.borrow().is_empty());
+    }
+
+    // Reverts the most recent change or, with `redo`, makes the most recently
+    // undone one again. A change that fails either way is dropped.
+    fn step(&self, redo: bool) {
+        let db = match *self.db.borrow() {
+            Some(ref db) => db.clone(),
+            None => return,
+        };
+        let (from, to) = if redo { (&self.redo, &self.undo) } else { (&self.undo, &self.redo) };
+        let change = match from.borrow_mut().pop() {
+            Some(change) => change,
+            None => return,
+        };
+        match if redo { change.apply(&db) } else { change.revert(&db) } {
+            Ok(_) => to.borrow_mut().push(change),
+            Err(err) => {
+                let message = if redo {
+                    trf("Couldn't redo {}: {}", &[&change.describe(), &err])
+                } else {
+                    trf("Couldn't undo {}: {}", &[&change.describe(), &err])
+                };
+                show_error(&self.window, &message);
+            },
+        };
+        self.update_history();
+        self.refresh();
+    }
+
+    fn refresh(&self) {
+        self.store.clear();
+        self.summary.remove_all(self.summary.get_context_id("summary"));
+        let db = match *self.db.borrow() {
+            Some(ref db) => db.clone(),
+            None => return,
+        };
+
+        let loans = amortization::refresh_statuses(&db).and_then(|_| amortization::load_loans(&db));
+        // milestones are only badges, so failing to check them isn't worth an error
+        let milestones = amortization::check_milestones(&db, amortization::today()).and_then(|_| amortization::load_milestones(&db, None))
+            .unwrap_or_default();
+        match loans {
+            Ok(loans) => {
+                for loan in loans.iter() {
+                    // the latest milestone reached
+                    let badge = milestones.iter().filter(|event| event.loan == loan.name).last().map_or(String::new(), |event| tr(event.milestone.as_str()));
+                    self.store.insert_with_values(None, &[0, 1, 2, 3, 4, 5, 6], &[&loan.name, &format_amount(loan.balance), &format!("{:.2}%", loan.apr),
+                                                                                &format_amount(loan.payment), &loan.status.as_str(), &badge,
+                                                                                &loan.lender.clone().unwrap_or_default()]);
+                }
+                self.summarize(&loans, &amortization::extra_savings(&db).unwrap_or_default());
+                self.remind(&loans, &milestones);
+            },
+            Err(err) => show_error(&self.window, &trf("Couldn't load loans from {}: {}", &[&db.display(), &err])),
+        };
+        *self.modified.borrow_mut() = modified_time(&db);
+    }
+
+    // Shows total debt and payments, per currency, and the next payment due.
+    // Loans lent out are totalled separately, as what's owed to us, and what
+    // extra payments have saved is added for encouragement.
+    fn summarize(&self, loans: &[Loan], savings: &[ExtraSavings]) {
+        let today = amortization::today();
+        let mut balances = BTreeMap::new();
+        let mut payments = BTreeMap::new();
+        let mut receivable = BTreeMap::new();
+        let mut next: Option<(time::Timespec, &str)> = None;
+        for loan in loans.iter().filter(|loan| loan.balance > 0f64) {
+            if loan.lent {
+                *receivable.entry(&loan.currency[..]).or_insert(0f64) += loan.balance;
+                continue;
+            }
+            *balances.entry(&loan.currency[..]).or_insert(0f64) += loan.balance;
+            *payments.entry(&loan.currency[..]).or_insert(0f64) += loan.payment;
+            let due = loan.next_due(today);
+            if next.map_or(true, |(date, _)| due < date) {
+                next = Some((due, &loan.name));
+            }
+        }
+
+        let totals = |amounts: BTreeMap<&str, f64>| {
+            amounts.iter().map(|(currency, amount)| format!("{:.2} {}", amount, currency)).collect::<Vec<_>>().join(", ")
+        };
+        let mut text = if balances.is_empty() {
+            tr("No outstanding loans")
+        } else {
+            trf("Total debt: {}    Monthly payments: {}", &[&totals(balances), &totals(payments)])
+        };
+        if !receivable.is_empty() {
+            text.push_str(&trf("    Owed to you: {}", &[&totals(receivable)]));
+        }
+        if let Some((date, name)) = next {
+            text.push_str(&trf("    Next due: {} ({})", &[&format_date(date), &name]));
+        }
+        let mut saved = BTreeMap::new();
+        for saving in savings.iter() {
+            *saved.entry(&saving.currency[..]).or_insert(0f64) += saving.interest_saved;
+        }
+        if !saved.is_empty() {
+            text.push_str(&trf("    Saved by extra payments: {}", &[&totals(saved)]));
+        }
+
+        let context = self.summary.get_context_id("summary");
+        self.summary.remove_all(context);
+        self.summary.push(context, &text);
+    }
+
+    // Points out loans with a payment overdue or due within the configured
+    // number of days, offering to record the scheduled payment on the most pressing one.
+    // Deferred-interest promotions about to run out are pointed out too, as
+    // are milestones reached within the same number of days.
+    fn remind(&self, loans: &[Loan], milestones: &[MilestoneEvent]) {
+        let today = amortization::today();
+        let reminder_days = self.settings.borrow().reminder_days;
+        let promos = match *self.db.borrow() {
+            Some(ref db) => amortization::deferred_promos(db, today).unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let mut due = Vec::new();
+        for promo in promos.iter().filter(|promo| promo.due_soon(today)) {
+            due.push((false, trf("{} must be paid off by {} to avoid {} in deferred interest",
+                                 &[&promo.loan, &format_date(promo.deadline), &format_amount(promo.interest)]), &promo.loan));
+        }
+        for loan in loans.iter().filter(|loan| loan.balance > 0f64) {
+            let (overdue, note) = match loan.status {
+                LoanStatus::Grace => (true, trf("{} is past due", &[&loan.name])),
+                LoanStatus::Late | LoanStatus::Delinquent => (true, format!("{} is {}", loan.name, loan.status.as_str())),
+                _ => {
+                    let date = loan.next_due(today);
+                    if date.sec - today.sec > reminder_days * 24 * 60 * 60 {
+                        continue;
+                    }
+                    (false, trf("{} is due {}", &[&loan.name, &format_date(date)]))
+                },
+            };
+            due.push((!overdue, note, &loan.name));
+        }
+        due.sort();
+
+        let mut notes: Vec<String> = due.iter().map(|&(_, ref note, _)| note.clone()).collect();
+        for event in milestones.iter().filter(|event| today.sec - event.date.sec <= reminder_days * 24 * 60 * 60) {
+            notes.push(event.milestone.describe(&event.loan));
+        }
+        *self.reminder_loan.borrow_mut() = due.first().map(|&(_, _, name)| name.clone());
+        if notes.is_empty() {
+            self.reminders.hide();
+            return;
+        }
+        self.reminder_text.set_text(&notes.join("\n"));
+        self.reminders.set_message_type(if due.first().map_or(true, |&(soon, _, _)| soon) { gtk::MessageType::Info } else { gtk::MessageType::Warning });
+        self.reminders.show_all();
+        match due.first() {
+            Some(&(_, _, name)) => self.reminder_button.set_label(&trf("Record {}'s Scheduled Payment", &[&name])),
+            // nothing to pay, only milestones
+            None => self.reminder_button.hide(),
+        };
+    }
+
+    // Records the regular payment, dated today, on the loan the reminder is
+    // about.
+    fn record_scheduled(&self) {
+        let (db, name) = match (self.db.borrow().clone(), self.reminder_loan.borrow().clone()) {
+            (Some(db), Some(name)) => (db, name),
+            _ => return,
+        };
+        let today = amortization::today();
+        let res = amortization::load_loan(&db, &name)
+            .and_then(|loan| amortization::commit_transaction(&db, name.clone(), loan.total_due(today), false, today, None));
+        match res {
+            Ok(payment) => {
+                self.did(Change::Payments(vec![payment]));
+                self.refresh();
+            },
+            Err(err) => show_error(&self.window, &trf("Couldn't record the payment on {}: {}", &[&name, &err])),
+        };
+    }
+
+    // Name of the highlighted loan, if any.
+    fn selected(&self) -> Option<String> {
+        self.view.get_selection().get_selected().and_then(|(model, iter)| model.get_value(&iter, 0).get::<String>())
+    }
+
+    // Reloads the list if something else (e.g. the CLI) changed the database.
+    fn poll(&self) {
+        let modified = match *self.db.borrow() {
+            Some(ref db) => modified_time(db),
+            None => return,
+        };
+        if modified != *self.modified.borrow() {
+            self.refresh();
+        }
+    }
+}
+
+// The selected loan's full amortization table, with a row per year that
+// totals the payments in it.
+struct ScheduleView {
+    store: TreeStore,
+    view: TreeView,
+}
+
+impl ScheduleView {
+    fn new() -> ScheduleView {
+        let store = TreeStore::new(&[gtk::Type::String, gtk::Type::String, gtk::Type::String, gtk::Type::String, gtk::Type::String]);
+        let view = TreeView::new_with_model(&store);
+        for (i, title) in ["Date", "Payment", "Principal", "Interest", "Balance"].iter().enumerate() {
+            let cell = CellRendererText::new();
+            let column = TreeViewColumn::new();
+            column.set_title(&tr(title));
+            column.pack_start(&cell, true);
+            column.add_attribute(&cell, "text", i as i32);
+            view.append_column(&column);
+        }
+
+        ScheduleView{
+            store: store,
+            view: view,
+        }
+    }
+
+    fn show(&self, loan: Option<(&Loan, &[calc::Installment])>) {
+        self.store.clear();
+        let (loan, schedule) = match loan {
+            Some(loan) => loan,
+            None => return,
+        };
+
+        let columns = [0, 1, 2, 3, 4];
+        let mut year = None;
+        let mut totals = (0f64, 0f64, 0f64);
+        for inst in schedule {
+            let date = time::at_utc(loan.due_date(inst.period));
+            let new_year = match year {
+                Some((y, _)) => y != date.tm_year,
+                None => true,
+            };
+            if new_year {
+                totals = (0f64, 0f64, 0f64);
+                let title = format!("{}", date.tm_year + 1900);
+                year = Some((date.tm_year, self.store.insert_with_values(None, None, &[0], &[&title])));
+            }
+            let iter = &year.as_ref().unwrap().1;
+            self.store.insert_with_values(Some(iter), None, &columns,
+                                          &[&format_date(loan.due_date(inst.period)), &format_amount(inst.payment),
+                                            &format_amount(inst.principal), &format_amount(inst.interest), &format_amount(inst.balance)]);
+
+            totals.0 += inst.payment;
+            totals.1 += inst.principal;
+            totals.2 += inst.interest;
+            self.store.set(iter, &columns[1..], &[&format_amount(totals.0), &format_amount(totals.1),
+                                                 &format_amount(totals.2), &format_amount(inst.balance)]);
+        }
+    }
+}
+
+// Lays out labelled fields one per row.
+fn form_grid(rows: &[(&str, gtk::Widget)]) -> Grid {
+    let grid = Grid::new();
+    grid.set_row_spacing(6);
+    grid.set_column_spacing(12);
+    grid.set_border_width(12);
+    for (row, &(text, ref widget)) in rows.iter().enumerate() {
+        let label = Label::new(Some(&tr(text)));
+        label.set_halign(gtk::Align::Start);
+        grid.attach(&label, 0, row as i32, 1, 1);
+        grid.attach(widget, 1, row as i32, 1, 1);
+    }
+    grid
+}
+
+// Running totals of principal and interest by month: what's been paid so
+// far, then what the schedule says is still to come.
+#[derive(Default)]
+struct CostData {
+    title: String,
+    // (month index, principal, interest), cumulative
+    points: Vec<(i32, f64, f64)>,
+    today: i32,
+}
+
+struct CostChart {
+    area: DrawingArea,
+    data: Rc<RefCell<CostData>>,
+}
+
+impl CostChart {
+    fn new() -> CostChart {
+        let area = DrawingArea::new();
+        area.set_size_request(400, 300);
+        let data = Rc::new(RefCell::new(CostData::default()));
+        {
+            let data = data.clone();
+            area.connect_draw(move |area, cr| {
+                draw_costs(cr, &data.borrow(), area.get_allocated_width() as f64, area.get_allocated_height() as f64);
+                Inhibit(false)
+            });
+        }
+
+        CostChart{
+            area: area,
+            data: data,
+        }
+    }
+
+    // Charts a single loan, or every loan if `name` is None. Amounts are
+    // converted to the currency of the first loan charted.
+    fn show(&self, db: &Path, name: Option<&str>) {
+        *self.data.borrow_mut() = CostData::default();
+        self.area.queue_draw();
+
+        let loans = match amortization::load_schedules(db) {
+            Ok(loans) => loans.into_iter().filter(|&(ref loan, _)| name.map_or(true, |name| loan.name == name)).collect::<Vec<_>>(),
+            Err(_) => return,
+        };
+        let (transactions, rates) = match (amortization::load_transactions(db, name, None), amortization::load_rates(db)) {
+            (Ok(transactions), Ok(rates)) => (transactions, rates),
+            _ => return,
+        };
+        let currency = match loans.first() {
+            Some(&(ref loan, _)) => loan.currency.clone(),
+            None => return,
+        };
+
+        let today = amortization::today();
+        let mut loan_rates = HashMap::new();
+        let mut months = BTreeMap::new();
+        for &(ref loan, ref schedule) in loans.iter() {
+            // loans we can't convert are left out rather than mixed in
+            let rate = match rates.convert(1f64, &loan.currency, &currency) {
+                Some(rate) => rate,
+                None => continue,
+            };
+            loan_rates.insert(&loan.name[..], rate);
+            if loan.balance <= 0f64 {
+                continue;
+            }
+            let first = month_index(loan.next_due(today));
+            for (i, inst) in schedule.iter().enumerate() {
+                let month = months.entry(first + i as i32).or_insert((0f64, 0f64));
+                month.0 += inst.principal * rate;
+                month.1 += inst.interest * rate;
+            }
+        }
+        for transaction in transactions.iter().filter(|transaction| transaction.paid()) {
+            if let Some(rate) = loan_rates.get(&transaction.name[..]) {
+                let month = months.entry(month_index(transaction.date)).or_insert((0f64, 0f64));
+                month.0 += transaction.principal * rate;
+                month.1 += transaction.interest * rate;
+            }
+        }
+
+        let mut data = self.data.borrow_mut();
+        data.title = format!("{} ({})", name.map_or(tr("All loans"), |name| name.to_string()), currency);
+        data.today = month_index(today);
+        let (mut principal, mut interest) = (0f64, 0f64);
+        for (&month, &(p, i)) in months.iter() {
+            principal += p;
+            interest += i;
+            data.points.push((month, principal, interest));
+        }
+    }
+}
+
+// Months since year 1900, so consecutive months differ by one.
+fn month_index(date: time::Timespec) -> i32 {
+    let tm = time::at_utc(date);
+    tm.tm_year * 12 + tm.tm_mon
+}
+
+// Stacks interest on top of principal. Months already paid are drawn solid
+// and the projection faded.
+fn draw_costs(cr: &cairo::Context, data: &CostData, width: f64, height: f64) {
+    const MARGIN: f64 = 40f64;
+
+    cr.set_source_rgb(1f64, 1f64, 1f64);
+    cr.paint();
+    let (first, last) = match (data.points.first(), data.points.last()) {
+        (Some(first), Some(last)) => (first.0, last),
+        _ => return,
+    };
+    let total = last.1 + last.2;
+    if total <= 0f64 {
+        return;
+    }
+
+    let span = if last.0 > first { (last.0 - first) as f64 } else { 1f64 };
+    let x = |month: i32| MARGIN + (month - first) as f64 / span * (width - 2f64 * MARGIN);
+    let y = |amount: f64| height - MARGIN - amount / total * (height - 2f64 * MARGIN);
+    let today = x(data.today).max(MARGIN).min(width - MARGIN);
+
+    for &(alpha, clip) in [(0.35, false), (1.0, true)].iter() {
+        cr.save();
+        if clip {
+            cr.rectangle(0f64, 0f64, today, height);
+            cr.clip();
+        }
+
+        // principal
+        cr.move_to(x(first), y(0f64));
+        for &(month, principal, _) in data.points.iter() {
+            cr.line_to(x(month), y(principal));
+        }
+        cr.line_to(x(last.0), y(0f64));
+        cr.close_path();
+        cr.set_source_rgba(0.2, 0.4, 0.8, alpha);
+        cr.fill();
+
+        // interest, on top of principal
+        cr.move_to(x(first), y(data.points[0].1));
+        for &(month, principal, interest) in data.points.iter() {
+            cr.line_to(x(month), y(principal + interest));
+        }
+        for &(month, principal, _) in data.points.iter().rev() {
+            cr.line_to(x(month), y(principal));
+        }
+        cr.close_path();
+        cr.set_source_rgba(0.9, 0.5, 0.1, alpha);
+        cr.fill();
+        cr.restore();
+    }
+
+    cr.set_source_rgb(0f64, 0f64, 0f64);
+    cr.set_line_width(1f64);
+    cr.move_to(MARGIN, MARGIN);
+    cr.line_to(MARGIN, height - MARGIN);
+    cr.line_to(width - MARGIN, height - MARGIN);
+    cr.stroke();
+    cr.set_dash(&[4f64], 0f64);
+    cr.move_to(today, MARGIN);
+    cr.line_to(today, height - MARGIN);
+    cr.stroke();
+
+    cr.set_font_size(12f64);
+    cr.move_to(MARGIN, MARGIN - 20f64);
+    cr.show_text(&trf("{}: principal {}, interest {}", &[&data.title, &format_amount(last.1), &format_amount(last.2)]));
+    cr.move_to(MARGIN + 4f64, MARGIN + 12f64);
+    cr.show_text(&format!("{:.0}", total));
+    cr.move_to(today + 4f64, height - MARGIN - 4f64);
+    cr.show_text(&tr("today"));
+}
+
+// Balance curves of the loans and saved scenarios ticked in the list, drawn
+// over each other to compare how quickly they pay off.
+struct CompareChart {
+    // ticked, label, "loan" or "scenario", name
+    store: ListStore,
+    view: TreeView,
+    area: DrawingArea,
+    db: RefCell<Option<PathBuf>>,
+    // label and balance after each payment, starting from today's
+    curves: Rc<RefCell<Vec<(String, Vec<f64>)>>>,
+}
+
+impl CompareChart {
+    fn new() -> Rc<CompareChart> {
+        let store = ListStore::new(&[gtk::Type::Bool, gtk::Type::String, gtk::Type::String, gtk::Type::String]);
+        let view = TreeView::new_with_model(&store);
+        let toggle = CellRendererToggle::new();
+        let column = TreeViewColumn::new();
+        column.pack_start(&toggle, false);
+        column.add_attribute(&toggle, "active", 0);
+        view.append_column(&column);
+        let cell = CellRendererText::new();
+        let column = TreeViewColumn::new();
+        column.set_title(&tr("Compare"));
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", 1);
+        view.append_column(&column);
+
+        let area = DrawingArea::new();
+        area.set_size_request(400, 300);
+        let curves = Rc::new(RefCell::new(Vec::new()));
+        {
+            let curves = curves.clone();
+            area.connect_draw(move |area, cr| {
+                draw_comparison(cr, &curves.borrow(), area.get_allocated_width() as f64, area.get_allocated_height() as f64);
+                Inhibit(false)
+            });
+        }
+
+        let chart = Rc::new(CompareChart{
+            store: store,
+            view: view,
+            area: area,
+            db: RefCell::new(None),
+            curves: curves,
+        });
+        {
+            let chart = chart.clone();
+            toggle.connect_toggled(move |_, path| {
+                if let Some(iter) = chart.store.get_iter(path) {
+                    let ticked = chart.store.get_value(&iter, 0).get::<bool>().unwrap_or(false);
+                    chart.store.set(&iter, &[0], &[&!ticked]);
+                    chart.update();
+                }
+            });
+        }
+        chart
+    }
+
+    // Lists the loans and scenarios in `db`, keeping whatever was ticked.
+    fn load(&self, db: &Path) {
+        let mut ticked = HashSet::new();
+        for (kind, name, checked) in self.rows() {
+            if checked {
+                ticked.insert((kind, name));
+            }
+        }
+
+        self.store.clear();
+        *self.db.borrow_mut() = Some(db.to_path_buf());
+        let mut rows: Vec<(String, String, String)> = Vec::new();
+        if let Ok(loans) = amortization::load_loans(db) {
+            rows.extend(loans.into_iter().map(|loan| (format!("{} ({})", loan.name, loan.currency), "loan".to_string(), loan.name)));
+        }
+        if let Ok(scenarios) = amortization::load_scenarios(db) {
+            rows.extend(scenarios.into_iter().map(|scenario| (trf("{} (scenario on {})", &[&scenario.name, &scenario.loan]), "scenario".to_string(), scenario.name)));
+        }
+        for (label, kind, name) in rows {
+            let checked = ticked.contains(&(kind.clone(), name.clone()));
+            self.store.insert_with_values(None, &[0, 1, 2, 3], &[&checked, &label, &kind, &name]);
+        }
+        self.update();
+    }
+
+    // (kind, name, ticked) for every row in the list.
+    fn rows(&self) -> Vec<(String, String, bool)> {
+        let mut rows = Vec::new();
+        if let Some(iter) = self.store.get_iter_first() {
+            loop {
+                let text = |col| self.store.get_value(&iter, col).get::<String>().unwrap_or_default();
+                rows.push((text(2), text(3), self.store.get_value(&iter, 0).get::<bool>().unwrap_or(false)));
+                if !self.store.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        rows
+    }
+
+    fn update(&self) {
+        let mut curves = Vec::new();
+        if let Some(ref db) = *self.db.borrow() {
+            let today = amortization::today();
+            for (kind, name, checked) in self.rows() {
+                if !checked {
+                    continue;
+                }
+                let curve = if kind == "scenario" {
+                    amortization::load_scenario(db, &name).and_then(|scenario| {
+                        amortization::load_loan(db, &scenario.loan).map(|loan| {
+                            let first_month = time::at_utc(loan.next_due(today)).tm_mon + 1;
+                            (loan.balance, scenario.apply(loan.schedule(), first_month))
+                        })
+                    })
+                } else {
+                    amortization::load_loan(db, &name).map(|loan| (loan.balance, loan.schedule()))
+                };
+                if let Ok((balance, schedule)) = curve {
+                    curves.push((name, Some(balance).into_iter().chain(schedule.map(|inst| inst.balance)).collect()));
+                }
+            }
+        }
+        *self.curves.borrow_mut() = curves;
+        self.area.queue_draw();
+    }
+}
+
+// One line per curve, months from today across and balance up.
+fn draw_comparison(cr: &cairo::Context, curves: &[(String, Vec<f64>)], width: f64, height: f64) {
+    const MARGIN: f64 = 40f64;
+    const COLORS: [(f64, f64, f64); 6] = [(0.2, 0.4, 0.8), (0.9, 0.5, 0.1), (0.2, 0.6, 0.3), (0.8, 0.2, 0.2), (0.5, 0.3, 0.7), (0.4, 0.4, 0.4)];
+
+    cr.set_source_rgb(1f64, 1f64, 1f64);
+    cr.paint();
+    let months = curves.iter().map(|&(_, ref balances)| balances.len()).max().unwrap_or(0);
+    let top = curves.iter().flat_map(|&(_, ref balances)| balances.iter()).fold(0f64, |top, &balance| top.max(balance));
+    if months < 2 || top <= 0f64 {
+        return;
+    }
+
+    let x = |month: usize| MARGIN + month as f64 / (months - 1) as f64 * (width - 2f64 * MARGIN);
+    let y = |balance: f64| height - MARGIN - balance / top * (height - 2f64 * MARGIN);
+
+    cr.set_source_rgb(0f64, 0f64, 0f64);
+    cr.set_line_width(1f64);
+    cr.move_to(MARGIN, MARGIN);
+    cr.line_to(MARGIN, height - MARGIN);
+    cr.line_to(width - MARGIN, height - MARGIN);
+    cr.stroke();
+    cr.set_font_size(12f64);
+    cr.move_to(MARGIN + 4f64, MARGIN + 12f64);
+    cr.show_text(&format!("{:.0}", top));
+    cr.move_to(width - MARGIN - 80f64, height - MARGIN + 16f64);
+    cr.show_text(&trf("{} months", &[&(months - 1)]));
+
+    cr.set_line_width(2f64);
+    for (i, &(ref label, ref balances)) in curves.iter().enumerate() {
+        let (r, g, b) = COLORS[i % COLORS.len()];
+        cr.set_source_rgb(r, g, b);
+        for (month, &balance) in balances.iter().enumerate() {
+            if month == 0 {
+                cr.move_to(x(month), y(balance));
+            } else {
+                cr.line_to(x(month), y(balance));
+            }
+        }
+        cr.stroke();
+
+        // legend
+        let row = MARGIN + 16f64 * i as f64;
+        cr.rectangle(width - MARGIN - 160f64, row, 10f64, 10f64);
+        cr.fill();
+        cr.move_to(width - MARGIN - 144f64, row + 10f64);
+        cr.show_text(&trf("{}: {} months", &[label, &(balances.len() - 1)]));
+    }
+}
+
+// Asks for a new loan's terms, asking again until they're valid. Returns None
+// if cancelled.
+fn new_loan_dialog(parent: &Window, db: &Path) -> Option<Loan> {
+    const OK: i32 = 1;
+    const CANCEL: i32 = 0;
+
+    let dialog = Dialog::new_with_buttons(Some(&tr("New Loan")), Some(parent), gtk::DIALOG_MODAL, &[("_Cancel", CANCEL), ("_Create", OK)]);
+    dialog.set_default_response(OK);
+
+    let name = Entry::new();
+    name.set_activates_default(true);
+    let principal = SpinButton::new_with_range(0f64, 100000000f64, 100f64);
+    principal.set_digits(2);
+    let apr = SpinButton::new_with_range(0f64, 100f64, 0.125);
+    apr.set_digits(3);
+    let term = SpinButton::new_with_range(1f64, 50f64, 1f64);
+    term.set_value(30f64);
+    let start = Calendar::new();
+    let error = Label::new(None);
+
+    let grid = form_grid(&[("Name", name.clone().upcast()),
+                           ("Principal", principal.clone().upcast()),
+                           ("APR (%)", apr.clone().upcast()),
+                           ("Term (years)", term.clone().upcast()),
+                           ("First payment", start.clone().upcast())]);
+    grid.attach(&error, 0, 5, 2, 1);
+    dialog.get_content_area().add(&grid);
+    dialog.show_all();
+
+    loop {
+        if dialog.run() != OK {
+            dialog.destroy();
+            return None;
+        }
+
+        let start_time = calendar_date(&start);
+        let loan_name = name.get_text().unwrap_or_default().trim().to_string();
+        let problem = if loan_name.is_empty() {
+            Some(tr("The loan needs a name"))
+        } else if amortization::load_loan(db, &loan_name).is_ok() {
+            Some(trf("There's already a loan named {}", &[&loan_name]))
+        } else if principal.get_value() <= 0f64 {
+            Some(tr("The principal must be more than zero"))
+        } else {
+            None
+        };
+
+        match problem {
+            Some(problem) => error.set_text(&problem),
+            None => {
+                dialog.destroy();
+                return Some(Loan::new(loan_name, principal.get_value(), term.get_value_as_int() * 12, apr.get_value(), start_time));
+            },
+        }
+    }
+}
+
+// Walks through setting up a loan a step at a time, previewing the payments
+// before anything is saved. Returns the loan and the fees financed into it, or
+// None if cancelled.
+fn loan_wizard(parent: &Window, db: &Path) -> Option<(Loan, Vec<Fee>)> {
+    const CREATE: i32 = 1;
+    const CANCEL: i32 = 0;
+    const BACK: i32 = 2;
+    const NEXT: i32 = 3;
+    const PAGES: [&str; 5] = ["type", "terms", "fees", "start", "review"];
+
+    let dialog = Dialog::new_with_buttons(Some(&tr("New Loan")), Some(parent), gtk::DIALOG_MODAL,
+                                          &[("_Cancel", CANCEL), ("_Back", BACK), ("_Next", NEXT), ("_Create", CREATE)]);
+    let stack = Stack::new();
+
+    // what kind of loan
+    let name = Entry::new();
+    name.set_activates_default(true);
+    let standard = RadioButton::new_with_label(&[], &tr("Standard: the same payment every month until it's paid off"));
+    let interest_only = RadioButton::new_with_label_from_widget(Some(&standard), &tr("Interest-only at first, like a HELOC's draw period"));
+    let promo = RadioButton::new_with_label_from_widget(Some(&standard), &tr("Promotional rate for the first few months"));
+    let page = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    page.set_border_width(12);
+    page.pack_start(&Label::new(Some(&tr("What should this loan be called?"))), false, false, 0);
+    page.pack_start(&name, false, false, 0);
+    page.pack_start(&Label::new(Some(&tr("What kind of loan is it?"))), false, false, 0);
+    page.pack_start(&standard, false, false, 0);
+    page.pack_start(&interest_only, false, false, 0);
+    page.pack_start(&promo, false, false, 0);
+    let lent = CheckButton::new_with_label(&tr("I lent this money to someone else"));
+    page.pack_start(&lent, false, false, 0);
+    stack.add_named(&page, PAGES[0]);
+
+    // terms
+    let principal = SpinButton::new_with_range(0f64, 100000000f64, 100f64);
+    principal.set_digits(2);
+    let apr = SpinButton::new_with_range(0f64, 100f64, 0.125);
+    apr.set_digits(3);
+    let term = SpinButton::new_with_range(1f64, 50f64, 1f64);
+    term.set_value(30f64);
+    let draw = SpinButton::new_with_range(1f64, 600f64, 1f64);
+    draw.set_value(120f64);
+    let promo_apr = SpinButton::new_with_range(0f64, 100f64, 0.125);
+    promo_apr.set_digits(3);
+    let promo_months = SpinButton::new_with_range(1f64, 120f64, 1f64);
+    promo_months.set_value(12f64);
+    let deferred = CheckButton::new_with_label(&tr("Promotional interest is deferred, not waived"));
+    let terms = form_grid(&[("Amount borrowed", principal.clone().upcast()),
+                            ("APR (%)", apr.clone().upcast()),
+                            ("Term (years)", term.clone().upcast()),
+                            ("Interest-only months", draw.clone().upcast()),
+                            ("Promotional APR (%)", promo_apr.clone().upcast()),
+                            ("Promotional months", promo_months.clone().upcast()),
+                            ("", deferred.clone().upcast())]);
+    stack.add_named(&terms, PAGES[1]);
+
+    // fees financed into the loan
+    let fee_rows: Rc<RefCell<Vec<(Entry, SpinButton)>>> = Rc::new(RefCell::new(Vec::new()));
+    let fee_grid = Grid::new();
+    fee_grid.set_row_spacing(6);
+    fee_grid.set_column_spacing(12);
+    let add_fee = Button::new_with_label(&tr("Add Fee"));
+    {
+        let fee_rows = fee_rows.clone();
+        let fee_grid = fee_grid.clone();
+        let add_fee_row = move || {
+            let fee_name = Entry::new();
+            fee_name.set_placeholder_text(Some("e.g. Origination"));
+            let amount = SpinButton::new_with_range(0f64, 1000000f64, 10f64);
+            amount.set_digits(2);
+            let row = fee_rows.borrow().len() as i32;
+            fee_grid.attach(&fee_name, 0, row, 1, 1);
+            fee_grid.attach(&amount, 1, row, 1, 1);
+            fee_grid.show_all();
+            fee_rows.borrow_mut().push((fee_name, amount));
+        };
+        add_fee_row();
+        add_fee.connect_clicked(move |_| add_fee_row());
+    }
+    let page = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    page.set_border_width(12);
+    page.pack_start(&Label::new(Some(&tr("Closing costs or other fees added to the balance, if any:"))), false, false, 0);
+    page.pack_start(&fee_grid, false, false, 0);
+    page.pack_start(&add_fee, false, false, 0);
+    stack.add_named(&page, PAGES[2]);
+
+    // first payment
+    let start = Calendar::new();
+    let paid_ahead = CheckButton::new_with_label(&tr("Paying more than is due pushes out the next due date"));
+    let page = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    page.set_border_width(12);
+    page.pack_start(&Label::new(Some(&tr("When is the first payment due?"))), false, false, 0);
+    page.pack_start(&start, false, false, 0);
+    page.pack_start(&paid_ahead, false, false, 0);
+    stack.add_named(&page, PAGES[3]);
+
+    // review
+    let summary = Label::new(None);
+    summary.set_halign(gtk::Align::Start);
+    let first_year = ListStore::new(&[gtk::Type::String, gtk::Type::String, gtk::Type::String, gtk::Type::String, gtk::Type::String]);
+    let view = TreeView::new_with_model(&first_year);
+    for (i, title) in ["Date", "Payment", "Principal", "Interest", "Balance"].iter().enumerate() {
+        let cell = CellRendererText::new();
+        let column = TreeViewColumn::new();
+        column.set_title(&tr(title));
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", i as i32);
+        view.append_column(&column);
+    }
+    let scroll = ScrolledWindow::new(None, None);
+    scroll.set_size_request(-1, 240);
+    scroll.add(&view);
+    let page = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    page.set_border_width(12);
+    page.pack_start(&summary, false, false, 0);
+    page.pack_start(&Label::new(Some(&tr("First year of payments:"))), false, false, 0);
+    page.pack_start(&scroll, true, true, 0);
+    stack.add_named(&page, PAGES[4]);
+
+    let error = Label::new(None);
+    dialog.get_content_area().add(&stack);
+    dialog.get_content_area().add(&error);
+    dialog.show_all();
+
+    let fees = || -> Vec<Fee> {
+        fee_rows.borrow().iter().filter_map(|&(ref fee_name, ref amount)| {
+            let fee_name = fee_name.get_text().unwrap_or_default().trim().to_string();
+            if fee_name.is_empty() || amount.get_value() <= 0f64 {
+                None
+            } else {
+                Some(Fee{ name: fee_name, amount: amount.get_value() })
+            }
+        }).collect()
+    };
+    let build = |fees: &[Fee]| -> Loan {
+        let fee_total: f64 = fees.iter().map(|fee| fee.amount).sum();
+        let mut loan = Loan::new(name.get_text().unwrap_or_default().trim().to_string(), principal.get_value() + fee_total,
+                                 term.get_value_as_int() * 12, apr.get_value(), calendar_date(&start));
+        if interest_only.get_active() {
+            loan.set_draw_periods(draw.get_value_as_int());
+        }
+        if promo.get_active() {
+            loan.set_promo(promo_apr.get_value(), promo_months.get_value_as_int(), deferred.get_active());
+        }
+        loan.paid_ahead = paid_ahead.get_active();
+        loan.lent = lent.get_active();
+        loan
+    };
+
+    let mut page = 0;
+    loop {
+        stack.set_visible_child_name(PAGES[page]);
+        dialog.set_response_sensitive(BACK, page > 0);
+        dialog.set_response_sensitive(NEXT, page < PAGES.len() - 1);
+        dialog.set_response_sensitive(CREATE, page == PAGES.len() - 1);
+        dialog.set_default_response(if page == PAGES.len() - 1 { CREATE } else { NEXT });
+        draw.set_sensitive(interest_only.get_active());
+        promo_apr.set_sensitive(promo.get_active());
+        promo_months.set_sensitive(promo.get_active());
+        deferred.set_sensitive(promo.get_active());
+
+        let res = dialog.run();
+        error.set_text("");
+        if res == BACK {
+            page -= 1;
+            continue;
+        }
+        if res == CREATE {
+            let fees = fees();
+            let loan = build(&fees);
+            dialog.destroy();
+            return Some((loan, fees));
+        }
+        if res != NEXT {
+            dialog.destroy();
+            return None;
+        }
+
+        let loan_name = name.get_text().unwrap_or_default().trim().to_string();
+        let problem = match PAGES[page] {
+            "type" if loan_name.is_empty() => Some(tr("The loan needs a name")),
+            "type" if amortization::load_loan(db, &loan_name).is_ok() => Some(trf("There's already a loan named {}", &[&loan_name])),
+            "terms" if principal.get_value() <= 0f64 => Some(tr("The amount borrowed must be more than zero")),
+            "terms" if interest_only.get_active() && draw.get_value_as_int() >= term.get_value_as_int() * 12 => {
+                Some(tr("The interest-only period must end before the term does"))
+            },
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            error.set_text(&problem);
+            continue;
+        }
+        page += 1;
+
+        if PAGES[page] == "review" {
+            let fees = fees();
+            let loan = build(&fees);
+            let schedule: Vec<calc::Installment> = loan.contract_schedule().collect();
+            let total_interest: f64 = schedule.iter().map(|inst| inst.interest).sum();
+            let mut text = trf("Amount financed: {}", &[&format_amount(loan.principal)]);
+            if !fees.is_empty() {
+                text.push_str(&trf(" (including {} in fees)", &[&format_amount(loan.principal - principal.get_value())]));
+            }
+            text.push_str(&trf("\nFirst payment: {} on {}", &[&format_amount(loan.payment_due(1)), &format_date(loan.start_time)]));
+            if loan.draw_periods > 0 || loan.promo_periods > 0 {
+                text.push_str(&trf("\nPayment after the introductory period: {}", &[&format_amount(loan.payment_due(loan.draw_periods + loan.promo_periods + 1))]));
+            }
+            text.push_str(&trf("\nTotal interest over {} payments: {}", &[&schedule.len(), &format_amount(total_interest)]));
+            summary.set_text(&text);
+
+            first_year.clear();
+            for inst in schedule.iter().take(12) {
+                first_year.insert_with_values(None, &[0, 1, 2, 3, 4], &[&format_date(loan.due_date(inst.period)),
+                                                                        &format!("{:.2}", inst.payment), &format!("{:.2}", inst.principal),
+                                                                        &format!("{:.2}", inst.interest), &format!("{:.2}", inst.balance)]);
+            }
+        }
+    }
+}
+
+fn calendar_date(calendar: &Calendar) -> time::Timespec {
+    let (year, month, day) = calendar.get_date();
+    time::strptime(&format!("{}-{:02}-{:02}", year, month + 1, day), "%F").unwrap().to_timespec()
+}
+
+// Asks for the details of a payment on `loan`, showing how it splits between
+// interest and principal. Returns (amount, extra, date), or None if cancelled.
+fn payment_dialog(parent: &Window, loan: Loan) -> Option<(f64, bool, time::Timespec)> {
+    const OK: i32 = 1;
+    const CANCEL: i32 = 0;
+
+    let title = trf("Payment on {}", &[&loan.name]);
+    let dialog = Dialog::new_with_buttons(Some(&title), Some(parent), gtk::DIALOG_MODAL, &[("_Cancel", CANCEL), ("_Record", OK)]);
+    dialog.set_default_response(OK);
+
+    let today = amortization::today();
+    let amount = SpinButton::new_with_range(0f64, 100000000f64, 10f64);
+    amount.set_digits(2);
+    amount.set_value(loan.amount_due(today));
+    let extra = CheckButton::new_with_label(&tr("Extra payment (all principal)"));
+    let date = Calendar::new();
+    let split = Label::new(None);
+    let error = Label::new(None);
+
+    let grid = form_grid(&[("Amount", amount.clone().upcast()),
+                           ("", extra.clone().upcast()),
+                           ("Date", date.clone().upcast())]);
+    grid.attach(&split, 0, 3, 2, 1);
+    grid.attach(&error, 0, 4, 2, 1);
+    dialog.get_content_area().add(&grid);
+
+    let loan = Rc::new(loan);
+    let preview = {
+        let loan = loan.clone();
+        let (amount, extra, date, split) = (amount.clone(), extra.clone(), date.clone(), split.clone());
+        Rc::new(move || {
+            let (interest, principal) = loan.split_payment(amount.get_value(), extra.get_active(), calendar_date(&date));
+            split.set_text(&trf("Interest: {}, Principal: {}, Balance after: {}", &[&format_amount(interest), &format_amount(principal), &format_amount(loan.balance - principal)]));
+        })
+    };
+    preview();
+    {
+        let preview = preview.clone();
+        amount.get_adjustment().connect_value_changed(move |_| preview());
+    }
+    {
+        let preview = preview.clone();
+        extra.connect_toggled(move |_| preview());
+    }
+    date.connect_day_selected(move |_| preview());
+    dialog.show_all();
+
+    loop {
+        if dialog.run() != OK {
+            dialog.destroy();
+            return None;
+        }
+
+        let when = calendar_date(&date);
+        let due = loan.amount_due(when);
+        if !extra.get_active() && amount.get_value() < due {
+            error.set_text(&trf("At least {} is due; mark it as an extra payment to pay less", &[&format_amount(due)]));
+            continue;
+        }
+        let payment = (amount.get_value(), extra.get_active(), when);
+        dialog.destroy();
+        return Some(payment);
+    }
+}
+
+// How many databases the Recent menu remembers.
+const MAX_RECENT: usize = 10;
+
+// Directory for the GUI's own files, e.g. ~/.config/amortization.
+fn config_dir() -> Option<PathBuf> {
+    let config = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => match env::var_os("HOME") {
+            Some(home) => Path::new(&home).join(".config"),
+            None => return None,
+        },
+    };
+    Some(config.join("amortization"))
+}
+
+// Preferences, saved as KEY=VALUE lines in the config directory.
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    // open the most recent database on startup
+    reopen_last: bool,
+    // how far ahead a payment is due before it shows up as a reminder
+    reminder_days: i64,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings{
+            reopen_last: true,
+            reminder_days: 7,
+        }
+    }
+}
+
+impl Settings {
+    fn load() -> Settings {
+        let mut settings = Settings::default();
+        let mut contents = String::new();
+        match config_dir().map(|dir| File::open(dir.join("settings")).and_then(|mut f| f.read_to_string(&mut contents))) {
+            Some(Ok(_)) => (),
+            _ => return settings,
+        };
+        for line in contents.lines() {
+            let mut parts = line.splitn(2, '=');
+            match (parts.next().map(|key| key.trim()), parts.next().map(|value| value.trim())) {
+                (Some("reopen_last"), Some(value)) => settings.reopen_last = value == "true",
+                (Some("reminder_days"), Some(value)) => settings.reminder_days = value.parse().unwrap_or(settings.reminder_days),
+                _ => (),
+            }
+        }
+        settings
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let dir = match config_dir() {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+        try!(fs::create_dir_all(&dir));
+        let mut f = try!(File::create(dir.join("settings")));
+        try!(writeln!(f, "reopen_last={}", self.reopen_last));
+        writeln!(f, "reminder_days={}", self.reminder_days)
+    }
+}
+
+// Lets the user change their preferences, saving them if they click OK.
+fn preferences_dialog(parent: &Window, settings: &mut Settings) {
+    const OK: i32 = 1;
+    const CANCEL: i32 = 0;
+
+    let dialog = Dialog::new_with_buttons(Some(&tr("Preferences")), Some(parent), gtk::DIALOG_MODAL, &[("_Cancel", CANCEL), ("_OK", OK)]);
+    dialog.set_default_response(OK);
+    let reopen_last = CheckButton::new_with_label(&tr("Open the last database on startup"));
+    reopen_last.set_active(settings.reopen_last);
+    let reminder_days = SpinButton::new_with_range(0f64, 60f64, 1f64);
+    reminder_days.set_value(settings.reminder_days as f64);
+    let grid = form_grid(&[("", reopen_last.clone().upcast()),
+                           ("Remind me of payments due within (days)", reminder_days.clone().upcast())]);
+    dialog.get_content_area().add(&grid);
+    dialog.show_all();
+
+    if dialog.run() == OK {
+        settings.reopen_last = reopen_last.get_active();
+        settings.reminder_days = reminder_days.get_value_as_int() as i64;
+        if let Err(err) = settings.save() {
+            show_error(parent, &trf("Couldn't save preferences: {}", &[&err]));
+        }
+    }
+    dialog.destroy();
+}
+
+fn shortcuts_dialog(parent: &Window) {
+    let message = tr("Ctrl+N\tNew database\nCtrl+O\tOpen database\nCtrl+P\tPrint the selected loan\nCtrl+Z\tUndo\nShift+Ctrl+Z\tRedo\nShift+Ctrl+C\tCopy the selected loan's schedule\nCtrl+Q\tQuit\n\
+                   Enter\tRecord a payment on the selected loan");
+    let dialog = MessageDialog::new(Some(parent), gtk::DIALOG_MODAL, gtk::MessageType::Info, gtk::ButtonsType::Close, &message);
+    dialog.set_title(&tr("Keyboard Shortcuts"));
+    dialog.run();
+    dialog.destroy();
+}
+
+fn about_dialog(parent: &Window) {
+    let dialog = AboutDialog::new();
+    dialog.set_transient_for(Some(parent));
+    dialog.set_program_name(&tr("Amortization Calculator"));
+    dialog.set_version(Some(env!("CARGO_PKG_VERSION")));
+    dialog.set_comments(Some(&tr("Tracks loans, payments and what-if scenarios")));
+    dialog.set_authors(&["T. Jameson Little <t.jameson.little@gmail.com>"]);
+    dialog.set_license_type(gtk::License::Bsd);
+    dialog.run();
+    dialog.destroy();
+}
+
+// File listing recently opened databases, most recent first.
+fn recent_list_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("recent"))
+}
+
+// Recently opened databases that still exist, most recent first.
+fn load_recent() -> Vec<PathBuf> {
+    let mut contents = String::new();
+    match recent_list_path().map(|path| File::open(path).and_then(|mut f| f.read_to_string(&mut contents))) {
+        Some(Ok(_)) => (),
+        _ => return Vec::new(),
+    };
+    contents.lines().map(PathBuf::from).filter(|path| path.exists()).collect()
+}
+
+fn remember_recent(db: &Path) -> io::Result<()> {
+    let db = fs::canonicalize(db).unwrap_or(db.to_path_buf());
+    let mut recent = load_recent();
+    recent.retain(|path| *path != db);
+    recent.insert(0, db);
+    recent.truncate(MAX_RECENT);
+
+    let path = match recent_list_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    if let Some(dir) = path.parent() {
+        try!(fs::create_dir_all(dir));
+    }
+    let mut f = try!(File::create(&path));
+    for db in recent.iter() {
+        try!(writeln!(f, "{}", db.display()));
+    }
+    Ok(())
+}
+
+// Fills the Recent submenu with the databases opened lately. Each entry
+// activates win.open-recent with the database's path.
+fn fill_recent_menu(menu: &gio::Menu) {
+    menu.remove_all();
+    let recent = load_recent();
+    if recent.is_empty() {
+        menu.append(Some(&tr("No recent databases")), None);
+    }
+    for path in recent {
+        let path = path.display().to_string();
+        let item = gio::MenuItem::new(Some(&path), None);
+        item.set_action_and_target_value(Some("win.open-recent"), Some(&path.to_variant()));
+        menu.append_item(&item);
+    }
+}
+
+// gtk 0.1 doesn't bind gtk_drag_dest_set, so `widget` is made a drop target
+// for files by hand.
+fn accept_file_drops(widget: &gtk::Widget) {
+    unsafe {
+        gtk_sys::gtk_drag_dest_set(widget.to_glib_none().0, gtk_sys::GTK_DEST_DEFAULT_ALL, ptr::null_mut(), 0, gdk_sys::GDK_ACTION_COPY);
+    }
+    widget.drag_dest_add_uri_targets();
+}
+
+// Local path for a file:// URI, undoing percent-encoding.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    if !uri.starts_with("file://") {
+        return None;
+    }
+    // skip the host, usually empty or localhost
+    let path = &uri["file://".len()..];
+    let path = match path.find('/') {
+        Some(start) => &path[start..],
+        None => return None,
+    };
+
+    let mut bytes = Vec::new();
+    let mut chars = path.bytes();
+    while let Some(b) = chars.next() {
+        if b == b'%' {
+            let hex: Vec<u8> = chars.by_ref().take(2).collect();
+            match String::from_utf8(hex).ok().and_then(|hex| u8::from_str_radix(&hex, 16).ok()) {
+                Some(b) => bytes.push(b),
+                None => return None,
+            }
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+// Records the payments in a CSV file against `name`, oldest first, after
+// showing what's about to be imported. Each is recorded as a regular payment
+// or extra principal as import::Classifier guesses, unless the file says.
+// Regular payments smaller than the amount due are skipped rather than
+// recorded as partial payments.
+fn import_payments(loans: &LoanList, name: &str, csv: &Path) {
+    let window = &loans.window;
+    let db = match *loans.db.borrow() {
+        Some(ref db) => db.clone(),
+        None => return,
+    };
+    let mut contents = String::new();
+    let rows = File::open(csv).and_then(|mut f| f.read_to_string(&mut contents)).map_err(|err| err.to_string())
+        .and_then(|_| import::parse_payments(&contents));
+    let mut rows = match rows {
+        Ok(rows) => rows,
+        Err(err) => {
+            show_error(window, &trf("Couldn't read payments from {}: {}", &[&csv.display(), &err]));
+            return;
+        },
+    };
+    rows.sort_by_key(|row| row.date);
+    if rows.is_empty() {
+        show_error(window, &trf("{} doesn't contain any payments.", &[&csv.display()]));
+        return;
+    }
+
+    let total: f64 = rows.iter().map(|row| row.amount).sum();
+    let message = trf("Import {} payments totalling {} into {}?", &[&rows.len(), &format_amount(total), &name]);
+    let dialog = MessageDialog::new(Some(window), gtk::DIALOG_MODAL, gtk::MessageType::Question, gtk::ButtonsType::OkCancel, &message);
+    let res = dialog.run();
+    dialog.destroy();
+    if res != gtk::ResponseType::Ok.into() {
+        return;
+    }
+
+    let classifier = amortization::load_loan(&db, name).and_then(|loan| {
+        amortization::load_transactions(&db, Some(name), None).map(|transactions| import::Classifier::for_loan(&loan, &transactions))
+    });
+    let mut classifier = match classifier {
+        Ok(classifier) => classifier,
+        Err(err) => {
+            show_error(window, &trf("Couldn't load the payments already made on {}: {}", &[&name, &err]));
+            return;
+        },
+    };
+    let mut skipped = 0;
+    let mut imported = Vec::new();
+    for row in rows.iter() {
+        let res = amortization::load_loan(&db, name).and_then(|loan| {
+            let due = loan.total_due(row.date);
+            let extra = classifier.classify(row, loan.period_at(row.date), due) == PaymentKind::Extra;
+            if !extra && row.amount < due {
+                skipped += 1;
+                return Ok(None);
+            }
+            amortization::commit_transaction(&db, name.to_string(), row.amount, extra, row.date, row.method.clone()).map(Some)
+        });
+        match res {
+            Ok(payment) => imported.extend(payment),
+            Err(err) => {
+                show_error(window, &trf("Couldn't import the payment from {}: {}", &[&format_date(row.date), &err]));
+                break;
+            },
+        };
+    }
+    // the whole import is undone in one go
+    if !imported.is_empty() {
+        loans.did(Change::Payments(imported));
+    }
+    loans.refresh();
+    if skipped > 0 {
+        show_error(window, &trf("{} regular payments were less than the amount due and weren't imported.", &[&skipped]));
+    }
+}
+
+// The loan's contractual schedule, from the database's cache when it can be.
+fn contract_schedule(db: &Path, loan: &Loan) -> Vec<calc::Installment> {
+    amortization::cached_contract_schedule(db, loan).unwrap_or_else(|_| loan.contract_schedule().collect())
+}
+
+// A line of the printed schedule: a payment, or the totals for a year.
+enum PrintLine {
+    Payment(time::Tm, calc::Installment),
+    Year(i32, f64, f64, f64),
+}
+
+const PRINT_LINES_PER_PAGE: usize = 45;
+
+// Prints the loan's terms and full schedule, subtotalled by year. gtk 0.1
+// doesn't bind GtkPrintOperation, so it's driven through gtk-sys.
+fn print_schedule(parent: &Window, loan: &Loan, schedule: &[calc::Installment]) {
+    let mut lines = Vec::new();
+    let mut year = (0, 0f64, 0f64, 0f64);
+    for inst in schedule {
+        let date = time::at_utc(loan.due_date(inst.period));
+        if year.0 != 0 && year.0 != date.tm_year + 1900 {
+            lines.push(PrintLine::Year(year.0, year.1, year.2, year.3));
+            year = (0, 0f64, 0f64, 0f64);
+        }
+        year = (date.tm_year + 1900, year.1 + inst.payment, year.2 + inst.principal, year.3 + inst.interest);
+        lines.push(PrintLine::Payment(date, *inst));
+    }
+    if year.0 != 0 {
+        lines.push(PrintLine::Year(year.0, year.1, year.2, year.3));
+    }
+    let pages = (lines.len() + PRINT_LINES_PER_PAGE - 1) / PRINT_LINES_PER_PAGE;
+    let header = trf("{}: {} at {}% over {} months, {} a month", &[&loan.name, &format_amount(loan.principal), &format!("{:.3}", loan.apr), &loan.periods,
+                                                                 &format_amount(loan.payment)]);
+
+    let draw = move |cr: &cairo::Context, width: f64, page: usize| {
+        let columns = [0f64, 0.25, 0.45, 0.65, 0.85];
+        let line = |y: f64, texts: &[String]| {
+            for (text, &x) in texts.iter().zip(columns.iter()) {
+                cr.move_to(x * width, y);
+                cr.show_text(text);
+            }
+        };
+
+        cr.set_source_rgb(0f64, 0f64, 0f64);
+        cr.set_font_size(12f64);
+        cr.move_to(0f64, 14f64);
+        cr.show_text(&header);
+        cr.set_font_size(9f64);
+        cr.move_to(0f64, 28f64);
+        cr.show_text(&trf("Page {} of {}", &[&(page + 1), &pages]));
+        line(48f64, &[tr("Date"), tr("Payment"), tr("Principal"), tr("Interest"), tr("Balance")]);
+
+        for (i, print_line) in lines.iter().skip(page * PRINT_LINES_PER_PAGE).take(PRINT_LINES_PER_PAGE).enumerate() {
+            let y = 64f64 + 13f64 * i as f64;
+            match *print_line {
+                PrintLine::Payment(ref date, ref inst) => {
+                    line(y, &[format_date(date.to_timespec()), format_amount(inst.payment), format_amount(inst.principal),
+                              format_amount(inst.interest), format_amount(inst.balance)]);
+                },
+                PrintLine::Year(year, payment, principal, interest) => {
+                    cr.select_font_face("sans-serif", cairo::enums::FontSlant::Normal, cairo::enums::FontWeight::Bold);
+                    line(y, &[trf("{} total", &[&year]), format_amount(payment), format_amount(principal), format_amount(interest)]);
+                    cr.select_font_face("sans-serif", cairo::enums::FontSlant::Normal, cairo::enums::FontWeight::Normal);
+                },
+            }
+        }
+    };
+
+    unsafe extern "C" fn draw_page(_: *mut gtk_sys::GtkPrintOperation, context: *mut gtk_sys::GtkPrintContext, page: i32, data: *mut c_void) {
+        let draw: &Box<dyn Fn(&cairo::Context, f64, usize)> = &*(data as *const Box<dyn Fn(&cairo::Context, f64, usize)>);
+        let cr: cairo::Context = from_glib_none(gtk_sys::gtk_print_context_get_cairo_context(context));
+        draw(&cr, gtk_sys::gtk_print_context_get_width(context), page as usize);
+    }
+
+    let res = unsafe {
+        let op = gtk_sys::gtk_print_operation_new();
+        gtk_sys::gtk_print_operation_set_n_pages(op, pages as i32);
+        let draw: Box<Box<dyn Fn(&cairo::Context, f64, usize)>> = Box::new(Box::new(draw));
+        glib::signal::connect(op as *mut _, "draw-page", Some(mem::transmute(draw_page as *const ())), Box::into_raw(draw) as *mut _);
+        let res = gtk_sys::gtk_print_operation_run(op, gtk_sys::GTK_PRINT_OPERATION_ACTION_PRINT_DIALOG, parent.to_glib_none().0, ptr::null_mut());
+        // drops the operation's reference
+        let _: glib::Object = from_glib_full(op as *mut _);
+        res
+    };
+    if res == gtk_sys::GTK_PRINT_OPERATION_RESULT_ERROR {
+        show_error(parent, &trf("Couldn't print the schedule for {}.", &[&loan.name]));
+    }
+}
+
+// Tells the user something went wrong and waits for them to dismiss it.
+fn show_error(parent: &Window, message: &str) {
+    let dialog = MessageDialog::new(Some(parent), gtk::DIALOG_MODAL, gtk::MessageType::Error, gtk::ButtonsType::Close, message);
+    dialog.run();
+    dialog.destroy();
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+const DB_EXTENSIONS: &[&str] = &["db", "sqlite", "sqlite3"];
+
+fn is_db_file(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()).map_or(false, |ext| DB_EXTENSIONS.contains(&ext))
+}
+
+// Limits a file chooser to database files.
+fn db_filter() -> gtk::FileFilter {
+    let filter = gtk::FileFilter::new();
+    filter.set_name(Some(&tr("Loan databases")));
+    for ext in DB_EXTENSIONS {
+        filter.add_pattern(&format!("*.{}", ext));
+    }
+    filter
+}
+
+// Opens an existing database after checking it's one of ours, offering to
+// set it up if it's an empty SQLite file.
+fn open_db(loans: &LoanList, db: PathBuf) {
+    let window = &loans.window;
+    match amortization::has_schema(&db) {
+        Ok(true) => loans.open(db),
+        Ok(false) => {
+            let message = trf("{} doesn't contain any loan data. Set it up as a loan database?", &[&db.display()]);
+            let dialog = MessageDialog::new(Some(window), gtk::DIALOG_MODAL, gtk::MessageType::Question, gtk::ButtonsType::YesNo, &message);
+            let res = dialog.run();
+            dialog.destroy();
+            if res != gtk::ResponseType::Yes.into() {
+                return;
+            }
+            match amortization::init_db(&db) {
+                Ok(_) => loans.open(db),
+                Err(err) => show_error(window, &trf("Couldn't set up {}: {}", &[&db.display(), &err])),
+            };
+        },
+        Err(err) => show_error(window, &trf("{} isn't a loan database: {}", &[&db.display(), &err])),
+    };
+}
 
 // Opens a file picker and returns the selected file.
 fn get_db_file(parent: &Window) -> Option<PathBuf> {
     const OK: i32 = 1;
     const CANCEL: i32 = 0;
 
-    let dialog: FileChooserDialog = FileChooserDialog::new(Some("Open Database"), Some(parent), FileChooserAction::Open);
+    let dialog: FileChooserDialog = FileChooserDialog::new(Some(&tr("Open Database")), Some(parent), FileChooserAction::Open);
     // TODO: figure out how to use ButtonsType enum
     dialog.add_button("_OK", OK);
     dialog.add_button("_Cancel", CANCEL);
+    dialog.add_filter(&db_filter());
 
     let res = dialog.run();
-    println!("Response: {}", res);
 
     let filename = dialog.get_filename();
     dialog.destroy();
@@ -32,26 +1622,177 @@ fn get_db_file(parent: &Window) -> Option<PathBuf> {
     }
 }
 
+// Asks where to save an export, suggesting `name`.
+fn export_file(parent: &Window, name: &str) -> Option<PathBuf> {
+    const OK: i32 = 1;
+    const CANCEL: i32 = 0;
+
+    let dialog: FileChooserDialog = FileChooserDialog::new(Some(&tr("Export")), Some(parent), FileChooserAction::Save);
+    dialog.add_button("_Cancel", CANCEL);
+    dialog.add_button("_Save", OK);
+    dialog.set_do_overwrite_confirmation(true);
+    dialog.set_current_name(name);
+
+    let filter = gtk::FileFilter::new();
+    filter.set_name(Some("CSV files"));
+    filter.add_pattern("*.csv");
+    dialog.add_filter(&filter);
+
+    let res = dialog.run();
+    let filename = dialog.get_filename();
+    dialog.destroy();
+
+    if res == OK {
+        filename
+    } else {
+        None
+    }
+}
+
+// Exports the selected loan's schedule, or payment history if `history` is
+// set, to a CSV file the user picks.
+fn export_selected(loans: &LoanList, history: bool) {
+    let window = &loans.window;
+    let (db, name) = match (loans.db.borrow().clone(), loans.selected()) {
+        (Some(db), Some(name)) => (db, name),
+        _ => {
+            show_error(window, &tr("Select the loan to export."));
+            return;
+        },
+    };
+    let suggested = format!("{}-{}.csv", name, if history { "history" } else { "schedule" });
+    let path = match export_file(window, &suggested) {
+        Some(path) => path,
+        None => return,
+    };
+
+    let res = if history {
+        amortization::load_transactions(&db, Some(&name), None).map_err(|err| err.to_string()).and_then(|transactions| {
+            File::create(&path).and_then(|mut f| export::history_csv(&mut f, &transactions)).map_err(|err| err.to_string())
+        })
+    } else {
+        amortization::load_loan(&db, &name).map_err(|err| err.to_string()).and_then(|loan| {
+            File::create(&path).and_then(|mut f| export::schedule_csv(&mut f, &loan)).map_err(|err| err.to_string())
+        })
+    };
+
+    match res {
+        Ok(rows) => {
+            let message = trf("Exported {} rows to {}", &[&rows, &path.display()]);
+            let dialog = MessageDialog::new(Some(window), gtk::DIALOG_MODAL, gtk::MessageType::Info, gtk::ButtonsType::Close, &message);
+            dialog.run();
+            dialog.destroy();
+        },
+        Err(err) => show_error(window, &trf("Couldn't export to {}: {}", &[&path.display(), &err])),
+    };
+}
+
+// Shows the selected loan's closing summary, offering to save it as text.
+fn show_closing(loans: &LoanList) {
+    const SAVE: i32 = 1;
+    let window = &loans.window;
+    let (db, name) = match (loans.db.borrow().clone(), loans.selected()) {
+        (Some(db), Some(name)) => (db, name),
+        _ => {
+            show_error(window, &tr("Select the loan to show the closing summary for."));
+            return;
+        },
+    };
+    let closing = amortization::load_loan(&db, &name).and_then(|loan| amortization::closing_summary(&db, &name).map(|summary| (loan, summary)));
+    let (loan, summary) = match closing {
+        Ok((loan, Some(summary))) => (loan, summary),
+        Ok((loan, None)) => {
+            show_error(window, &trf("{} isn't paid off yet; {} remains.", &[&loan.name, &loan.money(loan.balance)]));
+            return;
+        },
+        Err(err) => {
+            show_error(window, &trf("Couldn't load the closing summary for {}: {}", &[&name, &err]));
+            return;
+        },
+    };
+
+    let mut text = Vec::new();
+    export::closing_text(&mut text, &loan, &summary).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    let dialog = MessageDialog::new(Some(window), gtk::DIALOG_MODAL, gtk::MessageType::Info, gtk::ButtonsType::Close, &text);
+    dialog.add_button(&tr("_Save..."), SAVE);
+    let res = dialog.run();
+    dialog.destroy();
+    if res != SAVE {
+        return;
+    }
+    let path = match export_file(window, &format!("{}-closing.txt", name)) {
+        Some(path) => path,
+        None => return,
+    };
+    if let Err(err) = File::create(&path).and_then(|mut f| f.write_all(text.as_bytes())) {
+        show_error(window, &trf("Couldn't export to {}: {}", &[&path.display(), &err]));
+    }
+}
+
+// Puts the selected loan's schedule, or payment history if `history` is set,
+// on the clipboard as tab-separated text, which spreadsheets paste as columns.
+fn copy_selected(loans: &LoanList, history: bool) {
+    let window = &loans.window;
+    let (db, name) = match (loans.db.borrow().clone(), loans.selected()) {
+        (Some(db), Some(name)) => (db, name),
+        _ => {
+            show_error(window, &tr("Select the loan to copy."));
+            return;
+        },
+    };
+
+    let mut text = Vec::new();
+    let res = if history {
+        amortization::load_transactions(&db, Some(&name), None).map_err(|err| err.to_string()).and_then(|transactions| {
+            export::history_tsv(&mut text, &transactions).map_err(|err| err.to_string())
+        })
+    } else {
+        amortization::load_loan(&db, &name).map_err(|err| err.to_string()).and_then(|loan| {
+            export::schedule_tsv(&mut text, &loan).map_err(|err| err.to_string())
+        })
+    };
+
+    match res {
+        Ok(_) => clipboard(&window.clone().upcast()).set_text(&String::from_utf8_lossy(&text)),
+        Err(err) => show_error(window, &trf("Couldn't copy {}: {}", &[&name, &err])),
+    };
+}
+
+// gtk 0.1 can't look up a clipboard, so the widget's is fetched by hand.
+fn clipboard(widget: &gtk::Widget) -> gtk::Clipboard {
+    let selection = gdk::Atom::intern("CLIPBOARD");
+    unsafe { from_glib_none(gtk_sys::gtk_widget_get_clipboard(widget.to_glib_none().0, selection.to_glib_none().0)) }
+}
+
 fn new_db_file(parent: &Window) -> Option<PathBuf> {
     const OK: i32 = 1;
     const CANCEL: i32 = 0;
 
-    let dialog: FileChooserDialog = FileChooserDialog::new(Some("Create Database"), Some(parent), FileChooserAction::Save);
+    let dialog: FileChooserDialog = FileChooserDialog::new(Some(&tr("Create Database")), Some(parent), FileChooserAction::Save);
     // TODO: figure out how to use ButtonsType enum
     dialog.add_button("_OK", OK);
     dialog.add_button("_Cancel", CANCEL);
+    dialog.add_filter(&db_filter());
+    dialog.set_do_overwrite_confirmation(true);
 
     let res = dialog.run();
-    println!("Response: {}", res);
 
     let filename = dialog.get_filename();
     dialog.destroy();
 
     if res == OK {
-        if let Some(db_path) = filename {
-            let p = db_path.clone();
-            amortization::init_db(p.as_path());
-            Some(db_path)
+        if let Some(mut db_path) = filename {
+            if !is_db_file(&db_path) {
+                db_path.set_extension("db");
+            }
+            match amortization::init_db(&db_path) {
+                Ok(_) => Some(db_path),
+                Err(err) => {
+                    show_error(parent, &trf("Couldn't create {}: {}", &[&db_path.display(), &err]));
+                    None
+                },
+            }
         } else {
             filename
         }
@@ -60,86 +1801,455 @@ fn new_db_file(parent: &Window) -> Option<PathBuf> {
     }
 }
 
-fn main() {
-    App::new("Amortization Calculator")
-                          .version("0.1.0")
-                          .author("T. Jameson Little <t.jameson.little@gmail.com>")
-                          .about("Calculates an amortization table")
-                          .get_matches();
+const APP_ID: &str = "com.github.beatgammit.amortization";
 
-    if gtk::init().is_err() {
-        println!("Failed to initialize GTK.");
-        return;
-    }
+// Keyboard shortcuts, by the action they trigger.
+const ACCELS: &[(&str, &str)] = &[
+    ("win.new", "<Primary>n"),
+    ("win.open", "<Primary>o"),
+    ("win.print", "<Primary>p"),
+    ("win.undo", "<Primary>z"),
+    ("win.redo", "<Primary><Shift>z"),
+    ("win.copy-schedule", "<Primary><Shift>c"),
+    ("app.quit", "<Primary>q"),
+];
 
-    let window = Window::new(WindowType::Toplevel);
-    window.set_title("Amortization Calculator");
-    window.set_default_size(350, 70);
+// Adds an action to `map` that runs `activate`. Menus, buttons and
+// shortcuts refer to it as app.NAME or win.NAME.
+fn add_action<M: IsA<gio::ActionMap>, F: Fn() + 'static>(map: &M, name: &str, activate: F) -> gio::SimpleAction {
+    let action = gio::SimpleAction::new(name, None);
+    action.connect_activate(move |_, _| activate());
+    map.add_action(&action);
+    action
+}
 
-    let v_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+// The menu bar, built from the actions in build_window.
+fn menubar(recent: &gio::Menu) -> gio::Menu {
+    let export = gio::Menu::new();
+    export.append(Some(&tr("Schedule as CSV...")), Some("win.export-schedule"));
+    export.append(Some(&tr("Payment History as CSV...")), Some("win.export-history"));
 
-    // menu
+    let databases = gio::Menu::new();
+    databases.append(Some(&tr("New")), Some("win.new"));
+    databases.append(Some(&tr("Open")), Some("win.open"));
+    databases.append_submenu(Some(&tr("Recent")), recent);
+    let output = gio::Menu::new();
+    output.append_submenu(Some(&tr("Export")), &export);
+    output.append(Some(&tr("Print...")), Some("win.print"));
+    output.append(Some(&tr("Closing Summary...")), Some("win.closing-summary"));
+    let quit = gio::Menu::new();
+    quit.append(Some(&tr("Quit")), Some("app.quit"));
+    let file = gio::Menu::new();
+    file.append_section(None, &databases);
+    file.append_section(None, &output);
+    file.append_section(None, &quit);
 
-    let menubar = MenuBar::new();
+    let history = gio::Menu::new();
+    history.append(Some(&tr("Undo")), Some("win.undo"));
+    history.append(Some(&tr("Redo")), Some("win.redo"));
+    let copy = gio::Menu::new();
+    copy.append(Some(&tr("Copy Schedule")), Some("win.copy-schedule"));
+    copy.append(Some(&tr("Copy Payment History")), Some("win.copy-history"));
+    let preferences = gio::Menu::new();
+    preferences.append(Some(&tr("Preferences")), Some("win.preferences"));
+    let edit = gio::Menu::new();
+    edit.append_section(None, &history);
+    edit.append_section(None, &copy);
+    edit.append_section(None, &preferences);
 
-    let file = MenuItem::new_with_label("File");
+    let help = gio::Menu::new();
+    help.append(Some(&tr("Keyboard Shortcuts")), Some("win.shortcuts"));
+    help.append(Some(&tr("About")), Some("win.about"));
 
-    let file_menu = gtk::Menu::new();
+    let menubar = gio::Menu::new();
+    menubar.append_submenu(Some(&tr("_File")), &file);
+    menubar.append_submenu(Some(&tr("_Edit")), &edit);
+    menubar.append_submenu(Some(&tr("_Help")), &help);
+    menubar
+}
 
-    let new = MenuItem::new_with_label("New");
-    let open = MenuItem::new_with_label("Open");
-    let quit = MenuItem::new_with_label("Quit");
+// Builds the main window. The widgets only show what the library reports
+// and hand changes back to it; nothing about loans is kept here.
+fn build_window(app: &gtk::Application) {
+    let app_window = ApplicationWindow::new(app);
+    let window: Window = app_window.clone().upcast();
+    window.set_title(&tr("Amortization Calculator"));
+    window.set_default_size(600, 400);
+
+    let loans = LoanList::new(&window);
+    window.set_titlebar(Some(&loans.header));
+    app.set_menubar(Some(&menubar(&loans.recent)));
+
+    // actions
 
     {
         let w = window.clone();
-        new.connect_activate(move|_| {
-            println!("New thing");
-            let db_file = new_db_file(&w);
-            match db_file {
-                Some(file) => println!("File path: {}", file.display()),
-                None => println!("Nada"),
+        let loans = loans.clone();
+        add_action(&app_window, "new", move || {
+            if let Some(file) = new_db_file(&w) {
+                loans.open(file);
+            }
+        });
+    }
+    {
+        let w = window.clone();
+        let loans = loans.clone();
+        add_action(&app_window, "open", move || {
+            if let Some(file) = get_db_file(&w) {
+                open_db(&loans, file);
+            }
+        });
+    }
+    {
+        let loans = loans.clone();
+        let open_recent = gio::SimpleAction::new("open-recent", Some(&String::static_variant_type()));
+        open_recent.connect_activate(move |_, path| {
+            if let Some(path) = path.as_ref().and_then(|path| path.get_str()) {
+                open_db(&loans, PathBuf::from(path));
+            }
+        });
+        app_window.add_action(&open_recent);
+    }
+    {
+        let loans = loans.clone();
+        add_action(&app_window, "export-schedule", move || export_selected(&loans, false));
+    }
+    {
+        let loans = loans.clone();
+        add_action(&app_window, "export-history", move || export_selected(&loans, true));
+    }
+    {
+        let loans = loans.clone();
+        add_action(&app_window, "closing-summary", move || show_closing(&loans));
+    }
+    {
+        let loans = loans.clone();
+        add_action(&app_window, "copy-schedule", move || copy_selected(&loans, false));
+    }
+    {
+        let loans = loans.clone();
+        add_action(&app_window, "copy-history", move || copy_selected(&loans, true));
+    }
+    {
+        let w = window.clone();
+        let loans = loans.clone();
+        add_action(&app_window, "print", move || {
+            let (db, loan) = match (loans.db.borrow().as_ref(), loans.selected()) {
+                (Some(db), Some(name)) => (db.clone(), amortization::load_loan(db, &name)),
+                _ => {
+                    show_error(&w, &tr("Select the loan to print."));
+                    return;
+                },
+            };
+            match loan {
+                Ok(loan) => {
+                    let schedule = contract_schedule(&db, &loan);
+                    print_schedule(&w, &loan, &schedule);
+                },
+                Err(err) => show_error(&w, &trf("Couldn't load the loan: {}", &[&err])),
+            };
+        });
+    }
+    for &(action, redo) in [(&loans.undo_action, false), (&loans.redo_action, true)].iter() {
+        let loans = loans.clone();
+        action.connect_activate(move |_, _| loans.step(redo));
+        app_window.add_action(action);
+    }
+    {
+        let w = window.clone();
+        let loans = loans.clone();
+        add_action(&app_window, "preferences", move || {
+            let mut settings = *loans.settings.borrow();
+            preferences_dialog(&w, &mut settings);
+            *loans.settings.borrow_mut() = settings;
+            loans.refresh();
+        });
+    }
+    {
+        let w = window.clone();
+        add_action(&app_window, "shortcuts", move || shortcuts_dialog(&w));
+    }
+    {
+        let w = window.clone();
+        add_action(&app_window, "about", move || about_dialog(&w));
+    }
+    {
+        let w = window.clone();
+        let loans = loans.clone();
+        add_action(&app_window, "new-loan", move || {
+            let db = match *loans.db.borrow() {
+                Some(ref db) => db.clone(),
+                None => {
+                    show_error(&w, &tr("Open or create a database before adding loans."));
+                    return;
+                }
             };
+            if let Some(loan) = new_loan_dialog(&w, &db) {
+                match amortization::create_loan(&db, loan.clone()) {
+                    Ok(_) => {
+                        loans.did(Change::AddLoan(loan, Vec::new()));
+                        loans.refresh();
+                    },
+                    Err(err) => show_error(&w, &trf("Couldn't add the loan: {}", &[&err])),
+                };
+            }
         });
     }
     {
         let w = window.clone();
-        open.connect_activate(move |_| {
-            let db_file = get_db_file(&w);
-            match db_file {
-                Some(file) => println!("File path: {}", file.display()),
-                None => println!("Nada"),
+        let loans = loans.clone();
+        add_action(&app_window, "new-loan-wizard", move || {
+            let db = match *loans.db.borrow() {
+                Some(ref db) => db.clone(),
+                None => {
+                    show_error(&w, &tr("Open or create a database before adding loans."));
+                    return;
+                }
             };
+            if let Some((loan, fees)) = loan_wizard(&w, &db) {
+                let change = Change::AddLoan(loan, fees);
+                match change.apply(&db) {
+                    Ok(_) => {
+                        loans.did(change);
+                        loans.refresh();
+                    },
+                    Err(err) => show_error(&w, &trf("Couldn't add the loan: {}", &[&err])),
+                };
+            }
+        });
+    }
+    {
+        let w = window.clone();
+        let loans = loans.clone();
+        // opens the payment dialog for the selected loan
+        add_action(&app_window, "record-payment", move || {
+            let db = match *loans.db.borrow() {
+                Some(ref db) => db.clone(),
+                None => {
+                    show_error(&w, &tr("Open a database before recording payments."));
+                    return;
+                }
+            };
+            let name = match loans.selected() {
+                Some(name) => name,
+                None => {
+                    show_error(&w, &tr("Select the loan the payment is for."));
+                    return;
+                }
+            };
+            let loan = match amortization::load_loan(&db, &name) {
+                Ok(loan) => loan,
+                Err(err) => {
+                    show_error(&w, &trf("Couldn't load loan {}: {}", &[&name, &err]));
+                    return;
+                }
+            };
+            if let Some((amount, extra, date)) = payment_dialog(&w, loan) {
+                match amortization::commit_transaction(&db, name, amount, extra, date, None) {
+                    Ok(payment) => {
+                        loans.did(Change::Payments(vec![payment]));
+                        loans.refresh();
+                    },
+                    Err(err) => show_error(&w, &trf("Couldn't record the payment: {}", &[&err])),
+                };
+            }
         });
     }
-    quit.connect_activate(|_| {
-        gtk::main_quit();
-    });
-
-    file_menu.add(&new);
-    file_menu.add(&open);
-    file_menu.add(&quit);
-    file.set_submenu(Some(&file_menu));
-    menubar.append(&file);
 
     // window contents
 
-    let button = Button::new_with_label("Click me!");
+    let button = Button::new_with_label(&tr("New Loan"));
+    button.set_action_name("win.new-loan");
+    let guided = Button::new_with_label(&tr("New Loan Wizard"));
+    guided.set_action_name("win.new-loan-wizard");
+    let pay = Button::new_with_label(&tr("Record Payment"));
+    pay.set_action_name("win.record-payment");
+    loans.header.pack_start(&button);
+    loans.header.pack_start(&guided);
+    loans.header.pack_end(&pay);
+
+    let filters = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    filters.pack_start(&loans.search, true, true, 0);
+    filters.pack_start(&loans.status_filter, false, false, 0);
 
-    v_box.pack_start(&menubar, false, false, 0);
-    v_box.pack_start(&button, true, true, 0);
+    let scroll = ScrolledWindow::new(None, None);
+    scroll.add(&loans.view);
+    let loans_page = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    loans_page.pack_start(&filters, false, false, 0);
+    loans_page.pack_start(&scroll, true, true, 0);
+
+    let schedule = Rc::new(ScheduleView::new());
+    let schedule_scroll = ScrolledWindow::new(None, None);
+    schedule_scroll.add(&schedule.view);
+
+    let costs = CostChart::new();
+
+    let compare = CompareChart::new();
+    let compare_list = ScrolledWindow::new(None, None);
+    compare_list.set_size_request(220, -1);
+    compare_list.add(&compare.view);
+    let compare_page = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    compare_page.pack_start(&compare_list, false, false, 0);
+    compare_page.pack_start(&compare.area, true, true, 0);
+
+    let tabs = Notebook::new();
+    tabs.append_page(&loans_page, Some(&Label::new(Some(&tr("Loans")))));
+    tabs.append_page(&schedule_scroll, Some(&Label::new(Some(&tr("Schedule")))));
+    tabs.append_page(&costs.area, Some(&Label::new(Some(&tr("Costs")))));
+    let compare_tab = tabs.append_page(&compare_page, Some(&Label::new(Some(&tr("Compare")))));
+    {
+        let loans = loans.clone();
+        // reloaded on every visit so new loans and scenarios show up
+        tabs.connect_switch_page(move |_, _, page| {
+            if page == compare_tab {
+                if let Some(ref db) = *loans.db.borrow() {
+                    compare.load(db);
+                }
+            }
+        });
+    }
+
+    let v_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    v_box.pack_start(&loans.reminders, false, false, 0);
+    v_box.pack_start(&tabs, true, true, 0);
+    v_box.pack_start(&loans.summary, false, false, 0);
     window.add(&v_box);
 
     window.show_all();
 
-    window.connect_delete_event(|_, _| {
-        println!("We're going down!");
-        gtk::main_quit();
-        Inhibit(false)
+    // pick up where the last session left off
+    let reopen_last = loans.settings.borrow().reopen_last;
+    if let Some(last) = load_recent().into_iter().next().filter(|_| reopen_last) {
+        open_db(&loans, last);
+    }
+
+    {
+        let loans = loans.clone();
+        let reminders = loans.reminders.clone();
+        reminders.connect_response(move |bar, response| {
+            if response == RECORD_SCHEDULED {
+                loans.record_scheduled();
+            } else {
+                bar.hide();
+            }
+        });
+    }
+
+    // databases dropped anywhere are opened; a CSV dropped on a loan is
+    // imported as payments on it
+    accept_file_drops(&window.clone().upcast());
+    {
+        let loans = loans.clone();
+        window.connect_drag_data_received(move |_, _, _, _, data, _, _| {
+            if let Some(path) = data.get_uris().iter().filter_map(|uri| uri_to_path(uri)).next() {
+                if is_db_file(&path) {
+                    open_db(&loans, path);
+                } else {
+                    show_error(&loans.window, &trf("{} isn't a database. Drop payment files onto a loan to import them.", &[&path.display()]));
+                }
+            }
+        });
+    }
+    accept_file_drops(&loans.view.clone().upcast());
+    {
+        let loans = loans.clone();
+        let view = loans.view.clone();
+        view.connect_drag_data_received(move |view, _, x, y, data, _, _| {
+            let path = match data.get_uris().iter().filter_map(|uri| uri_to_path(uri)).next() {
+                Some(path) => path,
+                None => return,
+            };
+            if is_db_file(&path) {
+                open_db(&loans, path);
+                return;
+            }
+            let name = view.get_dest_row_at_pos(x, y).and_then(|(path, _)| path)
+                .and_then(|path| view.get_model().and_then(|model| model.get_iter(&path).and_then(|iter| model.get_value(&iter, 0).get::<String>())));
+            match name {
+                Some(name) => import_payments(&loans, &name, &path),
+                None => show_error(&loans.window, &tr("Drop the payment file onto the loan it's for.")),
+            };
+        });
+    }
+
+    {
+        let app_window = app_window.clone();
+        loans.view.connect_row_activated(move |_, _, _| app_window.activate_action("record-payment", None));
+    }
+
+    {
+        let loans = loans.clone();
+        let selection = loans.view.get_selection();
+        selection.connect_changed(move |_| {
+            let db = match *loans.db.borrow() {
+                Some(ref db) => db.clone(),
+                None => return,
+            };
+            let name = loans.selected();
+            let loan = name.as_ref().and_then(|name| amortization::load_loan(&db, name).ok());
+            let installments = loan.as_ref().map(|loan| contract_schedule(&db, loan));
+            schedule.show(loan.as_ref().and_then(|loan| installments.as_ref().map(|installments| (loan, &installments[..]))));
+            // with nothing selected, chart the whole portfolio
+            costs.show(&db, name.as_ref().map(|name| &name[..]));
+        });
+    }
+
+    {
+        let loans = loans.clone();
+        gtk::timeout_add_seconds(2, move || {
+            loans.poll();
+            Continue(true)
+        });
+    }
+}
+
+fn main() {
+    amortization::i18n::init();
+
+    let matches = App::new("Amortization Calculator")
+                          .version("0.1.0")
+                          .author("T. Jameson Little <t.jameson.little@gmail.com>")
+                          .about("Calculates an amortization table")
+                          .arg(Arg::with_name("trace-file")
+                               .long("trace-file")
+                               .takes_value(true)
+                               .help("Appends a line to FILE for every change made to the database"))
+                          .get_matches();
+    // shown once GTK is up, instead of opening the window
+    let trace_error = matches.value_of("trace-file").and_then(|path| {
+        trace::trace_to(Path::new(path)).err().map(|err| trf("Couldn't open trace file {}: {}", &[&path, &err]))
     });
 
-    button.connect_clicked(|_| {
-        println!("Clicked!");
+    let app = match gtk::Application::new(Some(APP_ID), gio::APPLICATION_FLAGS_NONE) {
+        Ok(app) => app,
+        Err(_) => {
+            // there's no display to put a dialog on
+            eprintln!("{}", tr("Failed to initialize GTK."));
+            std::process::exit(1);
+        }
+    };
+
+    app.connect_startup(|app| {
+        let running = app.clone();
+        add_action(app, "quit", move || running.quit());
+        for &(action, accel) in ACCELS.iter() {
+            app.set_accels_for_action(action, &[accel]);
+        }
+    });
+    app.connect_activate(move |app| {
+        if let Some(ref message) = trace_error {
+            let dialog = MessageDialog::new(None::<&Window>, gtk::DIALOG_MODAL, gtk::MessageType::Error, gtk::ButtonsType::Close, message);
+            dialog.run();
+            dialog.destroy();
+            return;
+        }
+        // launching it again lands here too; reuse the window that's open
+        match app.get_active_window() {
+            Some(window) => window.present(),
+            None => build_window(app),
+        }
     });
 
-    gtk::main();
+    // clap has already handled the command line
+    app.run(0, &[]);
 }
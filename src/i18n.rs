@@ -0,0 +1,171 @@
+// Translations and locale-aware formatting for the frontends.
+//
+// Messages are looked up with gettext in the "amortization" domain, so
+// catalogs go in $LOCALEDIR/<lang>/LC_MESSAGES/amortization.mo, compiled from
+// po/<lang>.po. Extract strings with `xgettext -L Rust --keyword=tr
+// --keyword=trf src/*.rs`. Outside Linux, where gettext isn't part of libc,
+// everything falls back to English.
+
+use std::fmt;
+
+use time;
+
+pub const DOMAIN: &str = "amortization";
+
+const MONTHS: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July", "August", "September",
+                                   "October", "November", "December"];
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::{c_char, c_int};
+    use std::ptr;
+
+    const LC_TIME: c_int = 2;
+    const LC_ALL: c_int = 6;
+    // _NL_ITEM(LC_TIME, n) from glibc's langinfo.h
+    const D_FMT: c_int = 0x20029;
+    const MON_1: c_int = 0x2001A;
+
+    // only the leading fields of struct lconv are read
+    #[repr(C)]
+    struct Lconv {
+        decimal_point: *const c_char,
+        thousands_sep: *const c_char,
+    }
+
+    extern "C" {
+        fn setlocale(category: c_int, locale: *const c_char) -> *const c_char;
+        fn bindtextdomain(domain: *const c_char, dir: *const c_char) -> *const c_char;
+        fn bind_textdomain_codeset(domain: *const c_char, codeset: *const c_char) -> *const c_char;
+        fn dgettext(domain: *const c_char, msgid: *const c_char) -> *const c_char;
+        fn nl_langinfo(item: c_int) -> *const c_char;
+        fn localeconv() -> *const Lconv;
+    }
+
+    fn string(s: *const c_char) -> Option<String> {
+        if s.is_null() {
+            None
+        } else {
+            unsafe { Some(CStr::from_ptr(s).to_string_lossy().into_owned()) }
+        }
+    }
+
+    pub fn init(domain: &str, dir: &str) {
+        let (domain, dir) = (CString::new(domain).unwrap(), CString::new(dir).unwrap());
+        unsafe {
+            setlocale(LC_ALL, b"\0".as_ptr() as *const c_char);
+            bindtextdomain(domain.as_ptr(), dir.as_ptr());
+            bind_textdomain_codeset(domain.as_ptr(), b"UTF-8\0".as_ptr() as *const c_char);
+        }
+    }
+
+    pub fn gettext(domain: &str, msgid: &str) -> String {
+        match (CString::new(domain), CString::new(msgid)) {
+            (Ok(domain), Ok(id)) => string(unsafe { dgettext(domain.as_ptr(), id.as_ptr()) }).unwrap_or(msgid.to_string()),
+            _ => msgid.to_string(),
+        }
+    }
+
+    pub fn month(month: i32) -> Option<String> {
+        string(unsafe { nl_langinfo(MON_1 + month - 1) })
+    }
+
+    // The C locale's %m/%d/%y is worse than ISO dates, so it isn't used.
+    pub fn date_format() -> Option<String> {
+        match string(unsafe { setlocale(LC_TIME, ptr::null()) }) {
+            Some(ref locale) if locale != "C" && locale != "POSIX" => string(unsafe { nl_langinfo(D_FMT) }),
+            _ => None,
+        }
+    }
+
+    pub fn separators() -> (Option<String>, Option<String>) {
+        unsafe {
+            let conv = localeconv();
+            if conv.is_null() {
+                (None, None)
+            } else {
+                (string((*conv).decimal_point), string((*conv).thousands_sep))
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    pub fn init(_: &str, _: &str) {}
+
+    pub fn gettext(_: &str, msgid: &str) -> String {
+        msgid.to_string()
+    }
+
+    pub fn month(_: i32) -> Option<String> {
+        None
+    }
+
+    pub fn date_format() -> Option<String> {
+        None
+    }
+
+    pub fn separators() -> (Option<String>, Option<String>) {
+        (None, None)
+    }
+}
+
+// Picks up the user's locale and finds the translations. Call once at
+// startup, before anything is translated.
+pub fn init() {
+    sys::init(DOMAIN, option_env!("LOCALEDIR").unwrap_or("/usr/share/locale"));
+}
+
+// The translation of `msgid`, or `msgid` itself if there isn't one.
+pub fn tr(msgid: &str) -> String {
+    sys::gettext(DOMAIN, msgid)
+}
+
+// Translates `msgid` and fills in its {} placeholders with `args`, in order,
+// so each message is translated whole.
+pub fn trf(msgid: &str, args: &[&dyn fmt::Display]) -> String {
+    let mut args = args.iter();
+    tr(msgid).split("{}").enumerate().fold(String::new(), |mut out, (i, piece)| {
+        if i > 0 {
+            if let Some(arg) = args.next() {
+                out.push_str(&arg.to_string());
+            }
+        }
+        out.push_str(piece);
+        out
+    })
+}
+
+// Full name of a month, 1 through 12.
+pub fn month_name(month: i32) -> String {
+    sys::month(month).unwrap_or(MONTHS[(month - 1) as usize].to_string())
+}
+
+// A date the way the locale writes it, e.g. 03/15/2017 or 15.03.2017.
+pub fn format_date(date: time::Timespec) -> String {
+    let format = sys::date_format().unwrap_or("%F".to_string());
+    time::strftime(&format, &time::at_utc(date)).or_else(|_| time::strftime("%F", &time::at_utc(date))).unwrap()
+}
+
+// An amount to two decimal places with the locale's decimal point and
+// thousands separator.
+pub fn format_amount(amount: f64) -> String {
+    let (point, sep) = sys::separators();
+    let plain = format!("{:.2}", amount.abs());
+    let (whole, cents) = plain.split_at(plain.len() - 3);
+
+    let mut out = if amount < 0f64 && plain != "0.00" { "-".to_string() } else { String::new() };
+    let sep = sep.unwrap_or_default();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            out.push_str(&sep);
+        }
+        out.push(digit);
+    }
+    out.push_str(&point.unwrap_or(".".to_string()));
+    out.push_str(&cents[1..]);
+    out
+}
+
@@ -0,0 +1,146 @@
+// Income-driven repayment (IDR) plans for student loans. The payment is a
+// percentage of discretionary income, recalculated once a year, and whatever
+// is left after the forgiveness period is written off.
+
+use calc;
+
+#[derive(Debug, Clone)]
+pub struct IdrPlan {
+    // annual income in the first year
+    pub income: f64,
+    // yearly income growth, in percent
+    pub income_growth: f64,
+    // annual poverty guideline for the household size
+    pub poverty_line: f64,
+    // income protected from the calculation, as a multiple of poverty_line (1.5 = 150%)
+    pub poverty_multiple: f64,
+    // percent of discretionary income paid each year
+    pub income_percent: f64,
+    pub forgiveness_years: i32,
+}
+
+impl IdrPlan {
+    // Monthly payment for a year with the given annual income.
+    pub fn monthly_payment(&self, income: f64) -> f64 {
+        let discretionary = income - self.poverty_line * self.poverty_multiple;
+        if discretionary <= 0f64 {
+            return 0f64;
+        }
+        discretionary * self.income_percent / 100.0 / 12.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IdrYear {
+    pub year: i32,
+    pub payment: f64,
+    pub paid: f64,
+    pub interest: f64,
+    pub balance: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdrProjection {
+    pub years: Vec<IdrYear>,
+    pub total_paid: f64,
+    pub forgiven: f64,
+}
+
+// Projects `balance` under `plan` until it's paid off or forgiven. Interest
+// the payment doesn't cover is added to the balance.
+pub fn project(balance: f64, apr: f64, plan: &IdrPlan) -> IdrProjection {
+    let mut balance = balance;
+    let mut income = plan.income;
+    let mut years = Vec::new();
+    let mut total_paid = 0f64;
+
+    for year in 1..plan.forgiveness_years+1 {
+        let payment = plan.monthly_payment(income);
+        let mut paid = 0f64;
+        let mut interest = 0f64;
+        for _ in 0..12 {
+            if balance <= 0f64 {
+                break;
+            }
+            let accrued = calc::interest(balance, apr);
+            let amount = if payment > balance + accrued { balance + accrued } else { payment };
+            balance += accrued - amount;
+            interest += accrued;
+            paid += amount;
+        }
+        total_paid += paid;
+        years.push(IdrYear{
+            year: year,
+            payment: payment,
+            paid: paid,
+            interest: interest,
+            balance: balance,
+        });
+
+        if balance <= 0f64 {
+            break;
+        }
+        income *= 1.0 + plan.income_growth / 100.0;
+    }
+
+    IdrProjection{
+        years: years,
+        total_paid: total_paid,
+        forgiven: if balance > 0f64 { balance } else { 0f64 },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10% of income above 150% of the 2024 poverty guideline for a household
+    // of one in the contiguous states ($15,060), as under IBR for new
+    // borrowers and PAYE.
+    fn plan(income: f64) -> IdrPlan {
+        IdrPlan{
+            income: income,
+            income_growth: 0f64,
+            poverty_line: 15060.0,
+            poverty_multiple: 1.5,
+            income_percent: 10.0,
+            forgiveness_years: 20,
+        }
+    }
+
+    #[test]
+    fn monthly_payment() {
+        // (50,000 - 22,590) * 10% / 12
+        assert_eq!(format!("{:.2}", plan(50000.0).monthly_payment(50000.0)), "228.42");
+        // (100,000 - 22,590) * 10% / 12
+        assert_eq!(format!("{:.2}", plan(100000.0).monthly_payment(100000.0)), "645.08");
+        // (50,000 - 22,590) * 15% / 12, under the old IBR
+        let old = IdrPlan{ income_percent: 15.0, ..plan(50000.0) };
+        assert_eq!(format!("{:.3}", old.monthly_payment(50000.0)), "342.625");
+    }
+
+    #[test]
+    fn nothing_due_below_the_protected_income() {
+        assert_eq!(plan(20000.0).monthly_payment(20000.0), 0f64);
+        assert_eq!(plan(22590.0).monthly_payment(22590.0), 0f64);
+    }
+
+    #[test]
+    fn forgiven_after_the_term() {
+        let projection = project(100000.0, 6.0, &plan(50000.0));
+        assert_eq!(projection.years.len(), 20);
+        // the payment never covers the interest, so it's all paid
+        assert_eq!(format!("{:.2}", projection.total_paid), format!("{:.2}", 228.41666666666666 * 12.0 * 20.0));
+        assert!(projection.forgiven > 100000.0);
+    }
+
+    #[test]
+    fn paid_off_early() {
+        let projection = project(5000.0, 6.0, &plan(100000.0));
+        assert!(projection.years.len() < 20);
+        assert_eq!(projection.forgiven, 0f64);
+        let last = projection.years.last().unwrap();
+        assert!(last.balance <= 0f64);
+        assert_eq!(format!("{:.2}", projection.total_paid), format!("{:.2}", 5000.0 + projection.years.iter().map(|year| year.interest).sum::<f64>()));
+    }
+}
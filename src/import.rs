@@ -0,0 +1,411 @@
+// Reads payments exported from a bank or lender's website so they can be
+// recorded in bulk, published rate index values, lenders' schedules and
+// files defining many loans at once.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use time;
+
+use calc;
+use db::{Loan, Transaction, TransactionKind};
+
+// Whether a payment is the regular one for its period, split between interest
+// and principal, or extra that goes entirely to principal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaymentKind {
+    Regular,
+    Extra,
+}
+
+impl PaymentKind {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            PaymentKind::Regular => "regular",
+            PaymentKind::Extra => "extra",
+        }
+    }
+}
+
+impl FromStr for PaymentKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PaymentKind, String> {
+        match &s.to_lowercase()[..] {
+            "regular" | "scheduled" => Ok(PaymentKind::Regular),
+            "extra" | "principal" => Ok(PaymentKind::Extra),
+            _ => Err(format!("Unknown payment kind: {}", s)),
+        }
+    }
+}
+
+// One payment from an import file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentRow {
+    pub date: time::Timespec,
+    pub amount: f64,
+    pub method: Option<String>,
+    // given in the file, overriding classify's guess
+    pub kind: Option<PaymentKind>,
+}
+
+// Parses CSV lines of DATE,AMOUNT[,METHOD[,KIND]], with dates as YYYY-MM-DD
+// and KIND as regular or extra. A header line, blank lines and # comments are
+// skipped. Amounts may include a dollar sign.
+pub fn parse_payments(input: &str) -> Result<Vec<PaymentRow>, String> {
+    let mut rows = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.splitn(4, ',').map(|field| field.trim().trim_matches('"')).collect();
+        let date = time::strptime(fields[0], "%F").map(|tm| tm.to_timespec());
+        let amount = fields.get(1).map(|amount| amount.replace("$", "").parse::<f64>());
+        let kind = match fields.get(3).filter(|kind| !kind.is_empty()).map(|kind| kind.parse::<PaymentKind>()) {
+            Some(Err(err)) if i > 0 => return Err(format!("Line {}: {}", i + 1, err)),
+            kind => kind.and_then(|kind| kind.ok()),
+        };
+        match (date, amount) {
+            (Ok(date), Some(Ok(amount))) => {
+                rows.push(PaymentRow{
+                    date: date,
+                    amount: amount,
+                    method: fields.get(2).filter(|method| !method.is_empty()).map(|method| method.to_string()),
+                    kind: kind,
+                });
+            },
+            // the first line is allowed to be a header
+            _ if i == 0 => continue,
+            _ => return Err(format!("Line {}: expected DATE,AMOUNT[,METHOD[,KIND]], got: {}", i + 1, line)),
+        }
+    }
+    Ok(rows)
+}
+
+// Parses CSV lines of DATE,RATE, with dates as YYYY-MM-DD and rates in
+// percent, e.g. a download of SOFR or prime. A header line, blank lines and
+// # comments are skipped. Rates may include a percent sign.
+pub fn parse_index_values(input: &str) -> Result<Vec<(time::Timespec, f64)>, String> {
+    let mut values = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.splitn(2, ',').map(|field| field.trim().trim_matches('"')).collect();
+        let date = time::strptime(fields[0], "%F").map(|tm| tm.to_timespec());
+        let rate = fields.get(1).map(|rate| rate.replace("%", "").trim().parse::<f64>());
+        match (date, rate) {
+            (Ok(date), Some(Ok(rate))) => values.push((date, rate)),
+            // the first line is allowed to be a header
+            _ if i == 0 => continue,
+            _ => return Err(format!("Line {}: expected DATE,RATE, got: {}", i + 1, line)),
+        }
+    }
+    Ok(values)
+}
+
+// Parses a schedule as CSV with a header naming its columns: payment,
+// interest, principal and balance, plus period if rows don't start at the
+// first payment. Other columns (e.g. date) are ignored. Amounts may include
+// dollar signs and thousands separators in quotes.
+pub fn parse_schedule(input: &str) -> Result<Vec<calc::Installment>, String> {
+    let mut lines = input.lines().enumerate().filter(|&(_, line)| !line.trim().is_empty() && !line.trim().starts_with('#'));
+    let header: Vec<String> = match lines.next() {
+        Some((_, line)) => split_csv(line).iter().map(|name| name.to_lowercase()).collect(),
+        None => return Ok(Vec::new()),
+    };
+    let column = |name: &str| header.iter().position(|column| column == name);
+    let (payment, interest, principal, balance) = match (column("payment"), column("interest"), column("principal"), column("balance")) {
+        (Some(payment), Some(interest), Some(principal), Some(balance)) => (payment, interest, principal, balance),
+        _ => return Err("Header must name payment, interest, principal and balance columns".to_string()),
+    };
+    let period = column("period");
+
+    let mut rows = Vec::new();
+    for (i, line) in lines {
+        let fields = split_csv(line);
+        let amount = |column: usize| fields.get(column).and_then(|field| field.replace("$", "").replace(",", "").parse::<f64>().ok());
+        let number = match period {
+            Some(column) => fields.get(column).and_then(|field| field.parse().ok()),
+            None => Some(rows.len() as i32 + 1),
+        };
+        match (number, amount(payment), amount(interest), amount(principal), amount(balance)) {
+            (Some(number), Some(payment), Some(interest), Some(principal), Some(balance)) => rows.push(calc::Installment{
+                period: number,
+                payment: payment,
+                interest: interest,
+                principal: principal,
+                balance: balance,
+            }),
+            _ => return Err(format!("Line {}: couldn't read the schedule row: {}", i + 1, line)),
+        }
+    }
+    Ok(rows)
+}
+
+// A loan from a definitions file. Only name, balance, apr and term (in
+// years) are required.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoanRow {
+    pub name: String,
+    pub balance: f64,
+    pub apr: f64,
+    pub term: i32,
+    // first payment due date
+    pub start: Option<time::Timespec>,
+    pub currency: Option<String>,
+    pub lender: Option<String>,
+    pub owner: Option<String>,
+    pub lent: bool,
+}
+
+// Builds a loan from its KEY, VALUE pairs, described by `at` in errors.
+fn loan_row(fields: &[(String, String)], at: &str) -> Result<LoanRow, String> {
+    let mut row = LoanRow{ name: String::new(), balance: 0f64, apr: -1f64, term: 0, start: None, currency: None, lender: None, owner: None, lent: false };
+    for &(ref key, ref value) in fields {
+        let invalid = || format!("{}: invalid {}: {}", at, key, value);
+        let text = || if value.is_empty() { None } else { Some(value.clone()) };
+        match &key[..] {
+            "name" => row.name = value.clone(),
+            "balance" => row.balance = try!(value.replace("$", "").replace(",", "").parse().map_err(|_| invalid())),
+            "apr" => row.apr = try!(value.replace("%", "").parse().map_err(|_| invalid())),
+            "term" => row.term = try!(value.parse().map_err(|_| invalid())),
+            "start" if value.is_empty() => row.start = None,
+            "start" => row.start = Some(try!(time::strptime(value, "%F").map(|tm| tm.to_timespec()).map_err(|_| invalid()))),
+            "currency" => row.currency = text().map(|currency| currency.to_uppercase()),
+            "lender" => row.lender = text(),
+            "owner" => row.owner = text(),
+            "lent" => row.lent = match &value.to_lowercase()[..] {
+                "true" | "yes" | "1" => true,
+                "false" | "no" | "0" | "" => false,
+                _ => return Err(invalid()),
+            },
+            _ => return Err(format!("{}: unknown field {} (expected name, balance, apr, term, start, currency, lender, owner or lent)", at, key)),
+        }
+    }
+
+    if row.name.is_empty() {
+        return Err(format!("{}: missing name", at));
+    }
+    if !row.balance.is_finite() || row.balance <= 0f64 {
+        return Err(format!("{}: balance must be positive", at));
+    }
+    if !(row.apr >= 0f64 && row.apr <= 100f64) {
+        return Err(format!("{}: apr must be given, from 0 to 100", at));
+    }
+    if row.term < 1 {
+        return Err(format!("{}: term must be a whole number of years, at least 1", at));
+    }
+    Ok(row)
+}
+
+// Parses loans as CSV with a header naming the columns, e.g.
+//
+//     name,balance,apr,term,start,lender
+//     car,18000,4.9,5,2024-03-01,Credit Union
+//
+// Blank lines and # comments are skipped.
+pub fn parse_loans_csv(input: &str) -> Result<Vec<LoanRow>, String> {
+    let mut lines = input.lines().enumerate().filter(|&(_, line)| !line.trim().is_empty() && !line.trim().starts_with('#'));
+    let header: Vec<String> = match lines.next() {
+        Some((_, line)) => split_csv(line).iter().map(|name| name.to_lowercase()).collect(),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut loans = Vec::new();
+    for (i, line) in lines {
+        let fields = split_csv(line);
+        if fields.len() > header.len() {
+            return Err(format!("Line {}: more fields than the header names", i + 1));
+        }
+        let pairs: Vec<(String, String)> = header.iter().cloned().zip(fields.into_iter()).collect();
+        loans.push(try!(loan_row(&pairs, &format!("Line {}", i + 1))));
+    }
+    Ok(loans)
+}
+
+// Parses loans from the subset of TOML used for definitions files: a
+// [[loan]] table per loan holding KEY = VALUE lines, where values are quoted
+// strings, numbers or booleans.
+//
+//     [[loan]]
+//     name = "car"
+//     balance = 18000
+//     apr = 4.9
+//     term = 5
+//
+// Comments start with #.
+pub fn parse_loans_toml(input: &str) -> Result<Vec<LoanRow>, String> {
+    let mut tables: Vec<(usize, Vec<(String, String)>)> = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "[[loan]]" {
+            tables.push((i + 1, Vec::new()));
+            continue;
+        }
+
+        let mut parts = line.splitn(2, '=');
+        let key = parts.next().unwrap().trim();
+        let value = match parts.next().map(str::trim) {
+            Some(value) if value.starts_with('"') => {
+                // a basic string, up to the closing quote
+                let mut string = String::new();
+                let mut chars = value[1..].chars();
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => {
+                            closed = true;
+                            break;
+                        },
+                        '\\' => match chars.next() {
+                            Some('n') => string.push('\n'),
+                            Some('t') => string.push('\t'),
+                            Some(c @ '"') | Some(c @ '\\') => string.push(c),
+                            _ => return Err(format!("Line {}: unsupported escape in string", i + 1)),
+                        },
+                        c => string.push(c),
+                    }
+                }
+                let rest = chars.as_str().trim();
+                if !closed || !(rest.is_empty() || rest.starts_with('#')) {
+                    return Err(format!("Line {}: expected a quoted string, got: {}", i + 1, value));
+                }
+                string
+            },
+            Some(value) => value.splitn(2, '#').next().unwrap().trim().replace("_", ""),
+            None => return Err(format!("Line {}: expected [[loan]] or KEY = VALUE, got: {}", i + 1, line)),
+        };
+        match tables.last_mut() {
+            Some(&mut (_, ref mut fields)) => fields.push((key.to_string(), value)),
+            None => return Err(format!("Line {}: {} comes before the first [[loan]]", i + 1, key)),
+        }
+    }
+
+    tables.iter().map(|&(line, ref fields)| loan_row(fields, &format!("Loan at line {}", line))).collect()
+}
+
+// Splits a CSV line, honoring quoted fields.
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    for c in line.trim().chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(field.split_off(0).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+// Sorts imported payments into regular and extra, remembering which due
+// periods already have their regular payment.
+pub struct Classifier {
+    // due periods of the loan already paid by a regular payment
+    periods: HashSet<i32>,
+}
+
+impl Classifier {
+    // `regular` are the due periods (Loan::period_at) of regular payments
+    // already recorded.
+    pub fn new<I: IntoIterator<Item = i32>>(regular: I) -> Classifier {
+        Classifier{
+            periods: regular.into_iter().collect(),
+        }
+    }
+
+    // A classifier for payments on `loan`, whose recorded transactions are
+    // `transactions`: the periods of its regular payments, less any returned
+    // unpaid, are already paid.
+    pub fn for_loan(loan: &Loan, transactions: &[Transaction]) -> Classifier {
+        Classifier::new(transactions.iter().filter(|payment| payment.kind == TransactionKind::Payment && !payment.reversed)
+                        .map(|payment| loan.period_at(payment.date)))
+    }
+
+    // Guesses whether `row`, made in due period `period` of the loan, was the
+    // regular payment for it or extra principal, given the amount due. In
+    // order:
+    //
+    // - a kind given in the file wins
+    // - a method mentioning principal means extra
+    // - a second payment in a period already paid is extra
+    // - everything else is the regular payment
+    //
+    // A regular payment short of the amount due is still regular, for the
+    // caller to skip and report; it doesn't pay the period, so a full
+    // payment after it is regular too.
+    pub fn classify(&mut self, row: &PaymentRow, period: i32, due: f64) -> PaymentKind {
+        let kind = match row.kind {
+            Some(kind) => kind,
+            None => {
+                let principal_only = row.method.as_ref().map_or(false, |method| method.to_lowercase().contains("principal"));
+                if principal_only || self.periods.contains(&period) {
+                    PaymentKind::Extra
+                } else {
+                    PaymentKind::Regular
+                }
+            },
+        };
+        if kind == PaymentKind::Regular && row.amount >= due - 0.005 {
+            self.periods.insert(period);
+        }
+        kind
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(input: &str) -> Vec<PaymentRow> {
+        parse_payments(input).unwrap()
+    }
+
+    #[test]
+    fn parses_payments() {
+        let rows = rows("date,amount,method,kind\n# January\n2024-01-05,$500.00,ACH\n\n2024-01-20,100,check #12,extra\n");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].amount, 500.0);
+        assert_eq!(rows[0].method, Some("ACH".to_string()));
+        assert_eq!(rows[0].kind, None);
+        assert_eq!(rows[1].kind, Some(PaymentKind::Extra));
+    }
+
+    #[test]
+    fn one_regular_payment_a_period() {
+        let rows = rows("2024-01-05,500\n2024-01-20,500\n2024-02-05,500\n2024-02-06,500,principal only\n");
+        let mut classifier = Classifier::new(vec![]);
+        let kinds: Vec<PaymentKind> = rows.iter().zip([1, 1, 2, 3].iter()).map(|(row, &period)| classifier.classify(row, period, 500.0)).collect();
+        assert_eq!(kinds, vec![PaymentKind::Regular, PaymentKind::Extra, PaymentKind::Regular, PaymentKind::Extra]);
+    }
+
+    // Two payments in the same calendar month are each regular when they
+    // pay different periods, as on a loan paid every two weeks.
+    #[test]
+    fn keyed_on_the_due_period() {
+        let rows = rows("2024-03-01,250\n2024-03-15,250\n2024-03-29,250\n");
+        let mut classifier = Classifier::new(vec![4]);
+        let kinds: Vec<PaymentKind> = rows.iter().zip([4, 5, 6].iter()).map(|(row, &period)| classifier.classify(row, period, 250.0)).collect();
+        assert_eq!(kinds, vec![PaymentKind::Extra, PaymentKind::Regular, PaymentKind::Regular]);
+    }
+
+    // A short payment is a regular one the caller skips, not extra
+    // principal, and leaves the period for a full payment to pay.
+    #[test]
+    fn short_payments_stay_regular() {
+        let rows = rows("2024-01-05,200\n2024-01-10,500\n2024-01-15,500\n");
+        let mut classifier = Classifier::new(vec![]);
+        let kinds: Vec<PaymentKind> = rows.iter().map(|row| classifier.classify(row, 1, 500.0)).collect();
+        assert_eq!(kinds, vec![PaymentKind::Regular, PaymentKind::Regular, PaymentKind::Extra]);
+    }
+}
@@ -0,0 +1,119 @@
+// How a lender turns a balance and an APR into interest. The built-in
+// methods cover the common day counts; a downstream crate can implement
+// InterestMethod for a lender's own rules and register it, after which loans
+// can name it like any other:
+//
+//     amortization::interest::register(Arc::new(MyLender));
+//
+// Methods are looked up by name, so a database naming a method that isn't
+// registered falls back to the standard one.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use calc;
+
+pub trait InterestMethod: fmt::Debug + Send + Sync {
+    // Stored with each loan that uses it, e.g. "actual/360".
+    fn name(&self) -> &str;
+
+    // Interest on `balance` for one regular monthly period at `apr` (in
+    // percent).
+    fn monthly(&self, balance: f64, apr: f64) -> f64;
+
+    // Interest accrued on `balance` over `days` days, e.g. between a payment
+    // and a payoff date.
+    fn daily(&self, balance: f64, apr: f64, days: i64) -> f64;
+}
+
+// A twelfth of the APR each month, and a 365-day year between dates. What
+// loans use unless told otherwise.
+#[derive(Debug, Clone, Copy)]
+pub struct Standard;
+
+impl InterestMethod for Standard {
+    fn name(&self) -> &str {
+        "standard"
+    }
+
+    fn monthly(&self, balance: f64, apr: f64) -> f64 {
+        calc::interest(balance, apr)
+    }
+
+    fn daily(&self, balance: f64, apr: f64, days: i64) -> f64 {
+        calc::daily_interest(balance, apr, days)
+    }
+}
+
+// Actual days over a 360-day year (365/360), common for commercial loans. A
+// month is taken to be an average 365/12 days, so it charges a little more
+// than the standard method.
+#[derive(Debug, Clone, Copy)]
+pub struct Actual360;
+
+impl InterestMethod for Actual360 {
+    fn name(&self) -> &str {
+        "actual/360"
+    }
+
+    fn monthly(&self, balance: f64, apr: f64) -> f64 {
+        balance * apr / 100.0 / 360.0 * 365.0 / 12.0
+    }
+
+    fn daily(&self, balance: f64, apr: f64, days: i64) -> f64 {
+        balance * apr / 100.0 / 360.0 * days as f64
+    }
+}
+
+// Twelve 30-day months (30/360), as for most bonds.
+#[derive(Debug, Clone, Copy)]
+pub struct Thirty360;
+
+impl InterestMethod for Thirty360 {
+    fn name(&self) -> &str {
+        "30/360"
+    }
+
+    fn monthly(&self, balance: f64, apr: f64) -> f64 {
+        calc::interest(balance, apr)
+    }
+
+    fn daily(&self, balance: f64, apr: f64, days: i64) -> f64 {
+        balance * apr / 100.0 / 360.0 * days as f64
+    }
+}
+
+// Methods registered on top of the built-in ones.
+static REGISTRY: Mutex<Vec<Arc<dyn InterestMethod>>> = Mutex::new(Vec::new());
+
+// Makes `method` available by name, replacing any method already registered
+// under it. The built-in methods can't be replaced.
+pub fn register(method: Arc<dyn InterestMethod>) {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.retain(|registered| registered.name() != method.name());
+    registry.push(method);
+}
+
+fn builtin() -> Vec<Arc<dyn InterestMethod>> {
+    vec![Arc::new(Standard), Arc::new(Actual360), Arc::new(Thirty360)]
+}
+
+// The method registered as `name`, if any.
+pub fn lookup(name: &str) -> Option<Arc<dyn InterestMethod>> {
+    builtin().into_iter()
+        .chain(REGISTRY.lock().unwrap().iter().cloned())
+        .find(|method| method.name() == name)
+}
+
+// The method registered as `name`, or the standard one.
+pub fn method(name: &str) -> Arc<dyn InterestMethod> {
+    lookup(name).unwrap_or_else(|| Arc::new(Standard))
+}
+
+// Names of every method available, built-in ones first.
+pub fn names() -> Vec<String> {
+    builtin().into_iter()
+        .chain(REGISTRY.lock().unwrap().iter().cloned())
+        .map(|method| method.name().to_string())
+        .collect()
+}
@@ -0,0 +1,67 @@
+// Loan metrics in the Prometheus text format, for a scraper to graph
+// balances and interest over time:
+//
+//     amortization_balance{loan="car",currency="USD",lent="false",owner=""} 8312.77
+//
+// Everything is recomputed on each scrape, without writing to the database;
+// see server for where they're served.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use rusqlite;
+use time;
+
+use db::{self, LoanStatus};
+
+// Escapes a label value.
+fn label(value: &str) -> String {
+    value.replace("\\", "\\\\").replace("\"", "\\\"").replace("\n", "\\n")
+}
+
+fn header(out: &mut String, name: &str, help: &str) {
+    out.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+}
+
+const STATUSES: [LoanStatus; 5] = [LoanStatus::Current, LoanStatus::Grace, LoanStatus::Late, LoanStatus::Delinquent, LoanStatus::PaidOff];
+
+// Every metric for the loans in `db`, as of `today`.
+pub fn render(db: &Path, today: time::Timespec) -> rusqlite::Result<String> {
+    // a scrape only reads, so statuses are worked out rather than refreshed
+    let loans = try!(db::load_loans_as_of(db, today));
+    let accrued = try!(db::interest_accrued_this_month(db, today));
+
+    let mut out = String::new();
+    header(&mut out, "amortization_balance", "Balance of each loan.");
+    for loan in loans.iter() {
+        out.push_str(&format!("amortization_balance{{loan=\"{}\",currency=\"{}\",lent=\"{}\",owner=\"{}\"}} {}\n", label(&loan.name), label(&loan.currency),
+                              loan.lent, label(loan.owner.as_ref().map_or("", |owner| &owner[..])), loan.balance));
+    }
+
+    // borrowed and lent kept apart, since one is owed and the other owing
+    let mut totals: BTreeMap<(String, bool), f64> = BTreeMap::new();
+    for loan in loans.iter() {
+        *totals.entry((loan.currency.clone(), loan.lent)).or_insert(0f64) += loan.balance;
+    }
+    header(&mut out, "amortization_balance_total", "Balance of all loans, per currency.");
+    for (&(ref currency, lent), total) in totals.iter() {
+        out.push_str(&format!("amortization_balance_total{{currency=\"{}\",lent=\"{}\"}} {}\n", label(currency), lent, total));
+    }
+
+    header(&mut out, "amortization_interest_accrued_month", "Interest accrued on each loan since the first of the month.");
+    for &(ref name, interest) in accrued.iter() {
+        let loan = loans.iter().find(|loan| loan.name == *name).unwrap();
+        out.push_str(&format!("amortization_interest_accrued_month{{loan=\"{}\",currency=\"{}\",lent=\"{}\"}} {}\n", label(name), label(&loan.currency),
+                              loan.lent, interest));
+    }
+
+    header(&mut out, "amortization_loans", "Number of loans in each status.");
+    for status in STATUSES.iter() {
+        out.push_str(&format!("amortization_loans{{status=\"{}\"}} {}\n", status.as_str(), loans.iter().filter(|loan| loan.status == *status).count()));
+    }
+
+    header(&mut out, "amortization_overdue_loans", "Number of loans late or delinquent on a payment.");
+    out.push_str(&format!("amortization_overdue_loans {}\n",
+                          loans.iter().filter(|loan| loan.status == LoanStatus::Late || loan.status == LoanStatus::Delinquent).count()));
+    Ok(out)
+}
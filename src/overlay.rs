@@ -0,0 +1,66 @@
+// Lines a loan's schedule as contracted up against where it's actually
+// headed, given the payments made so far.
+
+use calc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverlayRow {
+    pub period: i32,
+    // balance after this payment under the original contract
+    pub expected: Option<f64>,
+    // actual balance for payments already made, projected after that
+    pub projected: Option<f64>,
+}
+
+impl OverlayRow {
+    // How much lower the projected balance is than the contract's. Positive
+    // means ahead of schedule.
+    pub fn difference(&self) -> f64 {
+        self.expected.unwrap_or(0f64) - self.projected.unwrap_or(0f64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    pub rows: Vec<OverlayRow>,
+    // payments that have come due so far
+    pub elapsed: i32,
+    pub expected_periods: i32,
+    pub projected_periods: i32,
+}
+
+impl Overlay {
+    // `actual` holds the balance after each payment that has come due, and
+    // `projection` the remaining schedule from the current balance.
+    pub fn new(contract: calc::Schedule, actual: &[f64], projection: calc::Schedule) -> Overlay {
+        let expected: Vec<f64> = contract.map(|inst| inst.balance).collect();
+        let projected: Vec<f64> = actual.iter().cloned().chain(projection.map(|inst| inst.balance)).collect();
+        let periods = if expected.len() > projected.len() { expected.len() } else { projected.len() };
+
+        Overlay{
+            rows: (0..periods).map(|i| {
+                OverlayRow{
+                    period: i as i32 + 1,
+                    expected: expected.get(i).cloned(),
+                    projected: projected.get(i).cloned(),
+                }
+            }).collect(),
+            elapsed: actual.len() as i32,
+            expected_periods: expected.len() as i32,
+            projected_periods: projected.len() as i32,
+        }
+    }
+
+    // How far the balance is below the contract's as of the latest payment.
+    pub fn dollars_ahead(&self) -> f64 {
+        if self.elapsed == 0 {
+            return 0f64;
+        }
+        self.rows[self.elapsed as usize - 1].difference()
+    }
+
+    // How many months earlier than contracted the loan will be paid off.
+    pub fn months_ahead(&self) -> i32 {
+        self.expected_periods - self.projected_periods
+    }
+}
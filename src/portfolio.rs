@@ -0,0 +1,67 @@
+// Figures across all loans at once: what the debt costs on average, what it
+// takes each month, and how it winds down over time.
+
+use calc;
+
+// One loan's part in the portfolio. Amounts are multiplied by `rate` to put
+// every loan in the same reporting currency.
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub balance: f64,
+    pub apr: f64,
+    pub payment: f64,
+    // original amount financed
+    pub principal: f64,
+    pub rate: f64,
+    pub schedule: calc::Schedule,
+}
+
+// Balance-weighted average APR of the loans still owing.
+pub fn weighted_apr(positions: &[Position]) -> f64 {
+    let total: f64 = positions.iter().map(|pos| pos.balance * pos.rate).sum();
+    if total <= 0f64 {
+        return 0f64;
+    }
+    positions.iter().map(|pos| pos.balance * pos.rate * pos.apr).sum::<f64>() / total
+}
+
+// Sum of the regular payments on loans that aren't paid off.
+pub fn monthly_obligation(positions: &[Position]) -> f64 {
+    positions.iter().filter(|pos| pos.balance > 0f64).map(|pos| pos.payment * pos.rate).sum()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurvePoint {
+    pub period: i32,
+    pub payment: f64,
+    pub interest: f64,
+    pub balance: f64,
+    // balance left as a fraction of the total originally financed
+    pub debt_ratio: f64,
+}
+
+// Combined balance month by month until every loan is paid off.
+pub fn payoff_curve(positions: &[Position]) -> Vec<CurvePoint> {
+    let financed: f64 = positions.iter().map(|pos| pos.principal * pos.rate).sum();
+    let schedules: Vec<Vec<calc::Installment>> = positions.iter().map(|pos| pos.schedule.clone().collect()).collect();
+    let periods = schedules.iter().map(|rows| rows.len()).max().unwrap_or(0);
+
+    (0..periods).map(|i| {
+        let mut point = CurvePoint{ period: i as i32 + 1, payment: 0f64, interest: 0f64, balance: 0f64, debt_ratio: 0f64 };
+        for (pos, rows) in positions.iter().zip(schedules.iter()) {
+            match rows.get(i) {
+                Some(inst) => {
+                    point.payment += inst.payment * pos.rate;
+                    point.interest += inst.interest * pos.rate;
+                    point.balance += inst.balance * pos.rate;
+                },
+                // a schedule that ran out of term still owes its last balance
+                None => point.balance += rows.last().map(|inst| inst.balance).unwrap_or(pos.balance) * pos.rate,
+            }
+        }
+        if financed > 0f64 {
+            point.debt_ratio = point.balance / financed;
+        }
+        point
+    }).collect()
+}
@@ -0,0 +1,164 @@
+// Python bindings, built as the `amortization` extension module with:
+//
+//     maturin develop --features python
+//
+// Loans are read straight from the same SQLite files the CLI and GUI use.
+
+use std::path::Path;
+
+use pyo3::prelude::*;
+use pyo3::exceptions::PyRuntimeError;
+
+use calc;
+use db;
+
+#[pyclass(name = "Loan")]
+pub struct PyLoan {
+    inner: db::Loan,
+}
+
+impl From<db::Loan> for PyLoan {
+    fn from(loan: db::Loan) -> PyLoan {
+        PyLoan{ inner: loan }
+    }
+}
+
+#[pymethods]
+impl PyLoan {
+    #[getter]
+    fn id(&self) -> i32 {
+        self.inner.id
+    }
+
+    #[getter]
+    fn name(&self) -> String {
+        self.inner.name.clone()
+    }
+
+    #[getter]
+    fn payment(&self) -> f64 {
+        self.inner.payment
+    }
+
+    #[getter]
+    fn balance(&self) -> f64 {
+        self.inner.balance
+    }
+
+    #[getter]
+    fn periods(&self) -> i32 {
+        self.inner.periods
+    }
+
+    #[getter]
+    fn apr(&self) -> f64 {
+        self.inner.apr
+    }
+
+    // seconds since the epoch
+    #[getter]
+    fn start_time(&self) -> i64 {
+        self.inner.start_time.sec
+    }
+
+    #[getter]
+    fn draw_periods(&self) -> i32 {
+        self.inner.draw_periods
+    }
+
+    #[getter]
+    fn promo_apr(&self) -> f64 {
+        self.inner.promo_apr
+    }
+
+    #[getter]
+    fn promo_periods(&self) -> i32 {
+        self.inner.promo_periods
+    }
+
+    #[getter]
+    fn defer_interest(&self) -> bool {
+        self.inner.defer_interest
+    }
+
+    // The same schedule the CLI shows for the loan.
+    fn schedule(&self) -> PySchedule {
+        PySchedule{ inner: self.inner.schedule() }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Loan(name={:?}, balance={:.2}, apr={:.2})", self.inner.name, self.inner.balance, self.inner.apr)
+    }
+}
+
+// Iterates over `(period, payment, interest, principal, balance)` tuples.
+#[pyclass(name = "Schedule")]
+pub struct PySchedule {
+    inner: calc::Schedule,
+}
+
+#[pymethods]
+impl PySchedule {
+    #[new]
+    #[pyo3(signature = (balance, periods, apr, payment=None))]
+    fn new(balance: f64, periods: i32, apr: f64, payment: Option<f64>) -> PySchedule {
+        let payment = payment.unwrap_or_else(|| calc::payment(balance, periods, apr));
+        PySchedule{
+            inner: calc::Schedule::new(balance, periods, apr, payment),
+        }
+    }
+
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> Option<(i32, f64, f64, f64, f64)> {
+        slf.inner.next().map(|inst| (inst.period, inst.payment, inst.interest, inst.principal, inst.balance))
+    }
+}
+
+#[pyfunction]
+fn payment(principal: f64, periods: i32, apr: f64) -> f64 {
+    calc::payment(principal, periods, apr)
+}
+
+// APR at which `payment` a month for `periods` months pays off `principal`.
+#[pyfunction]
+fn solve_apr(principal: f64, periods: i32, payment: f64) -> f64 {
+    calc::solve_apr(principal, periods, payment)
+}
+
+// Returns `(periods, total_interest, total_paid)`.
+#[pyfunction]
+fn project_payoff(balance: f64, max_periods: i32, apr: f64, payment: f64) -> (i32, f64, f64) {
+    let payoff = calc::project_payoff(balance, max_periods, apr, payment);
+    (payoff.periods, payoff.total_interest, payoff.total_paid)
+}
+
+#[pyfunction]
+fn load_loan(db: &str, name: &str) -> PyResult<PyLoan> {
+    match db::load_loan(Path::new(db), name) {
+        Ok(loan) => Ok(PyLoan::from(loan)),
+        Err(err) => Err(PyRuntimeError::new_err(format!("Error loading loan {}: {}", name, err))),
+    }
+}
+
+#[pyfunction]
+fn load_loans(db: &str) -> PyResult<Vec<PyLoan>> {
+    match db::load_loans(Path::new(db)) {
+        Ok(loans) => Ok(loans.into_iter().map(PyLoan::from).collect()),
+        Err(err) => Err(PyRuntimeError::new_err(format!("Error loading loans: {}", err))),
+    }
+}
+
+#[pymodule]
+fn amortization(m: &Bound<PyModule>) -> PyResult<()> {
+    try!(m.add_class::<PyLoan>());
+    try!(m.add_class::<PySchedule>());
+    try!(m.add_function(try!(wrap_pyfunction!(self::payment, m))));
+    try!(m.add_function(try!(wrap_pyfunction!(self::solve_apr, m))));
+    try!(m.add_function(try!(wrap_pyfunction!(self::project_payoff, m))));
+    try!(m.add_function(try!(wrap_pyfunction!(self::load_loan, m))));
+    try!(m.add_function(try!(wrap_pyfunction!(self::load_loans, m))));
+    Ok(())
+}
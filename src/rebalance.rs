@@ -0,0 +1,172 @@
+// Suggests where to put a fixed extra amount each month across several
+// loans. Whatever a loan stops needing once it's paid off rolls into the
+// budget for the rest. Extra paid on a loan with a prepayment penalty costs
+// the penalty on top, out of the same budget.
+
+use std::str::FromStr;
+
+use calc;
+
+// Which loan gets the extra money first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    // highest rate first, for the least interest overall
+    Avalanche,
+    // smallest balance first, for the most loans closed soonest
+    Snowball,
+    // highest rate first, net of any prepayment penalty, and none to loans
+    // where the penalty costs more than the interest it saves
+    Net,
+}
+
+impl Strategy {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Strategy::Avalanche => "avalanche",
+            Strategy::Snowball => "snowball",
+            Strategy::Net => "net",
+        }
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Strategy, String> {
+        match s {
+            "avalanche" => Ok(Strategy::Avalanche),
+            "snowball" => Ok(Strategy::Snowball),
+            "net" => Ok(Strategy::Net),
+            _ => Err(format!("Unknown strategy: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Debt {
+    pub name: String,
+    pub balance: f64,
+    pub apr: f64,
+    pub payment: f64,
+    // percent of extra principal charged, for the next penalty_months
+    pub penalty: f64,
+    pub penalty_months: i32,
+}
+
+impl Debt {
+    fn penalized(&self, month: i32) -> bool {
+        month <= self.penalty_months && self.penalty > 0f64
+    }
+
+    // Yearly rate extra paid on the debt in `month` (1-based) earns once the
+    // penalty is taken out, spreading the penalty over the months left to
+    // pay off `balance`.
+    fn net_rate(&self, balance: f64, month: i32) -> f64 {
+        if !self.penalized(month) {
+            return self.apr;
+        }
+        let months = calc::project_payoff(balance, MAX_MONTHS, self.apr, self.payment).periods;
+        self.apr - self.penalty * 12.0 / months as f64
+    }
+}
+
+// Projected outcome of paying a budget on top of the regular payments.
+#[derive(Debug, Clone)]
+pub struct Plan {
+    // None for regular payments only
+    pub strategy: Option<Strategy>,
+    // extra for each loan this month
+    pub allocation: Vec<(String, f64)>,
+    pub months: i32,
+    pub total_interest: f64,
+    // prepayment penalties paid on the extra
+    pub total_penalties: f64,
+    // month each loan is paid off in, soonest first
+    pub payoffs: Vec<(String, i32)>,
+}
+
+impl Plan {
+    // Loans paid off within the first `months`.
+    pub fn closed_by(&self, months: i32) -> usize {
+        self.payoffs.iter().filter(|&&(_, month)| month <= months).count()
+    }
+
+    // Interest and penalties together.
+    pub fn total_cost(&self) -> f64 {
+        self.total_interest + self.total_penalties
+    }
+}
+
+// Gives up on loans whose payment never covers the interest.
+const MAX_MONTHS: i32 = 1200;
+
+// Pays every debt down month by month, putting `budget`, plus the payments of
+// loans already paid off, towards them in `strategy` order.
+pub fn plan(debts: &[Debt], budget: f64, strategy: Option<Strategy>) -> Plan {
+    let mut order: Vec<usize> = (0..debts.len()).collect();
+    match strategy {
+        Some(Strategy::Avalanche) => order.sort_by(|&a, &b| debts[b].apr.partial_cmp(&debts[a].apr).unwrap()),
+        Some(Strategy::Snowball) => order.sort_by(|&a, &b| debts[a].balance.partial_cmp(&debts[b].balance).unwrap()),
+        // reordered every month as penalties run out
+        Some(Strategy::Net) | None => {},
+    }
+
+    let mut balances: Vec<f64> = debts.iter().map(|debt| debt.balance).collect();
+    let mut plan = Plan{
+        strategy: strategy,
+        allocation: Vec::new(),
+        months: 0,
+        total_interest: 0f64,
+        total_penalties: 0f64,
+        payoffs: Vec::new(),
+    };
+    while balances.iter().any(|&balance| balance > 0f64) && plan.months < MAX_MONTHS {
+        plan.months += 1;
+        if strategy == Some(Strategy::Net) {
+            let rates: Vec<f64> = (0..debts.len()).map(|i| debts[i].net_rate(balances[i], plan.months)).collect();
+            order = (0..debts.len()).filter(|&i| rates[i] > 0f64).collect();
+            order.sort_by(|&a, &b| rates[b].partial_cmp(&rates[a]).unwrap());
+        }
+        let mut pool = if strategy.is_some() { budget } else { 0f64 };
+        for (i, debt) in debts.iter().enumerate() {
+            if balances[i] <= 0f64 {
+                // a paid-off loan's payment goes to the others
+                if strategy.is_some() {
+                    pool += debt.payment;
+                }
+                continue;
+            }
+            let interest = calc::interest(balances[i], debt.apr);
+            let principal = (debt.payment - interest).min(balances[i]);
+            plan.total_interest += interest;
+            balances[i] -= principal;
+            // so does what's left of a final payment
+            if strategy.is_some() {
+                pool += debt.payment - interest - principal;
+            }
+        }
+        for &i in order.iter() {
+            if pool <= 0f64 || strategy.is_none() {
+                break;
+            }
+            if balances[i] <= 0f64 {
+                continue;
+            }
+            // the penalty comes out of the same pool
+            let penalty = if debts[i].penalized(plan.months) { debts[i].penalty / 100.0 } else { 0f64 };
+            let extra = (pool / (1.0 + penalty)).min(balances[i]);
+            balances[i] -= extra;
+            pool -= extra * (1.0 + penalty);
+            plan.total_penalties += extra * penalty;
+            if plan.months == 1 {
+                plan.allocation.push((debts[i].name.clone(), extra));
+            }
+        }
+        for (i, debt) in debts.iter().enumerate() {
+            if balances[i] <= 0f64 && !plan.payoffs.iter().any(|(name, _)| *name == debt.name) {
+                plan.payoffs.push((debt.name.clone(), plan.months));
+            }
+        }
+    }
+    plan
+}
@@ -0,0 +1,246 @@
+// Renders tables of results (payment history, totals, cash flow...) for the
+// terminal or for other tools. Commands build a Table and hand it to whichever
+// renderer --output names, so a new format only needs a Renderer. Built in are
+// text (aligned columns), json, csv, markdown and html; a crate depending on
+// this one can add its own with register:
+//
+//     amortization::render::register(Arc::new(MyFormat));
+//
+// Output too big to hold as a Table, like export's schedules, is streamed a
+// row at a time with the same json_object and csv_row the renderers use.
+
+use std::cmp;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    // already formatted, e.g. to the loan's currency; unquoted in JSON
+    Number(String),
+}
+
+impl Cell {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Cell::Text(ref value) | Cell::Number(ref value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub fn new(columns: &[&str]) -> Table {
+        Table{
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    // Adds a row, which should have a cell for each column.
+    pub fn push(&mut self, row: Vec<Cell>) {
+        self.rows.push(row);
+    }
+}
+
+pub trait Renderer: Send + Sync {
+    // What --output calls it, e.g. "csv".
+    fn name(&self) -> &str;
+
+    fn render(&self, out: &mut dyn Write, table: &Table) -> io::Result<()>;
+}
+
+// Columns padded to line up, numbers to the right, under a header.
+#[derive(Debug, Clone, Copy)]
+pub struct Text;
+
+impl Renderer for Text {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn render(&self, out: &mut dyn Write, table: &Table) -> io::Result<()> {
+        let mut widths: Vec<usize> = table.columns.iter().map(|column| column.chars().count()).collect();
+        for row in table.rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = cmp::max(*width, cell.as_str().chars().count());
+            }
+        }
+        let header: Vec<String> = table.columns.iter().zip(widths.iter()).map(|(column, &width)| format!("{:<1$}", column, width)).collect();
+        try!(writeln!(out, "{}", header.join("  ").trim_end()));
+        for row in table.rows.iter() {
+            let cells: Vec<String> = row.iter().zip(widths.iter()).map(|(cell, &width)| match *cell {
+                Cell::Number(ref value) => format!("{:>1$}", value, width),
+                Cell::Text(ref value) => format!("{:<1$}", value, width),
+            }).collect();
+            try!(writeln!(out, "{}", cells.join("  ").trim_end()));
+        }
+        Ok(())
+    }
+}
+
+// `value` as a JSON string literal, quoted and escaped.
+pub fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// A row as a JSON object keyed by column. Numbers are written bare as long
+// as JSON can hold them, anything else as a string.
+pub fn json_object<S: AsRef<str>>(columns: &[S], row: &[Cell]) -> String {
+    let members: Vec<String> = columns.iter().zip(row.iter()).map(|(column, cell)| {
+        let value = match *cell {
+            Cell::Number(ref value) if value.parse::<f64>().map(|value| value.is_finite()).unwrap_or(false) => value.clone(),
+            _ => json_string(cell.as_str()),
+        };
+        format!("{}:{}", json_string(column.as_ref()), value)
+    }).collect();
+    format!("{{{}}}", members.join(","))
+}
+
+// A CSV field, quoted if it contains the separator or anything else CSV
+// treats specially.
+pub fn csv_field(value: &str, sep: char) -> String {
+    if value.contains(sep) || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace("\"", "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Writes a line of `fields` separated by `sep`, each quoted as needed.
+pub fn csv_row<S: AsRef<str>>(out: &mut dyn Write, sep: char, fields: &[S]) -> io::Result<()> {
+    writeln!(out, "{}", fields.iter().map(|field| csv_field(field.as_ref(), sep)).collect::<Vec<_>>().join(&sep.to_string()))
+}
+
+// An array with an object per row, keyed by column.
+#[derive(Debug, Clone, Copy)]
+pub struct Json;
+
+impl Renderer for Json {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn render(&self, out: &mut dyn Write, table: &Table) -> io::Result<()> {
+        try!(write!(out, "["));
+        for (i, row) in table.rows.iter().enumerate() {
+            try!(write!(out, "{}\n  {}", if i == 0 { "" } else { "," }, json_object(&table.columns, row)));
+        }
+        writeln!(out, "{}]", if table.rows.is_empty() { "" } else { "\n" })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Csv;
+
+impl Renderer for Csv {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn render(&self, out: &mut dyn Write, table: &Table) -> io::Result<()> {
+        try!(csv_row(out, ',', &table.columns));
+        for row in table.rows.iter() {
+            try!(csv_row(out, ',', &row.iter().map(|cell| cell.as_str()).collect::<Vec<_>>()));
+        }
+        Ok(())
+    }
+}
+
+// A GitHub-flavored Markdown table.
+#[derive(Debug, Clone, Copy)]
+pub struct Markdown;
+
+impl Renderer for Markdown {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn render(&self, out: &mut dyn Write, table: &Table) -> io::Result<()> {
+        let field = |value: &str| value.replace("|", "\\|").replace("\n", " ");
+        try!(writeln!(out, "| {} |", table.columns.iter().map(|column| field(column)).collect::<Vec<_>>().join(" | ")));
+        // numbers aligned right, going by the first row
+        let aligns: Vec<&str> = table.columns.iter().enumerate().map(|(i, _)| match table.rows.first().and_then(|row| row.get(i)) {
+            Some(&Cell::Number(_)) => "---:",
+            _ => "---",
+        }).collect();
+        try!(writeln!(out, "| {} |", aligns.join(" | ")));
+        for row in table.rows.iter() {
+            try!(writeln!(out, "| {} |", row.iter().map(|cell| field(cell.as_str())).collect::<Vec<_>>().join(" | ")));
+        }
+        Ok(())
+    }
+}
+
+// `value` with the characters HTML treats specially escaped.
+pub fn html_escape(value: &str) -> String {
+    value.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;").replace("\"", "&quot;")
+}
+
+// A bare <table>, for pasting into a page.
+#[derive(Debug, Clone, Copy)]
+pub struct Html;
+
+impl Renderer for Html {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn render(&self, out: &mut dyn Write, table: &Table) -> io::Result<()> {
+        try!(writeln!(out, "<table>"));
+        try!(writeln!(out, "  <tr>{}</tr>", table.columns.iter().map(|column| format!("<th>{}</th>", html_escape(column))).collect::<String>()));
+        for row in table.rows.iter() {
+            let cells: String = row.iter().map(|cell| match *cell {
+                Cell::Number(ref value) => format!("<td style=\"text-align: right\">{}</td>", html_escape(value)),
+                Cell::Text(ref value) => format!("<td>{}</td>", html_escape(value)),
+            }).collect();
+            try!(writeln!(out, "  <tr>{}</tr>", cells));
+        }
+        writeln!(out, "</table>")
+    }
+}
+
+// Renderers registered on top of the built-in ones.
+static REGISTRY: Mutex<Vec<Arc<dyn Renderer>>> = Mutex::new(Vec::new());
+
+// Makes `renderer` available by name, replacing any renderer already
+// registered under it. The built-in renderers can't be replaced.
+pub fn register(renderer: Arc<dyn Renderer>) {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.retain(|registered| registered.name() != renderer.name());
+    registry.push(renderer);
+}
+
+fn builtin() -> Vec<Arc<dyn Renderer>> {
+    vec![Arc::new(Text), Arc::new(Json), Arc::new(Csv), Arc::new(Markdown), Arc::new(Html)]
+}
+
+// The renderer registered as `name`, if any.
+pub fn lookup(name: &str) -> Option<Arc<dyn Renderer>> {
+    builtin().into_iter()
+        .chain(REGISTRY.lock().unwrap().iter().cloned())
+        .find(|renderer| renderer.name() == name)
+}
+
+// Names of every renderer available, built-in ones first.
+pub fn names() -> Vec<String> {
+    builtin().into_iter()
+        .chain(REGISTRY.lock().unwrap().iter().cloned())
+        .map(|renderer| renderer.name().to_string())
+        .collect()
+}
@@ -0,0 +1,74 @@
+// Reverse mortgages. Nothing is paid; the balance grows each month by the
+// accrued interest plus whatever is drawn, while the home (hopefully)
+// appreciates underneath it.
+
+use calc;
+
+#[derive(Debug, Clone)]
+pub struct ReverseMortgage {
+    pub balance: f64,
+    pub apr: f64,
+    // amount drawn at the start of every month
+    pub monthly_draw: f64,
+    pub home_value: f64,
+    // yearly change in home value, in percent
+    pub appreciation: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReverseMonth {
+    pub period: i32,
+    pub draw: f64,
+    pub interest: f64,
+    pub balance: f64,
+    pub home_value: f64,
+}
+
+impl ReverseMonth {
+    pub fn equity(&self) -> f64 {
+        self.home_value - self.balance
+    }
+}
+
+impl ReverseMortgage {
+    pub fn projection(&self, periods: i32) -> ReverseProjection {
+        ReverseProjection{
+            loan: self.clone(),
+            period: 0,
+            periods: periods,
+        }
+    }
+}
+
+// Iterates month by month over the balance and home value.
+#[derive(Debug, Clone)]
+pub struct ReverseProjection {
+    loan: ReverseMortgage,
+    period: i32,
+    periods: i32,
+}
+
+impl Iterator for ReverseProjection {
+    type Item = ReverseMonth;
+
+    fn next(&mut self) -> Option<ReverseMonth> {
+        if self.period >= self.periods {
+            return None;
+        }
+
+        self.period += 1;
+        let loan = &mut self.loan;
+        loan.balance += loan.monthly_draw;
+        let interest = calc::interest(loan.balance, loan.apr);
+        loan.balance += interest;
+        loan.home_value *= (1.0 + loan.appreciation / 100.0).powf(1.0 / 12.0);
+
+        Some(ReverseMonth{
+            period: self.period,
+            draw: loan.monthly_draw,
+            interest: interest,
+            balance: loan.balance,
+            home_value: loan.home_value,
+        })
+    }
+}
@@ -0,0 +1,105 @@
+// What-if scenarios: changes layered on top of a loan's regular schedule,
+// saved by name so they can be re-run and compared later.
+
+use std::fmt;
+use std::str::FromStr;
+
+use calc;
+
+// A yearly extra payment, e.g. a tax refund put towards the loan each April.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LumpSum {
+    // calendar month it's paid in, 1-12
+    pub month: i32,
+    pub amount: f64,
+}
+
+impl fmt::Display for LumpSum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.amount, self.month)
+    }
+}
+
+// Parses AMOUNT@MONTH, e.g. 3000@4.
+impl FromStr for LumpSum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<LumpSum, String> {
+        let mut parts = s.splitn(2, '@');
+        let amount = parts.next().unwrap().parse();
+        match (amount, parts.next().map(|month| month.parse())) {
+            (Ok(amount), Some(Ok(month))) if (1..=12).contains(&month) => Ok(LumpSum{ month: month, amount: amount }),
+            _ => Err(format!("Lump sums must look like AMOUNT@MONTH (1-12), got: {}", s)),
+        }
+    }
+}
+
+// Refinancing the remaining balance into a new loan.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Refinance {
+    pub apr: f64,
+    pub periods: i32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Scenario {
+    pub name: String,
+    // loan the scenario applies to
+    pub loan: String,
+    pub monthly_extra: f64,
+    pub lump_sums: Vec<LumpSum>,
+    pub refinance: Option<Refinance>,
+    // change in apr, in percentage points, after shock_periods payments
+    pub rate_shock: f64,
+    pub shock_periods: i32,
+}
+
+impl Scenario {
+    // Applies the scenario to a loan's schedule. `first_month` is the
+    // calendar month (1-12) the schedule's first payment falls in.
+    pub fn apply(&self, base: calc::Schedule, first_month: i32) -> calc::Schedule {
+        let mut schedule = match self.refinance {
+            Some(refi) => {
+                let balance = base.balance();
+                calc::Schedule::new(balance, refi.periods, refi.apr, calc::payment(balance, refi.periods, refi.apr))
+            },
+            None => base,
+        };
+
+        schedule = schedule.extra(self.monthly_extra);
+        for lump in self.lump_sums.iter() {
+            let offset = (lump.month - first_month + 12) % 12;
+            schedule = schedule.annual_extra(offset + 1, lump.amount);
+        }
+        if self.rate_shock != 0f64 {
+            schedule = schedule.rate_shock(self.shock_periods, self.rate_shock);
+        }
+        schedule
+    }
+
+    // Payoff if the scenario is put off for `delay` payments, the loan
+    // following its regular schedule until then.
+    pub fn delayed_payoff(&self, base: calc::Schedule, first_month: i32, delay: i32) -> calc::Payoff {
+        let mut base = base;
+        let mut payoff = calc::Payoff{
+            periods: 0,
+            total_interest: 0f64,
+            total_paid: 0f64,
+        };
+        for inst in base.by_ref().take(delay as usize) {
+            payoff.periods = inst.period;
+            payoff.total_interest += inst.interest;
+            payoff.total_paid += inst.payment;
+        }
+
+        // a refinanced loan counts its payments from 1 again
+        let offset = if self.refinance.is_some() { payoff.periods } else { 0 };
+        let rest = self.apply(base, first_month).payoff();
+        if rest.periods > 0 {
+            payoff.periods = offset + rest.periods;
+        }
+        payoff.total_interest += rest.total_interest;
+        payoff.total_paid += rest.total_paid;
+        payoff
+    }
+}
@@ -0,0 +1,141 @@
+// JSON Schemas for the JSON the CLI writes, so other tools can code against
+// it: the loan listing (list), export --format json (schedule), history and
+// status (summary). Each command prints its own with --schema.
+//
+// The shapes are versioned together. Adding a member is backwards compatible
+// and leaves VERSION alone; renaming, retyping or removing one bumps it.
+// The listing, history and status build their tables from these columns, so
+// the two can't drift.
+
+use render::json_string;
+
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Integer,
+    Number,
+    Text,
+    // YYYY-MM-DD
+    Date,
+    // a string, one of these
+    OneOf(&'static [&'static str]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Field {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub description: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Schema {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub fields: &'static [Field],
+}
+
+const STATUSES: &[&str] = &["current", "grace", "late", "delinquent", "paid off"];
+const KINDS: &[&str] = &["payment", "extra", "fee", "adjustment", "draw", "reversal", "capitalization", "payoff"];
+
+pub const LIST: Schema = Schema{
+    name: "list",
+    description: "Loans in the database, one object each",
+    fields: &[
+        Field{ name: "loan", kind: Kind::Text, description: "name of the loan" },
+        Field{ name: "balance", kind: Kind::Number, description: "principal still owed, in the loan's currency" },
+        Field{ name: "apr", kind: Kind::Number, description: "annual percentage rate, in percent" },
+        Field{ name: "payment", kind: Kind::Number, description: "regular payment, in the loan's currency" },
+        Field{ name: "currency", kind: Kind::Text, description: "ISO 4217 code" },
+        Field{ name: "status", kind: Kind::OneOf(STATUSES), description: "as of the last operation on the loan" },
+        Field{ name: "maturity", kind: Kind::Date, description: "when the last payment is due as contracted" },
+        Field{ name: "lender", kind: Kind::Text, description: "lender servicing the loan, or empty" },
+        Field{ name: "lent", kind: Kind::OneOf(&["true", "false"]), description: "whether the money was lent out, so it's owed to us" },
+    ],
+};
+
+pub const SCHEDULE: Schema = Schema{
+    name: "schedule",
+    description: "Installments of each loan's schedule, in order",
+    fields: &[
+        Field{ name: "loan", kind: Kind::Text, description: "name of the loan" },
+        Field{ name: "period", kind: Kind::Integer, description: "payment number, from 1" },
+        Field{ name: "date", kind: Kind::Date, description: "due date" },
+        Field{ name: "payment", kind: Kind::Number, description: "amount due" },
+        Field{ name: "principal", kind: Kind::Number, description: "part of the payment going to the balance" },
+        Field{ name: "interest", kind: Kind::Number, description: "part of the payment going to interest" },
+        Field{ name: "balance", kind: Kind::Number, description: "balance after the payment" },
+    ],
+};
+
+pub const HISTORY: Schema = Schema{
+    name: "history",
+    description: "Recorded transactions, oldest first",
+    fields: &[
+        Field{ name: "id", kind: Kind::Integer, description: "transaction number, as bounce takes" },
+        Field{ name: "date", kind: Kind::Date, description: "date of the transaction" },
+        Field{ name: "loan", kind: Kind::Text, description: "name of the loan" },
+        Field{ name: "kind", kind: Kind::OneOf(KINDS), description: "what the transaction was" },
+        Field{ name: "principal", kind: Kind::Number, description: "amount applied to the balance" },
+        Field{ name: "interest", kind: Kind::Number, description: "amount applied to interest" },
+        Field{ name: "escrow", kind: Kind::Number, description: "amount collected for escrow" },
+        Field{ name: "pmi", kind: Kind::Number, description: "amount collected for mortgage insurance" },
+        Field{ name: "method", kind: Kind::Text, description: "payment method, or empty" },
+        Field{ name: "state", kind: Kind::OneOf(&["", "reversed", "pending"]), description: "empty unless reversed or pending review" },
+    ],
+};
+
+pub const SUMMARY: Schema = Schema{
+    name: "summary",
+    description: "Totals over every loan shown, in the reporting currency, as the array's one object",
+    fields: &[
+        Field{ name: "currency", kind: Kind::Text, description: "reporting currency" },
+        Field{ name: "balance", kind: Kind::Number, description: "total owed" },
+        Field{ name: "payment", kind: Kind::Number, description: "total monthly payment" },
+        Field{ name: "apr", kind: Kind::Number, description: "average APR weighted by balance, in percent" },
+        Field{ name: "owed to you", kind: Kind::Number, description: "total of loans lent out" },
+        Field{ name: "equity", kind: Kind::Number, description: "collateral value less what's owed on it" },
+        Field{ name: "assets", kind: Kind::Number, description: "total assets" },
+        Field{ name: "debt free", kind: Kind::Text, description: "month the last loan is paid off, or empty" },
+        Field{ name: "interest saved", kind: Kind::Number, description: "interest saved by extra payments so far" },
+    ],
+};
+
+// Every schema, in the order --schema documents them.
+pub fn all() -> Vec<Schema> {
+    vec![LIST, SCHEDULE, HISTORY, SUMMARY]
+}
+
+// The schema called `name`, if any.
+pub fn lookup(name: &str) -> Option<Schema> {
+    all().into_iter().find(|schema| schema.name == name)
+}
+
+impl Schema {
+    // Column names, for a render::Table.
+    pub fn columns(&self) -> Vec<&'static str> {
+        self.fields.iter().map(|field| field.name).collect()
+    }
+
+    // The schema as a draft-07 JSON Schema: an array of objects, each with
+    // every field.
+    pub fn json(&self) -> String {
+        let properties: Vec<String> = self.fields.iter().map(|field| {
+            let kind = match field.kind {
+                Kind::Integer => "\"type\": \"integer\"".to_string(),
+                Kind::Number => "\"type\": \"number\"".to_string(),
+                Kind::Text => "\"type\": \"string\"".to_string(),
+                Kind::Date => "\"type\": \"string\", \"format\": \"date\"".to_string(),
+                Kind::OneOf(values) => format!("\"type\": \"string\", \"enum\": [{}]",
+                                               values.iter().map(|value| json_string(value)).collect::<Vec<_>>().join(", ")),
+            };
+            format!("        {}: {{{}, \"description\": {}}}", json_string(field.name), kind, json_string(field.description))
+        }).collect();
+        let required: Vec<String> = self.fields.iter().map(|field| json_string(field.name)).collect();
+        format!("{{\n  \"$schema\": \"http://json-schema.org/draft-07/schema#\",\n  \"$id\": \"urn:amortization:{}:v{}\",\n  \"title\": {},\n  \
+                 \"description\": {},\n  \"version\": {},\n  \"type\": \"array\",\n  \"items\": {{\n    \"type\": \"object\",\n    \
+                 \"properties\": {{\n{}\n    }},\n    \"required\": [{}],\n    \"additionalProperties\": false\n  }}\n}}\n",
+                self.name, VERSION, json_string(self.name), json_string(self.description), VERSION, properties.join(",\n"), required.join(", "))
+    }
+}
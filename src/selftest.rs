@@ -0,0 +1,165 @@
+// Canonical loans with schedules worked out independently of this crate, for
+// the selftest command: a build can be run against them on a new platform or
+// toolchain before it's trusted with money. Each case lists a few rows of its
+// schedule plus the number of payments and the total interest, and anything
+// a cent or more off is reported.
+
+use calc::{self, Installment, Repayment, Rounding};
+use validate::{self, RowDelta};
+
+const TOLERANCE: f64 = 0.01;
+
+pub struct Case {
+    pub name: &'static str,
+    schedule: fn() -> calc::Schedule,
+    // (period, payment, interest, principal, balance)
+    rows: &'static [(i32, f64, f64, f64, f64)],
+    periods: i32,
+    total_interest: f64,
+}
+
+impl Case {
+    fn expected(&self) -> Vec<Installment> {
+        self.rows.iter().map(|&(period, payment, interest, principal, balance)| Installment{
+            period: period,
+            payment: payment,
+            interest: interest,
+            principal: principal,
+            balance: balance,
+        }).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub name: &'static str,
+    // rows checked, and those that didn't match
+    pub rows: usize,
+    pub mismatches: Vec<RowDelta>,
+    pub expected_periods: i32,
+    pub periods: i32,
+    pub expected_interest: f64,
+    pub total_interest: f64,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty() && self.periods == self.expected_periods && (self.total_interest - self.expected_interest).abs() < TOLERANCE
+    }
+}
+
+fn fixed_30_years() -> calc::Schedule {
+    calc::Schedule::new(200000.0, 360, 6.0, calc::payment(200000.0, 360, 6.0))
+}
+
+fn auto_to_the_cent() -> calc::Schedule {
+    let rounding = Rounding::HalfUp(2);
+    calc::Schedule::new(25000.0, 60, 4.5, rounding.apply(calc::payment(25000.0, 60, 4.5)))
+        .rounding(rounding, rounding)
+}
+
+fn interest_free() -> calc::Schedule {
+    calc::Schedule::new(12000.0, 12, 0.0, calc::payment(12000.0, 12, 0.0))
+}
+
+fn fixed_principal() -> calc::Schedule {
+    calc::Schedule::new(12000.0, 12, 6.0, calc::linear_payment(12000.0, 12, 6.0, calc::Timing::Arrears))
+        .repayment(Repayment::Linear)
+}
+
+fn interest_only() -> calc::Schedule {
+    calc::Schedule::new(50000.0, 60, 7.0, calc::interest(50000.0, 7.0))
+        .repayment(Repayment::Bullet)
+}
+
+fn long_first_period() -> calc::Schedule {
+    calc::Schedule::new(10000.0, 12, 12.0, calc::payment(10000.0, 12, 12.0))
+        .first_period_days(45)
+}
+
+pub fn cases() -> Vec<Case> {
+    vec![
+        Case{
+            name: "30-year fixed, 200000 at 6%",
+            schedule: fixed_30_years,
+            rows: &[(1, 1199.10, 1000.00, 199.10, 199800.90),
+                    (2, 1199.10, 999.00, 200.10, 199600.80),
+                    (180, 1199.10, 712.92, 486.18, 142097.69),
+                    (359, 1199.10, 11.90, 1187.20, 1193.14),
+                    (360, 1199.10, 5.97, 1193.14, 0.00)],
+            periods: 360,
+            total_interest: 231676.38,
+        },
+        Case{
+            name: "5-year auto, 25000 at 4.5%, rounded to the cent",
+            schedule: auto_to_the_cent,
+            rows: &[(1, 466.08, 93.75, 372.33, 24627.67),
+                    (2, 466.08, 92.35, 373.73, 24253.94),
+                    (30, 466.08, 51.06, 415.02, 13200.93),
+                    (59, 466.08, 3.47, 462.61, 464.00),
+                    (60, 465.74, 1.74, 464.00, 0.00)],
+            periods: 60,
+            total_interest: 2964.46,
+        },
+        Case{
+            name: "interest-free, 12000 over a year",
+            schedule: interest_free,
+            rows: &[(1, 1000.00, 0.00, 1000.00, 11000.00),
+                    (6, 1000.00, 0.00, 1000.00, 6000.00),
+                    (12, 1000.00, 0.00, 1000.00, 0.00)],
+            periods: 12,
+            total_interest: 0.00,
+        },
+        Case{
+            name: "fixed principal, 12000 at 6%",
+            schedule: fixed_principal,
+            rows: &[(1, 1060.00, 60.00, 1000.00, 11000.00),
+                    (2, 1055.00, 55.00, 1000.00, 10000.00),
+                    (6, 1035.00, 35.00, 1000.00, 6000.00),
+                    (11, 1010.00, 10.00, 1000.00, 1000.00),
+                    (12, 1005.00, 5.00, 1000.00, 0.00)],
+            periods: 12,
+            total_interest: 390.00,
+        },
+        Case{
+            name: "interest only, 50000 at 7% for 5 years",
+            schedule: interest_only,
+            rows: &[(1, 291.67, 291.67, 0.00, 50000.00),
+                    (30, 291.67, 291.67, 0.00, 50000.00),
+                    (59, 291.67, 291.67, 0.00, 50000.00),
+                    (60, 50291.67, 291.67, 50000.00, 0.00)],
+            periods: 60,
+            total_interest: 17500.00,
+        },
+        Case{
+            name: "45 days to the first payment, 10000 at 12%",
+            schedule: long_first_period,
+            rows: &[(1, 936.43, 147.95, 788.49, 9211.51),
+                    (2, 888.49, 92.12, 796.37, 8415.14),
+                    (6, 888.49, 59.78, 828.71, 5149.21),
+                    (11, 888.49, 17.51, 870.98, 879.69),
+                    (12, 888.49, 8.80, 879.69, 0.00)],
+            periods: 12,
+            total_interest: 709.80,
+        },
+    ]
+}
+
+// Runs every case through the engine.
+pub fn run() -> Vec<Outcome> {
+    cases().iter().map(|case| {
+        let expected = case.expected();
+        let payoff = (case.schedule)().payoff();
+        Outcome{
+            name: case.name,
+            rows: expected.len(),
+            mismatches: validate::compare(&expected, (case.schedule)()).into_iter()
+                .filter(|delta| delta.expected.is_some() && delta.max() >= TOLERANCE)
+                .collect(),
+            expected_periods: case.periods,
+            periods: payoff.periods,
+            expected_interest: case.total_interest,
+            total_interest: payoff.total_interest,
+        }
+    }).collect()
+}
@@ -0,0 +1,267 @@
+// A small HTTP server for the serve command. It answers two requests, each
+// on its own listener:
+//
+//     GET /calculate?principal=250000&apr=6.5&term=30&frequency=monthly
+//     GET /metrics      the database's loans as Prometheus metrics
+//
+// /calculate never touches the database, so it's safe to put in front of a
+// public web calculator. It's limited to a number of requests per minute
+// from each client address. /metrics names every loan and its balance, so
+// it's only served when asked for, and only on a loopback address.
+//
+// Each connection is answered on a thread of its own, up to MAX_CONNECTIONS
+// at once, and a client that's slow to send its request or read the
+// response is cut off after TIMEOUT.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use calc::{self, Frequency};
+use db;
+use metrics;
+
+// Largest loan /calculate will lay out, to keep responses small.
+const MAX_YEARS: i32 = 50;
+
+// How long a client gets to send its request or take the response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// Longest request line or header line read, and most header lines.
+const MAX_LINE: usize = 8192;
+const MAX_HEADERS: usize = 64;
+
+// Connections answered at once on each listener; more are turned away.
+const MAX_CONNECTIONS: usize = 64;
+
+// Counts /calculate requests from each client over the current minute.
+struct RateLimit {
+    per_minute: u32,
+    clients: HashMap<IpAddr, (Instant, u32)>,
+}
+
+impl RateLimit {
+    fn allow(&mut self, client: IpAddr) -> bool {
+        let now = Instant::now();
+        // forget clients whose minute is up
+        self.clients.retain(|_, &mut (start, _)| now.duration_since(start) < Duration::from_secs(60));
+        let entry = self.clients.entry(client).or_insert((now, 0));
+        entry.1 += 1;
+        entry.1 <= self.per_minute
+    }
+}
+
+// What a listener answers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Endpoint {
+    Calculate,
+    Metrics,
+}
+
+// Only /calculate's responses may be read by pages on other sites.
+fn respond(stream: &mut TcpStream, endpoint: Endpoint, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    let cors = if endpoint == Endpoint::Calculate { "Access-Control-Allow-Origin: *\r\n" } else { "" };
+    write!(stream, "HTTP/1.0 {}\r\nContent-Type: {}\r\n{}Content-Length: {}\r\n\r\n{}", status, content_type, cors, body.len(), body)
+}
+
+fn json_error(message: &str) -> String {
+    format!("{{\"error\":\"{}\"}}\n", message.replace("\\", "\\\\").replace("\"", "\\\""))
+}
+
+fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&').filter_map(|pair| {
+        let mut parts = pair.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) if key == name => Some(value),
+            _ => None,
+        }
+    }).next()
+}
+
+// The schedule for the loan described by `query`, as JSON, or what's wrong
+// with the query.
+pub fn calculate(query: &str) -> Result<String, String> {
+    let number = |name: &str| -> Result<f64, String> {
+        match param(query, name).map(|value| value.parse::<f64>()) {
+            Some(Ok(value)) if value.is_finite() => Ok(value),
+            Some(_) => Err(format!("Invalid value for {}", name)),
+            None => Err(format!("Missing {}", name)),
+        }
+    };
+    let principal = try!(number("principal"));
+    let apr = try!(number("apr"));
+    let term = try!(number("term"));
+    let frequency: Frequency = try!(param(query, "frequency").unwrap_or("monthly").parse());
+    if principal <= 0f64 || principal > 1e12 {
+        return Err("principal must be positive and at most 1000000000000".to_string());
+    }
+    if apr < 0f64 || apr > 100f64 {
+        return Err("apr must be between 0 and 100".to_string());
+    }
+    if term < 1f64 || term > MAX_YEARS as f64 || term.fract() != 0f64 {
+        return Err(format!("term must be a whole number of years from 1 to {}", MAX_YEARS));
+    }
+
+    let schedule: Vec<calc::Installment> = frequency.schedule(principal, term as i32, apr).collect();
+    let payment = schedule.first().map_or(0f64, |inst| inst.payment);
+    let total_interest = schedule.iter().fold(0f64, |sum, inst| sum + inst.interest);
+    let rows: Vec<String> = schedule.iter().map(|inst| {
+        format!("{{\"period\":{},\"payment\":{:.2},\"principal\":{:.2},\"interest\":{:.2},\"balance\":{:.2}}}", inst.period, inst.payment,
+                inst.principal, inst.interest, inst.balance)
+    }).collect();
+    Ok(format!("{{\"principal\":{:.2},\"apr\":{},\"term\":{},\"frequency\":\"{}\",\"payment\":{:.2},\"total_interest\":{:.2},\"schedule\":[{}]}}\n",
+               principal, apr, term, frequency.as_str(), payment, total_interest, rows.join(",")))
+}
+
+// Reads a line of at most MAX_LINE bytes, or None if it's longer.
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> io::Result<Option<usize>> {
+    line.clear();
+    let read = try!(reader.by_ref().take(MAX_LINE as u64).read_line(line));
+    if read == MAX_LINE && !line.ends_with('\n') {
+        return Ok(None);
+    }
+    Ok(Some(read))
+}
+
+// The request line, or None if the request was too big.
+fn read_request(stream: &TcpStream) -> io::Result<Option<String>> {
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    if try!(read_line(&mut reader, &mut request)).is_none() {
+        return Ok(None);
+    }
+    // skip the headers
+    let mut line = String::new();
+    for _ in 0..MAX_HEADERS {
+        match try!(read_line(&mut reader, &mut line)) {
+            None => return Ok(None),
+            Some(read) if read <= 2 => return Ok(Some(request)),
+            Some(_) => {},
+        }
+    }
+    Ok(None)
+}
+
+fn handle(db: &Path, endpoint: Endpoint, limit: &Mutex<RateLimit>, mut stream: TcpStream) -> io::Result<()> {
+    try!(stream.set_read_timeout(Some(TIMEOUT)));
+    try!(stream.set_write_timeout(Some(TIMEOUT)));
+    let request = match try!(read_request(&stream)) {
+        Some(request) => request,
+        None => return respond(&mut stream, endpoint, "431 Request Header Fields Too Large", "text/plain", "Request too large\n"),
+    };
+    let client = try!(stream.peer_addr()).ip();
+    let mut parts = request.split_whitespace();
+    let (method, target) = (parts.next(), parts.next().unwrap_or(""));
+    let mut target = target.splitn(2, '?');
+    let (path, query) = (target.next().unwrap_or(""), target.next().unwrap_or(""));
+    match (endpoint, method, path) {
+        (Endpoint::Metrics, Some("GET"), "/metrics") => match metrics::render(db, db::today()) {
+            Ok(body) => respond(&mut stream, endpoint, "200 OK", "text/plain; version=0.0.4", &body),
+            Err(err) => {
+                error!("Error collecting metrics: {}", err);
+                respond(&mut stream, endpoint, "500 Internal Server Error", "text/plain", &format!("{}\n", err))
+            },
+        },
+        (Endpoint::Calculate, Some("GET"), "/calculate") => {
+            if !limit.lock().unwrap().allow(client) {
+                return respond(&mut stream, endpoint, "429 Too Many Requests", "application/json", &json_error("Too many requests; try again in a minute"));
+            }
+            match calculate(query) {
+                Ok(body) => respond(&mut stream, endpoint, "200 OK", "application/json", &body),
+                Err(err) => respond(&mut stream, endpoint, "400 Bad Request", "application/json", &json_error(&err)),
+            }
+        },
+        (Endpoint::Metrics, _, _) => respond(&mut stream, endpoint, "404 Not Found", "text/plain", "Not found; try /metrics\n"),
+        (Endpoint::Calculate, _, _) => respond(&mut stream, endpoint, "404 Not Found", "text/plain", "Not found; try /calculate\n"),
+    }
+}
+
+// Answers connections to `listener` until the process is killed.
+fn accept(db: PathBuf, endpoint: Endpoint, limit: Arc<Mutex<RateLimit>>, listener: TcpListener) {
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("Error accepting a connection: {}", err);
+                continue;
+            },
+        };
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+            let _ = stream.set_write_timeout(Some(TIMEOUT));
+            let _ = respond(&mut stream, endpoint, "503 Service Unavailable", "text/plain", "Too busy; try again shortly\n");
+            continue;
+        }
+        let (db, limit, active) = (db.clone(), limit.clone(), active.clone());
+        thread::spawn(move || {
+            if let Err(err) = handle(&db, endpoint, &limit, stream) {
+                warn!("Error answering a request: {}", err);
+            }
+            active.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+// Serves the calculator at `addr`, allowing each client `per_minute`
+// calculations a minute, and `db`'s metrics at `metrics_addr` if given, until
+// the process is killed. The metrics address must be a loopback one.
+pub fn serve(db: &Path, addr: &str, metrics_addr: Option<&str>, per_minute: u32) -> io::Result<()> {
+    let limit = Arc::new(Mutex::new(RateLimit{ per_minute: per_minute, clients: HashMap::new() }));
+    if let Some(metrics_addr) = metrics_addr {
+        let addrs: Vec<SocketAddr> = try!(metrics_addr.to_socket_addrs()).collect();
+        if addrs.is_empty() || addrs.iter().any(|addr| !addr.ip().is_loopback()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("metrics are only served on a loopback address, not {}", metrics_addr)));
+        }
+        let listener = try!(TcpListener::bind(&addrs[..]));
+        info!("Serving metrics on http://{}/metrics", metrics_addr);
+        let (db, limit) = (db.to_path_buf(), limit.clone());
+        thread::spawn(move || accept(db, Endpoint::Metrics, limit, listener));
+    }
+    let listener = try!(TcpListener::bind(addr));
+    info!("Serving the calculator on http://{}/calculate", addr);
+    accept(db.to_path_buf(), Endpoint::Calculate, limit, listener);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calculates_a_schedule() {
+        let body = calculate("principal=12000&apr=12&term=1").unwrap();
+        assert!(body.starts_with("{\"principal\":12000.00,\"apr\":12,\"term\":1,\"frequency\":\"monthly\",\"payment\":1066.19,"));
+        assert_eq!(body.matches("\"period\":").count(), 12);
+        assert!(body.ends_with("\"balance\":0.00}]}\n"));
+
+        let body = calculate("frequency=biweekly&term=1&apr=12&principal=12000").unwrap();
+        assert_eq!(body.matches("\"period\":").count(), 26);
+    }
+
+    #[test]
+    fn refuses_bad_queries() {
+        assert_eq!(calculate("apr=12&term=1"), Err("Missing principal".to_string()));
+        assert_eq!(calculate("principal=abc&apr=12&term=1"), Err("Invalid value for principal".to_string()));
+        assert_eq!(calculate("principal=NaN&apr=12&term=1"), Err("Invalid value for principal".to_string()));
+        assert!(calculate("principal=-5&apr=12&term=1").is_err());
+        assert!(calculate("principal=12000&apr=120&term=1").is_err());
+        assert!(calculate("principal=12000&apr=12&term=1.5").is_err());
+        assert!(calculate("principal=12000&apr=12&term=51").is_err());
+        assert!(calculate("principal=12000&apr=12&term=1&frequency=hourly").is_err());
+    }
+
+    #[test]
+    fn rate_limits_each_client() {
+        let mut limit = RateLimit{ per_minute: 2, clients: HashMap::new() };
+        let (one, two): (IpAddr, IpAddr) = ("192.0.2.1".parse().unwrap(), "192.0.2.2".parse().unwrap());
+        assert!(limit.allow(one) && limit.allow(one));
+        assert!(!limit.allow(one));
+        assert!(limit.allow(two));
+    }
+}
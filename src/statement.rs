@@ -0,0 +1,176 @@
+// Statements to send a borrower: what they paid over a period, how it was
+// split, and what they owe now. Statements are built as lines of text and
+// written out as plain text or as a PDF.
+
+use std::io::{self, Write};
+
+use time;
+
+use db::{Borrower, Lender, Loan, MonthlyStatement, Transaction, TransactionKind};
+
+fn date(ts: time::Timespec) -> String {
+    time::strftime("%F", &time::at_utc(ts)).unwrap()
+}
+
+fn heading(lines: &mut Vec<String>, title: String, lender: Option<&Lender>, borrowers: &[Borrower]) {
+    lines.push(title);
+    if let Some(lender) = lender {
+        lines.push(format!("From: {}", lender.name));
+        for detail in [&lender.phone, &lender.url].iter() {
+            if let Some(ref detail) = **detail {
+                lines.push(format!("      {}", detail));
+            }
+        }
+    }
+    if !borrowers.is_empty() {
+        let names: Vec<&str> = borrowers.iter().map(|borrower| &borrower.name[..]).collect();
+        lines.push(format!("Borrower: {}", names.join(", ")));
+    }
+}
+
+// One line per payment, with returned ones marked and amounts shown to the
+// loan currency's minor unit. Returns the principal and interest of those
+// that weren't returned.
+fn payment_lines<'a, I: Iterator<Item = &'a Transaction>>(lines: &mut Vec<String>, loan: &Loan, payments: I) -> (f64, f64) {
+    lines.push(format!("{:<12}{:>12}{:>12}{:>12}", "Date", "Paid", "Principal", "Interest"));
+    let mut totals = (0f64, 0f64);
+    for payment in payments {
+        if payment.reversed {
+            lines.push(format!("{:<12}{:>12}  returned unpaid", date(payment.date), loan.money(payment.principal + payment.interest)));
+            continue;
+        }
+        lines.push(format!("{:<12}{:>12}{:>12}{:>12}", date(payment.date), loan.money(payment.principal + payment.interest), loan.money(payment.principal),
+                           loan.money(payment.interest)));
+        totals.0 += payment.principal;
+        totals.1 += payment.interest;
+    }
+    lines.push(format!("{:<12}{:>12}{:>12}{:>12}", "Total", loan.money(totals.0 + totals.1), loan.money(totals.0), loan.money(totals.1)));
+    totals
+}
+
+// A statement for `loan` covering payments dated from `from` up to but not
+// including `to`. `payments` are the loan's transactions as loaded by
+// load_transactions; `lender`, if given, is who the statement is from.
+pub fn statement(loan: &Loan, lender: Option<&Lender>, borrowers: &[Borrower], payments: &[Transaction],
+                 from: time::Timespec, to: time::Timespec) -> Vec<String> {
+    let mut lines = Vec::new();
+    heading(&mut lines, format!("Loan statement: {}", loan.name), lender, borrowers);
+    lines.push(format!("Period: {} to {}", date(from), date(to)));
+    lines.push(String::new());
+
+    payment_lines(&mut lines, loan, payments.iter().filter(|payment| payment.kind.is_payment() && !payment.provisional && payment.date >= from && payment.date < to));
+    lines.push(String::new());
+
+    lines.push(format!("Balance: {} {}", loan.money(loan.balance), loan.currency));
+    if loan.balance > 0f64 {
+        let due = loan.next_due(to);
+        lines.push(format!("Next payment: {} due {}", loan.money(loan.amount_due(due)), date(due)));
+    }
+    lines
+}
+
+fn adjustment_label(kind: TransactionKind) -> &'static str {
+    match kind {
+        TransactionKind::Draw => "Draw",
+        TransactionKind::Reversal => "Returned payment",
+        TransactionKind::Capitalization => "Interest capitalized",
+        _ => "Balance adjustment",
+    }
+}
+
+// A month's statement, as built by monthly_statement.
+pub fn monthly(statement: &MonthlyStatement, lender: Option<&Lender>, borrowers: &[Borrower]) -> Vec<String> {
+    let loan = &statement.loan;
+    let mut lines = Vec::new();
+    heading(&mut lines, format!("Monthly statement: {}, {}", loan.name, time::strftime("%B %Y", &time::at_utc(statement.from)).unwrap()),
+            lender, borrowers);
+    lines.push(String::new());
+
+    lines.push(format!("{:<36}{:>12}", "Opening balance", loan.money(statement.opening_balance)));
+    lines.push(format!("{:<36}{:>12}", "Interest accrued", loan.money(statement.interest)));
+    for &(charged, ref fee) in statement.fees.iter() {
+        lines.push(format!("{:<36}{:>12}", format!("{} fee ({})", fee.name, date(charged)), loan.money(fee.amount)));
+    }
+    for adjustment in statement.adjustments.iter() {
+        lines.push(format!("{:<36}{:>12}", format!("{} ({})", adjustment_label(adjustment.kind), date(adjustment.date)), loan.money(-adjustment.principal)));
+    }
+    lines.push(String::new());
+
+    lines.push("Payments received".to_string());
+    payment_lines(&mut lines, loan, statement.payments.iter());
+    lines.push(String::new());
+
+    lines.push(format!("{:<36}{:>12} {}", "Closing balance", loan.money(statement.closing_balance), loan.currency));
+    if statement.closing_balance > 0f64 {
+        lines.push(format!("Next payment: {} due {}", loan.money(statement.amount_due), date(statement.next_due)));
+    }
+    lines
+}
+
+pub fn write_text<W: Write>(out: &mut W, lines: &[String]) -> io::Result<()> {
+    for line in lines {
+        try!(writeln!(out, "{}", line));
+    }
+    Ok(())
+}
+
+// US Letter, in points, and how many lines of 10 point Courier fit with an
+// inch of margin.
+const PAGE_WIDTH: i32 = 612;
+const PAGE_HEIGHT: i32 = 792;
+const LINES_PER_PAGE: usize = 54;
+
+fn pdf_string(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            },
+            // keep to ASCII, which every standard font's encoding covers
+            c if (c as u32) < 0x20 || (c as u32) > 0x7e => out.push('?'),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Writes the lines as a PDF in Courier, so columns stay lined up. Only the
+// standard PDF fonts are used, so nothing needs to be embedded.
+pub fn write_pdf<W: Write>(out: &mut W, lines: &[String]) -> io::Result<()> {
+    let pages: Vec<&[String]> = if lines.is_empty() { vec![lines] } else { lines.chunks(LINES_PER_PAGE).collect() };
+
+    // objects 1 and 2 are the catalog and page tree, 3 the font, then a page
+    // and its contents for each page
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..pages.len()).map(|i| format!("{} 0 R", 4 + i * 2)).collect::<Vec<_>>().join(" "), pages.len()),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier >>".to_string(),
+    ];
+    for (i, page) in pages.iter().enumerate() {
+        let mut content = format!("BT /F1 10 Tf 12 TL 72 {} Td\n", PAGE_HEIGHT - 72);
+        for line in page.iter() {
+            content.push_str(&format!("({}) Tj T*\n", pdf_string(line)));
+        }
+        content.push_str("ET");
+        objects.push(format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                             PAGE_WIDTH, PAGE_HEIGHT, 5 + i * 2));
+        objects.push(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref));
+    out.write_all(pdf.as_bytes())
+}
@@ -0,0 +1,74 @@
+// A machine-readable record of every change made to a database, for working
+// out after the fact how the data got the way it is. Each change is one
+// logfmt line:
+//
+//     time=2017-03-01T14:02:11Z op=payment loan=car amount=250.00 extra=false outcome=ok
+//
+// Changes are always logged at info level (error level when they fail, warn
+// when the loan's terms refuse them) and are also appended to a file once
+// trace_to has been called.
+
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use rusqlite;
+use time;
+
+use db;
+
+static SINK: Mutex<Option<File>> = Mutex::new(None);
+
+// Appends every change from now on to `path`.
+pub fn trace_to(path: &Path) -> io::Result<()> {
+    let file = try!(OpenOptions::new().create(true).append(true).open(path));
+    *SINK.lock().unwrap() = Some(file);
+    Ok(())
+}
+
+// Quotes a value if it wouldn't otherwise read back as one.
+fn value(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c == ' ' || c == '=' || c == '"' || c.is_control()) {
+        format!("{:?}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn write(line: &str) {
+    if let Some(ref mut file) = *SINK.lock().unwrap() {
+        if let Err(err) = writeln!(file, "{}", line) {
+            error!("Couldn't write to the trace file: {}", err);
+        }
+    }
+}
+
+// Runs `change`, tracing it as `op` with `fields` and whether it worked.
+pub fn traced<T, F>(op: &str, fields: &[(&str, &dyn Display)], change: F) -> rusqlite::Result<T>
+    where F: FnOnce() -> rusqlite::Result<T>
+{
+    let res = change();
+
+    let mut line = format!("time={} op={}", time::strftime("%FT%TZ", &time::now_utc()).unwrap(), op);
+    for &(key, field) in fields {
+        line.push_str(&format!(" {}={}", key, value(&field.to_string())));
+    }
+    match res {
+        Ok(_) => {
+            line.push_str(" outcome=ok");
+            info!("{}", line);
+        },
+        Err(ref err) if db::refusal(err).is_some() => {
+            line.push_str(&format!(" outcome=refused reason={}", value(&err.to_string())));
+            warn!("{}", line);
+        },
+        Err(ref err) => {
+            line.push_str(&format!(" outcome=error error={}", value(&err.to_string())));
+            error!("{}", line);
+        },
+    };
+    write(&line);
+    res
+}
@@ -0,0 +1,100 @@
+// Checks a computed schedule against one the lender provided, row by row, to
+// find the settings that reproduce the lender's numbers.
+
+use calc::{self, Rounding};
+
+// How far a computed row is from the lender's: computed minus expected. A
+// row missing from either side has no deltas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RowDelta {
+    pub period: i32,
+    pub expected: Option<calc::Installment>,
+    pub computed: Option<calc::Installment>,
+}
+
+impl RowDelta {
+    pub fn payment(&self) -> Option<f64> {
+        self.delta(|inst| inst.payment)
+    }
+
+    pub fn interest(&self) -> Option<f64> {
+        self.delta(|inst| inst.interest)
+    }
+
+    pub fn principal(&self) -> Option<f64> {
+        self.delta(|inst| inst.principal)
+    }
+
+    pub fn balance(&self) -> Option<f64> {
+        self.delta(|inst| inst.balance)
+    }
+
+    fn delta<F: Fn(&calc::Installment) -> f64>(&self, field: F) -> Option<f64> {
+        match (self.expected, self.computed) {
+            (Some(ref expected), Some(ref computed)) => Some(field(computed) - field(expected)),
+            _ => None,
+        }
+    }
+
+    // Largest difference in any column, or infinity if the row is missing
+    // from one side.
+    pub fn max(&self) -> f64 {
+        [self.payment(), self.interest(), self.principal(), self.balance()].iter()
+            .map(|delta| delta.map_or(f64::INFINITY, f64::abs))
+            .fold(0f64, f64::max)
+    }
+
+    // Whether the row matches to the cent.
+    pub fn matches(&self) -> bool {
+        self.max() < 0.005
+    }
+}
+
+// Lines up `computed` with the lender's rows by period.
+pub fn compare<I: Iterator<Item = calc::Installment>>(expected: &[calc::Installment], computed: I) -> Vec<RowDelta> {
+    let computed: Vec<calc::Installment> = computed.collect();
+    let periods = expected.iter().chain(computed.iter()).map(|inst| inst.period).max().unwrap_or(0);
+    (1..periods + 1).map(|period| RowDelta{
+        period: period,
+        expected: expected.iter().find(|inst| inst.period == period).cloned(),
+        computed: computed.iter().find(|inst| inst.period == period).cloned(),
+    }).filter(|delta| delta.expected.is_some() || delta.computed.is_some()).collect()
+}
+
+// A combination of rounding settings and how closely it reproduces the
+// lender's schedule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fit {
+    pub interest: Rounding,
+    pub payment: Rounding,
+    // rows that match to the cent
+    pub matched: usize,
+    pub rows: usize,
+    pub max_delta: f64,
+}
+
+const INTEREST_ROUNDINGS: [Rounding; 4] = [Rounding::Exact, Rounding::HalfUp(2), Rounding::Truncate(2), Rounding::Up(2)];
+const PAYMENT_ROUNDINGS: [Rounding; 5] = [Rounding::Exact, Rounding::HalfUp(2), Rounding::Truncate(2), Rounding::Up(2), Rounding::Up(0)];
+
+// Tries the common rounding settings, best fit first. `schedule` builds the
+// schedule under the given interest and payment rounding.
+pub fn fits<F: Fn(Rounding, Rounding) -> calc::Schedule>(expected: &[calc::Installment], schedule: F) -> Vec<Fit> {
+    let rows = expected.len();
+    let mut fits = Vec::new();
+    for &interest in INTEREST_ROUNDINGS.iter() {
+        for &payment in PAYMENT_ROUNDINGS.iter() {
+            let deltas: Vec<RowDelta> = compare(expected, schedule(interest, payment)).into_iter()
+                .filter(|delta| delta.expected.is_some())
+                .collect();
+            fits.push(Fit{
+                interest: interest,
+                payment: payment,
+                matched: deltas.iter().filter(|delta| delta.matches()).count(),
+                rows: rows,
+                max_delta: deltas.iter().map(RowDelta::max).fold(0f64, f64::max),
+            });
+        }
+    }
+    fits.sort_by(|a, b| b.matched.cmp(&a.matched).then(a.max_delta.partial_cmp(&b.max_delta).unwrap_or(std::cmp::Ordering::Equal)));
+    fits
+}
@@ -0,0 +1,39 @@
+// wasm-bindgen wrappers around the calculation core, so the same formulas can
+// back a browser calculator. Build with:
+//
+//     cargo build --lib --target wasm32-unknown-unknown --no-default-features --features wasm
+
+use wasm_bindgen::prelude::*;
+
+use calc;
+
+// Monthly payment for a fully amortizing loan.
+#[wasm_bindgen]
+pub fn payment(principal: f64, periods: i32, apr: f64) -> f64 {
+    calc::payment(principal, periods, apr)
+}
+
+// Full amortization table, flattened into `[interest, principal, balance]`
+// triples, one per month. Empty unless `periods` is positive, since it comes
+// straight from JS.
+#[wasm_bindgen]
+pub fn schedule(principal: f64, periods: i32, apr: f64) -> Vec<f64> {
+    if periods <= 0 {
+        return Vec::new();
+    }
+    let payment = calc::payment(principal, periods, apr);
+    let mut rows = Vec::with_capacity(periods as usize * 3);
+    for inst in calc::Schedule::new(principal, periods, apr, payment) {
+        rows.push(inst.interest);
+        rows.push(inst.principal);
+        rows.push(inst.balance);
+    }
+    rows
+}
+
+// Total interest paid over the life of the loan.
+#[wasm_bindgen]
+pub fn total_interest(principal: f64, periods: i32, apr: f64) -> f64 {
+    let payment = calc::payment(principal, periods, apr);
+    calc::project_payoff(principal, periods, apr, payment).total_interest
+}